@@ -0,0 +1,83 @@
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sider::client::Client;
+use sider::process::process_command;
+use sider::types::Store;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+// Clients hammering the store at the same time, each on keys of its own
+const CLIENTS: usize = 8;
+const COMMANDS_PER_CLIENT: usize = 1000;
+
+fn command(args: &[&str]) -> Vec<Bytes> {
+    args.iter()
+        .map(|arg| Bytes::copy_from_slice(arg.as_bytes()))
+        .collect()
+}
+
+// Runs `commands(client, i)` for every client concurrently and waits for all
+// of them. Commands are not appended to the AOF, only the locking is measured.
+fn run_clients<F>(rt: &Runtime, db: &Arc<Store>, commands: F)
+where
+    F: Fn(usize, usize) -> Vec<Vec<Bytes>> + Send + Sync + Copy + 'static,
+{
+    rt.block_on(async {
+        let tasks: Vec<_> = (0..CLIENTS)
+            .map(|client_number| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    let mut client = Client::new();
+                    for i in 0..COMMANDS_PER_CLIENT {
+                        for command in commands(client_number, i) {
+                            let _ = process_command(command, &db, &mut client, true).await;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+    });
+}
+
+// A single shard stands for the global lock the store used to be behind
+fn benchmark_contention(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("contention");
+    group.throughput(Throughput::Elements((CLIENTS * COMMANDS_PER_CLIENT) as u64));
+
+    for shards in [1, 16] {
+        let db = Arc::new(Store::with_shards(shards));
+
+        group.bench_with_input(BenchmarkId::new("set", shards), &shards, |b, _| {
+            b.iter(|| {
+                run_clients(&rt, &db, |client, i| {
+                    let key = format!("{client}:{i}");
+                    vec![command(&["SET", &key, "value"])]
+                })
+            });
+        });
+
+        // Keys spread over several shards, locked in order
+        group.bench_with_input(BenchmarkId::new("multi_key", shards), &shards, |b, _| {
+            b.iter(|| {
+                run_clients(&rt, &db, |client, i| {
+                    let keys = [0, 1, 2].map(|n| format!("{client}:{i}:{n}"));
+                    vec![
+                        command(&["SET", &keys[0], "value"]),
+                        command(&["EXISTS", &keys[2], &keys[1], &keys[0]]),
+                        command(&["DEL", &keys[0], &keys[1], &keys[2]]),
+                    ]
+                })
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_contention);
+criterion_main!(benches);
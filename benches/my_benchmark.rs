@@ -1,23 +1,32 @@
+use bytes::Bytes;
 use criterion::async_executor::FuturesExecutor;
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use indexmap::IndexMap;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sider::client::Client;
 use sider::process::process_command;
+use sider::server::handle_client;
+use sider::types::Store;
 use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::runtime::Runtime;
-use tokio::sync::RwLock;
 
 fn benchmark_process_command(c: &mut Criterion) {
     let rt = Runtime::new().unwrap();
-    let db = Arc::new(RwLock::new(IndexMap::new()));
+    let db = Arc::new(Store::new());
 
     for &size in &[1, 10, 100, 1000, 10_000] {
         c.bench_with_input(BenchmarkId::new("set_command", size), &size, |b, &size| {
             b.to_async(FuturesExecutor).iter(|| {
                 let db = db.clone();
                 rt.spawn(async move {
+                    let mut client = Client::new();
                     for i in 0..size {
-                        let command = vec!["SET".to_string(), i.to_string(), i.to_string()];
-                        let _ = process_command(command.clone(), &db, true).await;
+                        let command = vec![
+                            Bytes::from("SET"),
+                            Bytes::from(i.to_string()),
+                            Bytes::from(i.to_string()),
+                        ];
+                        let _ = process_command(command.clone(), &db, &mut client, true).await;
                     }
                 })
             });
@@ -27,9 +36,10 @@ fn benchmark_process_command(c: &mut Criterion) {
             b.to_async(FuturesExecutor).iter(|| {
                 let db = db.clone();
                 rt.spawn(async move {
+                    let mut client = Client::new();
                     for i in 0..size {
-                        let command = vec!["GET".to_string(), i.to_string()];
-                        let _ = process_command(command.clone(), &db, true).await;
+                        let command = vec![Bytes::from("GET"), Bytes::from(i.to_string())];
+                        let _ = process_command(command.clone(), &db, &mut client, true).await;
                     }
                 })
             });
@@ -39,9 +49,10 @@ fn benchmark_process_command(c: &mut Criterion) {
             b.to_async(FuturesExecutor).iter(|| {
                 let db = db.clone();
                 rt.spawn(async move {
+                    let mut client = Client::new();
                     for i in 0..size {
-                        let command = vec!["DEL".to_string(), i.to_string()];
-                        let _ = process_command(command.clone(), &db, true).await;
+                        let command = vec![Bytes::from("DEL"), Bytes::from(i.to_string())];
+                        let _ = process_command(command.clone(), &db, &mut client, true).await;
                     }
                 })
             });
@@ -54,8 +65,9 @@ fn benchmark_process_command(c: &mut Criterion) {
                 b.to_async(FuturesExecutor).iter(|| {
                     let db = db.clone();
                     rt.spawn(async move {
-                        let command = vec!["KEYS *".to_string()];
-                        let _ = process_command(command.clone(), &db, true).await;
+                        let mut client = Client::new();
+                        let command = vec![Bytes::from("KEYS"), Bytes::from("*")];
+                        let _ = process_command(command.clone(), &db, &mut client, true).await;
                     })
                 });
             },
@@ -63,5 +75,49 @@ fn benchmark_process_command(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, benchmark_process_command);
+// Sends `size` GET commands in a single write over a real TCP connection and
+// waits for every reply, like a batch loader would. GET is used because read
+// commands are not appended to the AOF.
+fn benchmark_pipeline(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let db = Arc::new(Store::new());
+
+    let listener = rt.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    rt.spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let db = db.clone();
+            tokio::spawn(async move {
+                let _ = handle_client(socket, db).await;
+            });
+        }
+    });
+
+    let mut group = c.benchmark_group("pipeline");
+
+    for &size in &[1, 100, 1000, 10_000] {
+        let request = "*2\r\n$3\r\nGET\r\n$7\r\nmissing\r\n".repeat(size);
+        // Each reply is a null bulk string, "$-1\r\n"
+        let expected = size * 5;
+
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::new("get", size), &size, |b, _| {
+            let mut socket = rt.block_on(TcpStream::connect(address)).unwrap();
+            let mut replies = vec![0; expected];
+
+            b.iter(|| {
+                rt.block_on(async {
+                    socket.write_all(request.as_bytes()).await.unwrap();
+                    socket.read_exact(&mut replies).await.unwrap();
+                })
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_process_command, benchmark_pipeline);
 criterion_main!(benches);
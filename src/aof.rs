@@ -1,160 +1,629 @@
-use crate::types::{Command, CommandArgs, CommandType, Db, DbValue};
+use crate::crc64::crc64;
+use crate::resp::encode_command;
+use crate::snapshot::add_changes;
+use crate::types::{Command, CommandArgs, CommandType};
+use bytes::Bytes;
 use dirs::home_dir;
+use once_cell::sync::OnceCell;
 use std::io::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::Mutex;
 use tokio::fs;
 use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
 use tokio::time::{self, Duration};
-use tracing::info;
+use tracing::{error, info, warn};
 
 pub fn get_aof_log_dir() -> PathBuf {
     let home = home_dir().expect("Failed to get home directory");
     home.join(".local/share/sider")
 }
 
-pub async fn delete_aof_file() {
-    let aof_log_dir = get_aof_log_dir();
-    if aof_log_dir.exists() {
-        let file_path = aof_log_dir.join("appendonly.aof");
-        let _ = fs::remove_file(&file_path).await;
-    }
-}
-
 pub fn get_aof_file() -> PathBuf {
     let log_path = get_aof_log_dir();
     log_path.join("appendonly.aof")
 }
 
-pub async fn write_aof(command: &Command) -> std::io::Result<()> {
-    if is_read_command(command.command_type.clone()) {
-        return Ok(());
+/// When the AOF is fsynced, the `appendfsync` setting of Redis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppendFsync {
+    // After every write, replies wait for it
+    Always,
+    // Once per second, up to a second of writes can be lost
+    EverySec,
+    // Whenever the OS flushes its buffers
+    No,
+}
+
+const FSYNC_POLICIES: [(&str, AppendFsync); 3] = [
+    ("always", AppendFsync::Always),
+    ("everysec", AppendFsync::EverySec),
+    ("no", AppendFsync::No),
+];
+
+impl AppendFsync {
+    pub fn parse(value: &[u8]) -> Option<Self> {
+        FSYNC_POLICIES
+            .iter()
+            .find(|(name, _)| name.as_bytes().eq_ignore_ascii_case(value))
+            .map(|(_, policy)| *policy)
+    }
+
+    pub fn name(&self) -> &'static str {
+        FSYNC_POLICIES
+            .iter()
+            .find(|(_, policy)| policy == self)
+            .map_or("everysec", |(name, _)| name)
+    }
+}
+
+// Every second by default, like Redis
+static APPENDFSYNC: AtomicU8 = AtomicU8::new(1);
+
+pub fn get_appendfsync() -> AppendFsync {
+    FSYNC_POLICIES[APPENDFSYNC.load(Ordering::Relaxed) as usize].1
+}
+
+pub fn set_appendfsync(policy: AppendFsync) {
+    let index = FSYNC_POLICIES
+        .iter()
+        .position(|(_, known)| *known == policy)
+        .unwrap_or(1);
+    APPENDFSYNC.store(index as u8, Ordering::Relaxed);
+}
+
+// Whether a torn last command is dropped on startup, else the server
+// refuses to start. Dropped by default, like Redis
+static AOF_LOAD_TRUNCATED: AtomicBool = AtomicBool::new(true);
+
+pub fn get_aof_load_truncated() -> bool {
+    AOF_LOAD_TRUNCATED.load(Ordering::Relaxed)
+}
+
+pub fn set_aof_load_truncated(load_truncated: bool) {
+    AOF_LOAD_TRUNCATED.store(load_truncated, Ordering::Relaxed);
+}
+
+// Why the last write to the AOF failed, until one succeeds again
+static AOF_WRITE_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+/// Set while the AOF can't be written, write commands are refused then.
+pub fn aof_write_error() -> Option<String> {
+    AOF_WRITE_ERROR
+        .lock()
+        .expect("AOF write error lock poisoned")
+        .clone()
+}
+
+fn set_aof_write_error(error: Option<String>) {
+    *AOF_WRITE_ERROR
+        .lock()
+        .expect("AOF write error lock poisoned") = error;
+}
+
+enum AofOperation {
+    Append(Vec<u8>),
+    // After FLUSHDB nothing logged before is needed
+    Truncate,
+    // From then on writes are also kept for the rewritten file
+    StartRewrite,
+    // The rewritten file, completed with the writes kept and renamed over
+    // the AOF
+    FinishRewrite(PathBuf),
+    AbortRewrite,
+    // A complete file renamed over the AOF, what was logged before isn't
+    // needed anymore
+    Replace(PathBuf),
+    // Only waits for what was sent before to be written
+    Position,
+}
+
+/// Where the AOF stands: its size and the checksum of its content, which
+/// tell whether it was replaced since.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AofPosition {
+    pub size: u64,
+    pub crc: u64,
+}
+
+type Done = oneshot::Sender<std::io::Result<AofPosition>>;
+
+struct AofRequest {
+    operation: AofOperation,
+    done: Done,
+}
+
+static AOF_WRITER: OnceCell<UnboundedSender<AofRequest>> = OnceCell::new();
+
+// Sizes in bytes of the AOF, now and after it was last rewritten
+static AOF_CURRENT_SIZE: AtomicU64 = AtomicU64::new(0);
+static AOF_BASE_SIZE: AtomicU64 = AtomicU64::new(0);
+
+pub fn get_aof_current_size() -> u64 {
+    AOF_CURRENT_SIZE.load(Ordering::Relaxed)
+}
+
+pub fn get_aof_base_size() -> u64 {
+    AOF_BASE_SIZE.load(Ordering::Relaxed)
+}
+
+/// Opens the AOF and starts the task every write to it goes through.
+pub async fn start_aof_writer() -> std::io::Result<()> {
+    let file = open_aof().await?;
+    let size = file.metadata().await?.len();
+    AOF_CURRENT_SIZE.store(size, Ordering::Relaxed);
+    AOF_BASE_SIZE.store(size, Ordering::Relaxed);
+
+    let writer = AofWriter {
+        file,
+        position: AofPosition {
+            size,
+            crc: checksum_file(&get_aof_file()).await?,
+        },
+        dirty: false,
+        rewrite_buffer: None,
+        unwritten: Vec::new(),
+    };
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    if AOF_WRITER.set(sender).is_err() {
+        return Err(Error::other("The AOF writer is already running"));
     }
 
+    tokio::spawn(writer.run(receiver));
+    Ok(())
+}
+
+async fn open_aof() -> std::io::Result<File> {
     let log_path = get_aof_log_dir();
 
     if !log_path.exists() {
         fs::create_dir_all(&log_path).await?;
     }
 
-    let keys_value = format_command_args(&command.args, command.command_type.clone());
-    let formatted = format!("{:?} {}\n", command.command_type, keys_value);
-
-    let file_path = log_path.join("appendonly.aof");
-
-    let mut file = File::options()
+    File::options()
         .append(true)
         .create(true)
-        .open(&file_path)
-        .await?;
+        .open(get_aof_file())
+        .await
+}
 
-    file.write_all(formatted.as_bytes()).await?;
-    Ok(())
+async fn checksum_file(path: &Path) -> std::io::Result<u64> {
+    let mut file = File::open(path).await?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut crc = 0;
+
+    loop {
+        match file.read(&mut buffer).await? {
+            0 => return Ok(crc),
+            read => crc = crc64(crc, &buffer[..read]),
+        }
+    }
 }
 
-fn is_read_command(cmd_type: CommandType) -> bool {
-    matches!(
-        cmd_type,
-        CommandType::GET
-            | CommandType::KEYS
-            | CommandType::EXISTS
-            | CommandType::TTL
-            | CommandType::HGET
-            | CommandType::HGETALL
-            | CommandType::LRANGE
-    )
+// Returns once the operation is written, and fsynced under `always`
+async fn send_to_aof(operation: AofOperation) -> std::io::Result<AofPosition> {
+    let stopped = || Error::other("The AOF writer is not running");
+    let writer = AOF_WRITER.get().ok_or_else(stopped)?;
+    let (done, written) = oneshot::channel();
+
+    writer
+        .send(AofRequest { operation, done })
+        .map_err(|_| stopped())?;
+    written.await.map_err(|_| stopped())?
 }
 
-fn format_command_args(args: &CommandArgs, cmd_type: CommandType) -> String {
-    match args {
-        CommandArgs::NoArgs => format!("{:?}", cmd_type),
-        CommandArgs::SingleKey(key) => key.clone(),
-        CommandArgs::MultipleKeys(keys) => keys.join(" "),
-        CommandArgs::KeyWithValue { key, value } => format!("{} {}", key, value),
-        CommandArgs::KeyWithValues { key, values } => format!("{} {}", key, values.join(" ")),
-        CommandArgs::HashFields { key, fields } => format!(
-            "{} {}",
-            key,
-            fields
-                .iter()
-                .map(|(field, value)| format!("{} {}", field, value))
-                .collect::<Vec<_>>()
-                .join(" ")
-        ),
-    }
-}
-
-async fn dump_db_to_aof(db: &Db) -> Result<(), Error> {
-    let db_write = db.write().await;
-    let db_dump_aof = get_aof_log_dir().join("db-dump.aof");
-    let aof_file = get_aof_file();
-
-    let mut file = File::options()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&db_dump_aof)
-        .await?;
+/// Starts keeping the writes for a rewrite, the dataset must not change
+/// until this returns.
+pub async fn start_aof_rewrite() -> std::io::Result<()> {
+    send_to_aof(AofOperation::StartRewrite).await.map(|_| ())
+}
+
+/// Appends the writes kept since `start_aof_rewrite` to the rewritten file
+/// at `path` and atomically replaces the AOF with it.
+pub async fn finish_aof_rewrite(path: PathBuf) -> std::io::Result<()> {
+    send_to_aof(AofOperation::FinishRewrite(path))
+        .await
+        .map(|_| ())
+}
 
-    let mut output = String::new();
+/// Atomically replaces the AOF with the file at `path`, which must be on
+/// disk already. Writes not written yet and a rewrite in progress are
+/// dropped, the file stands for the whole dataset.
+pub async fn replace_aof(path: PathBuf) -> std::io::Result<()> {
+    send_to_aof(AofOperation::Replace(path)).await.map(|_| ())
+}
+
+pub async fn abort_aof_rewrite() -> std::io::Result<()> {
+    send_to_aof(AofOperation::AbortRewrite).await.map(|_| ())
+}
+
+/// Where the AOF stands once the writes made so far are in it.
+pub async fn aof_position() -> std::io::Result<AofPosition> {
+    send_to_aof(AofOperation::Position).await
+}
 
-    for (key, value) in db_write.iter() {
-        match value {
-            DbValue::StringKey(k) => {
-                if let Some(val) = &k.data {
-                    output.push_str(&format!("SET {} {}\n", key, val));
+struct AofWriter {
+    file: File,
+    position: AofPosition,
+    // Written since the last fsync
+    dirty: bool,
+    // Writes since the rewrite in progress started, like the rewrite buffer
+    // of Redis
+    rewrite_buffer: Option<Vec<u8>>,
+    // Writes that failed, applied to the dataset already so retried until
+    // they make it to the file
+    unwritten: Vec<u8>,
+}
+
+impl AofWriter {
+    async fn run(mut self, mut requests: UnboundedReceiver<AofRequest>) {
+        let mut fsync_timer = time::interval(Duration::from_secs(1));
+
+        loop {
+            tokio::select! {
+                request = requests.recv() => {
+                    let Some(request) = request else {
+                        return;
+                    };
+
+                    // Writes sent while the previous ones were written are
+                    // committed together, with a single fsync
+                    let mut batch = vec![request];
+                    while let Ok(request) = requests.try_recv() {
+                        batch.push(request);
+                    }
+
+                    self.handle(batch).await;
+                }
+                _ = fsync_timer.tick() => {
+                    if !self.unwritten.is_empty() {
+                        let _ = self.write_unwritten().await;
+                    }
+
+                    if self.dirty && get_appendfsync() == AppendFsync::EverySec {
+                        match self.file.sync_data().await {
+                            Ok(()) => self.dirty = false,
+                            Err(e) => warn!("Failed to fsync the AOF file: {e}"),
+                        }
+                    }
                 }
             }
-            DbValue::ListKey(l) => {
-                let values = l.data.iter().cloned().collect::<Vec<String>>().join(" ");
-                output.push_str(&format!("LPUSH {} {}\n", key, values));
+        }
+    }
+
+    async fn handle(&mut self, batch: Vec<AofRequest>) {
+        let mut data = Vec::new();
+        let mut waiting = Vec::new();
+
+        for request in batch {
+            match request.operation {
+                AofOperation::Append(command) => {
+                    data.extend_from_slice(&command);
+                    waiting.push(request.done);
+                }
+                // Whatever was appended before goes first
+                AofOperation::Truncate => {
+                    self.commit(&mut data, &mut waiting).await;
+                    let result = self.truncate().await;
+                    let _ = request.done.send(result.map(|_| self.position));
+                }
+                AofOperation::StartRewrite => {
+                    self.commit(&mut data, &mut waiting).await;
+                    self.rewrite_buffer = Some(Vec::new());
+                    let _ = request.done.send(Ok(self.position));
+                }
+                AofOperation::FinishRewrite(path) => {
+                    self.commit(&mut data, &mut waiting).await;
+                    let result = self.finish_rewrite(&path).await;
+                    let _ = request.done.send(result.map(|_| self.position));
+                }
+                AofOperation::Replace(path) => {
+                    self.commit(&mut data, &mut waiting).await;
+                    let result = self.replace(&path).await;
+                    let _ = request.done.send(result.map(|_| self.position));
+                }
+                AofOperation::AbortRewrite => {
+                    self.commit(&mut data, &mut waiting).await;
+                    self.rewrite_buffer = None;
+                    let _ = request.done.send(Ok(self.position));
+                }
+                // A snapshot must not be taken at a position missing writes
+                // it has
+                AofOperation::Position => {
+                    self.commit(&mut data, &mut waiting).await;
+                    let result = if self.unwritten.is_empty() {
+                        Ok(())
+                    } else {
+                        self.write_unwritten().await
+                    };
+                    let _ = request.done.send(result.map(|_| self.position));
+                }
+            }
+        }
+
+        self.commit(&mut data, &mut waiting).await;
+    }
+
+    async fn commit(&mut self, data: &mut Vec<u8>, waiting: &mut Vec<Done>) {
+        if waiting.is_empty() {
+            return;
+        }
+
+        // Already applied to the dataset, whether it could be written or not
+        if let Some(buffer) = self.rewrite_buffer.as_mut() {
+            buffer.extend_from_slice(data);
+        }
+
+        self.unwritten.append(data);
+        let result = self.write_unwritten().await;
+
+        for done in waiting.drain(..) {
+            let result = match &result {
+                Ok(()) => Ok(self.position),
+                Err(e) => Err(Error::new(e.kind(), e.to_string())),
+            };
+            let _ = done.send(result);
+        }
+    }
+
+    // Writes that failed before go first, kept for the next try if this
+    // one fails too
+    async fn write_unwritten(&mut self) -> std::io::Result<()> {
+        let data = std::mem::take(&mut self.unwritten);
+
+        let result = match self.append(&data).await {
+            Ok(()) => self.sync_if_always().await,
+            Err(e) => {
+                self.unwritten = data;
+                Err(e)
             }
-            DbValue::SetKey(s) => {
-                let values = s
-                    .data
-                    .iter()
-                    .map(|value| value.to_string())
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                output.push_str(&format!("SADD {} {}\n", s.name, values));
+        };
+
+        match &result {
+            Ok(()) if aof_write_error().is_some() => {
+                info!("The AOF file can be written again");
+                set_aof_write_error(None);
             }
-            DbValue::HashKey(hash_key) => {
-                let fields = hash_key
-                    .data
-                    .iter()
-                    .map(|(field, value)| format!("{} {}", field, value))
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                output.push_str(&format!("HSET {} {}\n", hash_key.name, fields));
+            Ok(()) => {}
+            Err(e) => {
+                error!("Failed to write to the AOF file: {e}");
+                set_aof_write_error(Some(e.to_string()));
             }
         }
+
+        result
     }
 
-    file.write_all(output.as_bytes()).await?;
+    async fn append(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let written = async {
+            self.file.write_all(data).await?;
+            self.file.flush().await
+        }
+        .await;
 
-    // Ensure all data is written
-    file.flush().await?;
+        if let Err(e) = written {
+            // Cut what made it, the file must end with a whole command
+            let _ = self.file.set_len(self.position.size).await;
+            return Err(e);
+        }
 
-    // Deleting actual aof file
-    delete_aof_file().await;
+        self.position.size += data.len() as u64;
+        self.position.crc = crc64(self.position.crc, data);
+        AOF_CURRENT_SIZE.store(self.position.size, Ordering::Relaxed);
+        self.dirty = true;
+        Ok(())
+    }
 
-    // Replacing aof file by dump file
-    fs::rename(&db_dump_aof, &aof_file).await?;
+    async fn truncate(&mut self) -> std::io::Result<()> {
+        // The rewritten file would bring the flushed keys back
+        self.rewrite_buffer = None;
+        self.unwritten.clear();
 
-    // Deleting dump
-    if db_dump_aof.exists() {
-        let _ = fs::remove_file(&db_dump_aof).await;
+        self.file.set_len(0).await?;
+        self.position = AofPosition { size: 0, crc: 0 };
+        AOF_CURRENT_SIZE.store(0, Ordering::Relaxed);
+        AOF_BASE_SIZE.store(0, Ordering::Relaxed);
+        self.dirty = true;
+        self.sync_if_always().await
     }
 
-    Ok(())
+    async fn sync_if_always(&mut self) -> std::io::Result<()> {
+        if get_appendfsync() == AppendFsync::Always {
+            self.file.sync_data().await?;
+            self.dirty = false;
+        }
+
+        Ok(())
+    }
+
+    // The rewritten file is only renamed over the AOF once complete and on
+    // disk, so a crash leaves either file whole
+    async fn finish_rewrite(&mut self, path: &Path) -> std::io::Result<()> {
+        let Some(buffer) = self.rewrite_buffer.take() else {
+            return Err(Error::other(
+                "The rewrite was aborted by FLUSHDB or DEBUG RELOAD",
+            ));
+        };
+
+        let mut file = File::options().append(true).open(path).await?;
+        file.write_all(&buffer).await?;
+        file.sync_all().await?;
+        self.install(path).await?;
+
+        // The rewritten file has them, they were applied before the rewrite
+        // or kept for it
+        self.unwritten.clear();
+        Ok(())
+    }
+
+    async fn replace(&mut self, path: &Path) -> std::io::Result<()> {
+        self.install(path).await?;
+
+        // The rewritten file would bring the replaced keys back, and the
+        // writes not written yet were made to the replaced dataset
+        self.rewrite_buffer = None;
+        self.unwritten.clear();
+        Ok(())
+    }
+
+    // Renames the file over the AOF and writes to it from then on
+    async fn install(&mut self, path: &Path) -> std::io::Result<()> {
+        fs::rename(path, get_aof_file()).await?;
+        self.file = open_aof().await?;
+        self.dirty = false;
+
+        self.position = AofPosition {
+            size: self.file.metadata().await?.len(),
+            crc: checksum_file(&get_aof_file()).await?,
+        };
+        AOF_CURRENT_SIZE.store(self.position.size, Ordering::Relaxed);
+        AOF_BASE_SIZE.store(self.position.size, Ordering::Relaxed);
+        Ok(())
+    }
 }
 
-pub async fn clean_up_db(db: Db) {
-    let mut interval = time::interval(Duration::from_secs(60));
+pub async fn write_aof(command: &Command) -> std::io::Result<()> {
+    if is_read_command(command.command_type.clone()) {
+        return Ok(());
+    }
 
-    loop {
-        interval.tick().await;
-        info!("Cleaning up Database");
-        let _ = dump_db_to_aof(&db).await;
+    add_changes(1);
+
+    // Nothing logged before a flush is needed to rebuild the dataset
+    if command.command_type == CommandType::FLUSHDB {
+        return truncate_aof().await;
     }
+
+    append_aof(format_aof_command(command)).await
+}
+
+/// Appends commands already in the AOF format, e.g. the keys loaded by
+/// `DEBUG RELOAD`.
+pub async fn append_aof(data: Vec<u8>) -> std::io::Result<()> {
+    send_to_aof(AofOperation::Append(data)).await.map(|_| ())
+}
+
+/// Empties the AOF, once nothing logged so far is needed to rebuild the
+/// dataset.
+pub async fn truncate_aof() -> std::io::Result<()> {
+    send_to_aof(AofOperation::Truncate).await.map(|_| ())
+}
+
+/// Logs a whole transaction as a `MULTI` ... `EXEC` block, written at once
+/// so replaying the file applies all of it or, if the block was cut short,
+/// none of it.
+pub async fn write_aof_transaction(commands: &[Command]) -> std::io::Result<()> {
+    let writes: Vec<&Command> = commands
+        .iter()
+        .filter(|command| !is_read_command(command.command_type.clone()))
+        .collect();
+
+    if writes.is_empty() {
+        return Ok(());
+    }
+
+    add_changes(writes.len() as u64);
+
+    let mut formatted = Vec::new();
+    encode_command(&mut formatted, &["MULTI"]);
+    for command in writes {
+        formatted.extend_from_slice(&format_aof_command(command));
+    }
+    encode_command(&mut formatted, &["EXEC"]);
+
+    append_aof(formatted).await
+}
+
+/// Encodes a command as a RESP array, the format of the AOF, like Redis.
+pub fn format_aof_command(command: &Command) -> Vec<u8> {
+    let mut formatted = Vec::new();
+    encode_command(&mut formatted, &command_args(command));
+    formatted
+}
+
+// Commands that don't change the dataset are not logged
+pub fn is_read_command(cmd_type: CommandType) -> bool {
+    matches!(
+        cmd_type,
+        CommandType::DOCS
+            | CommandType::PONG
+            | CommandType::CLIENT
+            | CommandType::GET
+            | CommandType::KEYS
+            | CommandType::EXISTS
+            | CommandType::TTL
+            | CommandType::PTTL
+            | CommandType::EXPIRETIME
+            | CommandType::PEXPIRETIME
+            | CommandType::HGET
+            | CommandType::HGETALL
+            | CommandType::LRANGE
+            | CommandType::SMEMBERS
+            | CommandType::HELLO
+            | CommandType::MULTI
+            | CommandType::EXEC
+            | CommandType::DISCARD
+            | CommandType::WATCH
+            | CommandType::UNWATCH
+            | CommandType::SUBSCRIBE
+            | CommandType::UNSUBSCRIBE
+            | CommandType::PSUBSCRIBE
+            | CommandType::PUNSUBSCRIBE
+            | CommandType::PUBLISH
+            | CommandType::PUBSUB
+            | CommandType::CONFIG
+            | CommandType::INFO
+            | CommandType::BGREWRITEAOF
+            | CommandType::SAVE
+            | CommandType::BGSAVE
+            | CommandType::LASTSAVE
+            // Logs the keys it loads itself
+            | CommandType::DEBUG
+            | CommandType::ZSCORE
+            | CommandType::ZCARD
+            | CommandType::ZRANK
+            | CommandType::ZREVRANK
+            | CommandType::ZRANGE
+            | CommandType::ZRANGEBYSCORE
+            | CommandType::ZCOUNT
+            | CommandType::XLEN
+            | CommandType::XRANGE
+            | CommandType::XREVRANGE
+            | CommandType::XREAD
+            | CommandType::XPENDING
+    )
+}
+
+// The command name followed by its arguments, as they were received
+fn command_args(command: &Command) -> Vec<Bytes> {
+    let mut args = vec![Bytes::from(format!("{:?}", command.command_type))];
+
+    match &command.args {
+        CommandArgs::NoArgs => {}
+        CommandArgs::SingleKey(key) => args.push(key.clone()),
+        CommandArgs::MultipleKeys(keys) => args.extend(keys.iter().cloned()),
+        CommandArgs::KeyWithValue { key, value } => {
+            args.push(key.clone());
+            args.push(value.clone());
+        }
+        CommandArgs::KeyWithValues { key, values } => {
+            args.push(key.clone());
+            args.extend(values.iter().cloned());
+        }
+        CommandArgs::HashFields { key, fields } => {
+            args.push(key.clone());
+            for (field, value) in fields.iter() {
+                args.push(field.clone());
+                args.push(value.clone());
+            }
+        }
+        // Connection commands are never logged
+        CommandArgs::Hello { .. } => {}
+    }
+
+    args
 }
@@ -0,0 +1,72 @@
+//! Checks an AOF file like Sider does on startup and reports the offset of
+//! the first bad command. With `--fix`, the file is truncated there, like
+//! `redis-check-aof --fix`. The server must be stopped.
+
+use clap::Parser;
+use sider::aof::get_aof_file;
+use sider::database::{check_aof, AofCheck};
+use std::fs::{self, OpenOptions};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    /// AOF file, the one of the data directory by default.
+    file: Option<PathBuf>,
+
+    /// Truncates the file at the first bad command, the commands after it
+    /// are lost.
+    #[arg(long)]
+    fix: bool,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let path = cli.file.unwrap_or_else(get_aof_file);
+
+    let content = match fs::read(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read {}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let offset = match check_aof(&content) {
+        AofCheck::Valid => {
+            println!("AOF {} is valid", path.display());
+            return ExitCode::SUCCESS;
+        }
+        AofCheck::Truncated { offset } => {
+            println!("AOF is truncated: the last command, at offset {offset}, is incomplete");
+            offset
+        }
+        AofCheck::Corrupt { offset, reason } => {
+            println!("AOF is corrupt at offset {offset}: {reason}");
+            offset
+        }
+    };
+
+    if !cli.fix {
+        println!("Run with --fix to truncate it at offset {offset}");
+        return ExitCode::FAILURE;
+    }
+
+    let truncated = OpenOptions::new().write(true).open(&path).and_then(|file| {
+        file.set_len(offset as u64)?;
+        file.sync_all()
+    });
+
+    match truncated {
+        Ok(()) => {
+            let dropped = content.len() - offset;
+            println!("Truncated to {offset} bytes, {dropped} bytes were dropped");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to truncate {}: {e}", path.display());
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Converts Redis RDB files to the data Sider loads on startup and back,
+//! to move a dataset between Redis and Sider while the servers are stopped.
+
+use clap::{Parser, Subcommand};
+use sider::aof::{get_aof_file, get_aof_log_dir, AofPosition};
+use sider::crc64::crc64;
+use sider::rdb::{count_streams, decode_rdb, encode_rdb};
+use sider::rewrite::dump_shard;
+use sider::shard::Shard;
+use sider::snapshot::{decode_snapshot, encode_snapshot, get_snapshot_file};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    action: Action,
+
+    /// Data directory of Sider, `~/.local/share/sider` by default.
+    #[arg(long, global = true)]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Action {
+    /// Turns an RDB file into the AOF and snapshot Sider starts from.
+    Import {
+        rdb: PathBuf,
+
+        /// Replaces the AOF of the data directory if it isn't empty.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Writes the last snapshot taken by Sider, with `SAVE`, as an RDB file.
+    Export {
+        rdb: PathBuf,
+
+        /// Exports the snapshot even if the AOF has writes made after it,
+        /// they are missing from the RDB file.
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    tracing_subscriber::fmt::init();
+
+    let cli = Cli::parse();
+    let dir = cli.dir.unwrap_or_else(get_aof_log_dir);
+    let snapshot_file = in_dir(&dir, get_snapshot_file());
+    let aof_file = in_dir(&dir, get_aof_file());
+
+    match cli.action {
+        Action::Import { rdb, force } => {
+            let entries = decode_rdb(&fs::read(&rdb)?)?;
+
+            let aof_len = fs::metadata(&aof_file).map(|file| file.len()).unwrap_or(0);
+            if aof_len > 0 && !force {
+                return Err(format!(
+                    "{} is not empty, delete it or pass --force",
+                    aof_file.display()
+                )
+                .into());
+            }
+
+            let mut shard = Shard::new();
+            let count = entries.len();
+            for (key, value) in entries {
+                shard.insert(key, value);
+            }
+
+            // Both files as SAVE leaves them: the AOF rebuilds the dataset
+            // on its own and the snapshot was taken at its end
+            let aof = dump_shard(&shard);
+            let position = AofPosition {
+                size: aof.len() as u64,
+                crc: crc64(0, &aof),
+            };
+
+            fs::create_dir_all(&dir)?;
+            fs::write(&aof_file, &aof)?;
+            fs::write(&snapshot_file, encode_snapshot(position, [&shard]))?;
+            println!("Imported {count} keys into {}", dir.display());
+        }
+        Action::Export { rdb, force } => {
+            let snapshot = decode_snapshot(&fs::read(&snapshot_file)?)?;
+
+            if let Ok(aof) = fs::read(&aof_file) {
+                let outdated =
+                    !snapshot.matches_aof(&aof) || aof.len() as u64 > snapshot.aof.size;
+                if outdated && !force {
+                    return Err(
+                        "The AOF has writes made after the snapshot, run SAVE first \
+                        to export them or pass --force"
+                            .into(),
+                    );
+                }
+            }
+
+            let mut shard = Shard::new();
+            for (key, value) in snapshot.entries {
+                shard.insert(key, value);
+            }
+
+            // What the file has, without the expired keys and streams
+            let streams = count_streams(&shard);
+            let count = shard
+                .iter()
+                .filter(|(_, value)| !value.is_expired())
+                .count()
+                - streams;
+            if streams > 0 {
+                eprintln!("{streams} streams were left out, RDB files can't have them");
+            }
+
+            fs::write(&rdb, encode_rdb([&shard]))?;
+            println!("Exported {count} keys to {}", rdb.display());
+        }
+    }
+
+    Ok(())
+}
+
+// The file of the default data directory, in `dir`
+fn in_dir(dir: &Path, file: PathBuf) -> PathBuf {
+    match file.file_name() {
+        Some(name) => dir.join(name),
+        None => file,
+    }
+}
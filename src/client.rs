@@ -0,0 +1,119 @@
+use crate::pubsub::{get_pubsub, MessageSender};
+use crate::response::SiderResponse;
+use crate::types::Command;
+use bytes::Bytes;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::sync::oneshot;
+
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// RESP version spoken on a connection, switched with HELLO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Protocol {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl Protocol {
+    pub fn version(&self) -> i64 {
+        match self {
+            Protocol::Resp2 => 2,
+            Protocol::Resp3 => 3,
+        }
+    }
+}
+
+/// Set by BLPOP, BRPOP and BLMOVE when nothing could be popped, the
+/// connection task then waits for the reply or the timeout.
+#[derive(Debug)]
+pub struct BlockedWait {
+    pub reply: oneshot::Receiver<SiderResponse>,
+    // `None` waits forever
+    pub timeout: Option<Duration>,
+}
+
+/// A key given to WATCH.
+#[derive(Debug, Clone)]
+pub struct WatchedKey {
+    pub key: Bytes,
+    // Version in its shard when watched, whether the key existed or not
+    pub version: u64,
+    // Existed and hadn't expired, its expiring before EXEC is a change too
+    pub live: bool,
+}
+
+/// Per-connection state.
+#[derive(Debug)]
+pub struct Client {
+    pub id: u64,
+    pub protocol: Protocol,
+    pub name: Option<Bytes>,
+    // Commands queued since MULTI, `None` outside of a transaction
+    pub transaction: Option<Vec<Command>>,
+    // Set when a command failed to queue, EXEC will then refuse to run
+    pub transaction_aborted: bool,
+    // Registered in their shards until EXEC, DISCARD, UNWATCH or the
+    // connection closes
+    pub watched: Vec<WatchedKey>,
+    pub channels: HashSet<Bytes>,
+    pub patterns: HashSet<Bytes>,
+    // Published messages waiting to be written by the connection task
+    pub sender: MessageSender,
+    pub messages: UnboundedReceiver<SiderResponse>,
+    pub blocked: Option<BlockedWait>,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        let (sender, messages) = mpsc::unbounded_channel();
+
+        Client {
+            id: NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed),
+            protocol: Protocol::default(),
+            name: None,
+            transaction: None,
+            transaction_aborted: false,
+            watched: Vec::new(),
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            sender,
+            messages,
+            blocked: None,
+        }
+    }
+
+    pub fn subscription_count(&self) -> i64 {
+        (self.channels.len() + self.patterns.len()) as i64
+    }
+
+    /// RESP2 connections with subscriptions can only (un)subscribe and PING,
+    /// RESP3 ones get messages as push frames and keep working normally.
+    pub fn in_subscribe_mode(&self) -> bool {
+        self.protocol == Protocol::Resp2 && self.subscription_count() > 0
+    }
+}
+
+// Closing the connection drops its subscriptions
+impl Drop for Client {
+    fn drop(&mut self) {
+        let pubsub = get_pubsub();
+
+        for channel in self.channels.iter() {
+            pubsub.unsubscribe(channel, self.id);
+        }
+
+        for pattern in self.patterns.iter() {
+            pubsub.punsubscribe(pattern, self.id);
+        }
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client::new()
+    }
+}
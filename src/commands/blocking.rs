@@ -0,0 +1,289 @@
+use crate::commands::lists::{parse_pop_side, parse_push_side};
+use crate::commands::streams::{get_stream, parse_read_args, resolve_read_ids};
+use crate::errors::SiderError;
+use crate::response::SiderResponse;
+use crate::types::{
+    BlockingOperation, BlockingRequest, Command, CommandArgs, CommandType, DbValue, Keyspace,
+    ListPushType, PopType, StreamId,
+};
+use bytes::Bytes;
+use std::time::Duration;
+
+fn parse_timeout(timeout: &[u8]) -> Result<Option<Duration>, SiderError> {
+    let seconds = std::str::from_utf8(timeout)
+        .ok()
+        .and_then(|timeout| timeout.parse::<f64>().ok())
+        .filter(|seconds| seconds.is_finite())
+        .ok_or(SiderError::TimeoutNotFloat)?;
+
+    if seconds < 0.0 {
+        return Err(SiderError::NegativeTimeout);
+    }
+
+    // 0 blocks forever
+    if seconds == 0.0 {
+        return Ok(None);
+    }
+
+    Ok(Some(Duration::from_secs_f64(seconds)))
+}
+
+// XREAD and XREADGROUP take milliseconds, 0 blocks forever too
+fn stream_timeout(milliseconds: u64) -> Option<Duration> {
+    Some(Duration::from_millis(milliseconds)).filter(|timeout| !timeout.is_zero())
+}
+
+/// Parses BLPOP, BRPOP, BLMOVE, and XREAD or XREADGROUP with BLOCK. `None`
+/// for any other command.
+pub fn blocking_request(
+    db: &Keyspace,
+    command: &Command,
+) -> Result<Option<BlockingRequest>, SiderError> {
+    let request = match (&command.command_type, &command.args) {
+        (CommandType::BLPOP | CommandType::BRPOP, CommandArgs::MultipleKeys(args)) => {
+            let Some((timeout, keys)) = args.split_last() else {
+                return Err(SiderError::WrongNumberArgs);
+            };
+
+            let side = match command.command_type {
+                CommandType::BLPOP => PopType::LPOP,
+                _ => PopType::RPOP,
+            };
+
+            BlockingRequest {
+                keys: keys.to_vec(),
+                timeout: parse_timeout(timeout)?,
+                operation: BlockingOperation::Pop(side),
+            }
+        }
+        (CommandType::BLMOVE, CommandArgs::KeyWithValues { key, values })
+            if values.len() == 4 =>
+        {
+            BlockingRequest {
+                keys: vec![key.clone()],
+                timeout: parse_timeout(&values[3])?,
+                operation: BlockingOperation::Move {
+                    destination: values[0].clone(),
+                    from: parse_pop_side(&values[1])?,
+                    to: parse_push_side(&values[2])?,
+                },
+            }
+        }
+        (CommandType::BLPOP | CommandType::BRPOP | CommandType::BLMOVE, _) => {
+            return Err(SiderError::InvalidCommand)
+        }
+        (CommandType::XREAD, _) => {
+            let read = parse_read_args(command)?;
+            let Some(block) = read.block else {
+                return Ok(None);
+            };
+
+            BlockingRequest {
+                keys: read.keys.clone(),
+                timeout: stream_timeout(block),
+                operation: BlockingOperation::ReadStreams {
+                    streams: resolve_read_ids(db, &read)?,
+                    count: read.count,
+                },
+            }
+        }
+        (CommandType::XREADGROUP, _) => {
+            let read = parse_read_args(command)?;
+
+            // Reading the pending entries of the consumer never blocks
+            let (Some(block), Some((group, consumer))) = (read.block, read.group) else {
+                return Ok(None);
+            };
+            if read.ids.iter().any(|id| &id[..] != b">") {
+                return Ok(None);
+            }
+
+            BlockingRequest {
+                keys: read.keys.clone(),
+                timeout: stream_timeout(block),
+                operation: BlockingOperation::ReadGroup {
+                    group,
+                    consumer,
+                    keys: read.keys,
+                    count: read.count,
+                    noack: read.noack,
+                },
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(request))
+}
+
+/// First of the keys the request can be served from right away: a list with
+/// elements, or a stream with entries to read. Like Redis, a key of another
+/// type is an error even if a later key could be served.
+pub fn blocking_source(
+    db: &Keyspace,
+    request: &BlockingRequest,
+) -> Result<Option<Bytes>, SiderError> {
+    for key in request.keys.iter() {
+        match (&request.operation, db.get(key)) {
+            (BlockingOperation::Pop(_) | BlockingOperation::Move { .. }, value) => {
+                match value {
+                    Some(DbValue::ListKey(list)) if !list.data.is_empty() => {
+                        return Ok(Some(key.clone()))
+                    }
+                    Some(DbValue::ListKey(_)) | None => continue,
+                    Some(_) => return Err(SiderError::WrongType),
+                }
+            }
+            (BlockingOperation::ReadGroup { group, .. }, _) => {
+                // Missing groups are reported right away
+                let has_group = get_stream(db, key)?
+                    .is_some_and(|stream| stream.groups.contains_key(group));
+
+                if !has_group {
+                    return Err(SiderError::NoGroup(
+                        String::from_utf8_lossy(key).to_string(),
+                        String::from_utf8_lossy(group).to_string(),
+                    ));
+                }
+            }
+            (BlockingOperation::ReadStreams { .. }, _) => {
+                get_stream(db, key)?;
+            }
+        }
+
+        if request.operation.is_ready(key, db.get(key)) {
+            return Ok(Some(key.clone()));
+        }
+    }
+
+    Ok(None)
+}
+
+impl BlockingOperation {
+    /// Whether a client blocked on `key` can be served from `value`.
+    pub fn is_ready(&self, key: &[u8], value: Option<&DbValue>) -> bool {
+        match (self, value) {
+            (
+                BlockingOperation::Pop(_) | BlockingOperation::Move { .. },
+                Some(DbValue::ListKey(list)),
+            ) => !list.data.is_empty(),
+            (
+                BlockingOperation::ReadStreams { streams, .. },
+                Some(DbValue::StreamKey(stream)),
+            ) => {
+                let after = streams
+                    .iter()
+                    .find(|(stream_key, _)| stream_key == key)
+                    .map_or(StreamId::MAX, |(_, id)| *id);
+
+                stream.data.last_entry_id().is_some_and(|last| last > after)
+            }
+            (BlockingOperation::ReadGroup { group, .. }, Some(DbValue::StreamKey(stream))) => {
+                // A destroyed group is served too, with an error
+                match stream.data.groups.get(group) {
+                    Some(group) => stream
+                        .data
+                        .last_entry_id()
+                        .is_some_and(|last| last > group.last_delivered),
+                    None => true,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// The non-blocking command it amounts to once served from `key`, which
+    /// is also what gets logged to the AOF. Streams are all read again, not
+    /// only `key`.
+    pub fn command(&self, key: Bytes) -> Command {
+        let side = |side: bool| Bytes::from_static(if side { b"LEFT" } else { b"RIGHT" });
+        let count = |count: &Option<usize>| match count {
+            Some(count) => vec![Bytes::from_static(b"COUNT"), Bytes::from(count.to_string())],
+            None => Vec::new(),
+        };
+
+        match self {
+            BlockingOperation::Pop(PopType::LPOP) => Command {
+                command_type: CommandType::LPOP,
+                args: CommandArgs::SingleKey(key),
+            },
+            BlockingOperation::Pop(PopType::RPOP) => Command {
+                command_type: CommandType::RPOP,
+                args: CommandArgs::SingleKey(key),
+            },
+            BlockingOperation::Move {
+                destination,
+                from,
+                to,
+            } => Command {
+                command_type: CommandType::LMOVE,
+                args: CommandArgs::KeyWithValues {
+                    key,
+                    values: vec![
+                        destination.clone(),
+                        side(matches!(from, PopType::LPOP)),
+                        side(matches!(to, ListPushType::LPUSH)),
+                    ],
+                },
+            },
+            BlockingOperation::ReadStreams {
+                streams,
+                count: read_count,
+            } => {
+                let mut args = count(read_count);
+                args.push(Bytes::from_static(b"STREAMS"));
+                args.extend(streams.iter().map(|(key, _)| key.clone()));
+                args.extend(streams.iter().map(|(_, id)| id.to_bytes()));
+
+                Command {
+                    command_type: CommandType::XREAD,
+                    args: CommandArgs::MultipleKeys(args),
+                }
+            }
+            BlockingOperation::ReadGroup {
+                group,
+                consumer,
+                keys,
+                count: read_count,
+                noack,
+            } => {
+                let mut args = vec![
+                    Bytes::from_static(b"GROUP"),
+                    group.clone(),
+                    consumer.clone(),
+                ];
+                args.extend(count(read_count));
+                if *noack {
+                    args.push(Bytes::from_static(b"NOACK"));
+                }
+                args.push(Bytes::from_static(b"STREAMS"));
+                args.extend(keys.iter().cloned());
+                args.extend(keys.iter().map(|_| Bytes::from_static(b">")));
+
+                Command {
+                    command_type: CommandType::XREADGROUP,
+                    args: CommandArgs::MultipleKeys(args),
+                }
+            }
+        }
+    }
+
+    /// BLPOP and BRPOP reply with the key along with the element.
+    pub fn reply(&self, key: Bytes, reply: SiderResponse) -> SiderResponse {
+        match (self, reply) {
+            (BlockingOperation::Pop(_), SiderResponse::BulkString(value)) => {
+                SiderResponse::bulk_strings([key, value])
+            }
+            (_, reply) => reply,
+        }
+    }
+
+    pub fn timeout_reply(&self) -> SiderResponse {
+        match self {
+            BlockingOperation::Move { .. } => SiderResponse::NullBulk,
+            BlockingOperation::Pop(_)
+            | BlockingOperation::ReadStreams { .. }
+            | BlockingOperation::ReadGroup { .. } => SiderResponse::NullArray,
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use crate::errors::SiderError;
 use crate::types::{Command, CommandArgs, CommandType};
+use crate::utils::parse_number;
+use bytes::Bytes;
 use indexmap::IndexMap;
 
 pub fn build_docs_command() -> Result<Command, SiderError> {
@@ -23,40 +25,52 @@ pub fn build_flush_db_command() -> Result<Command, SiderError> {
     })
 }
 
-pub fn build_get_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_get_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.is_empty() {
         return Err(SiderError::WrongNumberArgs);
     }
     Ok(Command {
         command_type: CommandType::GET,
-        args: CommandArgs::SingleKey(args[0].to_string()),
+        args: CommandArgs::SingleKey(args[0].clone()),
     })
 }
 
-pub fn build_keys_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_keys_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.is_empty() {
         return Err(SiderError::WrongNumberArgs);
     }
     Ok(Command {
         command_type: CommandType::KEYS,
-        args: CommandArgs::SingleKey(args[0].to_string()),
+        args: CommandArgs::SingleKey(args[0].clone()),
     })
 }
 
-pub fn build_set_command(args: &[String]) -> Result<Command, SiderError> {
-    if args.len() != 2 {
+// SET key value [NX | XX] [GET] [EX seconds | PX milliseconds |
+// EXAT unix-time-seconds | PXAT unix-time-milliseconds | KEEPTTL]
+pub fn build_set_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.len() < 2 {
         return Err(SiderError::WrongNumberArgs);
     }
+
+    // The value comes first in `values`, followed by the options
+    let args = match args.len() {
+        2 => CommandArgs::KeyWithValue {
+            key: args[0].clone(),
+            value: args[1].clone(),
+        },
+        _ => CommandArgs::KeyWithValues {
+            key: args[0].clone(),
+            values: args[1..].to_vec(),
+        },
+    };
+
     Ok(Command {
         command_type: CommandType::SET,
-        args: CommandArgs::KeyWithValue {
-            key: args[0].to_string(),
-            value: args[1].to_string(),
-        },
+        args,
     })
 }
 
-pub fn build_delete_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_delete_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.is_empty() {
         return Err(SiderError::WrongNumberArgs);
     }
@@ -66,7 +80,7 @@ pub fn build_delete_command(args: &[String]) -> Result<Command, SiderError> {
     })
 }
 
-pub fn build_exists_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_exists_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.is_empty() {
         return Err(SiderError::WrongNumberArgs);
     }
@@ -76,51 +90,105 @@ pub fn build_exists_command(args: &[String]) -> Result<Command, SiderError> {
     })
 }
 
-pub fn build_expire_command(args: &[String]) -> Result<Command, SiderError> {
+// EXPIRE key seconds [NX | XX | GT | LT], and PEXPIRE, EXPIREAT, PEXPIREAT
+fn build_expire_generic_command(
+    args: &[Bytes],
+    cmd_type: CommandType,
+) -> Result<Command, SiderError> {
     if args.len() < 2 {
         return Err(SiderError::WrongNumberArgs);
     }
 
-    Ok(Command {
-        command_type: CommandType::EXPIRE,
-        args: CommandArgs::KeyWithValue {
-            key: args[0].to_string(),
-            value: args[1].to_string(),
+    // The time comes first in `values`, followed by the flags
+    let args = match args.len() {
+        2 => CommandArgs::KeyWithValue {
+            key: args[0].clone(),
+            value: args[1].clone(),
         },
+        _ => CommandArgs::KeyWithValues {
+            key: args[0].clone(),
+            values: args[1..].to_vec(),
+        },
+    };
+
+    Ok(Command {
+        command_type: cmd_type,
+        args,
     })
 }
 
-pub fn build_ttl_command(args: &[String]) -> Result<Command, SiderError> {
-    if args.is_empty() {
+pub fn build_expire_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_expire_generic_command(args, CommandType::EXPIRE)
+}
+
+pub fn build_pexpire_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_expire_generic_command(args, CommandType::PEXPIRE)
+}
+
+pub fn build_expireat_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_expire_generic_command(args, CommandType::EXPIREAT)
+}
+
+pub fn build_pexpireat_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_expire_generic_command(args, CommandType::PEXPIREAT)
+}
+
+// TTL key, PTTL key, EXPIRETIME key, PEXPIRETIME key, PERSIST key
+fn build_single_key_command(
+    args: &[Bytes],
+    cmd_type: CommandType,
+) -> Result<Command, SiderError> {
+    if args.len() != 1 {
         return Err(SiderError::WrongNumberArgs);
     }
+
     Ok(Command {
-        command_type: CommandType::TTL,
-        args: CommandArgs::SingleKey(args[0].to_string()),
+        command_type: cmd_type,
+        args: CommandArgs::SingleKey(args[0].clone()),
     })
 }
 
-pub fn build_incr_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_ttl_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_single_key_command(args, CommandType::TTL)
+}
+
+pub fn build_pttl_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_single_key_command(args, CommandType::PTTL)
+}
+
+pub fn build_expiretime_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_single_key_command(args, CommandType::EXPIRETIME)
+}
+
+pub fn build_pexpiretime_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_single_key_command(args, CommandType::PEXPIRETIME)
+}
+
+pub fn build_persist_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_single_key_command(args, CommandType::PERSIST)
+}
+
+pub fn build_incr_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.is_empty() {
         return Err(SiderError::WrongNumberArgs);
     }
     Ok(Command {
         command_type: CommandType::INCR,
-        args: CommandArgs::SingleKey(args[0].to_string()),
+        args: CommandArgs::SingleKey(args[0].clone()),
     })
 }
 
-pub fn build_decr_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_decr_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.is_empty() {
         return Err(SiderError::WrongNumberArgs);
     }
     Ok(Command {
         command_type: CommandType::DECR,
-        args: CommandArgs::SingleKey(args[0].to_string()),
+        args: CommandArgs::SingleKey(args[0].clone()),
     })
 }
 
-pub fn build_incrby_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_incrby_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.len() < 2 {
         return Err(SiderError::WrongNumberArgs);
     }
@@ -128,31 +196,35 @@ pub fn build_incrby_command(args: &[String]) -> Result<Command, SiderError> {
     Ok(Command {
         command_type: CommandType::INCRBY,
         args: CommandArgs::KeyWithValue {
-            key: args[0].to_string(),
-            value: args[1].to_string(),
+            key: args[0].clone(),
+            value: args[1].clone(),
         },
     })
 }
 
-fn build_push_command(args: &[String], cmd_type: CommandType) -> Result<Command, SiderError> {
+fn build_push_command(args: &[Bytes], cmd_type: CommandType) -> Result<Command, SiderError> {
+    if args.len() < 2 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
     Ok(Command {
         command_type: cmd_type,
         args: CommandArgs::KeyWithValues {
-            key: args[0].to_string(),
-            values: args.iter().skip(1).cloned().collect::<Vec<String>>(),
+            key: args[0].clone(),
+            values: args.iter().skip(1).cloned().collect::<Vec<Bytes>>(),
         },
     })
 }
 
-pub fn build_lpush_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_lpush_command(args: &[Bytes]) -> Result<Command, SiderError> {
     build_push_command(args, CommandType::LPUSH)
 }
 
-pub fn build_rpush_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_rpush_command(args: &[Bytes]) -> Result<Command, SiderError> {
     build_push_command(args, CommandType::RPUSH)
 }
 
-pub fn build_lrange_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_lrange_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.len() < 3 {
         return Err(SiderError::WrongNumberArgs);
     }
@@ -160,21 +232,21 @@ pub fn build_lrange_command(args: &[String]) -> Result<Command, SiderError> {
     Ok(Command {
         command_type: CommandType::LRANGE,
         args: CommandArgs::KeyWithValues {
-            key: args[0].to_string(),
-            values: vec![args[1].to_string(), args[2].to_string()],
+            key: args[0].clone(),
+            values: vec![args[1].clone(), args[2].clone()],
         },
     })
 }
 
-pub fn build_lpop_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_lpop_command(args: &[Bytes]) -> Result<Command, SiderError> {
     build_lpop_rpop_command(args, CommandType::LPOP)
 }
-pub fn build_rpop_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_rpop_command(args: &[Bytes]) -> Result<Command, SiderError> {
     build_lpop_rpop_command(args, CommandType::RPOP)
 }
 
 fn build_lpop_rpop_command(
-    args: &[String],
+    args: &[Bytes],
     cmd_type: CommandType,
 ) -> Result<Command, SiderError> {
     if args.is_empty() {
@@ -199,7 +271,57 @@ fn build_lpop_rpop_command(
     Ok(command)
 }
 
-pub fn build_hset_command(args: &[String]) -> Result<Command, SiderError> {
+// LMOVE source destination LEFT|RIGHT LEFT|RIGHT, BLMOVE adds a timeout
+fn build_move_command(
+    args: &[Bytes],
+    cmd_type: CommandType,
+    nb_args: usize,
+) -> Result<Command, SiderError> {
+    if args.len() != nb_args {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: cmd_type,
+        args: CommandArgs::KeyWithValues {
+            key: args[0].clone(),
+            values: args[1..].to_vec(),
+        },
+    })
+}
+
+pub fn build_lmove_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_move_command(args, CommandType::LMOVE, 4)
+}
+
+pub fn build_blmove_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_move_command(args, CommandType::BLMOVE, 5)
+}
+
+// BLPOP key [key ...] timeout, the timeout is kept as the last argument
+fn build_blocking_pop_command(
+    args: &[Bytes],
+    cmd_type: CommandType,
+) -> Result<Command, SiderError> {
+    if args.len() < 2 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: cmd_type,
+        args: CommandArgs::MultipleKeys(args.to_vec()),
+    })
+}
+
+pub fn build_blpop_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_blocking_pop_command(args, CommandType::BLPOP)
+}
+
+pub fn build_brpop_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_blocking_pop_command(args, CommandType::BRPOP)
+}
+
+pub fn build_hset_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.len() < 3 || args.len() % 2 == 0 {
         return Err(SiderError::WrongNumberArgs);
     }
@@ -209,8 +331,8 @@ pub fn build_hset_command(args: &[String]) -> Result<Command, SiderError> {
         .iter()
         .step_by(2) // Selects every other element starting from the first (field)
         .zip(args[2..].iter().step_by(2)) // Pairs each field with the next value. zip() is great!
-        .map(|(field, value)| (field.clone(), value.clone())) // Convert to owned Strings
-        .collect::<IndexMap<String, String>>();
+        .map(|(field, value)| (field.clone(), value.clone())) // Bytes clones are cheap
+        .collect::<IndexMap<Bytes, Bytes>>();
 
     Ok(Command {
         command_type: CommandType::HSET,
@@ -218,7 +340,7 @@ pub fn build_hset_command(args: &[String]) -> Result<Command, SiderError> {
     })
 }
 
-pub fn build_hget_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_hget_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.len() != 2 {
         return Err(SiderError::WrongNumberArgs);
     }
@@ -226,24 +348,24 @@ pub fn build_hget_command(args: &[String]) -> Result<Command, SiderError> {
     Ok(Command {
         command_type: CommandType::HGET,
         args: CommandArgs::KeyWithValue {
-            key: args[0].to_string(),
-            value: args[1].to_string(),
+            key: args[0].clone(),
+            value: args[1].clone(),
         },
     })
 }
 
-pub fn build_hgetall_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_hgetall_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.is_empty() {
         return Err(SiderError::WrongNumberArgs);
     }
 
     Ok(Command {
         command_type: CommandType::HGETALL,
-        args: CommandArgs::SingleKey(args[0].to_string()),
+        args: CommandArgs::SingleKey(args[0].clone()),
     })
 }
 
-pub fn build_hdel_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_hdel_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.len() < 2 {
         return Err(SiderError::WrongNumberArgs);
     }
@@ -252,12 +374,12 @@ pub fn build_hdel_command(args: &[String]) -> Result<Command, SiderError> {
         command_type: CommandType::HDEL,
         args: CommandArgs::KeyWithValues {
             key: args[0].clone(),
-            values: args.iter().skip(1).cloned().collect::<Vec<String>>(),
+            values: args.iter().skip(1).cloned().collect::<Vec<Bytes>>(),
         },
     })
 }
 
-pub fn build_client_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_client_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.len() < 2 {
         return Err(SiderError::WrongNumberArgs);
     }
@@ -266,12 +388,12 @@ pub fn build_client_command(args: &[String]) -> Result<Command, SiderError> {
         command_type: CommandType::CLIENT,
         args: CommandArgs::KeyWithValues {
             key: args[0].clone(),
-            values: args.iter().skip(1).cloned().collect::<Vec<String>>(),
+            values: args.iter().skip(1).cloned().collect::<Vec<Bytes>>(),
         },
     })
 }
 
-pub fn build_sadd_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_sadd_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.len() < 2 {
         return Err(SiderError::WrongNumberArgs);
     }
@@ -279,24 +401,24 @@ pub fn build_sadd_command(args: &[String]) -> Result<Command, SiderError> {
     Ok(Command {
         command_type: CommandType::SADD,
         args: CommandArgs::KeyWithValues {
-            key: args[0].to_string(),
-            values: args.iter().skip(1).cloned().collect::<Vec<String>>(),
+            key: args[0].clone(),
+            values: args.iter().skip(1).cloned().collect::<Vec<Bytes>>(),
         },
     })
 }
 
-pub fn build_smembers_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_smembers_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.is_empty() {
         return Err(SiderError::WrongNumberArgs);
     }
 
     Ok(Command {
         command_type: CommandType::SMEMBERS,
-        args: CommandArgs::SingleKey(args[0].to_string()),
+        args: CommandArgs::SingleKey(args[0].clone()),
     })
 }
 
-pub fn build_srem_command(args: &[String]) -> Result<Command, SiderError> {
+pub fn build_srem_command(args: &[Bytes]) -> Result<Command, SiderError> {
     if args.is_empty() {
         return Err(SiderError::WrongNumberArgs);
     }
@@ -304,8 +426,433 @@ pub fn build_srem_command(args: &[String]) -> Result<Command, SiderError> {
     Ok(Command {
         command_type: CommandType::SREM,
         args: CommandArgs::KeyWithValues {
-            key: args[0].to_string(),
-            values: args.iter().skip(1).cloned().collect::<Vec<String>>(),
+            key: args[0].clone(),
+            values: args.iter().skip(1).cloned().collect::<Vec<Bytes>>(),
+        },
+    })
+}
+
+pub fn build_hello_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    let mut protover = None;
+    let mut auth = None;
+    let mut setname = None;
+
+    let options = match args.split_first() {
+        Some((version, options)) => {
+            protover = Some(parse_number::<i64>(version).ok_or(SiderError::NoProto)?);
+            options
+        }
+        None => args,
+    };
+
+    let mut i = 0;
+    while i < options.len() {
+        match options[i].to_ascii_uppercase().as_slice() {
+            b"AUTH" if options.len() >= i + 3 => {
+                auth = Some((options[i + 1].clone(), options[i + 2].clone()));
+                i += 3;
+            }
+            b"SETNAME" if options.len() >= i + 2 => {
+                setname = Some(options[i + 1].clone());
+                i += 2;
+            }
+            _ => return Err(SiderError::Syntax),
+        }
+    }
+
+    Ok(Command {
+        command_type: CommandType::HELLO,
+        args: CommandArgs::Hello {
+            protover,
+            auth,
+            setname,
+        },
+    })
+}
+
+fn build_transaction_command(
+    args: &[Bytes],
+    cmd_type: CommandType,
+) -> Result<Command, SiderError> {
+    if !args.is_empty() {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: cmd_type,
+        args: CommandArgs::NoArgs,
+    })
+}
+
+pub fn build_multi_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_transaction_command(args, CommandType::MULTI)
+}
+
+pub fn build_exec_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_transaction_command(args, CommandType::EXEC)
+}
+
+pub fn build_discard_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_transaction_command(args, CommandType::DISCARD)
+}
+
+pub fn build_watch_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.is_empty() {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: CommandType::WATCH,
+        args: CommandArgs::MultipleKeys(args.to_vec()),
+    })
+}
+
+pub fn build_unwatch_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_transaction_command(args, CommandType::UNWATCH)
+}
+
+fn build_channels_command(
+    args: &[Bytes],
+    cmd_type: CommandType,
+) -> Result<Command, SiderError> {
+    Ok(Command {
+        command_type: cmd_type,
+        args: CommandArgs::MultipleKeys(args.to_vec()),
+    })
+}
+
+pub fn build_subscribe_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.is_empty() {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    build_channels_command(args, CommandType::SUBSCRIBE)
+}
+
+pub fn build_psubscribe_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.is_empty() {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    build_channels_command(args, CommandType::PSUBSCRIBE)
+}
+
+// Without channels, UNSUBSCRIBE and PUNSUBSCRIBE drop every subscription
+pub fn build_unsubscribe_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_channels_command(args, CommandType::UNSUBSCRIBE)
+}
+
+pub fn build_punsubscribe_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_channels_command(args, CommandType::PUNSUBSCRIBE)
+}
+
+pub fn build_publish_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.len() != 2 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: CommandType::PUBLISH,
+        args: CommandArgs::KeyWithValue {
+            key: args[0].clone(),
+            value: args[1].clone(),
+        },
+    })
+}
+
+pub fn build_pubsub_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.is_empty() {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: CommandType::PUBSUB,
+        args: CommandArgs::KeyWithValues {
+            key: args[0].clone(),
+            values: args[1..].to_vec(),
+        },
+    })
+}
+
+pub fn build_config_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.is_empty() {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: CommandType::CONFIG,
+        args: CommandArgs::KeyWithValues {
+            key: args[0].clone(),
+            values: args[1..].to_vec(),
+        },
+    })
+}
+
+// The sections aren't keys, `Command::keys` knows to skip them
+pub fn build_info_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    Ok(Command {
+        command_type: CommandType::INFO,
+        args: CommandArgs::MultipleKeys(args.to_vec()),
+    })
+}
+
+pub fn build_bgrewriteaof_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if !args.is_empty() {
+        return Err(SiderError::WrongNumberArgs);
+    }
+    Ok(Command {
+        command_type: CommandType::BGREWRITEAOF,
+        args: CommandArgs::NoArgs,
+    })
+}
+
+pub fn build_save_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if !args.is_empty() {
+        return Err(SiderError::WrongNumberArgs);
+    }
+    Ok(Command {
+        command_type: CommandType::SAVE,
+        args: CommandArgs::NoArgs,
+    })
+}
+
+// BGSAVE [SCHEDULE], saves are always scheduled
+pub fn build_bgsave_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    match args {
+        [] => {}
+        [option] if option.eq_ignore_ascii_case(b"SCHEDULE") => {}
+        [_] => return Err(SiderError::Syntax),
+        _ => return Err(SiderError::WrongNumberArgs),
+    }
+    Ok(Command {
+        command_type: CommandType::BGSAVE,
+        args: CommandArgs::NoArgs,
+    })
+}
+
+pub fn build_lastsave_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if !args.is_empty() {
+        return Err(SiderError::WrongNumberArgs);
+    }
+    Ok(Command {
+        command_type: CommandType::LASTSAVE,
+        args: CommandArgs::NoArgs,
+    })
+}
+
+// The subcommand takes the place of the key, like CONFIG
+pub fn build_debug_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.is_empty() {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: CommandType::DEBUG,
+        args: CommandArgs::KeyWithValues {
+            key: args[0].clone(),
+            values: args[1..].to_vec(),
+        },
+    })
+}
+
+// Sorted set and stream commands with a key followed by at least
+// `min_values` values, options are parsed by the handlers
+fn build_key_values_command(
+    args: &[Bytes],
+    cmd_type: CommandType,
+    min_values: usize,
+) -> Result<Command, SiderError> {
+    if args.len() < min_values + 1 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: cmd_type,
+        args: CommandArgs::KeyWithValues {
+            key: args[0].clone(),
+            values: args[1..].to_vec(),
+        },
+    })
+}
+
+// ZSCORE key member, ZRANK key member, ZREVRANK key member
+fn build_zset_member_command(
+    args: &[Bytes],
+    cmd_type: CommandType,
+) -> Result<Command, SiderError> {
+    if args.len() != 2 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: cmd_type,
+        args: CommandArgs::KeyWithValue {
+            key: args[0].clone(),
+            value: args[1].clone(),
+        },
+    })
+}
+
+pub fn build_zadd_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::ZADD, 2)
+}
+
+pub fn build_zrem_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::ZREM, 1)
+}
+
+pub fn build_zscore_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_zset_member_command(args, CommandType::ZSCORE)
+}
+
+pub fn build_zincrby_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.len() != 3 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    build_key_values_command(args, CommandType::ZINCRBY, 2)
+}
+
+pub fn build_zcard_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.len() != 1 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: CommandType::ZCARD,
+        args: CommandArgs::SingleKey(args[0].clone()),
+    })
+}
+
+pub fn build_zrank_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_zset_member_command(args, CommandType::ZRANK)
+}
+
+pub fn build_zrevrank_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_zset_member_command(args, CommandType::ZREVRANK)
+}
+
+pub fn build_zrange_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::ZRANGE, 2)
+}
+
+pub fn build_zrangebyscore_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::ZRANGEBYSCORE, 2)
+}
+
+pub fn build_zcount_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.len() != 3 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    build_key_values_command(args, CommandType::ZCOUNT, 2)
+}
+
+// ZPOPMIN key [count], same arguments as LPOP
+pub fn build_zpopmin_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_lpop_rpop_command(args, CommandType::ZPOPMIN)
+}
+
+pub fn build_zpopmax_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_lpop_rpop_command(args, CommandType::ZPOPMAX)
+}
+
+pub fn build_zremrangebyscore_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.len() != 3 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    build_key_values_command(args, CommandType::ZREMRANGEBYSCORE, 2)
+}
+
+// XREAD and XREADGROUP keep every argument, keys only come after STREAMS
+fn build_stream_read_command(
+    args: &[Bytes],
+    cmd_type: CommandType,
+    min_args: usize,
+) -> Result<Command, SiderError> {
+    if args.len() < min_args {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: cmd_type,
+        args: CommandArgs::MultipleKeys(args.to_vec()),
+    })
+}
+
+pub fn build_xadd_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::XADD, 3)
+}
+
+pub fn build_xlen_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.len() != 1 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: CommandType::XLEN,
+        args: CommandArgs::SingleKey(args[0].clone()),
+    })
+}
+
+pub fn build_xrange_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::XRANGE, 2)
+}
+
+pub fn build_xrevrange_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::XREVRANGE, 2)
+}
+
+// XREAD STREAMS key id
+pub fn build_xread_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_stream_read_command(args, CommandType::XREAD, 3)
+}
+
+// XREADGROUP GROUP group consumer STREAMS key id
+pub fn build_xreadgroup_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_stream_read_command(args, CommandType::XREADGROUP, 6)
+}
+
+pub fn build_xdel_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::XDEL, 1)
+}
+
+pub fn build_xtrim_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::XTRIM, 2)
+}
+
+pub fn build_xsetid_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    if args.len() != 2 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    Ok(Command {
+        command_type: CommandType::XSETID,
+        args: CommandArgs::KeyWithValue {
+            key: args[0].clone(),
+            value: args[1].clone(),
         },
     })
 }
+
+// The subcommand takes the place of the key, like CONFIG
+pub fn build_xgroup_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::XGROUP, 0)
+}
+
+pub fn build_xack_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::XACK, 2)
+}
+
+pub fn build_xpending_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::XPENDING, 1)
+}
+
+pub fn build_xclaim_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::XCLAIM, 4)
+}
+
+pub fn build_xautoclaim_command(args: &[Bytes]) -> Result<Command, SiderError> {
+    build_key_values_command(args, CommandType::XAUTOCLAIM, 4)
+}
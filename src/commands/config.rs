@@ -0,0 +1,149 @@
+use crate::aof::{
+    get_aof_load_truncated, get_appendfsync, set_aof_load_truncated, set_appendfsync,
+    AppendFsync,
+};
+use crate::commands::keys::convert_redis_pattern_to_regex;
+use crate::errors::SiderError;
+use crate::memory::{
+    get_maxmemory, get_maxmemory_policy, get_maxmemory_samples, parse_memory, set_maxmemory,
+    set_maxmemory_policy, set_maxmemory_samples, EvictionPolicy,
+};
+use crate::notify::{
+    format_notify_flags, get_notify_flags, parse_notify_flags, set_notify_flags,
+};
+use crate::response::SiderResponse;
+use crate::rewrite::{
+    get_auto_rewrite_min_size, get_auto_rewrite_percentage, set_auto_rewrite_min_size,
+    set_auto_rewrite_percentage,
+};
+use crate::snapshot::{format_save_rules, get_save_rules, parse_save_rules, set_save_rules};
+use crate::types::{Command, CommandArgs};
+use crate::utils::{format_yes_no, parse_number, parse_yes_no};
+use bytes::Bytes;
+use regex::bytes::Regex;
+
+// Settings that can be read and changed at runtime
+const PARAMETERS: [&str; 9] = [
+    "notify-keyspace-events",
+    "maxmemory",
+    "maxmemory-policy",
+    "maxmemory-samples",
+    "appendfsync",
+    "auto-aof-rewrite-percentage",
+    "auto-aof-rewrite-min-size",
+    "save",
+    "aof-load-truncated",
+];
+
+fn get_parameter(name: &str) -> Option<String> {
+    match name {
+        "notify-keyspace-events" => Some(format_notify_flags(get_notify_flags())),
+        "maxmemory" => Some(get_maxmemory().to_string()),
+        "maxmemory-policy" => Some(get_maxmemory_policy().name().to_string()),
+        "maxmemory-samples" => Some(get_maxmemory_samples().to_string()),
+        "appendfsync" => Some(get_appendfsync().name().to_string()),
+        "auto-aof-rewrite-percentage" => Some(get_auto_rewrite_percentage().to_string()),
+        "auto-aof-rewrite-min-size" => Some(get_auto_rewrite_min_size().to_string()),
+        "save" => Some(format_save_rules(&get_save_rules())),
+        "aof-load-truncated" => Some(format_yes_no(get_aof_load_truncated()).to_string()),
+        _ => None,
+    }
+}
+
+fn set_parameter(name: &str, value: &[u8]) -> Result<(), SiderError> {
+    let invalid = || SiderError::InvalidConfigValue(name.to_string());
+
+    match name {
+        "notify-keyspace-events" => {
+            let flags = parse_notify_flags(value).ok_or_else(invalid)?;
+            set_notify_flags(flags);
+            Ok(())
+        }
+        "maxmemory" => {
+            set_maxmemory(parse_memory(value).ok_or_else(invalid)?);
+            Ok(())
+        }
+        "maxmemory-policy" => {
+            set_maxmemory_policy(EvictionPolicy::parse(value).ok_or_else(invalid)?);
+            Ok(())
+        }
+        "maxmemory-samples" => {
+            let samples = parse_number::<usize>(value)
+                .filter(|samples| *samples > 0)
+                .ok_or_else(invalid)?;
+            set_maxmemory_samples(samples);
+            Ok(())
+        }
+        "appendfsync" => {
+            set_appendfsync(AppendFsync::parse(value).ok_or_else(invalid)?);
+            Ok(())
+        }
+        "auto-aof-rewrite-percentage" => {
+            set_auto_rewrite_percentage(parse_number::<u64>(value).ok_or_else(invalid)?);
+            Ok(())
+        }
+        "auto-aof-rewrite-min-size" => {
+            set_auto_rewrite_min_size(parse_memory(value).ok_or_else(invalid)?);
+            Ok(())
+        }
+        "save" => {
+            set_save_rules(parse_save_rules(value).ok_or_else(invalid)?);
+            Ok(())
+        }
+        "aof-load-truncated" => {
+            set_aof_load_truncated(parse_yes_no(value).ok_or_else(invalid)?);
+            Ok(())
+        }
+        _ => Err(SiderError::UnknownConfig(name.to_string())),
+    }
+}
+
+// CONFIG GET pattern [pattern ...] and CONFIG SET parameter value [parameter value ...]
+pub fn config(command: Command) -> Result<SiderResponse, SiderError> {
+    let (subcommand, args) = match &command.args {
+        CommandArgs::KeyWithValues { key, values } => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    match subcommand.to_ascii_uppercase().as_slice() {
+        b"GET" if !args.is_empty() => config_get(args),
+        b"SET" if !args.is_empty() && args.len() % 2 == 0 => config_set(args),
+        b"GET" | b"SET" => Err(SiderError::WrongNumberArgs),
+        _ => Err(SiderError::UnknownSubcommand),
+    }
+}
+
+fn config_get(patterns: &[Bytes]) -> Result<SiderResponse, SiderError> {
+    let mut regexes = vec![];
+
+    for pattern in patterns {
+        let pattern = pattern.to_ascii_lowercase();
+        let regex = Regex::new(&convert_redis_pattern_to_regex(&pattern))
+            .map_err(|_| SiderError::RegexError)?;
+        regexes.push(regex);
+    }
+
+    let pairs = PARAMETERS
+        .iter()
+        .filter(|name| regexes.iter().any(|regex| regex.is_match(name.as_bytes())))
+        .filter_map(|name| {
+            get_parameter(name).map(|value| {
+                (
+                    SiderResponse::BulkString(Bytes::from_static(name.as_bytes())),
+                    SiderResponse::BulkString(Bytes::from(value)),
+                )
+            })
+        })
+        .collect();
+
+    Ok(SiderResponse::Map(pairs))
+}
+
+fn config_set(args: &[Bytes]) -> Result<SiderResponse, SiderError> {
+    for pair in args.chunks(2) {
+        let name = String::from_utf8_lossy(&pair[0]).to_lowercase();
+        set_parameter(&name, &pair[1])?;
+    }
+
+    Ok(SiderResponse::Ok)
+}
@@ -1,10 +1,13 @@
-use crate::aof::delete_aof_file;
 use crate::errors::SiderError;
+use crate::notify::{notify_keyspace_event, NOTIFY_GENERIC};
 use crate::response::SiderResponse;
-use crate::types::Db;
+use crate::types::Keyspace;
 
-pub async fn flush_db(db: &Db) -> Result<SiderResponse, SiderError> {
-    db.write().await.clear();
-    delete_aof_file().await;
+pub fn flush_db(db: &mut Keyspace) -> Result<SiderResponse, SiderError> {
+    for key in db.keys() {
+        notify_keyspace_event(NOTIFY_GENERIC, "del", key);
+    }
+
+    db.clear();
     Ok(SiderResponse::Ok)
 }
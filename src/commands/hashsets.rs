@@ -1,37 +1,33 @@
 use crate::errors::SiderError;
+use crate::notify::{notify_keyspace_event, NOTIFY_GENERIC, NOTIFY_HASH};
 use crate::response::SiderResponse;
 use crate::types::Command;
 use crate::types::CommandArgs;
-use crate::types::Db;
 use crate::types::DbValue;
 use crate::types::KeyHash;
+use crate::types::Keyspace;
 
-pub async fn hset(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn hset(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let (key_name, key_values) = match &command.args {
-        CommandArgs::HashFields { key, fields } => (key.to_string(), fields),
+        CommandArgs::HashFields { key, fields } => (key.clone(), fields),
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let key = {
-        let db_read = db.read().await;
-        db_read.get(&key_name).cloned()
-    };
-
-    let mut db_write = db.write().await;
+    let key = db.get(&key_name).cloned();
 
     let nb = match key {
         Some(DbValue::HashKey(mut k)) => {
             let before_len = k.data.len();
             k.data.extend(key_values.clone());
             let after_len = k.data.len();
-            db_write.insert(key_name, DbValue::HashKey(k));
+            db.insert(key_name.clone(), DbValue::HashKey(k));
             after_len - before_len
         }
         None => {
-            db_write.insert(
+            db.insert(
                 key_name.clone(),
                 DbValue::HashKey(KeyHash {
-                    name: key_name,
+                    name: key_name.clone(),
                     data: key_values.clone(),
                     ..Default::default()
                 }),
@@ -41,57 +37,58 @@ pub async fn hset(db: &Db, command: Command) -> Result<SiderResponse, SiderError
         Some(_) => return Err(SiderError::WrongType),
     };
 
-    Ok(SiderResponse::Int(nb as i64))
+    notify_keyspace_event(NOTIFY_HASH, "hset", &key_name);
+    Ok(SiderResponse::Integer(nb as i64))
 }
 
-pub async fn hget(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn hget(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let (hash_name, field_name) = match &command.args {
         CommandArgs::KeyWithValue { key, value } => (key, value),
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let db_read = db.read().await;
-
-    match db_read.get(hash_name) {
+    match db.get(hash_name) {
         Some(DbValue::HashKey(hash)) => match hash.data.get(field_name) {
-            Some(value) => Ok(SiderResponse::SimpleString(value.to_string())),
-            None => Ok(SiderResponse::Nil),
+            Some(value) => Ok(SiderResponse::BulkString(value.clone())),
+            None => Ok(SiderResponse::NullBulk),
         },
-        None => Ok(SiderResponse::Nil),
+        None => Ok(SiderResponse::NullBulk),
         Some(_) => Err(SiderError::WrongType),
     }
 }
 
-pub async fn hgetall(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn hgetall(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let key_name = match &command.args {
         CommandArgs::SingleKey(key_name) => key_name,
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let db_read = db.read().await;
-
-    let results = match db_read.get(key_name) {
+    // A map in RESP3, a flat field/value array in RESP2
+    let results = match db.get(key_name) {
         Some(DbValue::HashKey(key)) => key
             .data
             .iter()
-            .flat_map(|(k, v)| vec![k.clone(), v.clone()])
-            .collect::<Vec<String>>(),
+            .map(|(k, v)| {
+                (
+                    SiderResponse::BulkString(k.clone()),
+                    SiderResponse::BulkString(v.clone()),
+                )
+            })
+            .collect(),
         Some(_) => return Err(SiderError::WrongType),
-        None => return Ok(SiderResponse::EmptyArray),
+        None => vec![],
     };
 
-    Ok(SiderResponse::List(results))
+    Ok(SiderResponse::Map(results))
 }
 
-pub async fn hdel(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn hdel(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let (key_name, fields) = match &command.args {
         CommandArgs::KeyWithValues { key, values } => (key.clone(), values.clone()),
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let mut db_write = db.write().await;
-
-    match db_write.get_mut(&key_name) {
+    match db.get_mut(&key_name) {
         Some(DbValue::HashKey(hash)) => {
             let mut deleted_count = 0;
             for field in fields {
@@ -100,12 +97,20 @@ pub async fn hdel(db: &Db, command: Command) -> Result<SiderResponse, SiderError
                 }
             }
 
-            if hash.data.is_empty() {
-                db_write.swap_remove(&key_name);
+            let is_empty = hash.data.is_empty();
+
+            if deleted_count > 0 {
+                notify_keyspace_event(NOTIFY_HASH, "hdel", &key_name);
+                db.signal_modified_key(&key_name);
+            }
+
+            if is_empty {
+                db.swap_remove(&key_name);
+                notify_keyspace_event(NOTIFY_GENERIC, "del", &key_name);
             }
-            Ok(SiderResponse::Int(deleted_count))
+            Ok(SiderResponse::Integer(deleted_count))
         }
         Some(_) => Err(SiderError::WrongType),
-        None => Ok(SiderResponse::Int(0)),
+        None => Ok(SiderResponse::Integer(0)),
     }
 }
@@ -0,0 +1,167 @@
+use crate::aof::{aof_write_error, get_aof_base_size, get_aof_current_size};
+use crate::database::loading_progress;
+use crate::errors::SiderError;
+use crate::memory::{format_memory, get_maxmemory, get_maxmemory_policy};
+use crate::response::SiderResponse;
+use crate::rewrite::{is_rewrite_in_progress, is_rewrite_scheduled, last_rewrite_ok, rewrites};
+use crate::snapshot::{
+    changes_since_last_save, get_last_save, is_save_in_progress, last_save_ok,
+};
+use crate::types::{Command, CommandArgs, Keyspace};
+use crate::utils::current_time_millis;
+use bytes::Bytes;
+
+// Sections in the order they are listed when none is asked for
+const SECTIONS: [&str; 5] = ["server", "memory", "persistence", "stats", "keyspace"];
+
+fn section_fields(db: &Keyspace, section: &str) -> Vec<(&'static str, String)> {
+    match section {
+        "server" => vec![
+            ("sider_version", env!("CARGO_PKG_VERSION").to_string()),
+            ("process_id", std::process::id().to_string()),
+        ],
+        "memory" => {
+            let (used, max) = (db.used_memory() as u64, get_maxmemory());
+            vec![
+                ("used_memory", used.to_string()),
+                ("used_memory_human", format_memory(used)),
+                ("maxmemory", max.to_string()),
+                ("maxmemory_human", format_memory(max)),
+                (
+                    "maxmemory_policy",
+                    get_maxmemory_policy().name().to_string(),
+                ),
+            ]
+        }
+        "persistence" => [
+            loading_fields(),
+            vec![
+                (
+                    "rdb_changes_since_last_save",
+                    changes_since_last_save().to_string(),
+                ),
+                ("rdb_bgsave_in_progress", flag(is_save_in_progress())),
+                ("rdb_last_save_time", get_last_save().to_string()),
+                (
+                    "rdb_last_bgsave_status",
+                    if last_save_ok() { "ok" } else { "err" }.to_string(),
+                ),
+                ("aof_enabled", "1".to_string()),
+                ("aof_rewrite_in_progress", flag(is_rewrite_in_progress())),
+                ("aof_rewrite_scheduled", flag(is_rewrite_scheduled())),
+                ("aof_rewrites", rewrites().to_string()),
+                (
+                    "aof_last_bgrewrite_status",
+                    if last_rewrite_ok() { "ok" } else { "err" }.to_string(),
+                ),
+                (
+                    "aof_last_write_status",
+                    if aof_write_error().is_none() {
+                        "ok"
+                    } else {
+                        "err"
+                    }
+                    .to_string(),
+                ),
+                ("aof_current_size", get_aof_current_size().to_string()),
+                ("aof_base_size", get_aof_base_size().to_string()),
+            ],
+        ]
+        .concat(),
+        "stats" => {
+            let stats = db.expire_stats();
+            vec![
+                ("expired_keys", stats.expired_keys.to_string()),
+                (
+                    "expired_stale_perc",
+                    format!("{:.2}", stats.expired_stale_perc),
+                ),
+                (
+                    "expired_time_cap_reached_count",
+                    stats.expired_time_cap_reached_count.to_string(),
+                ),
+                (
+                    "expire_cycle_cpu_milliseconds",
+                    stats.expire_cycle_time.as_millis().to_string(),
+                ),
+                ("evicted_keys", db.evicted_keys().to_string()),
+            ]
+        }
+        // Only listed when not empty, like Redis does
+        "keyspace" if !db.is_empty() => vec![(
+            "db0",
+            format!("keys={},expires={}", db.len(), db.volatile_len()),
+        )],
+        _ => Vec::new(),
+    }
+}
+
+// The progress is only listed while loading, like Redis does
+fn loading_fields() -> Vec<(&'static str, String)> {
+    let Some(progress) = loading_progress() else {
+        return vec![("loading", flag(false))];
+    };
+
+    vec![
+        ("loading", flag(true)),
+        ("loading_start_time", (progress.start / 1000).to_string()),
+        ("loading_total_bytes", progress.total_bytes.to_string()),
+        ("loading_loaded_bytes", progress.loaded_bytes.to_string()),
+        (
+            "loading_loaded_perc",
+            format!("{:.2}", progress.loaded_percent()),
+        ),
+        (
+            "loading_eta_seconds",
+            progress.eta_seconds(current_time_millis()).to_string(),
+        ),
+    ]
+}
+
+fn flag(value: bool) -> String {
+    (value as u8).to_string()
+}
+
+// INFO [section [section ...]]
+pub fn info(db: &Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let args = match command.args {
+        CommandArgs::MultipleKeys(args) => args,
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let requested: Vec<String> = args
+        .iter()
+        .map(|arg| String::from_utf8_lossy(arg).to_ascii_lowercase())
+        .collect();
+    let everything = requested.is_empty()
+        || requested
+            .iter()
+            .any(|section| matches!(section.as_str(), "all" | "default" | "everything"));
+
+    let mut text = String::new();
+
+    for section in SECTIONS {
+        if !everything && !requested.iter().any(|requested| requested == section) {
+            continue;
+        }
+
+        if !text.is_empty() {
+            text.push_str("\r\n");
+        }
+
+        // "# Stats"
+        text.push_str("# ");
+        text.push_str(&section[..1].to_ascii_uppercase());
+        text.push_str(&section[1..]);
+        text.push_str("\r\n");
+
+        for (field, value) in section_fields(db, section) {
+            text.push_str(&format!("{field}:{value}\r\n"));
+        }
+    }
+
+    Ok(SiderResponse::Verbatim {
+        format: "txt".to_string(),
+        text: Bytes::from(text),
+    })
+}
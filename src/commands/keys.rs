@@ -1,68 +1,166 @@
 use crate::errors::SiderError;
+use crate::notify::{notify_keyspace_event, NOTIFY_GENERIC, NOTIFY_STRING};
 use crate::response::SiderResponse;
-use crate::types::{Command, CommandArgs, Db, DbValue, Key};
-use regex::Regex;
+use crate::types::{Command, CommandArgs, CommandType, DbValue, Key, Keyspace};
+use crate::utils::{current_time_millis, parse_number};
+use bytes::Bytes;
+use regex::bytes::Regex;
 
-pub async fn get_key(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn get_key(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let key_name = match &command.args {
-        CommandArgs::SingleKey(key) => &key.clone(),
+        CommandArgs::SingleKey(key) => key,
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let key = {
-        let db_read = db.read().await;
-        db_read.get(key_name).cloned() // Clone key to release lock
-    };
+    if delete_expired_key(db, key_name) {
+        return Ok(SiderResponse::NullBulk);
+    }
 
-    let key = match key {
-        Some(DbValue::StringKey(k)) => k,
-        None => return Ok(SiderResponse::Nil),
-        Some(_) => return Err(SiderError::WrongType),
-    };
+    match db.get(key_name) {
+        Some(DbValue::StringKey(Key {
+            data: Some(value), ..
+        })) => Ok(SiderResponse::BulkString(value.clone())),
+        Some(DbValue::StringKey(_)) | None => Ok(SiderResponse::NullBulk),
+        Some(_) => Err(SiderError::WrongType),
+    }
+}
 
-    if let Some(value) = &key.data {
-        let deleted = delete_expired_key(db, key.clone()).await; // No read lock at this point
+#[derive(Default)]
+struct SetOptions {
+    nx: bool,
+    xx: bool,
+    get: bool,
+    keep_ttl: bool,
+    // Unix time in milliseconds
+    expires_at: Option<i64>,
+}
 
-        if !deleted {
-            return Ok(SiderResponse::SimpleString(value.to_string()));
+fn parse_set_options(args: &[Bytes]) -> Result<SetOptions, SiderError> {
+    let mut options = SetOptions::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let option = arg.to_ascii_uppercase();
+        let has_expiry = options.keep_ttl || options.expires_at.is_some();
+
+        match option.as_slice() {
+            b"NX" if !options.xx => options.nx = true,
+            b"XX" if !options.nx => options.xx = true,
+            b"GET" => options.get = true,
+            b"KEEPTTL" if !has_expiry => options.keep_ttl = true,
+            b"EX" | b"PX" | b"EXAT" | b"PXAT" if !has_expiry => {
+                let value = args.next().ok_or(SiderError::Syntax)?;
+                let value = parse_number::<i64>(value).ok_or(SiderError::NotInt)?;
+                let expires_at =
+                    set_expiry(&option, value).ok_or(SiderError::InvalidExpireTime("set"))?;
+                options.expires_at = Some(expires_at);
+            }
+            _ => return Err(SiderError::Syntax),
         }
     }
 
-    Ok(SiderResponse::Nil)
+    Ok(options)
 }
 
-pub async fn set_key(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
-    let (key_name, value) = match command.args {
-        CommandArgs::KeyWithValue { key, value } => (key, value),
+// Unix time in milliseconds the key expires at, `None` if not positive or
+// out of range
+fn set_expiry(option: &[u8], value: i64) -> Option<i64> {
+    if value <= 0 {
+        return None;
+    }
+
+    match option {
+        b"EX" => value.checked_mul(1000)?.checked_add(current_time_millis()),
+        b"PX" => value.checked_add(current_time_millis()),
+        b"EXAT" => value.checked_mul(1000),
+        _ => Some(value),
+    }
+}
+
+pub fn set_key(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, value, options) = match command.args {
+        CommandArgs::KeyWithValue { key, value } => (key, value, SetOptions::default()),
+        CommandArgs::KeyWithValues { key, values } => {
+            let Some((value, args)) = values.split_first() else {
+                return Err(SiderError::WrongNumberArgs);
+            };
+            (key, value.clone(), parse_set_options(args)?)
+        }
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let key = Key::new(key_name.clone(), Some(value.clone()), None);
+    delete_expired_key(db, &key_name);
+
+    let current = db.get(&key_name);
+    let old_value = match current {
+        Some(DbValue::StringKey(key)) => key.data.clone(),
+        // GET only works on strings, SET alone overwrites any type
+        Some(_) if options.get => return Err(SiderError::WrongType),
+        _ => None,
+    };
+
+    let reply = match old_value {
+        Some(old_value) if options.get => SiderResponse::BulkString(old_value),
+        _ if options.get => SiderResponse::NullBulk,
+        _ => SiderResponse::Ok,
+    };
+
+    let exists = current.is_some();
+    if (options.nx && exists) || (options.xx && !exists) {
+        // Nothing changed, nothing to log
+        db.propagate_as(Vec::new());
+        return Ok(match options.get {
+            true => reply,
+            false => SiderResponse::NullBulk,
+        });
+    }
+
+    let expires_at = match options.keep_ttl {
+        true => current.and_then(|current| current.expires_at()),
+        false => options.expires_at,
+    };
 
-    db.write()
-        .await
-        .insert(key.name.clone(), DbValue::StringKey(key));
+    let key = Key::new(key_name.clone(), Some(value.clone()), expires_at);
+    db.insert(key_name.clone(), DbValue::StringKey(key));
+    notify_keyspace_event(NOTIFY_STRING, "set", &key_name);
+
+    // Logged with the absolute expiry, replaying the AOF later must not
+    // extend the TTL
+    if let Some(expires_at) = options.expires_at {
+        notify_keyspace_event(NOTIFY_GENERIC, "expire", &key_name);
+        db.propagate_as(vec![Command {
+            command_type: CommandType::SET,
+            args: CommandArgs::KeyWithValues {
+                key: key_name,
+                values: vec![
+                    value,
+                    Bytes::from_static(b"PXAT"),
+                    Bytes::from(expires_at.to_string()),
+                ],
+            },
+        }]);
+    }
 
-    Ok(SiderResponse::Ok)
+    Ok(reply)
 }
 
-pub async fn delete_key(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn delete_key(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let keys = match &command.args {
         CommandArgs::SingleKey(key) => vec![key.clone()],
         CommandArgs::MultipleKeys(keys) => keys.to_vec(),
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let mut db_write = db.write().await;
     let mut deleted_count = 0;
 
     for key in keys {
-        if db_write.swap_remove(&key).is_some() {
+        if db.swap_remove(&key).is_some() {
+            notify_keyspace_event(NOTIFY_GENERIC, "del", &key);
             deleted_count += 1;
         }
     }
 
-    Ok(SiderResponse::Int(deleted_count))
+    Ok(SiderResponse::Integer(deleted_count))
 }
 
 // Increases the numeric value stored at the key by one.
@@ -70,8 +168,8 @@ pub async fn delete_key(db: &Db, command: Command) -> Result<SiderResponse, Side
 // applying the operation. Returns an error if the key holds
 // a non-numeric value or a string that cannot be interpreted
 // as an integer.
-pub async fn incr(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
-    incr_decr(db, command, true).await
+pub fn incr(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    incr_decr(db, command, true)
 }
 
 // Decrements the number stored at key by one.
@@ -80,8 +178,8 @@ pub async fn incr(db: &Db, command: Command) -> Result<SiderResponse, SiderError
 // of the wrong type or contains a string that can not be
 // represented as integer.
 // This operation is limited to 64 bit signed integers.
-pub async fn decr(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
-    incr_decr(db, command, false).await
+pub fn decr(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    incr_decr(db, command, false)
 }
 
 // Increases the number stored at the given key by the specified
@@ -89,31 +187,29 @@ pub async fn decr(db: &Db, command: Command) -> Result<SiderResponse, SiderError
 // to 0 before applying the operation. Returns an error
 // if the key holds a non-numeric value or a string that cannot
 // be parsed as a 64-bit signed integer.
-pub async fn incrby(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn incrby(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let (key_name, by_str) = match &command.args {
         CommandArgs::KeyWithValue { key, value } => (key.clone(), value.clone()),
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let by = match by_str.parse::<i64>() {
-        Ok(num) => num,
-        Err(_) => return Err(SiderError::NotInt),
+    let by = match parse_number::<i64>(&by_str) {
+        Some(num) => num,
+        None => return Err(SiderError::NotInt),
     };
 
-    let mut db_write = db.write().await;
-
-    let key = match db_write.get_mut(&key_name) {
+    let key = match db.get_mut(&key_name) {
         Some(DbValue::StringKey(existing_key)) => existing_key,
         None => {
-            db_write.insert(
+            db.insert(
                 key_name.clone(),
                 DbValue::StringKey(Key {
                     name: key_name.clone(),
-                    data: Some("0".to_string()),
+                    data: Some(Bytes::from_static(b"0")),
                     ..Default::default()
                 }),
             );
-            match db_write.get_mut(&key_name) {
+            match db.get_mut(&key_name) {
                 Some(DbValue::StringKey(new_key)) => new_key,
                 _ => return Err(SiderError::DatabaseError),
             }
@@ -121,33 +217,37 @@ pub async fn incrby(db: &Db, command: Command) -> Result<SiderResponse, SiderErr
         Some(_) => return Err(SiderError::WrongType),
     };
 
-    let num_str = key.data.as_deref().unwrap_or("0");
+    let num_str = key.data.as_deref().unwrap_or(b"0");
 
-    let num = match num_str.parse::<i64>() {
-        Ok(n) => n,
-        Err(_) => return Err(SiderError::NotInt),
+    let num = match parse_number::<i64>(num_str) {
+        Some(n) => n,
+        None => return Err(SiderError::NotInt),
     };
 
-    let new_value = num + by;
-    key.data = Some(new_value.to_string());
+    let new_value = num.checked_add(by).ok_or(SiderError::Overflow)?;
+    key.data = Some(Bytes::from(new_value.to_string()));
+    notify_keyspace_event(NOTIFY_STRING, "incrby", &key_name);
+    db.signal_modified_key(&key_name);
 
-    Ok(SiderResponse::Int(new_value))
+    Ok(SiderResponse::Integer(new_value))
 }
 
-async fn incr_decr(db: &Db, command: Command, inc: bool) -> Result<SiderResponse, SiderError> {
+fn incr_decr(
+    db: &mut Keyspace,
+    command: Command,
+    inc: bool,
+) -> Result<SiderResponse, SiderError> {
     let key_name = match command.args {
         CommandArgs::SingleKey(key) => key,
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let mut db_write = db.write().await;
-
-    let key = match db_write.get_mut(&key_name) {
+    let key = match db.get_mut(&key_name) {
         Some(DbValue::StringKey(key)) => key,
         None => {
-            let key = Key::new(key_name.clone(), Some("0".to_string()), None);
-            db_write.insert(key_name.clone(), DbValue::StringKey(key));
-            match db_write.get_mut(&key_name) {
+            let key = Key::new(key_name.clone(), Some(Bytes::from_static(b"0")), None);
+            db.insert(key_name.clone(), DbValue::StringKey(key));
+            match db.get_mut(&key_name) {
                 Some(DbValue::StringKey(key)) => key,
                 Some(DbValue::ListKey(_)) => return Err(SiderError::WrongType),
                 _ => return Err(SiderError::DatabaseError),
@@ -156,19 +256,26 @@ async fn incr_decr(db: &Db, command: Command, inc: bool) -> Result<SiderResponse
         Some(_) => return Err(SiderError::WrongType),
     };
 
-    let Ok(num) = key.data.as_deref().unwrap_or("0").parse::<i64>() else {
+    let Some(num) = parse_number::<i64>(key.data.as_deref().unwrap_or(b"0")) else {
         return Err(SiderError::NotInt);
     };
 
-    let new_value = if inc { num + 1 } else { num - 1 };
+    let new_value = if inc {
+        num.checked_add(1)
+    } else {
+        num.checked_sub(1)
+    }
+    .ok_or(SiderError::Overflow)?;
 
-    key.data = Some(new_value.to_string());
+    key.data = Some(Bytes::from(new_value.to_string()));
+    notify_keyspace_event(NOTIFY_STRING, "incrby", &key_name);
+    db.signal_modified_key(&key_name);
 
-    Ok(SiderResponse::Int(new_value))
+    Ok(SiderResponse::Integer(new_value))
 }
 
 /// Returns keys matching the Redis-style pattern
-pub async fn get_keys(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn get_keys(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let pattern = match &command.args {
         CommandArgs::SingleKey(key) => key,
         _ => return Err(SiderError::InvalidCommand),
@@ -183,80 +290,238 @@ pub async fn get_keys(db: &Db, command: Command) -> Result<SiderResponse, SiderE
 
     let mut results = vec![];
 
-    let db_read = db.read().await;
-
-    for key in db_read.keys() {
-        if re.is_match(key) {
+    // Expired keys are left for the active expiry to delete
+    for (key, value) in db.iter() {
+        if re.is_match(key) && !value.is_expired() {
             results.push(key.clone());
         }
     }
 
-    if results.is_empty() {
-        return Ok(SiderResponse::EmptyArray);
-    }
-
-    Ok(SiderResponse::List(results))
+    Ok(SiderResponse::bulk_strings(results))
 }
 
-pub async fn exists(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn exists(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let keys = match &command.args {
-        CommandArgs::SingleKey(key) => vec![key.to_string()],
+        CommandArgs::SingleKey(key) => vec![key.clone()],
         CommandArgs::MultipleKeys(keys) => keys.to_vec(),
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let db_read = db.read().await;
-    let nb_keys = keys.iter().filter(|key| db_read.contains_key(*key)).count() as i64;
+    let nb_keys = keys.iter().filter(|key| db.contains_key(*key)).count() as i64;
+
+    Ok(SiderResponse::Integer(nb_keys))
+}
+
+#[derive(Clone, Copy)]
+enum TimeUnit {
+    Seconds,
+    Milliseconds,
+}
+
+#[derive(Default)]
+struct ExpireFlags {
+    nx: bool,
+    xx: bool,
+    gt: bool,
+    lt: bool,
+}
+
+fn parse_expire_flags(args: &[Bytes]) -> Result<ExpireFlags, SiderError> {
+    let mut flags = ExpireFlags::default();
+
+    for arg in args {
+        match arg.to_ascii_uppercase().as_slice() {
+            b"NX" => flags.nx = true,
+            b"XX" => flags.xx = true,
+            b"GT" => flags.gt = true,
+            b"LT" => flags.lt = true,
+            _ => {
+                return Err(SiderError::UnsupportedOption(
+                    String::from_utf8_lossy(arg).to_string(),
+                ))
+            }
+        }
+    }
+
+    if flags.nx && (flags.xx || flags.gt || flags.lt) {
+        return Err(SiderError::ExpireNXAndOthers);
+    }
+
+    if flags.gt && flags.lt {
+        return Err(SiderError::ExpireGTAndLT);
+    }
 
-    Ok(SiderResponse::Int(nb_keys))
+    Ok(flags)
 }
 
-pub async fn expire(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
-    let (key_name, ttl) = match command.args {
-        CommandArgs::KeyWithValue { key, value } => (key, value),
+// Sets the expiry of a key of any type. Returns 1 if it was set, 0 if the
+// key doesn't exist or the flags prevented it. A time in the past deletes
+// the key.
+fn expire_generic(
+    db: &mut Keyspace,
+    command: Command,
+    unit: TimeUnit,
+    absolute: bool,
+    name: &'static str,
+) -> Result<SiderResponse, SiderError> {
+    let (key_name, time, flags) = match command.args {
+        CommandArgs::KeyWithValue { key, value } => (key, value, ExpireFlags::default()),
+        CommandArgs::KeyWithValues { key, values } => {
+            let Some((time, args)) = values.split_first() else {
+                return Err(SiderError::WrongNumberArgs);
+            };
+            (key, time.clone(), parse_expire_flags(args)?)
+        }
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let ttl = ttl.parse::<i64>().map_err(|_| SiderError::TTL)?;
+    let time = parse_number::<i64>(&time).ok_or(SiderError::NotInt)?;
+    let millis = match unit {
+        TimeUnit::Seconds => time.checked_mul(1000),
+        TimeUnit::Milliseconds => Some(time),
+    };
+    let expires_at = match absolute {
+        true => millis,
+        false => millis.and_then(|millis| millis.checked_add(current_time_millis())),
+    }
+    .ok_or(SiderError::InvalidExpireTime(name))?;
 
-    let mut db_write = db.write().await;
+    delete_expired_key(db, &key_name);
 
-    match db_write.get_mut(&key_name) {
-        Some(DbValue::StringKey(key)) => {
-            key.set_ttl(ttl);
-            Ok(SiderResponse::Int(1))
-        }
-        Some(DbValue::ListKey(key)) => {
-            key.set_ttl(ttl);
-            Ok(SiderResponse::Int(1))
-        }
-        Some(DbValue::SetKey(key)) => {
-            key.set_ttl(ttl);
-            Ok(SiderResponse::Int(1))
-        }
-        Some(DbValue::HashKey(key)) => {
-            key.set_ttl(ttl);
-            Ok(SiderResponse::Int(1))
+    let Some(value) = db.get(&key_name) else {
+        return Ok(SiderResponse::Integer(0));
+    };
+
+    // A key without expiry has an infinite TTL for GT and LT
+    let allowed = match value.expires_at() {
+        Some(current) => {
+            !flags.nx
+                && (!flags.gt || expires_at > current)
+                && (!flags.lt || expires_at < current)
         }
-        None => Ok(SiderResponse::Int(0)),
+        None => !flags.xx && !flags.gt,
+    };
+
+    if !allowed {
+        db.propagate_as(Vec::new());
+        return Ok(SiderResponse::Integer(0));
     }
+
+    if expires_at <= current_time_millis() {
+        db.swap_remove(&key_name);
+        notify_keyspace_event(NOTIFY_GENERIC, "del", &key_name);
+        db.propagate_as(vec![Command {
+            command_type: CommandType::DEL,
+            args: CommandArgs::SingleKey(key_name),
+        }]);
+        return Ok(SiderResponse::Integer(1));
+    }
+
+    db.set_expires_at(&key_name, Some(expires_at));
+    notify_keyspace_event(NOTIFY_GENERIC, "expire", &key_name);
+
+    // Logged with the absolute time, replaying the AOF later must not
+    // extend the TTL
+    db.propagate_as(vec![Command {
+        command_type: CommandType::PEXPIREAT,
+        args: CommandArgs::KeyWithValue {
+            key: key_name,
+            value: Bytes::from(expires_at.to_string()),
+        },
+    }]);
+
+    Ok(SiderResponse::Integer(1))
+}
+
+pub fn expire(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    expire_generic(db, command, TimeUnit::Seconds, false, "expire")
 }
 
-pub async fn ttl(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn pexpire(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    expire_generic(db, command, TimeUnit::Milliseconds, false, "pexpire")
+}
+
+pub fn expireat(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    expire_generic(db, command, TimeUnit::Seconds, true, "expireat")
+}
+
+pub fn pexpireat(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    expire_generic(db, command, TimeUnit::Milliseconds, true, "pexpireat")
+}
+
+// Time left to live, or the Unix time the key expires at if `absolute`.
+// -2 if the key doesn't exist, -1 if it has no expiry.
+fn ttl_generic(
+    db: &mut Keyspace,
+    command: Command,
+    unit: TimeUnit,
+    absolute: bool,
+) -> Result<SiderResponse, SiderError> {
     let key_name = match command.args {
         CommandArgs::SingleKey(key) => key,
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let db_read = db.read().await;
+    delete_expired_key(db, &key_name);
 
-    let key = match db_read.get(&key_name) {
-        Some(DbValue::StringKey(key)) => key,
-        None => return Ok(SiderResponse::Int(-2)),
-        Some(_) => return Err(SiderError::WrongType),
+    let Some(value) = db.get(&key_name) else {
+        return Ok(SiderResponse::Integer(-2));
+    };
+    let Some(expires_at) = value.expires_at() else {
+        return Ok(SiderResponse::Integer(-1));
     };
 
-    Ok(SiderResponse::Int(key.get_ttl()))
+    let millis = match absolute {
+        true => expires_at,
+        false => (expires_at - current_time_millis()).max(0),
+    };
+
+    Ok(SiderResponse::Integer(match (unit, absolute) {
+        (TimeUnit::Milliseconds, _) => millis,
+        (TimeUnit::Seconds, true) => millis / 1000,
+        // Rounded to the nearest second like Redis does
+        (TimeUnit::Seconds, false) => (millis + 500) / 1000,
+    }))
+}
+
+pub fn ttl(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    ttl_generic(db, command, TimeUnit::Seconds, false)
+}
+
+pub fn pttl(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    ttl_generic(db, command, TimeUnit::Milliseconds, false)
+}
+
+pub fn expiretime(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    ttl_generic(db, command, TimeUnit::Seconds, true)
+}
+
+pub fn pexpiretime(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    ttl_generic(db, command, TimeUnit::Milliseconds, true)
+}
+
+/// Removes the expiry of the key. Returns 1 if it had one.
+pub fn persist(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let key_name = match command.args {
+        CommandArgs::SingleKey(key) => key,
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    delete_expired_key(db, &key_name);
+
+    if db
+        .get(&key_name)
+        .and_then(|value| value.expires_at())
+        .is_none()
+    {
+        db.propagate_as(Vec::new());
+        return Ok(SiderResponse::Integer(0));
+    }
+
+    db.set_expires_at(&key_name, None);
+
+    notify_keyspace_event(NOTIFY_GENERIC, "persist", &key_name);
+    Ok(SiderResponse::Integer(1))
 }
 
 /// Converts Redis-style glob pattern into a valid regex pattern
@@ -264,16 +529,34 @@ pub async fn ttl(db: &Db, command: Command) -> Result<SiderResponse, SiderError>
 // '?' becomes '.'
 // '[' stays '[' (range starts)
 // ']' stays ']' (range ends)
-pub fn convert_redis_pattern_to_regex(pattern: &str) -> String {
-    let mut regex_pattern = String::from("^");
-
-    for c in pattern.chars() {
+// '\x' matches x literally
+// The regex works on raw bytes (`(?s-u)`) so it can be used with
+// `regex::bytes::Regex` against binary keys.
+pub fn convert_redis_pattern_to_regex(pattern: &[u8]) -> String {
+    let mut regex_pattern = String::from("(?s-u)^");
+    let mut escaped = false;
+    let mut in_range = false;
+
+    for &c in pattern {
         match c {
-            '*' => regex_pattern.push_str(".*"),
-            '?' => regex_pattern.push('.'),
-            '[' => regex_pattern.push('['),
-            ']' => regex_pattern.push(']'),
-            _ => regex_pattern.push_str(&regex::escape(&c.to_string())), // Escape other chars
+            _ if escaped => {
+                push_escaped_byte(&mut regex_pattern, c);
+                escaped = false;
+            }
+            b'\\' => escaped = true,
+            b'[' if !in_range => {
+                regex_pattern.push('[');
+                in_range = true;
+            }
+            b']' if in_range => {
+                regex_pattern.push(']');
+                in_range = false;
+            }
+            // Range and negation markers keep their meaning inside brackets
+            b'-' | b'^' if in_range => regex_pattern.push(c as char),
+            b'*' if !in_range => regex_pattern.push_str(".*"),
+            b'?' if !in_range => regex_pattern.push('.'),
+            _ => push_escaped_byte(&mut regex_pattern, c), // Escape other chars
         }
     }
 
@@ -281,13 +564,15 @@ pub fn convert_redis_pattern_to_regex(pattern: &str) -> String {
     regex_pattern
 }
 
-pub async fn delete_expired_key(db: &Db, key: Key) -> bool {
-    let mut db_write = db.write().await;
-
-    if key.is_expired() {
-        db_write.swap_remove(&key.name);
-        return true;
+fn push_escaped_byte(regex_pattern: &mut String, c: u8) {
+    if c.is_ascii_alphanumeric() || c == b'_' {
+        regex_pattern.push(c as char);
+    } else {
+        regex_pattern.push_str(&format!("\\x{:02x}", c));
     }
+}
 
-    false
+/// Removes the key if it has expired. Returns true if it was removed.
+pub fn delete_expired_key(db: &mut Keyspace, key_name: &[u8]) -> bool {
+    db.expire_if_needed(key_name)
 }
@@ -1,9 +1,14 @@
 use crate::errors::SiderError;
+use crate::notify::{notify_keyspace_event, NOTIFY_GENERIC, NOTIFY_LIST};
 use crate::response::SiderResponse;
-use crate::types::{Command, CommandArgs, Db, DbValue, KeyList, ListPushType, PopType};
-
-async fn push_to_list(
-    db: &Db,
+use crate::types::{
+    Command, CommandArgs, CommandType, DbValue, KeyList, Keyspace, ListPushType, PopType,
+};
+use crate::utils::parse_number;
+use bytes::Bytes;
+
+fn push_to_list(
+    db: &mut Keyspace,
     command: Command,
     push_type: ListPushType,
 ) -> Result<SiderResponse, SiderError> {
@@ -14,9 +19,12 @@ async fn push_to_list(
 
     let mut new_values = values;
 
-    let mut db_write = db.write().await;
+    let event = match push_type {
+        ListPushType::LPUSH => "lpush",
+        ListPushType::RPUSH => "rpush",
+    };
 
-    match db_write.get_mut(&key_name) {
+    let result = match db.get_mut(&key_name) {
         Some(DbValue::ListKey(existing_list)) => {
             match push_type {
                 ListPushType::LPUSH => {
@@ -29,57 +37,63 @@ async fn push_to_list(
                 }
             }
             let nb = existing_list.data.len() as i64;
-            Ok(SiderResponse::Int(nb))
+            Ok(SiderResponse::Integer(nb))
         }
         None => {
             if let ListPushType::LPUSH = push_type {
                 new_values.reverse();
             }
-            db_write.insert(
+            db.insert(
                 key_name.clone(),
                 DbValue::ListKey(KeyList {
-                    name: key_name,
+                    name: key_name.clone(),
                     data: new_values.clone().into(),
                     ..Default::default()
                 }),
             );
             let nb = new_values.len() as i64;
-            Ok(SiderResponse::Int(nb))
+            Ok(SiderResponse::Integer(nb))
         }
         Some(_) => Err(SiderError::WrongType),
+    };
+
+    if result.is_ok() {
+        notify_keyspace_event(NOTIFY_LIST, event, &key_name);
+        db.signal_modified_key(&key_name);
+        db.signal_key_as_ready(&key_name);
     }
+
+    result
 }
 
-pub async fn lpush(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
-    push_to_list(db, command, ListPushType::LPUSH).await
+pub fn lpush(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    push_to_list(db, command, ListPushType::LPUSH)
 }
 
-pub async fn rpush(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
-    push_to_list(db, command, ListPushType::RPUSH).await
+pub fn rpush(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    push_to_list(db, command, ListPushType::RPUSH)
 }
 
-pub async fn lrange(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn lrange(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let (key_name, values) = match command.args {
         CommandArgs::KeyWithValues { key, values } => (key, values),
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let min: isize = match values[0].parse::<isize>() {
-        Ok(val) => val,
-        Err(_) => return Err(SiderError::NotIntOrOutOfRange),
+    let min: isize = match parse_number::<isize>(&values[0]) {
+        Some(val) => val,
+        None => return Err(SiderError::NotIntOrOutOfRange),
     };
 
-    let max: isize = match values[1].parse::<isize>() {
-        Ok(val) => val,
-        Err(_) => return Err(SiderError::NotIntOrOutOfRange),
+    let max: isize = match parse_number::<isize>(&values[1]) {
+        Some(val) => val,
+        None => return Err(SiderError::NotIntOrOutOfRange),
     };
 
-    let db_read = db.read().await;
-
-    let key = match db_read.get(&key_name) {
+    let key = match db.get(&key_name) {
         Some(DbValue::ListKey(key)) => key,
-        Some(DbValue::StringKey(_)) => return Err(SiderError::WrongType),
-        _ => return Ok(SiderResponse::EmptyArray),
+        None => return Ok(SiderResponse::Array(vec![])),
+        Some(_) => return Err(SiderError::WrongType),
     };
 
     let len = key.data.len();
@@ -97,28 +111,24 @@ pub async fn lrange(db: &Db, command: Command) -> Result<SiderResponse, SiderErr
     };
 
     if min >= max || min >= len {
-        return Ok(SiderResponse::EmptyArray);
-    }
-
-    let results: Vec<String> = key.data.range(min..max).cloned().collect();
-
-    if results.is_empty() {
-        return Ok(SiderResponse::EmptyArray);
+        return Ok(SiderResponse::Array(vec![]));
     }
 
-    Ok(SiderResponse::List(results.to_vec()))
+    Ok(SiderResponse::bulk_strings(
+        key.data.range(min..max).cloned(),
+    ))
 }
 
-pub async fn lpop(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
-    pop_list(db, command, PopType::LPOP).await
+pub fn lpop(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    pop_list(db, command, PopType::LPOP)
 }
 
-pub async fn rpop(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
-    pop_list(db, command, PopType::RPOP).await
+pub fn rpop(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    pop_list(db, command, PopType::RPOP)
 }
 
-async fn pop_list(
-    db: &Db,
+fn pop_list(
+    db: &mut Keyspace,
     command: Command,
     pop_type: PopType,
 ) -> Result<SiderResponse, SiderError> {
@@ -128,42 +138,111 @@ async fn pop_list(
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let mut db_write = db.write().await;
+    // Without a count a single bulk string is returned, with a count an array
+    let nb = match value.as_deref() {
+        None => None,
+        Some(v) => Some(parse_number::<usize>(v).ok_or(SiderError::NotPositive)?),
+    };
 
-    let key_db = match db_write.get_mut(&key_name) {
+    let key_db = match db.get_mut(&key_name) {
         Some(DbValue::ListKey(key)) => key,
-        Some(DbValue::StringKey(_)) => return Err(SiderError::WrongType),
-        _ => return Ok(SiderResponse::EmptyArray),
+        None if nb.is_some() => return Ok(SiderResponse::NullArray),
+        None => return Ok(SiderResponse::NullBulk),
+        Some(_) => return Err(SiderError::WrongType),
     };
 
-    let nb = value
-        .as_deref()
-        .map_or(1, |v| v.parse::<usize>().unwrap_or(1));
-
     let len = key_db.data.len();
+    let count = nb.unwrap_or(1).min(len);
 
     let (start, end) = match pop_type {
-        PopType::LPOP => (0, nb),
-        PopType::RPOP => (len.saturating_sub(nb), len),
+        PopType::LPOP => (0, count),
+        PopType::RPOP => (len - count, len),
     };
 
-    let mut removed: Vec<String> = key_db
-        .data
-        .drain(start..end.min(key_db.data.len()))
-        .collect();
+    let mut removed: Vec<Bytes> = key_db.data.drain(start..end).collect();
+    let is_empty = key_db.data.is_empty();
+
+    if !removed.is_empty() {
+        let event = match pop_type {
+            PopType::LPOP => "lpop",
+            PopType::RPOP => "rpop",
+        };
+        notify_keyspace_event(NOTIFY_LIST, event, &key_name);
+        db.signal_modified_key(&key_name);
+    }
 
-    if removed.is_empty() {
-        return Ok(SiderResponse::Nil);
+    if is_empty {
+        db.swap_remove(&key_name);
+        notify_keyspace_event(NOTIFY_GENERIC, "del", &key_name);
     }
 
     if let PopType::RPOP = pop_type {
         removed.reverse();
     }
 
-    // If LPOP is passed without arguments, return the first element
-    if nb == 1 {
-        return Ok(SiderResponse::SimpleString(removed[0].clone()));
+    match nb {
+        Some(_) => Ok(SiderResponse::bulk_strings(removed)),
+        None => Ok(removed
+            .into_iter()
+            .next()
+            .map_or(SiderResponse::NullBulk, SiderResponse::BulkString)),
+    }
+}
+
+pub fn parse_pop_side(side: &[u8]) -> Result<PopType, SiderError> {
+    match side.to_ascii_uppercase().as_slice() {
+        b"LEFT" => Ok(PopType::LPOP),
+        b"RIGHT" => Ok(PopType::RPOP),
+        _ => Err(SiderError::Syntax),
+    }
+}
+
+pub fn parse_push_side(side: &[u8]) -> Result<ListPushType, SiderError> {
+    match side.to_ascii_uppercase().as_slice() {
+        b"LEFT" => Ok(ListPushType::LPUSH),
+        b"RIGHT" => Ok(ListPushType::RPUSH),
+        _ => Err(SiderError::Syntax),
     }
+}
+
+/// Pops an element from `source` and pushes it to `destination`, which can
+/// be the same list to rotate it.
+pub fn lmove(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (source, values) = match command.args {
+        CommandArgs::KeyWithValues { key, values } if values.len() >= 3 => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let destination = values[0].clone();
+    let from = parse_pop_side(&values[1])?;
+    let to = parse_push_side(&values[2])?;
+
+    // Checked first so nothing is popped if the push is going to fail
+    if let Some(existing) = db.get(&destination) {
+        if !matches!(existing, DbValue::ListKey(_)) {
+            return Err(SiderError::WrongType);
+        }
+    }
+
+    let pop_command = Command {
+        command_type: CommandType::LPOP,
+        args: CommandArgs::SingleKey(source),
+    };
+
+    let value = match pop_list(db, pop_command, from)? {
+        SiderResponse::BulkString(value) => value,
+        _ => return Ok(SiderResponse::NullBulk),
+    };
+
+    let push_command = Command {
+        command_type: CommandType::LPUSH,
+        args: CommandArgs::KeyWithValues {
+            key: destination,
+            values: vec![value.clone()],
+        },
+    };
+
+    push_to_list(db, push_command, to)?;
 
-    Ok(SiderResponse::List(removed))
+    Ok(SiderResponse::BulkString(value))
 }
@@ -1,18 +1,81 @@
+use crate::client::{Client, Protocol};
 use crate::errors::SiderError;
 use crate::response::SiderResponse;
+use crate::types::{Command, CommandArgs};
+use bytes::Bytes;
 
-pub async fn pong() -> Result<SiderResponse, SiderError> {
+pub fn pong() -> Result<SiderResponse, SiderError> {
     Ok(SiderResponse::SimpleString("PONG".to_string()))
 }
 
-pub async fn docs() -> Result<SiderResponse, SiderError> {
+pub fn docs() -> Result<SiderResponse, SiderError> {
     Ok(SiderResponse::SimpleString(
         "DOCS is not implemented yet".to_string(),
     ))
 }
 
-pub async fn client() -> Result<SiderResponse, SiderError> {
-    Ok(SiderResponse::SimpleString(
-        "CLIENT SETINFO is not implemented yet".to_string(),
-    ))
+// Only acknowledges CLIENT SETINFO and friends, nothing is stored yet
+pub fn client() -> Result<SiderResponse, SiderError> {
+    Ok(SiderResponse::Ok)
+}
+
+/// Switches the connection protocol and returns the server properties.
+/// Sider has no ACL, the `default` user is accepted with any password.
+pub fn hello(client: &mut Client, command: Command) -> Result<SiderResponse, SiderError> {
+    let (protover, auth, setname) = match command.args {
+        CommandArgs::Hello {
+            protover,
+            auth,
+            setname,
+        } => (protover, auth, setname),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let protocol = match protover {
+        None => client.protocol,
+        Some(2) => Protocol::Resp2,
+        Some(3) => Protocol::Resp3,
+        Some(_) => return Err(SiderError::NoProto),
+    };
+
+    if let Some((user, _)) = auth {
+        if user != "default" {
+            return Err(SiderError::WrongPass);
+        }
+    }
+
+    client.protocol = protocol;
+
+    if setname.is_some() {
+        client.name = setname;
+    }
+
+    let property = |name: &'static str, value: SiderResponse| {
+        (
+            SiderResponse::BulkString(Bytes::from_static(name.as_bytes())),
+            value,
+        )
+    };
+
+    Ok(SiderResponse::Map(vec![
+        property(
+            "server",
+            SiderResponse::BulkString(Bytes::from_static(b"sider")),
+        ),
+        property(
+            "version",
+            SiderResponse::BulkString(Bytes::from_static(env!("CARGO_PKG_VERSION").as_bytes())),
+        ),
+        property("proto", SiderResponse::Integer(protocol.version())),
+        property("id", SiderResponse::Integer(client.id as i64)),
+        property(
+            "mode",
+            SiderResponse::BulkString(Bytes::from_static(b"standalone")),
+        ),
+        property(
+            "role",
+            SiderResponse::BulkString(Bytes::from_static(b"master")),
+        ),
+        property("modules", SiderResponse::Array(vec![])),
+    ]))
 }
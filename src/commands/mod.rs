@@ -1,7 +1,15 @@
+pub mod blocking;
 pub mod build;
+pub mod config;
 pub mod db;
 pub mod hashsets;
+pub mod info;
 pub mod keys;
 pub mod lists;
 pub mod misc;
+pub mod persistence;
+pub mod pubsub;
 pub mod sets;
+pub mod streams;
+pub mod transactions;
+pub mod zsets;
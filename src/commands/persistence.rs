@@ -0,0 +1,78 @@
+use crate::errors::SiderError;
+use crate::rdb::{load_rdb, save_rdb};
+use crate::response::SiderResponse;
+use crate::rewrite::{is_rewrite_in_progress, schedule_rewrite};
+use crate::snapshot::{get_last_save, is_save_in_progress, save_snapshot, schedule_save};
+use crate::types::{Command, CommandArgs, Db};
+
+// BGREWRITEAOF, the rewrite starts within the next 100ms
+pub fn bgrewriteaof() -> Result<SiderResponse, SiderError> {
+    if is_rewrite_in_progress() {
+        return Err(SiderError::RewriteInProgress);
+    }
+
+    schedule_rewrite();
+    Ok(SiderResponse::SimpleString(
+        "Background append only file rewriting scheduled".to_string(),
+    ))
+}
+
+// SAVE, replies once the snapshot is on disk
+pub async fn save(db: &Db) -> Result<SiderResponse, SiderError> {
+    save_snapshot(db).await?;
+    Ok(SiderResponse::Ok)
+}
+
+// BGSAVE [SCHEDULE], the snapshot starts within the next 100ms
+pub fn bgsave() -> Result<SiderResponse, SiderError> {
+    if is_save_in_progress() {
+        return Err(SiderError::SaveInProgress);
+    }
+
+    schedule_save();
+    Ok(SiderResponse::SimpleString(
+        "Background saving scheduled".to_string(),
+    ))
+}
+
+// LASTSAVE
+pub fn lastsave() -> Result<SiderResponse, SiderError> {
+    Ok(SiderResponse::Integer(get_last_save()))
+}
+
+// DEBUG RELOAD [MERGE] [NOSAVE], saves the dataset to dump.rdb and loads it
+// back like Redis. NOSAVE loads the file as is, e.g. one written by Redis,
+// and MERGE keeps the keys it doesn't have. Saving is refused while there
+// are streams, the file can't have them and they would be lost.
+pub async fn debug(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+    let (subcommand, args) = match &command.args {
+        CommandArgs::KeyWithValues { key, values } => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    if !subcommand.eq_ignore_ascii_case(b"RELOAD") {
+        return Err(SiderError::UnknownSubcommand);
+    }
+
+    let mut merge = false;
+    let mut save = true;
+
+    for option in args {
+        match option.to_ascii_uppercase().as_slice() {
+            b"MERGE" => merge = true,
+            b"NOSAVE" => save = false,
+            _ => return Err(SiderError::Syntax),
+        }
+    }
+
+    // Saved and loaded under the same lock, a write made in between would be
+    // missing from the file and lost by the load
+    let mut keyspace = db.lock_all().await;
+
+    if save {
+        save_rdb(&keyspace).await?;
+    }
+
+    load_rdb(&mut keyspace, merge).await?;
+    Ok(SiderResponse::Ok)
+}
@@ -0,0 +1,172 @@
+use crate::client::Client;
+use crate::commands::keys::convert_redis_pattern_to_regex;
+use crate::errors::SiderError;
+use crate::pubsub::get_pubsub;
+use crate::response::SiderResponse;
+use crate::types::{Command, CommandArgs};
+use bytes::Bytes;
+use regex::bytes::Regex;
+
+// Every (un)subscription is confirmed with its own
+// [kind, channel, subscription count] frame
+fn confirmation(kind: &'static [u8], channel: Option<Bytes>, count: i64) -> SiderResponse {
+    SiderResponse::Push(vec![
+        SiderResponse::BulkString(Bytes::from_static(kind)),
+        channel.map_or(SiderResponse::NullBulk, SiderResponse::BulkString),
+        SiderResponse::Integer(count),
+    ])
+}
+
+fn command_channels(command: Command) -> Result<Vec<Bytes>, SiderError> {
+    match command.args {
+        CommandArgs::MultipleKeys(channels) => Ok(channels),
+        _ => Err(SiderError::InvalidCommand),
+    }
+}
+
+pub fn subscribe(client: &mut Client, command: Command) -> Result<SiderResponse, SiderError> {
+    let mut replies = vec![];
+
+    for channel in command_channels(command)? {
+        get_pubsub().subscribe(channel.clone(), client.id, client.sender.clone());
+        client.channels.insert(channel.clone());
+        replies.push(confirmation(
+            b"subscribe",
+            Some(channel),
+            client.subscription_count(),
+        ));
+    }
+
+    Ok(SiderResponse::Sequence(replies))
+}
+
+pub fn psubscribe(client: &mut Client, command: Command) -> Result<SiderResponse, SiderError> {
+    let mut replies = vec![];
+
+    for pattern in command_channels(command)? {
+        get_pubsub().psubscribe(pattern.clone(), client.id, client.sender.clone())?;
+        client.patterns.insert(pattern.clone());
+        replies.push(confirmation(
+            b"psubscribe",
+            Some(pattern),
+            client.subscription_count(),
+        ));
+    }
+
+    Ok(SiderResponse::Sequence(replies))
+}
+
+/// Without arguments, unsubscribes from every channel.
+pub fn unsubscribe(client: &mut Client, command: Command) -> Result<SiderResponse, SiderError> {
+    let mut channels = command_channels(command)?;
+
+    if channels.is_empty() {
+        channels = client.channels.iter().cloned().collect();
+    }
+
+    if channels.is_empty() {
+        return Ok(confirmation(
+            b"unsubscribe",
+            None,
+            client.subscription_count(),
+        ));
+    }
+
+    let mut replies = vec![];
+
+    for channel in channels {
+        get_pubsub().unsubscribe(&channel, client.id);
+        client.channels.remove(&channel);
+        replies.push(confirmation(
+            b"unsubscribe",
+            Some(channel),
+            client.subscription_count(),
+        ));
+    }
+
+    Ok(SiderResponse::Sequence(replies))
+}
+
+/// Without arguments, unsubscribes from every pattern.
+pub fn punsubscribe(
+    client: &mut Client,
+    command: Command,
+) -> Result<SiderResponse, SiderError> {
+    let mut patterns = command_channels(command)?;
+
+    if patterns.is_empty() {
+        patterns = client.patterns.iter().cloned().collect();
+    }
+
+    if patterns.is_empty() {
+        return Ok(confirmation(
+            b"punsubscribe",
+            None,
+            client.subscription_count(),
+        ));
+    }
+
+    let mut replies = vec![];
+
+    for pattern in patterns {
+        get_pubsub().punsubscribe(&pattern, client.id);
+        client.patterns.remove(&pattern);
+        replies.push(confirmation(
+            b"punsubscribe",
+            Some(pattern),
+            client.subscription_count(),
+        ));
+    }
+
+    Ok(SiderResponse::Sequence(replies))
+}
+
+/// Returns the number of clients the message was delivered to.
+pub fn publish(command: Command) -> Result<SiderResponse, SiderError> {
+    let (channel, message) = match &command.args {
+        CommandArgs::KeyWithValue { key, value } => (key, value),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    Ok(SiderResponse::Integer(
+        get_pubsub().publish(channel, message),
+    ))
+}
+
+// PUBSUB CHANNELS [pattern], PUBSUB NUMSUB [channel ...] and PUBSUB NUMPAT
+pub fn pubsub(command: Command) -> Result<SiderResponse, SiderError> {
+    let (subcommand, args) = match &command.args {
+        CommandArgs::KeyWithValues { key, values } => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    match subcommand.to_ascii_uppercase().as_slice() {
+        b"CHANNELS" if args.len() <= 1 => {
+            let filter = match args.first() {
+                Some(pattern) => Some(
+                    Regex::new(&convert_redis_pattern_to_regex(pattern))
+                        .map_err(|_| SiderError::RegexError)?,
+                ),
+                None => None,
+            };
+
+            Ok(SiderResponse::bulk_strings(
+                get_pubsub().channels(filter.as_ref()),
+            ))
+        }
+        // A flat array of channels and counts, even in RESP3
+        b"NUMSUB" => Ok(SiderResponse::Array(
+            args.iter()
+                .flat_map(|channel| {
+                    [
+                        SiderResponse::BulkString(channel.clone()),
+                        SiderResponse::Integer(get_pubsub().numsub(channel)),
+                    ]
+                })
+                .collect(),
+        )),
+        b"NUMPAT" if args.is_empty() => Ok(SiderResponse::Integer(get_pubsub().numpat())),
+        b"CHANNELS" | b"NUMPAT" => Err(SiderError::WrongNumberArgs),
+        _ => Err(SiderError::UnknownSubcommand),
+    }
+}
@@ -1,21 +1,20 @@
 use crate::errors::SiderError;
+use crate::notify::{notify_keyspace_event, NOTIFY_GENERIC, NOTIFY_SET};
 use crate::response::SiderResponse;
-use crate::types::{Command, CommandArgs, Db, DbValue, KeySet};
+use crate::types::{Command, CommandArgs, DbValue, KeySet, Keyspace};
 use std::collections::HashSet;
 
-pub async fn sadd(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn sadd(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let (set_name, values) = match command.args {
         CommandArgs::KeyWithValues { key, values } => (key, values),
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let mut db_write = db.write().await;
-
-    match db_write.get_mut(&set_name) {
+    let (nb, added) = match db.get_mut(&set_name) {
         Some(DbValue::SetKey(db_set)) => {
+            let before = db_set.data.len();
             db_set.data.extend(values);
-            let nb = db_set.data.len() as i64;
-            Ok(SiderResponse::Int(nb))
+            (db_set.data.len() as i64, db_set.data.len() > before)
         }
         None => {
             let new_set = DbValue::SetKey(KeySet {
@@ -23,40 +22,48 @@ pub async fn sadd(db: &Db, command: Command) -> Result<SiderResponse, SiderError
                 data: HashSet::from_iter(values.clone()),
                 ..Default::default()
             });
-            db_write.insert(set_name, new_set);
-            let nb = values.len() as i64;
-            Ok(SiderResponse::Int(nb))
+            db.insert(set_name.clone(), new_set);
+            (values.len() as i64, true)
         }
-        Some(_) => Err(SiderError::WrongType),
+        Some(_) => return Err(SiderError::WrongType),
+    };
+
+    // Members already in the set leave it unchanged
+    if added {
+        db.signal_modified_key(&set_name);
     }
+
+    notify_keyspace_event(NOTIFY_SET, "sadd", &set_name);
+    Ok(SiderResponse::Integer(nb))
 }
 
-pub async fn smembers(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn smembers(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let key_name = match &command.args {
         CommandArgs::SingleKey(key_name) => key_name,
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let db_read = db.read().await;
-
-    let results = match db_read.get(key_name) {
-        Some(DbValue::SetKey(key)) => key.data.iter().cloned().collect::<Vec<String>>(),
-        None => return Ok(SiderResponse::EmptyArray),
+    let results = match db.get(key_name) {
+        Some(DbValue::SetKey(key)) => key
+            .data
+            .iter()
+            .cloned()
+            .map(SiderResponse::BulkString)
+            .collect(),
+        None => vec![],
         Some(_) => return Err(SiderError::WrongType),
     };
 
-    Ok(SiderResponse::List(results))
+    Ok(SiderResponse::Set(results))
 }
 
-pub async fn srem(db: &Db, command: Command) -> Result<SiderResponse, SiderError> {
+pub fn srem(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
     let (set_name, members) = match &command.args {
         CommandArgs::KeyWithValues { key, values } => (key.clone(), values.clone()),
         _ => return Err(SiderError::InvalidCommand),
     };
 
-    let mut db_write = db.write().await;
-
-    match db_write.get_mut(&set_name) {
+    match db.get_mut(&set_name) {
         Some(DbValue::SetKey(key)) => {
             let mut deleted_count = 0;
             for member in members {
@@ -65,12 +72,20 @@ pub async fn srem(db: &Db, command: Command) -> Result<SiderResponse, SiderError
                 }
             }
 
-            if key.data.is_empty() {
-                db_write.swap_remove(&set_name);
+            let is_empty = key.data.is_empty();
+
+            if deleted_count > 0 {
+                notify_keyspace_event(NOTIFY_SET, "srem", &set_name);
+                db.signal_modified_key(&set_name);
+            }
+
+            if is_empty {
+                db.swap_remove(&set_name);
+                notify_keyspace_event(NOTIFY_GENERIC, "del", &set_name);
             }
-            Ok(SiderResponse::Int(deleted_count))
+            Ok(SiderResponse::Integer(deleted_count))
         }
         Some(_) => Err(SiderError::WrongType),
-        None => Ok(SiderResponse::Int(0)),
+        None => Ok(SiderResponse::Integer(0)),
     }
 }
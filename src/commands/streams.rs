@@ -0,0 +1,1325 @@
+use crate::errors::SiderError;
+use crate::notify::{notify_keyspace_event, NOTIFY_STREAM};
+use crate::response::SiderResponse;
+use crate::stream::{ConsumerGroup, PendingEntry, StreamFields};
+use crate::types::{Command, CommandArgs, CommandType, DbValue, KeyStream, Keyspace};
+use crate::types::{Stream, StreamId};
+use crate::utils::{current_time_millis, parse_number};
+use bytes::Bytes;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+// XAUTOCLAIM claims at most this many entries by default
+const AUTOCLAIM_DEFAULT_COUNT: usize = 100;
+
+// `ms-seq`, or `ms` alone for `ms-0`
+fn parse_id(value: &[u8]) -> Result<StreamId, SiderError> {
+    StreamId::parse(value, 0).ok_or(SiderError::InvalidStreamId)
+}
+
+// Start of an XRANGE interval: `-`, an ID, or `(ID` to exclude it. `None`
+// if nothing can be after an excluded ID.
+fn parse_range_start(value: &[u8]) -> Result<Option<StreamId>, SiderError> {
+    match value {
+        b"-" => Ok(Some(StreamId::MIN)),
+        [b'(', id @ ..] => Ok(parse_id(id)?.next()),
+        _ => parse_id(value).map(Some),
+    }
+}
+
+// End of an XRANGE interval: `+`, an ID, or `(ID` to exclude it. `ms` alone
+// includes every sequence of that millisecond.
+fn parse_range_end(value: &[u8]) -> Result<Option<StreamId>, SiderError> {
+    let parse = |id: &[u8]| StreamId::parse(id, u64::MAX).ok_or(SiderError::InvalidStreamId);
+
+    match value {
+        b"+" => Ok(Some(StreamId::MAX)),
+        [b'(', id @ ..] => Ok(parse(id)?.prev()),
+        _ => parse(value).map(Some),
+    }
+}
+
+// A negative COUNT returns nothing, like Redis
+fn parse_count(value: &[u8]) -> Result<usize, SiderError> {
+    let count = parse_number::<i64>(value).ok_or(SiderError::NotInt)?;
+    Ok(count.max(0) as usize)
+}
+
+pub fn get_stream<'a>(db: &'a Keyspace, key: &[u8]) -> Result<Option<&'a Stream>, SiderError> {
+    match db.get(key) {
+        Some(DbValue::StreamKey(stream)) => Ok(Some(&stream.data)),
+        None => Ok(None),
+        Some(_) => Err(SiderError::WrongType),
+    }
+}
+
+fn get_stream_mut<'a>(
+    db: &'a mut Keyspace,
+    key: &[u8],
+) -> Result<Option<&'a mut Stream>, SiderError> {
+    match db.get_mut(key) {
+        Some(DbValue::StreamKey(stream)) => Ok(Some(&mut stream.data)),
+        None => Ok(None),
+        Some(_) => Err(SiderError::WrongType),
+    }
+}
+
+fn no_group(key: &[u8], group: &[u8]) -> SiderError {
+    SiderError::NoGroup(
+        String::from_utf8_lossy(key).to_string(),
+        String::from_utf8_lossy(group).to_string(),
+    )
+}
+
+// The group of a stream, NOGROUP if either of them is missing
+fn get_group_mut<'a>(
+    db: &'a mut Keyspace,
+    key: &[u8],
+    group: &[u8],
+) -> Result<&'a mut ConsumerGroup, SiderError> {
+    get_stream_mut(db, key)?
+        .and_then(|stream| stream.groups.get_mut(group))
+        .ok_or_else(|| no_group(key, group))
+}
+
+// [id, [field, value, ...]]
+fn entry_reply(id: &StreamId, fields: &StreamFields) -> SiderResponse {
+    let fields = fields
+        .iter()
+        .flat_map(|(field, value)| [field.clone(), value.clone()]);
+
+    SiderResponse::Array(vec![
+        SiderResponse::BulkString(id.to_bytes()),
+        SiderResponse::bulk_strings(fields),
+    ])
+}
+
+fn entries_reply<'a, I>(entries: I) -> SiderResponse
+where
+    I: IntoIterator<Item = (&'a StreamId, &'a StreamFields)>,
+{
+    SiderResponse::Array(
+        entries
+            .into_iter()
+            .map(|(id, fields)| entry_reply(id, fields))
+            .collect(),
+    )
+}
+
+fn stream_command(command_type: CommandType, key: &Bytes, values: Vec<Bytes>) -> Command {
+    Command {
+        command_type,
+        args: CommandArgs::KeyWithValues {
+            key: key.clone(),
+            values,
+        },
+    }
+}
+
+// What a claim is logged as: the same owner, delivery time and count
+// whatever the time the AOF is replayed at
+fn claim_command(key: &Bytes, group: &Bytes, id: &StreamId, pending: &PendingEntry) -> Command {
+    stream_command(
+        CommandType::XCLAIM,
+        key,
+        vec![
+            group.clone(),
+            pending.consumer.clone(),
+            Bytes::from_static(b"0"),
+            id.to_bytes(),
+            Bytes::from_static(b"TIME"),
+            Bytes::from(pending.delivered_at.to_string()),
+            Bytes::from_static(b"RETRYCOUNT"),
+            Bytes::from(pending.delivery_count.to_string()),
+            Bytes::from_static(b"FORCE"),
+            Bytes::from_static(b"JUSTID"),
+        ],
+    )
+}
+
+fn create_consumer_command(key: &Bytes, group: &Bytes, consumer: &Bytes) -> Command {
+    stream_command(
+        CommandType::XGROUP,
+        &Bytes::from_static(b"CREATECONSUMER"),
+        vec![key.clone(), group.clone(), consumer.clone()],
+    )
+}
+
+// Marks the consumer as active, a consumer created on the fly is logged and
+// notified like XGROUP CREATECONSUMER
+fn see_consumer(
+    group: &mut ConsumerGroup,
+    key: &Bytes,
+    group_name: &Bytes,
+    consumer: &Bytes,
+    propagated: &mut Vec<Command>,
+) {
+    if group.see_consumer(consumer, current_time_millis()) {
+        propagated.push(create_consumer_command(key, group_name, consumer));
+        notify_keyspace_event(NOTIFY_STREAM, "xgroup-createconsumer", key);
+    }
+}
+
+enum TrimStrategy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+#[derive(Default)]
+struct AddOptions {
+    nomkstream: bool,
+    trim: Option<TrimStrategy>,
+    approximate: bool,
+    limit: Option<usize>,
+}
+
+// [NOMKSTREAM] [MAXLEN|MINID [=|~] threshold] [LIMIT count], in any order.
+// Stops at the first argument that isn't an option and returns its index.
+fn parse_add_options(args: &[Bytes], xadd: bool) -> Result<(AddOptions, usize), SiderError> {
+    let mut options = AddOptions::default();
+    let mut i = 0;
+
+    while let Some(arg) = args.get(i) {
+        match arg.to_ascii_uppercase().as_slice() {
+            b"NOMKSTREAM" if xadd => {
+                options.nomkstream = true;
+                i += 1;
+            }
+            option @ (b"MAXLEN" | b"MINID") => {
+                let mut threshold = args.get(i + 1).ok_or(SiderError::Syntax)?;
+                i += 2;
+
+                if matches!(&threshold[..], b"=" | b"~") {
+                    options.approximate = &threshold[..] == b"~";
+                    threshold = args.get(i).ok_or(SiderError::Syntax)?;
+                    i += 1;
+                }
+
+                options.trim = Some(match option {
+                    b"MAXLEN" => {
+                        let max_len =
+                            parse_number::<i64>(threshold).ok_or(SiderError::NotInt)?;
+                        if max_len < 0 {
+                            return Err(SiderError::NegativeMaxLen);
+                        }
+                        TrimStrategy::MaxLen(max_len as usize)
+                    }
+                    _ => TrimStrategy::MinId(parse_id(threshold)?),
+                });
+            }
+            b"LIMIT" => {
+                let limit = args.get(i + 1).ok_or(SiderError::Syntax)?;
+                let limit = parse_number::<i64>(limit).ok_or(SiderError::NotInt)?;
+                if limit < 0 {
+                    return Err(SiderError::NotPositive);
+                }
+
+                // 0 removes the limit
+                options.limit = Some(limit as usize).filter(|limit| *limit > 0);
+                i += 2;
+            }
+            _ => break,
+        }
+    }
+
+    // Trimming is always exact here, `~` only allows LIMIT like Redis
+    if options.limit.is_some() && !options.approximate {
+        return Err(SiderError::LimitWithoutApprox);
+    }
+
+    Ok((options, i))
+}
+
+// Returns how many entries were removed
+fn trim_stream(stream: &mut Stream, options: &AddOptions) -> usize {
+    match options.trim {
+        Some(TrimStrategy::MaxLen(max_len)) => stream.trim_max_len(max_len, options.limit),
+        Some(TrimStrategy::MinId(min_id)) => stream.trim_min_id(min_id, options.limit),
+        None => 0,
+    }
+}
+
+// ID of the entry XADD adds after `last_id`: `*` generates it, `ms-*` only
+// generates the sequence
+fn next_entry_id(value: &[u8], last_id: StreamId) -> Result<StreamId, SiderError> {
+    if value == b"*" {
+        let now = current_time_millis().max(0) as u64;
+
+        return match now > last_id.ms {
+            true => Ok(StreamId::new(now, 0)),
+            false => last_id.next().ok_or(SiderError::StreamIdTooSmall),
+        };
+    }
+
+    if let Some(ms) = value.strip_suffix(b"-*") {
+        let ms = parse_number::<u64>(ms).ok_or(SiderError::InvalidStreamId)?;
+
+        return match ms.cmp(&last_id.ms) {
+            std::cmp::Ordering::Greater => Ok(StreamId::new(ms, 0)),
+            std::cmp::Ordering::Equal if last_id.seq < u64::MAX => {
+                Ok(StreamId::new(ms, last_id.seq + 1))
+            }
+            _ => Err(SiderError::StreamIdTooSmall),
+        };
+    }
+
+    let id = parse_id(value)?;
+
+    if id == StreamId::MIN {
+        return Err(SiderError::StreamIdZero);
+    }
+
+    if id <= last_id {
+        return Err(SiderError::StreamIdTooSmall);
+    }
+
+    Ok(id)
+}
+
+/// XADD key [NOMKSTREAM] [MAXLEN|MINID [=|~] threshold [LIMIT count]] *|id
+/// field value [field value ...]
+///
+/// Logged with the ID it generated so the AOF replays the same entries.
+pub fn xadd(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, mut values) = match command.args {
+        CommandArgs::KeyWithValues { key, values } => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let (options, id_index) = parse_add_options(&values, true)?;
+    let fields = values.get(id_index + 1..).unwrap_or_default();
+
+    if fields.is_empty() || fields.len() % 2 != 0 {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    let fields: StreamFields = fields
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+
+    let last_id = match get_stream(db, &key_name)? {
+        Some(stream) => stream.last_id,
+        None if options.nomkstream => return Ok(SiderResponse::NullBulk),
+        None => StreamId::MIN,
+    };
+
+    let id = next_entry_id(&values[id_index], last_id)?;
+
+    if !db.contains_key(&key_name) {
+        db.insert(
+            key_name.clone(),
+            DbValue::StreamKey(KeyStream {
+                name: key_name.clone(),
+                ..Default::default()
+            }),
+        );
+    }
+
+    let Some(stream) = get_stream_mut(db, &key_name)? else {
+        return Err(SiderError::DatabaseError);
+    };
+
+    stream.insert(id, fields);
+    let trimmed = trim_stream(stream, &options);
+
+    notify_keyspace_event(NOTIFY_STREAM, "xadd", &key_name);
+    if trimmed > 0 {
+        notify_keyspace_event(NOTIFY_STREAM, "xtrim", &key_name);
+    }
+
+    db.signal_modified_key(&key_name);
+    db.signal_key_as_ready(&key_name);
+
+    values[id_index] = id.to_bytes();
+    db.propagate_as(vec![stream_command(CommandType::XADD, &key_name, values)]);
+
+    Ok(SiderResponse::BulkString(id.to_bytes()))
+}
+
+pub fn xlen(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let key_name = match command.args {
+        CommandArgs::SingleKey(key) => key,
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let len = get_stream(db, &key_name)?.map_or(0, |stream| stream.len());
+    Ok(SiderResponse::Integer(len as i64))
+}
+
+fn range_generic(
+    db: &mut Keyspace,
+    command: Command,
+    rev: bool,
+) -> Result<SiderResponse, SiderError> {
+    let (key_name, values) = match command.args {
+        CommandArgs::KeyWithValues { key, values } if values.len() >= 2 => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    // XREVRANGE takes the end first
+    let (start, end) = match rev {
+        true => (&values[1], &values[0]),
+        false => (&values[0], &values[1]),
+    };
+
+    let count = match &values[2..] {
+        [] => None,
+        [option, count] if option.eq_ignore_ascii_case(b"COUNT") => Some(parse_count(count)?),
+        _ => return Err(SiderError::Syntax),
+    };
+
+    let (start, end) = (parse_range_start(start)?, parse_range_end(end)?);
+
+    let (Some(stream), Some(start), Some(end)) = (get_stream(db, &key_name)?, start, end)
+    else {
+        return Ok(SiderResponse::Array(Vec::new()));
+    };
+
+    let count = count.unwrap_or(usize::MAX);
+    let range = stream.range(start, end);
+
+    Ok(match rev {
+        true => entries_reply(range.rev().take(count)),
+        false => entries_reply(range.take(count)),
+    })
+}
+
+/// XRANGE key start end [COUNT count]
+pub fn xrange(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    range_generic(db, command, false)
+}
+
+/// XREVRANGE key end start [COUNT count]
+pub fn xrevrange(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    range_generic(db, command, true)
+}
+
+/// Arguments of XREAD and XREADGROUP.
+#[derive(Debug, Default)]
+pub struct ReadArgs {
+    // Group and consumer names, only for XREADGROUP
+    pub group: Option<(Bytes, Bytes)>,
+    pub count: Option<usize>,
+    // Milliseconds, 0 blocks forever
+    pub block: Option<u64>,
+    pub noack: bool,
+    pub keys: Vec<Bytes>,
+    pub ids: Vec<Bytes>,
+}
+
+/// [GROUP group consumer] [COUNT count] [BLOCK milliseconds] [NOACK]
+/// STREAMS key [key ...] id [id ...]
+pub fn parse_read_args(command: &Command) -> Result<ReadArgs, SiderError> {
+    let args = match &command.args {
+        CommandArgs::MultipleKeys(args) => args,
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let xreadgroup = command.command_type == CommandType::XREADGROUP;
+    let mut read = ReadArgs::default();
+    let mut i = 0;
+
+    let streams = loop {
+        let Some(arg) = args.get(i) else {
+            return Err(SiderError::Syntax);
+        };
+
+        let value = args.get(i + 1);
+
+        match (arg.to_ascii_uppercase().as_slice(), value) {
+            (b"STREAMS", _) => break &args[i + 1..],
+            (b"COUNT", Some(count)) => {
+                // 0 doesn't limit the reply
+                read.count = Some(parse_count(count)?).filter(|count| *count > 0);
+            }
+            (b"BLOCK", Some(timeout)) => {
+                let timeout = parse_number::<i64>(timeout).ok_or(SiderError::TimeoutNotInt)?;
+                if timeout < 0 {
+                    return Err(SiderError::NegativeTimeout);
+                }
+                read.block = Some(timeout as u64);
+            }
+            (b"GROUP", Some(group)) if xreadgroup => {
+                let consumer = args.get(i + 2).ok_or(SiderError::Syntax)?;
+                read.group = Some((group.clone(), consumer.clone()));
+                i += 1;
+            }
+            (b"NOACK", _) if xreadgroup => {
+                read.noack = true;
+                i += 1;
+                continue;
+            }
+            _ => return Err(SiderError::Syntax),
+        }
+
+        i += 2;
+    };
+
+    if streams.is_empty() || streams.len() % 2 != 0 {
+        return Err(match xreadgroup {
+            true => SiderError::UnbalancedStreams {
+                command: "xreadgroup",
+                id: ">",
+            },
+            false => SiderError::UnbalancedStreams {
+                command: "xread",
+                id: "$",
+            },
+        });
+    }
+
+    if xreadgroup && read.group.is_none() {
+        return Err(SiderError::Syntax);
+    }
+
+    let (keys, ids) = streams.split_at(streams.len() / 2);
+    read.keys = keys.to_vec();
+    read.ids = ids.to_vec();
+
+    Ok(read)
+}
+
+/// IDs the entries of each stream are read after, `$` being the last ID of
+/// the stream when the command runs.
+pub fn resolve_read_ids(
+    db: &Keyspace,
+    read: &ReadArgs,
+) -> Result<Vec<(Bytes, StreamId)>, SiderError> {
+    read.keys
+        .iter()
+        .zip(read.ids.iter())
+        .map(|(key, id)| {
+            let id = match &id[..] {
+                b"$" => get_stream(db, key)?.map_or(StreamId::MIN, |stream| stream.last_id),
+                id => parse_id(id)?,
+            };
+
+            Ok((key.clone(), id))
+        })
+        .collect()
+}
+
+/// XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]
+///
+/// Only runs without blocking, blocking reads are handled by `process`.
+pub fn xread(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let read = parse_read_args(&command)?;
+    let streams = resolve_read_ids(db, &read)?;
+    let count = read.count.unwrap_or(usize::MAX);
+    let mut reply = Vec::new();
+
+    for (key, after) in streams {
+        let (Some(stream), Some(start)) = (get_stream(db, &key)?, after.next()) else {
+            continue;
+        };
+
+        let entries: Vec<_> = stream.range(start, StreamId::MAX).take(count).collect();
+
+        if !entries.is_empty() {
+            reply.push(SiderResponse::Array(vec![
+                SiderResponse::BulkString(key),
+                entries_reply(entries),
+            ]));
+        }
+    }
+
+    if reply.is_empty() {
+        return Ok(SiderResponse::NullArray);
+    }
+
+    Ok(SiderResponse::Array(reply))
+}
+
+/// XREADGROUP GROUP group consumer [COUNT count] [BLOCK milliseconds] [NOACK]
+/// STREAMS key [key ...] id [id ...]
+///
+/// `>` delivers entries no consumer of the group got yet, any other ID reads
+/// the consumer's own pending entries after it. Deliveries are logged as the
+/// XCLAIM and XGROUP SETID commands that restore them.
+pub fn xreadgroup(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let read = parse_read_args(&command)?;
+    let Some((group_name, consumer)) = read.group else {
+        return Err(SiderError::Syntax);
+    };
+
+    let count = read.count.unwrap_or(usize::MAX);
+    let now = current_time_millis();
+
+    // Every ID and group is checked before anything is delivered
+    for (key, id) in read.keys.iter().zip(read.ids.iter()) {
+        if &id[..] != b">" {
+            parse_id(id)?;
+        }
+        get_group_mut(db, key, &group_name)?;
+    }
+
+    let mut propagated = Vec::new();
+    let mut reply = Vec::new();
+    let mut history = false;
+
+    for (key, id) in read.keys.iter().zip(read.ids.iter()) {
+        let Some(stream) = get_stream_mut(db, key)? else {
+            return Err(no_group(key, &group_name));
+        };
+
+        let entries = match &id[..] {
+            b">" => {
+                let last_delivered = stream
+                    .groups
+                    .get(&group_name)
+                    .map_or(StreamId::MAX, |group| group.last_delivered);
+
+                let new_entries: Vec<(StreamId, StreamFields)> = last_delivered
+                    .next()
+                    .into_iter()
+                    .flat_map(|start| stream.range(start, StreamId::MAX))
+                    .take(count)
+                    .map(|(id, fields)| (*id, fields.clone()))
+                    .collect();
+
+                let Some(group) = stream.groups.get_mut(&group_name) else {
+                    return Err(no_group(key, &group_name));
+                };
+
+                see_consumer(group, key, &group_name, &consumer, &mut propagated);
+
+                for (id, _) in new_entries.iter() {
+                    group.last_delivered = *id;
+
+                    if !read.noack {
+                        let pending = PendingEntry {
+                            consumer: consumer.clone(),
+                            delivered_at: now,
+                            delivery_count: 1,
+                        };
+
+                        propagated.push(claim_command(key, &group_name, id, &pending));
+                        group.pending.insert(*id, pending);
+                    }
+                }
+
+                if let Some((last, _)) = new_entries.last() {
+                    propagated.push(stream_command(
+                        CommandType::XGROUP,
+                        &Bytes::from_static(b"SETID"),
+                        vec![key.clone(), group_name.clone(), last.to_bytes()],
+                    ));
+                }
+
+                new_entries
+                    .iter()
+                    .map(|(id, fields)| entry_reply(id, fields))
+                    .collect::<Vec<_>>()
+            }
+            id => {
+                history = true;
+                let after = parse_id(id)?;
+
+                let Some(group) = stream.groups.get_mut(&group_name) else {
+                    return Err(no_group(key, &group_name));
+                };
+
+                see_consumer(group, key, &group_name, &consumer, &mut propagated);
+
+                let ids: Vec<StreamId> = after
+                    .next()
+                    .into_iter()
+                    .flat_map(|start| group.pending.range(start..))
+                    .filter(|(_, pending)| pending.consumer == consumer)
+                    .map(|(id, _)| *id)
+                    .take(count)
+                    .collect();
+
+                // Deleted entries are still pending, they come without fields
+                ids.iter()
+                    .map(|id| match stream.get(id) {
+                        Some(fields) => entry_reply(id, fields),
+                        None => SiderResponse::Array(vec![
+                            SiderResponse::BulkString(id.to_bytes()),
+                            SiderResponse::NullArray,
+                        ]),
+                    })
+                    .collect()
+            }
+        };
+
+        if history || !entries.is_empty() {
+            reply.push(SiderResponse::Array(vec![
+                SiderResponse::BulkString(key.clone()),
+                SiderResponse::Array(entries),
+            ]));
+        }
+    }
+
+    db.propagate_as(propagated);
+
+    if reply.is_empty() {
+        return Ok(SiderResponse::NullArray);
+    }
+
+    Ok(SiderResponse::Array(reply))
+}
+
+/// XDEL key id [id ...]
+pub fn xdel(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, values) = match command.args {
+        CommandArgs::KeyWithValues { key, values } => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let ids = values
+        .iter()
+        .map(|id| parse_id(id))
+        .collect::<Result<Vec<_>, SiderError>>()?;
+
+    let Some(stream) = get_stream_mut(db, &key_name)? else {
+        return Ok(SiderResponse::Integer(0));
+    };
+
+    let deleted = ids.iter().filter(|id| stream.remove(id)).count();
+
+    if deleted > 0 {
+        notify_keyspace_event(NOTIFY_STREAM, "xdel", &key_name);
+        db.signal_modified_key(&key_name);
+    }
+
+    Ok(SiderResponse::Integer(deleted as i64))
+}
+
+/// XTRIM key MAXLEN|MINID [=|~] threshold [LIMIT count]
+pub fn xtrim(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, values) = match command.args {
+        CommandArgs::KeyWithValues { key, values } => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let (options, parsed) = parse_add_options(&values, false)?;
+
+    if options.trim.is_none() || parsed != values.len() {
+        return Err(SiderError::Syntax);
+    }
+
+    let Some(stream) = get_stream_mut(db, &key_name)? else {
+        return Ok(SiderResponse::Integer(0));
+    };
+
+    let trimmed = trim_stream(stream, &options);
+
+    if trimmed > 0 {
+        notify_keyspace_event(NOTIFY_STREAM, "xtrim", &key_name);
+        db.signal_modified_key(&key_name);
+    }
+
+    Ok(SiderResponse::Integer(trimmed as i64))
+}
+
+/// XSETID key last-id, the ID new entries have to be above.
+pub fn xsetid(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, value) = match command.args {
+        CommandArgs::KeyWithValue { key, value } => (key, value),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let id = parse_id(&value)?;
+
+    let Some(stream) = get_stream_mut(db, &key_name)? else {
+        return Err(SiderError::NoSuchKey);
+    };
+
+    if stream.last_entry_id().is_some_and(|last| id < last) {
+        return Err(SiderError::StreamLastIdTooSmall);
+    }
+
+    stream.last_id = id;
+    notify_keyspace_event(NOTIFY_STREAM, "xsetid", &key_name);
+    db.signal_modified_key(&key_name);
+
+    Ok(SiderResponse::Ok)
+}
+
+// `$` is the last ID of the stream
+fn parse_group_id(stream: &Stream, value: &[u8]) -> Result<StreamId, SiderError> {
+    match value {
+        b"$" => Ok(stream.last_id),
+        _ => parse_id(value),
+    }
+}
+
+// [MKSTREAM] [ENTRIESREAD entries-read], only CREATE accepts MKSTREAM
+fn parse_group_options(args: &[Bytes], create: bool) -> Result<bool, SiderError> {
+    let mut mkstream = false;
+    let mut i = 0;
+
+    while let Some(arg) = args.get(i) {
+        match arg.to_ascii_uppercase().as_slice() {
+            b"MKSTREAM" if create => mkstream = true,
+            // Lag isn't tracked, the value is only checked
+            b"ENTRIESREAD" => {
+                let entries_read = args.get(i + 1).ok_or(SiderError::Syntax)?;
+                parse_number::<i64>(entries_read).ok_or(SiderError::NotInt)?;
+                i += 1;
+            }
+            _ => return Err(SiderError::Syntax),
+        }
+        i += 1;
+    }
+
+    Ok(mkstream)
+}
+
+/// XGROUP CREATE key group id|$ [MKSTREAM] [ENTRIESREAD entries-read]
+/// XGROUP SETID key group id|$ [ENTRIESREAD entries-read]
+/// XGROUP DESTROY key group
+/// XGROUP CREATECONSUMER key group consumer
+/// XGROUP DELCONSUMER key group consumer
+pub fn xgroup(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (subcommand, args) = match command.args {
+        CommandArgs::KeyWithValues { key, values } => (key.to_ascii_uppercase(), values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let valid_args = match subcommand.as_slice() {
+        b"CREATE" | b"SETID" => args.len() >= 3,
+        b"DESTROY" => args.len() == 2,
+        b"CREATECONSUMER" | b"DELCONSUMER" => args.len() == 3,
+        _ => return Err(SiderError::UnknownSubcommand),
+    };
+
+    if !valid_args {
+        return Err(SiderError::WrongNumberArgs);
+    }
+
+    let (key_name, group_name) = (&args[0], &args[1]);
+
+    match subcommand.as_slice() {
+        b"CREATE" => {
+            let mkstream = parse_group_options(&args[3..], true)?;
+
+            if get_stream(db, key_name)?.is_none() {
+                if !mkstream {
+                    return Err(SiderError::XGroupKeyMissing);
+                }
+
+                db.insert(
+                    key_name.clone(),
+                    DbValue::StreamKey(KeyStream {
+                        name: key_name.clone(),
+                        ..Default::default()
+                    }),
+                );
+            }
+
+            let Some(stream) = get_stream_mut(db, key_name)? else {
+                return Err(SiderError::DatabaseError);
+            };
+
+            let id = parse_group_id(stream, &args[2])?;
+
+            if stream.groups.contains_key(group_name) {
+                return Err(SiderError::BusyGroup);
+            }
+
+            stream
+                .groups
+                .insert(group_name.clone(), ConsumerGroup::new(id));
+            notify_keyspace_event(NOTIFY_STREAM, "xgroup-create", key_name);
+            db.signal_modified_key(key_name);
+
+            Ok(SiderResponse::Ok)
+        }
+        b"SETID" => {
+            parse_group_options(&args[3..], false)?;
+
+            let Some(stream) = get_stream_mut(db, key_name)? else {
+                return Err(SiderError::XGroupKeyMissing);
+            };
+
+            let id = parse_group_id(stream, &args[2])?;
+            let Some(group) = stream.groups.get_mut(group_name) else {
+                return Err(no_group(key_name, group_name));
+            };
+
+            group.last_delivered = id;
+            notify_keyspace_event(NOTIFY_STREAM, "xgroup-setid", key_name);
+            db.signal_modified_key(key_name);
+
+            Ok(SiderResponse::Ok)
+        }
+        b"DESTROY" => {
+            let Some(stream) = get_stream_mut(db, key_name)? else {
+                return Err(SiderError::XGroupKeyMissing);
+            };
+
+            if stream.groups.shift_remove(group_name).is_none() {
+                return Ok(SiderResponse::Integer(0));
+            }
+
+            notify_keyspace_event(NOTIFY_STREAM, "xgroup-destroy", key_name);
+            db.signal_modified_key(key_name);
+
+            // Clients blocked on the group get an error
+            db.signal_key_as_ready(key_name);
+
+            Ok(SiderResponse::Integer(1))
+        }
+        b"CREATECONSUMER" => {
+            let group = get_group_mut(db, key_name, group_name)?;
+
+            if group.consumers.contains_key(&args[2]) {
+                return Ok(SiderResponse::Integer(0));
+            }
+
+            group.see_consumer(&args[2], current_time_millis());
+            notify_keyspace_event(NOTIFY_STREAM, "xgroup-createconsumer", key_name);
+            db.signal_modified_key(key_name);
+
+            Ok(SiderResponse::Integer(1))
+        }
+        _ => {
+            let group = get_group_mut(db, key_name, group_name)?;
+
+            if group.consumers.shift_remove(&args[2]).is_none() {
+                return Ok(SiderResponse::Integer(0));
+            }
+
+            // Its pending entries are dropped along with it
+            let pending = group.pending_count(&args[2]);
+            group
+                .pending
+                .retain(|_, pending| pending.consumer != args[2]);
+            notify_keyspace_event(NOTIFY_STREAM, "xgroup-delconsumer", key_name);
+            db.signal_modified_key(key_name);
+
+            Ok(SiderResponse::Integer(pending as i64))
+        }
+    }
+}
+
+/// XACK key group id [id ...], removes the entries from the pending list.
+pub fn xack(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, values) = match command.args {
+        CommandArgs::KeyWithValues { key, values } if values.len() >= 2 => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let ids = values[1..]
+        .iter()
+        .map(|id| parse_id(id))
+        .collect::<Result<Vec<_>, SiderError>>()?;
+
+    let group = match get_group_mut(db, &key_name, &values[0]) {
+        Ok(group) => group,
+        Err(SiderError::NoGroup(..)) => return Ok(SiderResponse::Integer(0)),
+        Err(e) => return Err(e),
+    };
+
+    let acknowledged = ids
+        .iter()
+        .filter(|id| group.pending.remove(id).is_some())
+        .count();
+
+    Ok(SiderResponse::Integer(acknowledged as i64))
+}
+
+/// XPENDING key group [[IDLE min-idle-time] start end count [consumer]]
+///
+/// Without a range, a summary: the number of pending entries, the smallest
+/// and greatest of their IDs and how many each consumer has.
+pub fn xpending(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, values) = match command.args {
+        CommandArgs::KeyWithValues { key, values } if !values.is_empty() => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let group_name = &values[0];
+    let mut args = &values[1..];
+
+    let mut min_idle = 0;
+    if args
+        .first()
+        .is_some_and(|arg| arg.eq_ignore_ascii_case(b"IDLE"))
+    {
+        let idle = args.get(1).ok_or(SiderError::Syntax)?;
+        min_idle = parse_number::<i64>(idle).ok_or(SiderError::NotInt)?;
+        args = &args[2..];
+    }
+
+    let range = match args {
+        [] if min_idle == 0 && values.len() == 1 => None,
+        [start, end, count] => Some((start, end, count, None)),
+        [start, end, count, consumer] => Some((start, end, count, Some(consumer))),
+        _ => return Err(SiderError::Syntax),
+    };
+
+    let group = get_group_mut(db, &key_name, group_name)?;
+
+    let Some((start, end, count, consumer)) = range else {
+        let (Some(first), Some(last)) = (
+            group.pending.keys().next(),
+            group.pending.keys().next_back(),
+        ) else {
+            return Ok(SiderResponse::Array(vec![
+                SiderResponse::Integer(0),
+                SiderResponse::NullBulk,
+                SiderResponse::NullBulk,
+                SiderResponse::NullArray,
+            ]));
+        };
+
+        // Sorted by name like Redis
+        let mut consumers: BTreeMap<&Bytes, i64> = BTreeMap::new();
+        for pending in group.pending.values() {
+            *consumers.entry(&pending.consumer).or_default() += 1;
+        }
+
+        let consumers = consumers
+            .into_iter()
+            .map(|(consumer, count)| {
+                SiderResponse::bulk_strings([consumer.clone(), Bytes::from(count.to_string())])
+            })
+            .collect();
+
+        return Ok(SiderResponse::Array(vec![
+            SiderResponse::Integer(group.pending.len() as i64),
+            SiderResponse::BulkString(first.to_bytes()),
+            SiderResponse::BulkString(last.to_bytes()),
+            SiderResponse::Array(consumers),
+        ]));
+    };
+
+    let (start, end, count) = (
+        parse_range_start(start)?,
+        parse_range_end(end)?,
+        parse_count(count)?,
+    );
+
+    let (Some(start), Some(end)) = (start, end) else {
+        return Ok(SiderResponse::Array(Vec::new()));
+    };
+
+    if start > end {
+        return Ok(SiderResponse::Array(Vec::new()));
+    }
+
+    let now = current_time_millis();
+
+    let entries = group
+        .pending
+        .range(start..=end)
+        .filter(|(_, pending)| consumer.is_none_or(|consumer| pending.consumer == consumer))
+        .filter(|(_, pending)| now - pending.delivered_at >= min_idle)
+        .take(count)
+        .map(|(id, pending)| {
+            SiderResponse::Array(vec![
+                SiderResponse::BulkString(id.to_bytes()),
+                SiderResponse::BulkString(pending.consumer.clone()),
+                SiderResponse::Integer((now - pending.delivered_at).max(0)),
+                SiderResponse::Integer(pending.delivery_count as i64),
+            ])
+        })
+        .collect();
+
+    Ok(SiderResponse::Array(entries))
+}
+
+#[derive(Default)]
+struct ClaimOptions {
+    // Delivery time given with IDLE or TIME
+    delivered_at: Option<i64>,
+    retry_count: Option<u64>,
+    force: bool,
+    justid: bool,
+    last_id: Option<StreamId>,
+}
+
+fn parse_claim_options(args: &[Bytes]) -> Result<ClaimOptions, SiderError> {
+    let mut options = ClaimOptions::default();
+    let mut i = 0;
+
+    while let Some(arg) = args.get(i) {
+        let option = arg.to_ascii_uppercase();
+        let value = args.get(i + 1);
+
+        match (option.as_slice(), value) {
+            (b"FORCE", _) => options.force = true,
+            (b"JUSTID", _) => options.justid = true,
+            (b"IDLE", Some(idle)) => {
+                let idle = parse_number::<i64>(idle).ok_or(SiderError::NotInt)?;
+                options.delivered_at = Some(current_time_millis() - idle.max(0));
+                i += 1;
+            }
+            (b"TIME", Some(time)) => {
+                options.delivered_at = Some(parse_number(time).ok_or(SiderError::NotInt)?);
+                i += 1;
+            }
+            (b"RETRYCOUNT", Some(count)) => {
+                options.retry_count = Some(parse_number(count).ok_or(SiderError::NotInt)?);
+                i += 1;
+            }
+            (b"LASTID", Some(id)) => {
+                options.last_id = Some(parse_id(id)?);
+                i += 1;
+            }
+            _ => return Err(SiderError::Syntax),
+        }
+        i += 1;
+    }
+
+    Ok(options)
+}
+
+// Gives the pending entry to `consumer` if it has been idle long enough,
+// creating it with FORCE. Returns false if it wasn't claimed.
+fn claim_entry(
+    group: &mut ConsumerGroup,
+    id: StreamId,
+    consumer: &Bytes,
+    min_idle: i64,
+    options: &ClaimOptions,
+) -> bool {
+    let now = current_time_millis();
+
+    let pending = match group.pending.entry(id) {
+        Entry::Occupied(entry) if now - entry.get().delivered_at < min_idle => return false,
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(_) if !options.force => return false,
+        Entry::Vacant(entry) => entry.insert(PendingEntry {
+            consumer: consumer.clone(),
+            delivered_at: now,
+            delivery_count: 0,
+        }),
+    };
+
+    pending.consumer = consumer.clone();
+    pending.delivered_at = options.delivered_at.unwrap_or(now);
+
+    // JUSTID doesn't count as a delivery
+    match options.retry_count {
+        Some(count) => pending.delivery_count = count,
+        None if !options.justid => pending.delivery_count += 1,
+        None => {}
+    }
+
+    true
+}
+
+fn claimed_reply(stream: &Stream, claimed: &[StreamId], justid: bool) -> SiderResponse {
+    SiderResponse::Array(
+        claimed
+            .iter()
+            .filter_map(|id| match justid {
+                true => Some(SiderResponse::BulkString(id.to_bytes())),
+                false => stream.get(id).map(|fields| entry_reply(id, fields)),
+            })
+            .collect(),
+    )
+}
+
+#[derive(Default)]
+struct Claims {
+    claimed: Vec<StreamId>,
+    // Pending entries dropped because they were deleted from the stream
+    deleted: Vec<StreamId>,
+    propagated: Vec<Command>,
+}
+
+// Runs a claim for each ID, pending entries deleted from the stream are
+// dropped instead
+fn claim_entries<I>(
+    stream: &mut Stream,
+    key: &Bytes,
+    group_name: &Bytes,
+    consumer: &Bytes,
+    ids: I,
+    min_idle: i64,
+    options: &ClaimOptions,
+) -> Result<Claims, SiderError>
+where
+    I: IntoIterator<Item = StreamId>,
+{
+    let mut claims = Claims::default();
+
+    for id in ids {
+        let exists = stream.get(&id).is_some();
+        let Some(group) = stream.groups.get_mut(group_name) else {
+            return Err(no_group(key, group_name));
+        };
+
+        if !exists {
+            if group.pending.remove(&id).is_some() {
+                claims.propagated.push(stream_command(
+                    CommandType::XACK,
+                    key,
+                    vec![group_name.clone(), id.to_bytes()],
+                ));
+                claims.deleted.push(id);
+            }
+            continue;
+        }
+
+        if claim_entry(group, id, consumer, min_idle, options) {
+            if let Some(pending) = group.pending.get(&id) {
+                claims
+                    .propagated
+                    .push(claim_command(key, group_name, &id, pending));
+            }
+            claims.claimed.push(id);
+        }
+    }
+
+    Ok(claims)
+}
+
+/// XCLAIM key group consumer min-idle-time id [id ...] [IDLE ms]
+/// [TIME unix-time-milliseconds] [RETRYCOUNT count] [FORCE] [JUSTID]
+/// [LASTID lastid]
+///
+/// Takes over pending entries idle for at least `min-idle-time`
+/// milliseconds, e.g. from a consumer that crashed.
+pub fn xclaim(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, values) = match command.args {
+        CommandArgs::KeyWithValues { key, values } if values.len() >= 4 => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let (group_name, consumer) = (&values[0], &values[1]);
+    let min_idle = parse_number::<i64>(&values[2]).ok_or(SiderError::NotInt)?;
+
+    // IDs come first, options start at the first argument that isn't one
+    let ids: Vec<StreamId> = values[3..]
+        .iter()
+        .map_while(|id| StreamId::parse(id, 0))
+        .collect();
+
+    if ids.is_empty() {
+        return Err(SiderError::InvalidStreamId);
+    }
+
+    let options = parse_claim_options(&values[3 + ids.len()..])?;
+
+    let group = get_group_mut(db, &key_name, group_name)?;
+    let mut propagated = Vec::new();
+
+    if let Some(last_id) = options.last_id.filter(|id| *id > group.last_delivered) {
+        group.last_delivered = last_id;
+    }
+
+    see_consumer(group, &key_name, group_name, consumer, &mut propagated);
+
+    let Some(stream) = get_stream_mut(db, &key_name)? else {
+        return Err(no_group(&key_name, group_name));
+    };
+
+    let claims = claim_entries(
+        stream, &key_name, group_name, consumer, ids, min_idle, &options,
+    )?;
+
+    let reply = claimed_reply(stream, &claims.claimed, options.justid);
+
+    propagated.extend(claims.propagated);
+    if let Some(last_id) = options.last_id {
+        propagated.push(stream_command(
+            CommandType::XGROUP,
+            &Bytes::from_static(b"SETID"),
+            vec![key_name.clone(), group_name.clone(), last_id.to_bytes()],
+        ));
+    }
+
+    db.propagate_as(propagated);
+
+    Ok(reply)
+}
+
+/// XAUTOCLAIM key group consumer min-idle-time start [COUNT count] [JUSTID]
+///
+/// Like XCLAIM for the pending entries from `start`, replies with the ID to
+/// continue from (`0-0` once the whole list was scanned), the claimed entries
+/// and the IDs of the pending entries that were deleted from the stream.
+pub fn xautoclaim(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, values) = match command.args {
+        CommandArgs::KeyWithValues { key, values } if values.len() >= 4 => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let (group_name, consumer) = (&values[0], &values[1]);
+    let min_idle = parse_number::<i64>(&values[2]).ok_or(SiderError::NotInt)?;
+    let start = parse_range_start(&values[3])?;
+
+    let mut options = ClaimOptions::default();
+    let mut count = AUTOCLAIM_DEFAULT_COUNT;
+    let mut i = 4;
+
+    while let Some(arg) = values.get(i) {
+        match (arg.to_ascii_uppercase().as_slice(), values.get(i + 1)) {
+            (b"COUNT", Some(value)) => {
+                count = parse_number::<usize>(value)
+                    .filter(|count| *count > 0)
+                    .ok_or(SiderError::NotPositive)?;
+                i += 1;
+            }
+            (b"JUSTID", _) => options.justid = true,
+            _ => return Err(SiderError::Syntax),
+        }
+        i += 1;
+    }
+
+    let group = get_group_mut(db, &key_name, group_name)?;
+    let mut propagated = Vec::new();
+
+    see_consumer(group, &key_name, group_name, consumer, &mut propagated);
+
+    let Some(stream) = get_stream_mut(db, &key_name)? else {
+        return Err(no_group(&key_name, group_name));
+    };
+
+    let mut claims = Claims::default();
+    let mut cursor = StreamId::MIN;
+    // Scans a bounded part of the list so a huge one doesn't stall the server
+    let mut attempts = count.saturating_mul(10);
+    let mut next = start;
+
+    while let Some(from) = next {
+        let Some(id) = stream
+            .groups
+            .get(group_name)
+            .and_then(|group| group.pending.range(from..).next())
+            .map(|(id, _)| *id)
+        else {
+            break;
+        };
+
+        if claims.claimed.len() >= count || attempts == 0 {
+            cursor = id;
+            break;
+        }
+
+        let mut new_claims = claim_entries(
+            stream,
+            &key_name,
+            group_name,
+            consumer,
+            [id],
+            min_idle,
+            &options,
+        )?;
+
+        claims.claimed.append(&mut new_claims.claimed);
+        claims.deleted.append(&mut new_claims.deleted);
+        claims.propagated.append(&mut new_claims.propagated);
+
+        attempts -= 1;
+        next = id.next();
+    }
+
+    let reply = SiderResponse::Array(vec![
+        SiderResponse::BulkString(cursor.to_bytes()),
+        claimed_reply(stream, &claims.claimed, options.justid),
+        SiderResponse::bulk_strings(claims.deleted.iter().map(|id| id.to_bytes())),
+    ]);
+
+    propagated.append(&mut claims.propagated);
+    db.propagate_as(propagated);
+
+    Ok(reply)
+}
@@ -0,0 +1,161 @@
+use crate::aof::write_aof_transaction;
+use crate::client::{Client, WatchedKey};
+use crate::errors::SiderError;
+use crate::process::{execute_command, logged_commands, serve_blocked_clients};
+use crate::response::SiderResponse;
+use crate::types::{Command, CommandArgs, Db, Keyspace, Store};
+use bytes::Bytes;
+
+pub fn multi(client: &mut Client) -> Result<SiderResponse, SiderError> {
+    if client.transaction.is_some() {
+        return Err(SiderError::NestedMulti);
+    }
+
+    client.transaction = Some(Vec::new());
+    client.transaction_aborted = false;
+    Ok(SiderResponse::Ok)
+}
+
+pub async fn discard(db: &Db, client: &mut Client) -> Result<SiderResponse, SiderError> {
+    if client.transaction.take().is_none() {
+        return Err(SiderError::DiscardWithoutMulti);
+    }
+
+    client.transaction_aborted = false;
+    unwatch_all_keys(db, client).await;
+    Ok(SiderResponse::Ok)
+}
+
+/// Registers each key in its shard, EXEC fails if any of them changes
+/// before it runs.
+pub fn watch(
+    db: &mut Keyspace,
+    client: &mut Client,
+    command: Command,
+) -> Result<SiderResponse, SiderError> {
+    let keys = match command.args {
+        CommandArgs::MultipleKeys(keys) => keys,
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    for key in keys {
+        // Watching a key twice keeps the version seen first
+        if client.watched.iter().any(|watched| watched.key == key) {
+            continue;
+        }
+
+        let version = db.watch(&key);
+        let live = is_live(db, &key);
+        client.watched.push(WatchedKey { key, version, live });
+    }
+
+    Ok(SiderResponse::Ok)
+}
+
+pub async fn unwatch(db: &Db, client: &mut Client) -> Result<SiderResponse, SiderError> {
+    unwatch_all_keys(db, client).await;
+    Ok(SiderResponse::Ok)
+}
+
+/// Forgets the keys the client watches, their shards only track them while
+/// somebody does.
+pub async fn unwatch_all_keys(db: &Store, client: &mut Client) {
+    if client.watched.is_empty() {
+        return;
+    }
+
+    let keys: Vec<Bytes> = client
+        .watched
+        .drain(..)
+        .map(|watched| watched.key)
+        .collect();
+    let mut db = db.lock(&keys).await;
+
+    for key in keys {
+        db.unwatch(&key);
+    }
+}
+
+// An expired key counts as missing, whether or not it was deleted yet
+fn is_live(db: &Keyspace, key: &[u8]) -> bool {
+    db.get(key).is_some_and(|value| !value.is_expired())
+}
+
+// Modified, deleted, created or expired since WATCH
+fn is_modified(db: &Keyspace, watched: &WatchedKey) -> bool {
+    db.watched_version(&watched.key) != Some(watched.version)
+        || (watched.live && !is_live(db, &watched.key))
+}
+
+/// Runs every queued command and replies with an array of their results,
+/// or a null reply if a watched key was modified since WATCH. The shards are
+/// locked for the whole queue so no other client can see the transaction
+/// half applied.
+pub async fn exec(
+    db: &Db,
+    client: &mut Client,
+    restore: bool,
+) -> Result<SiderResponse, SiderError> {
+    let Some(queued) = client.transaction.take() else {
+        return Err(SiderError::ExecWithoutMulti);
+    };
+
+    let watched = std::mem::take(&mut client.watched);
+    let aborted = std::mem::take(&mut client.transaction_aborted);
+
+    // The shards of every key queued or watched, all of them if a command
+    // goes through every key
+    let keys: Option<Vec<Bytes>> = queued
+        .iter()
+        .map(|command| command.shard_keys())
+        .chain(std::iter::once(Some(
+            watched.iter().map(|watched| watched.key.clone()).collect(),
+        )))
+        .collect::<Option<Vec<_>>>()
+        .map(|keys| keys.concat());
+
+    let mut db = match keys {
+        Some(keys) => db.lock(&keys).await,
+        None => db.lock_all().await,
+    };
+
+    // The keys stop being watched whether the transaction runs or not
+    let modified = watched.iter().any(|watched| is_modified(&db, watched));
+    for watched in &watched {
+        db.unwatch(&watched.key);
+    }
+
+    if aborted {
+        return Err(SiderError::ExecAbort);
+    }
+
+    if modified {
+        return Ok(SiderResponse::NullArray);
+    }
+
+    let mut replies = Vec::with_capacity(queued.len());
+    let mut logged = Vec::new();
+
+    // A failing command doesn't stop the others, its error takes its place
+    for command in queued {
+        match execute_command(&mut db, client, command.clone()) {
+            Ok(reply) => {
+                logged.extend(logged_commands(&mut db, command));
+                replies.push(reply);
+            }
+            Err(e) => replies.push(SiderResponse::Error(e)),
+        }
+    }
+
+    let written = if restore {
+        Ok(())
+    } else {
+        write_aof_transaction(&logged).await
+    };
+
+    serve_blocked_clients(&mut db, client, restore).await;
+
+    // Applied, but not on disk
+    written.map_err(|e| SiderError::AofWriteFailed(e.to_string()))?;
+    Ok(SiderResponse::Array(replies))
+}
@@ -0,0 +1,596 @@
+use crate::errors::SiderError;
+use crate::notify::{notify_keyspace_event, NOTIFY_GENERIC, NOTIFY_ZSET};
+use crate::response::SiderResponse;
+use crate::types::{Command, CommandArgs, DbValue, KeyZSet, Keyspace, SortedSet};
+use crate::utils::parse_number;
+use crate::zset::LexBound;
+use bytes::Bytes;
+use std::ops::Bound;
+
+// Scores accept what Redis does, including `inf`, `+inf` and `-inf`
+fn parse_score(value: &[u8]) -> Result<f64, SiderError> {
+    parse_number::<f64>(value)
+        .filter(|score| !score.is_nan())
+        .ok_or(SiderError::NotFloat)
+}
+
+// `1.5` includes the score, `(1.5` excludes it
+fn parse_score_bound(value: &[u8]) -> Result<Bound<f64>, SiderError> {
+    let (exclusive, number) = match value.strip_prefix(b"(") {
+        Some(number) => (true, number),
+        None => (false, value),
+    };
+
+    let score = parse_number::<f64>(number)
+        .filter(|score| !score.is_nan())
+        .ok_or(SiderError::MinMaxNotFloat)?;
+
+    Ok(if exclusive {
+        Bound::Excluded(score)
+    } else {
+        Bound::Included(score)
+    })
+}
+
+fn parse_lex_bound(value: &[u8]) -> Result<LexBound, SiderError> {
+    match value {
+        b"-" => Ok(LexBound::Min),
+        b"+" => Ok(LexBound::Max),
+        [b'[', member @ ..] => Ok(LexBound::Included(Bytes::copy_from_slice(member))),
+        [b'(', member @ ..] => Ok(LexBound::Excluded(Bytes::copy_from_slice(member))),
+        _ => Err(SiderError::InvalidLexRange),
+    }
+}
+
+fn get_zset<'a>(db: &'a Keyspace, key: &[u8]) -> Result<Option<&'a SortedSet>, SiderError> {
+    match db.get(key) {
+        Some(DbValue::ZSetKey(zset)) => Ok(Some(&zset.data)),
+        None => Ok(None),
+        Some(_) => Err(SiderError::WrongType),
+    }
+}
+
+// Creates the sorted set if needed, the caller removes it if it stays empty
+fn get_or_create_zset<'a>(
+    db: &'a mut Keyspace,
+    key: &Bytes,
+) -> Result<&'a mut SortedSet, SiderError> {
+    if !db.contains_key(key) {
+        db.insert(
+            key.clone(),
+            DbValue::ZSetKey(KeyZSet {
+                name: key.clone(),
+                ..Default::default()
+            }),
+        );
+    }
+
+    match db.get_mut(key) {
+        Some(DbValue::ZSetKey(zset)) => Ok(&mut zset.data),
+        _ => Err(SiderError::WrongType),
+    }
+}
+
+// An emptied sorted set is deleted, like every other type
+fn remove_if_empty(db: &mut Keyspace, key: &Bytes) {
+    if matches!(db.get(key), Some(DbValue::ZSetKey(zset)) if zset.data.is_empty()) {
+        db.swap_remove(key);
+        notify_keyspace_event(NOTIFY_GENERIC, "del", key);
+    }
+}
+
+// Flat [member, score, ...] array, or only members without scores
+fn members_reply<'a, I>(members: I, with_scores: bool) -> SiderResponse
+where
+    I: IntoIterator<Item = (&'a Bytes, f64)>,
+{
+    let mut reply = Vec::new();
+
+    for (member, score) in members {
+        reply.push(SiderResponse::BulkString(member.clone()));
+
+        if with_scores {
+            reply.push(SiderResponse::Double(score));
+        }
+    }
+
+    SiderResponse::Array(reply)
+}
+
+#[derive(Default)]
+struct ZaddOptions {
+    nx: bool,
+    xx: bool,
+    gt: bool,
+    lt: bool,
+    ch: bool,
+    incr: bool,
+}
+
+/// ZADD key [NX|XX] [GT|LT] [CH] [INCR] score member [score member ...]
+pub fn zadd(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, args) = match command.args {
+        CommandArgs::KeyWithValues { key, values } => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let mut options = ZaddOptions::default();
+    let mut nb_options = 0;
+
+    for arg in args.iter() {
+        match arg.to_ascii_uppercase().as_slice() {
+            b"NX" => options.nx = true,
+            b"XX" => options.xx = true,
+            b"GT" => options.gt = true,
+            b"LT" => options.lt = true,
+            b"CH" => options.ch = true,
+            b"INCR" => options.incr = true,
+            _ => break,
+        }
+        nb_options += 1;
+    }
+
+    let pairs = &args[nb_options..];
+
+    if pairs.is_empty() || pairs.len() % 2 != 0 {
+        return Err(SiderError::Syntax);
+    }
+
+    if options.nx && options.xx {
+        return Err(SiderError::XXAndNX);
+    }
+
+    if [options.nx, options.gt, options.lt]
+        .iter()
+        .filter(|set| **set)
+        .count()
+        > 1
+    {
+        return Err(SiderError::GTLTAndNX);
+    }
+
+    if options.incr && pairs.len() > 2 {
+        return Err(SiderError::IncrSinglePair);
+    }
+
+    // Every score is checked before anything is added
+    let pairs = pairs
+        .chunks(2)
+        .map(|pair| Ok((parse_score(&pair[0])?, pair[1].clone())))
+        .collect::<Result<Vec<_>, SiderError>>()?;
+
+    if get_zset(db, &key_name)?.is_none() && options.xx {
+        return Ok(match options.incr {
+            true => SiderResponse::NullBulk,
+            false => SiderResponse::Integer(0),
+        });
+    }
+
+    let zset = get_or_create_zset(db, &key_name)?;
+    let mut added = 0;
+    let mut changed = 0;
+    let mut incremented = None;
+
+    for (score, member) in pairs {
+        let current = zset.score(&member);
+
+        if (options.nx && current.is_some()) || (options.xx && current.is_none()) {
+            continue;
+        }
+
+        let score = match (options.incr, current) {
+            (true, Some(current)) => current + score,
+            _ => score,
+        };
+
+        // inf + -inf
+        if score.is_nan() {
+            remove_if_empty(db, &key_name);
+            return Err(SiderError::ScoreNaN);
+        }
+
+        // GT and LT only restrict updates, new members are always added
+        if let Some(current) = current {
+            if (options.gt && score <= current) || (options.lt && score >= current) {
+                continue;
+            }
+
+            if score != current {
+                changed += 1;
+            }
+        } else {
+            added += 1;
+        }
+
+        zset.insert(member, score);
+        incremented = Some(score);
+    }
+
+    remove_if_empty(db, &key_name);
+
+    if added + changed > 0 {
+        let event = if options.incr { "zincr" } else { "zadd" };
+        notify_keyspace_event(NOTIFY_ZSET, event, &key_name);
+        db.signal_modified_key(&key_name);
+    }
+
+    if options.incr {
+        return Ok(incremented.map_or(SiderResponse::NullBulk, SiderResponse::Double));
+    }
+
+    match options.ch {
+        true => Ok(SiderResponse::Integer(added + changed)),
+        false => Ok(SiderResponse::Integer(added)),
+    }
+}
+
+pub fn zincrby(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, values) = match command.args {
+        CommandArgs::KeyWithValues { key, values } if values.len() == 2 => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let increment = parse_score(&values[0])?;
+    let member = values[1].clone();
+
+    let zset = get_or_create_zset(db, &key_name)?;
+    let score = zset.score(&member).unwrap_or(0.0) + increment;
+
+    if score.is_nan() {
+        remove_if_empty(db, &key_name);
+        return Err(SiderError::ScoreNaN);
+    }
+
+    zset.insert(member, score);
+    notify_keyspace_event(NOTIFY_ZSET, "zincr", &key_name);
+    db.signal_modified_key(&key_name);
+
+    Ok(SiderResponse::Double(score))
+}
+
+pub fn zrem(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, members) = match command.args {
+        CommandArgs::KeyWithValues { key, values } => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let zset = match db.get_mut(&key_name) {
+        Some(DbValue::ZSetKey(zset)) => &mut zset.data,
+        None => return Ok(SiderResponse::Integer(0)),
+        Some(_) => return Err(SiderError::WrongType),
+    };
+
+    let removed = members
+        .iter()
+        .filter(|member| zset.remove(member).is_some())
+        .count();
+
+    if removed > 0 {
+        notify_keyspace_event(NOTIFY_ZSET, "zrem", &key_name);
+        db.signal_modified_key(&key_name);
+    }
+
+    remove_if_empty(db, &key_name);
+    Ok(SiderResponse::Integer(removed as i64))
+}
+
+pub fn zscore(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, member) = match &command.args {
+        CommandArgs::KeyWithValue { key, value } => (key, value),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let score = get_zset(db, key_name)?.and_then(|zset| zset.score(member));
+
+    Ok(score.map_or(SiderResponse::NullBulk, SiderResponse::Double))
+}
+
+pub fn zcard(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let key_name = match &command.args {
+        CommandArgs::SingleKey(key) => key,
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let len = get_zset(db, key_name)?.map_or(0, |zset| zset.len());
+
+    Ok(SiderResponse::Integer(len as i64))
+}
+
+pub fn zrank(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    rank(db, command, false)
+}
+
+pub fn zrevrank(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    rank(db, command, true)
+}
+
+fn rank(db: &Keyspace, command: Command, rev: bool) -> Result<SiderResponse, SiderError> {
+    let (key_name, member) = match &command.args {
+        CommandArgs::KeyWithValue { key, value } => (key, value),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let Some(zset) = get_zset(db, key_name)? else {
+        return Ok(SiderResponse::NullBulk);
+    };
+
+    let rank = zset
+        .rank(member)
+        .map(|rank| if rev { zset.len() - 1 - rank } else { rank });
+
+    Ok(rank.map_or(SiderResponse::NullBulk, |rank| {
+        SiderResponse::Integer(rank as i64)
+    }))
+}
+
+#[derive(PartialEq)]
+enum RangeBy {
+    Rank,
+    Score,
+    Lex,
+}
+
+struct RangeOptions {
+    by: RangeBy,
+    rev: bool,
+    // Offset and count, a negative count returns everything after the offset
+    limit: Option<(i64, i64)>,
+    with_scores: bool,
+}
+
+fn parse_range_options(args: &[Bytes], by: RangeBy) -> Result<RangeOptions, SiderError> {
+    let mut options = RangeOptions {
+        by,
+        rev: false,
+        limit: None,
+        with_scores: false,
+    };
+
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.to_ascii_uppercase().as_slice() {
+            b"BYSCORE" => options.by = RangeBy::Score,
+            b"BYLEX" => options.by = RangeBy::Lex,
+            b"REV" => options.rev = true,
+            b"WITHSCORES" => options.with_scores = true,
+            b"LIMIT" => {
+                let (Some(offset), Some(count)) = (args.next(), args.next()) else {
+                    return Err(SiderError::Syntax);
+                };
+
+                let offset = parse_number::<i64>(offset).ok_or(SiderError::NotInt)?;
+                let count = parse_number::<i64>(count).ok_or(SiderError::NotInt)?;
+                options.limit = Some((offset, count));
+            }
+            _ => return Err(SiderError::Syntax),
+        }
+    }
+
+    if options.limit.is_some() && options.by == RangeBy::Rank {
+        return Err(SiderError::Syntax);
+    }
+
+    if options.with_scores && options.by == RangeBy::Lex {
+        return Err(SiderError::Syntax);
+    }
+
+    Ok(options)
+}
+
+// Applies LIMIT to members already in reply order
+fn limited<'a, I>(members: I, limit: Option<(i64, i64)>) -> Vec<(&'a Bytes, f64)>
+where
+    I: Iterator<Item = (&'a Bytes, f64)>,
+{
+    match limit {
+        Some((offset, _)) if offset < 0 => Vec::new(),
+        Some((offset, count)) if count >= 0 => {
+            members.skip(offset as usize).take(count as usize).collect()
+        }
+        Some((offset, _)) => members.skip(offset as usize).collect(),
+        None => members.collect(),
+    }
+}
+
+fn range(
+    zset: &SortedSet,
+    start: &[u8],
+    stop: &[u8],
+    options: &RangeOptions,
+) -> Result<SiderResponse, SiderError> {
+    let members = match options.by {
+        RangeBy::Rank => {
+            let start = parse_number::<i64>(start).ok_or(SiderError::NotIntOrOutOfRange)?;
+            let stop = parse_number::<i64>(stop).ok_or(SiderError::NotIntOrOutOfRange)?;
+            let len = zset.len() as i64;
+
+            let start = if start < 0 {
+                (len + start).max(0)
+            } else {
+                start
+            };
+            let stop = if stop < 0 {
+                len + stop
+            } else {
+                stop.min(len - 1)
+            };
+
+            if start > stop || start >= len {
+                return Ok(SiderResponse::Array(vec![]));
+            }
+
+            let count = (stop - start + 1) as usize;
+            let members: Vec<_> = match options.rev {
+                true => zset.iter().rev().skip(start as usize).take(count).collect(),
+                false => zset.iter().skip(start as usize).take(count).collect(),
+            };
+            members
+        }
+        // With REV the range is given as max then min
+        RangeBy::Score => {
+            let (min, max) = match options.rev {
+                true => (parse_score_bound(stop)?, parse_score_bound(start)?),
+                false => (parse_score_bound(start)?, parse_score_bound(stop)?),
+            };
+
+            match options.rev {
+                true => limited(zset.range_by_score(min, max).rev(), options.limit),
+                false => limited(zset.range_by_score(min, max), options.limit),
+            }
+        }
+        RangeBy::Lex => {
+            let (min, max) = match options.rev {
+                true => (parse_lex_bound(stop)?, parse_lex_bound(start)?),
+                false => (parse_lex_bound(start)?, parse_lex_bound(stop)?),
+            };
+
+            match options.rev {
+                true => limited(zset.range_by_lex(min, max).rev(), options.limit),
+                false => limited(zset.range_by_lex(min, max), options.limit),
+            }
+        }
+    };
+
+    Ok(members_reply(members, options.with_scores))
+}
+
+/// ZRANGE key start stop [BYSCORE|BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+pub fn zrange(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, args) = match &command.args {
+        CommandArgs::KeyWithValues { key, values } if values.len() >= 2 => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let options = parse_range_options(&args[2..], RangeBy::Rank)?;
+
+    match get_zset(db, key_name)? {
+        Some(zset) => range(zset, &args[0], &args[1], &options),
+        None => Ok(SiderResponse::Array(vec![])),
+    }
+}
+
+/// ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
+pub fn zrangebyscore(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, args) = match &command.args {
+        CommandArgs::KeyWithValues { key, values } if values.len() >= 2 => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let options = parse_range_options(&args[2..], RangeBy::Score)?;
+
+    // Only the options ZRANGEBYSCORE has
+    if options.by != RangeBy::Score || options.rev {
+        return Err(SiderError::Syntax);
+    }
+
+    match get_zset(db, key_name)? {
+        Some(zset) => range(zset, &args[0], &args[1], &options),
+        None => Ok(SiderResponse::Array(vec![])),
+    }
+}
+
+pub fn zcount(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    let (key_name, args) = match &command.args {
+        CommandArgs::KeyWithValues { key, values } if values.len() == 2 => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let min = parse_score_bound(&args[0])?;
+    let max = parse_score_bound(&args[1])?;
+
+    let count = get_zset(db, key_name)?.map_or(0, |zset| zset.range_by_score(min, max).count());
+
+    Ok(SiderResponse::Integer(count as i64))
+}
+
+pub fn zpopmin(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    pop(db, command, false)
+}
+
+pub fn zpopmax(db: &mut Keyspace, command: Command) -> Result<SiderResponse, SiderError> {
+    pop(db, command, true)
+}
+
+fn pop(db: &mut Keyspace, command: Command, max: bool) -> Result<SiderResponse, SiderError> {
+    let (key_name, count) = match command.args {
+        CommandArgs::SingleKey(key) => (key, None),
+        CommandArgs::KeyWithValue { key, value } => (key, Some(value)),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let count = match count {
+        Some(count) => parse_number::<usize>(&count).ok_or(SiderError::NotPositive)?,
+        None => 1,
+    };
+
+    let zset = match db.get_mut(&key_name) {
+        Some(DbValue::ZSetKey(zset)) => &mut zset.data,
+        None => return Ok(SiderResponse::Array(vec![])),
+        Some(_) => return Err(SiderError::WrongType),
+    };
+
+    let mut popped = Vec::new();
+
+    for _ in 0..count {
+        let next = if max {
+            zset.pop_last()
+        } else {
+            zset.pop_first()
+        };
+
+        match next {
+            Some(entry) => popped.push(entry),
+            None => break,
+        }
+    }
+
+    if !popped.is_empty() {
+        let event = if max { "zpopmax" } else { "zpopmin" };
+        notify_keyspace_event(NOTIFY_ZSET, event, &key_name);
+        db.signal_modified_key(&key_name);
+    }
+
+    remove_if_empty(db, &key_name);
+
+    Ok(members_reply(
+        popped.iter().map(|(member, score)| (member, *score)),
+        true,
+    ))
+}
+
+pub fn zremrangebyscore(
+    db: &mut Keyspace,
+    command: Command,
+) -> Result<SiderResponse, SiderError> {
+    let (key_name, args) = match &command.args {
+        CommandArgs::KeyWithValues { key, values } if values.len() == 2 => (key, values),
+        _ => return Err(SiderError::InvalidCommand),
+    };
+
+    let min = parse_score_bound(&args[0])?;
+    let max = parse_score_bound(&args[1])?;
+
+    let zset = match db.get_mut(key_name) {
+        Some(DbValue::ZSetKey(zset)) => &mut zset.data,
+        None => return Ok(SiderResponse::Integer(0)),
+        Some(_) => return Err(SiderError::WrongType),
+    };
+
+    let members: Vec<Bytes> = zset
+        .range_by_score(min, max)
+        .map(|(member, _)| member.clone())
+        .collect();
+
+    for member in members.iter() {
+        zset.remove(member);
+    }
+
+    if !members.is_empty() {
+        notify_keyspace_event(NOTIFY_ZSET, "zremrangebyscore", key_name);
+        db.signal_modified_key(key_name);
+    }
+
+    remove_if_empty(db, key_name);
+    Ok(SiderResponse::Integer(members.len() as i64))
+}
@@ -14,6 +14,31 @@ struct Cli {
 
     #[arg(long, short, default_value_t = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)))]
     pub bind: IpAddr,
+
+    /// Keyspace notifications to publish, e.g. `KEA`. Empty disables them.
+    #[arg(long, default_value = "")]
+    pub notify_keyspace_events: String,
+
+    /// Memory limit, e.g. `100mb`. 0 means no limit.
+    #[arg(long, default_value = "0")]
+    pub maxmemory: String,
+
+    /// What to evict once `maxmemory` is reached, e.g. `allkeys-lru`.
+    #[arg(long, default_value = "noeviction")]
+    pub maxmemory_policy: String,
+
+    /// When the AOF is fsynced: `always`, `everysec` or `no`.
+    #[arg(long, default_value = "everysec")]
+    pub appendfsync: String,
+
+    /// Snapshot rules, `<seconds> <changes>` pairs. Empty disables them.
+    #[arg(long, default_value = "3600 1 300 100 60 10000")]
+    pub save: String,
+
+    /// Whether a command cut short at the end of the AOF is dropped on
+    /// startup, `yes` or `no`. The server refuses to start otherwise.
+    #[arg(long, default_value = "yes")]
+    pub aof_load_truncated: String,
 }
 
 fn get_config_path() -> PathBuf {
@@ -55,6 +80,18 @@ static CONFIG: Lazy<Config> = Lazy::new(|| {
         .expect("Failed to set port override")
         .set_override("bind", cli.bind.to_string())
         .expect("Failed to set bind override")
+        .set_override("notify-keyspace-events", cli.notify_keyspace_events)
+        .expect("Failed to set notify-keyspace-events override")
+        .set_override("maxmemory", cli.maxmemory)
+        .expect("Failed to set maxmemory override")
+        .set_override("maxmemory-policy", cli.maxmemory_policy)
+        .expect("Failed to set maxmemory-policy override")
+        .set_override("appendfsync", cli.appendfsync)
+        .expect("Failed to set appendfsync override")
+        .set_override("save", cli.save)
+        .expect("Failed to set save override")
+        .set_override("aof-load-truncated", cli.aof_load_truncated)
+        .expect("Failed to set aof-load-truncated override")
         .build()
         .expect("Failed to load config")
 });
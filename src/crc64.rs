@@ -0,0 +1,36 @@
+//! CRC-64/Jones, the checksum Redis appends to RDB files. Reflected, with
+//! 0xad93d23594c935a9 as polynomial, no initial value nor final xor.
+
+const POLY: u64 = 0x95ac9329ac4bc9b5; // Reflected 0xad93d23594c935a9
+
+const TABLE: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+};
+
+/// Updates `crc` with `data`, start from 0. Checksumming data in several
+/// parts gives the same result as all at once.
+pub fn crc64(crc: u64, data: &[u8]) -> u64 {
+    data.iter().fold(crc, |crc, byte| {
+        TABLE[((crc ^ *byte as u64) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
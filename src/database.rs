@@ -1,51 +1,371 @@
-use crate::aof::get_aof_log_dir;
+use crate::aof::{get_aof_file, get_aof_load_truncated};
+use crate::client::Client;
+use crate::parser::parse_command;
 use crate::process::process_command;
-use crate::types::{Db, DbValue};
-use tokio::time::{self, Duration};
-use tracing::info;
+use crate::resp::RespDecoder;
+use crate::rewrite::rewrite_aof;
+use crate::snapshot::{decode_snapshot, get_snapshot_file, Snapshot};
+use crate::types::Db;
+use crate::utils::current_time_millis;
+use bytes::Bytes;
+use std::io::{Error, ErrorKind};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use tracing::{info, warn};
 
-pub async fn delete_expired_keys(db: Db) {
-    let mut interval = time::interval(Duration::from_secs(60));
+// Commands replayed between two yields, clients are answered meanwhile
+const REPLAY_BATCH: usize = 1000;
 
-    loop {
-        interval.tick().await;
-        info!("Deleting expired keys");
-
-        let mut db_write = db.write().await;
-        db_write.retain(|_, value| match value {
-            DbValue::StringKey(key) => !key.is_expired(),
-            DbValue::ListKey(key) => !key.is_expired(),
-            DbValue::SetKey(key) => !key.is_expired(),
-            DbValue::HashKey(key) => !key.is_expired(),
-        });
+static LOADING: AtomicBool = AtomicBool::new(false);
+static LOADING_START: AtomicI64 = AtomicI64::new(0);
+static LOADING_TOTAL_BYTES: AtomicU64 = AtomicU64::new(0);
+static LOADING_LOADED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// How far the restore in progress got, reported by `INFO persistence`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadingProgress {
+    /// Unix time in milliseconds.
+    pub start: i64,
+    pub total_bytes: u64,
+    pub loaded_bytes: u64,
+}
+
+impl LoadingProgress {
+    pub fn loaded_percent(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        self.loaded_bytes as f64 * 100.0 / self.total_bytes as f64
     }
+
+    /// Seconds left at the pace so far, `now` in milliseconds. Until
+    /// something is loaded the pace is unknown and 1 is reported, like Redis.
+    pub fn eta_seconds(&self, now: i64) -> u64 {
+        if self.loaded_bytes == 0 {
+            return 1;
+        }
+        let elapsed = (now - self.start).max(0) as u64;
+        let remaining = self.total_bytes.saturating_sub(self.loaded_bytes);
+        elapsed.saturating_mul(remaining) / self.loaded_bytes / 1000
+    }
+}
+
+/// Marks the dataset as being restored, commands get `-LOADING` until
+/// `restore` is done. Called before clients are accepted.
+pub fn start_loading() {
+    LOADING_START.store(current_time_millis(), Ordering::Relaxed);
+    LOADING_TOTAL_BYTES.store(0, Ordering::Relaxed);
+    LOADING_LOADED_BYTES.store(0, Ordering::Relaxed);
+    LOADING.store(true, Ordering::Relaxed);
+}
+
+pub fn is_loading() -> bool {
+    LOADING.load(Ordering::Relaxed)
+}
+
+/// `None` once the dataset is restored.
+pub fn loading_progress() -> Option<LoadingProgress> {
+    is_loading().then(|| LoadingProgress {
+        start: LOADING_START.load(Ordering::Relaxed),
+        total_bytes: LOADING_TOTAL_BYTES.load(Ordering::Relaxed),
+        loaded_bytes: LOADING_LOADED_BYTES.load(Ordering::Relaxed),
+    })
 }
 
-pub async fn restore_from_aof(db: Db) {
-    info!("Restoring DB from AOF file");
-    let log_path = get_aof_log_dir();
-    let file_path = log_path.join("appendonly.aof");
+fn add_loaded_bytes(bytes: u64) {
+    LOADING_LOADED_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// What was persisted before the restart. Read before the AOF writer
+/// starts, it creates the AOF if it is missing.
+#[derive(Debug, Default)]
+pub struct SavedData {
+    pub aof: Option<Vec<u8>>,
+    pub snapshot: Option<Snapshot>,
+    /// Size of the snapshot file, for the loading progress.
+    pub snapshot_size: u64,
+}
+
+/// Fails if the AOF can't be read, or is corrupt and the server must not
+/// start from it.
+pub async fn read_saved_data() -> std::io::Result<SavedData> {
+    let aof = match tokio::fs::read(get_aof_file()).await {
+        Ok(content) => Some(drop_torn_command(content).await?),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => {
+            let message = format!("Failed to read the AOF file: {e}");
+            return Err(Error::new(e.kind(), message));
+        }
+    };
 
-    if !file_path.exists() {
-        return;
+    let (snapshot, snapshot_size) = match tokio::fs::read(get_snapshot_file()).await {
+        Ok(content) => {
+            let snapshot = decode_snapshot(&content)
+                .inspect_err(|e| warn!("Ignoring the snapshot: {e}"))
+                .ok();
+            (snapshot, content.len() as u64)
+        }
+        Err(_) => (None, 0),
+    };
+
+    Ok(SavedData {
+        aof,
+        snapshot,
+        snapshot_size,
+    })
+}
+
+// A command cut short by a crash is dropped from the file if
+// `aof-load-truncated` allows it, like Redis
+async fn drop_torn_command(mut content: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    match check_aof(&content) {
+        AofCheck::Valid => Ok(content),
+        AofCheck::Truncated { offset } if get_aof_load_truncated() => {
+            warn!("The AOF file ends with an incomplete command at offset {offset}, it was truncated");
+
+            let file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .open(get_aof_file())
+                .await?;
+            file.set_len(offset as u64).await?;
+            file.sync_all().await?;
+
+            content.truncate(offset);
+            Ok(content)
+        }
+        AofCheck::Truncated { offset } => Err(Error::other(format!(
+            "The AOF file ends with an incomplete command at offset {offset}. \
+             Set aof-load-truncated to yes to drop it, or run sider-check-aof --fix"
+        ))),
+        AofCheck::Corrupt { offset, reason } => Err(Error::other(format!(
+            "Bad file format reading the AOF file at offset {offset}: {reason}. \
+             Run sider-check-aof --fix to truncate it there, the commands after it are lost"
+        ))),
     }
+}
+
+/// What `check_aof` found in an AOF file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AofCheck {
+    Valid,
+    /// Valid up to `offset`, where the last command, or transaction, was
+    /// cut short, e.g. by a crash while writing it.
+    Truncated {
+        offset: usize,
+    },
+    /// The command at `offset` is malformed, the file is damaged.
+    Corrupt {
+        offset: usize,
+        reason: String,
+    },
+}
+
+/// Checks that the AOF is made of whole commands and transactions, without
+/// replaying it. Files in the text format must have a valid command on
+/// every line.
+pub fn check_aof(content: &[u8]) -> AofCheck {
+    if is_legacy_aof(content) {
+        return check_legacy_aof(content);
+    }
+
+    let mut decoder = RespDecoder::new();
+    decoder.feed(content);
+
+    let mut offset = 0;
+    // Where the transaction being read started
+    let mut transaction = None;
+
+    loop {
+        let corrupt = |reason: &str| AofCheck::Corrupt {
+            offset,
+            reason: reason.to_string(),
+        };
+
+        // Commands are always logged as arrays, never inline
+        if content.get(offset).is_some_and(|&c| c != b'*') {
+            return corrupt("expected '*'");
+        }
+
+        match decoder.decode() {
+            Ok(Some(command)) => {
+                if command[0].eq_ignore_ascii_case(b"MULTI") {
+                    if transaction.is_some() {
+                        return corrupt("MULTI inside a transaction");
+                    }
+                    transaction = Some(offset);
+                } else if command[0].eq_ignore_ascii_case(b"EXEC") {
+                    if transaction.is_none() {
+                        return corrupt("EXEC without MULTI");
+                    }
+                    transaction = None;
+                }
 
-    let content = tokio::fs::read_to_string(file_path)
-        .await
-        .expect("Failed to read AOF file");
+                offset = content.len() - decoder.len();
+            }
+            Ok(None) if offset == content.len() && transaction.is_none() => {
+                return AofCheck::Valid
+            }
+            // A transaction only applies once its EXEC is read
+            Ok(None) => {
+                return AofCheck::Truncated {
+                    offset: transaction.unwrap_or(offset),
+                }
+            }
+            Err(e) => return corrupt(&e.to_string()),
+        }
+    }
+}
 
-    let commands: Vec<&str> = content.split("\n").collect();
+// Anything else than commands means the file is not in the text format,
+// it is not migrated
+fn check_legacy_aof(content: &[u8]) -> AofCheck {
+    let mut offset = 0;
 
-    for command in commands {
-        if command.is_empty() {
-            continue;
+    for line in content.split(|&c| c == b'\n') {
+        let command = legacy_command(line);
+        if !command.is_empty() {
+            if let Err(e) = parse_command(command) {
+                return AofCheck::Corrupt {
+                    offset,
+                    reason: format!("invalid command in the text format: {e}"),
+                };
+            }
         }
+        offset += line.len() + 1;
+    }
+
+    AofCheck::Valid
+}
 
-        let command_vec: Vec<String> =
-            command.split_whitespace().map(|s| s.to_string()).collect();
+/// Rebuilds the dataset from the newest consistent source: the snapshot
+/// followed by what the AOF logged after it if the AOF is the one it was
+/// taken with, else the AOF alone as it was rewritten or flushed since.
+/// Ends the loading phase once done. Fails if the AOF had to be rewritten
+/// and couldn't be, it would not rebuild the dataset anymore.
+pub async fn restore(db: Db, saved: SavedData) -> std::io::Result<()> {
+    match (saved.snapshot, saved.aof) {
+        (Some(snapshot), Some(aof)) if snapshot.matches_aof(&aof) => {
+            info!("Restoring DB from the snapshot and the end of the AOF file");
+            let tail = &aof[snapshot.aof.size as usize..];
+            LOADING_TOTAL_BYTES
+                .store(saved.snapshot_size + tail.len() as u64, Ordering::Relaxed);
+            load_snapshot(&db, snapshot, saved.snapshot_size).await;
+            replay_aof(&db, tail).await?;
+        }
+        (Some(snapshot), None) => {
+            info!("Restoring DB from the snapshot");
+            LOADING_TOTAL_BYTES.store(saved.snapshot_size, Ordering::Relaxed);
+            load_snapshot(&db, snapshot, saved.snapshot_size).await;
 
-        let _ = process_command(command_vec, &db, false).await;
+            // The AOF must rebuild the dataset on its own
+            rewrite_aof(&db).await.map_err(|e| {
+                Error::new(
+                    e.kind(),
+                    format!("Failed to rewrite the AOF file from the snapshot: {e}"),
+                )
+            })?;
+        }
+        (_, Some(aof)) => {
+            info!("Restoring DB from AOF file");
+            LOADING_TOTAL_BYTES.store(aof.len() as u64, Ordering::Relaxed);
+            replay_aof(&db, &aof).await?;
+        }
+        (None, None) => {}
     }
 
+    LOADING.store(false, Ordering::Relaxed);
     info!("DB restored!");
+    Ok(())
+}
+
+async fn load_snapshot(db: &Db, snapshot: Snapshot, size: u64) {
+    let mut db = db.lock_all().await;
+
+    for (key, value) in snapshot.entries {
+        if !value.is_expired() {
+            db.insert(key, value);
+        }
+    }
+
+    add_loaded_bytes(size);
+}
+
+// Replayed like restored commands, they are already in the AOF and aren't
+// appended again. Files in the text format are migrated, it fails if they
+// can't be rewritten
+async fn replay_aof(db: &Db, content: &[u8]) -> std::io::Result<()> {
+    let mut client = Client::new();
+
+    if is_legacy_aof(content) {
+        info!("Migrating AOF file from the text format");
+
+        // Nothing is appended to the old file, it is rewritten from the
+        // restored dataset once everything is replayed
+        for command in parse_legacy_aof(content) {
+            let _ = process_command(command, db, &mut client, true).await;
+        }
+
+        add_loaded_bytes(content.len() as u64);
+        return rewrite_aof(db).await.map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("Failed to migrate the AOF file from the text format: {e}"),
+            )
+        });
+    }
+
+    // Read with the decoder of client connections
+    let mut decoder = RespDecoder::new();
+    decoder.feed(content);
+    let mut consumed = 0;
+
+    for replayed in 1.. {
+        match decoder.decode() {
+            Ok(Some(command)) => {
+                let _ = process_command(command, db, &mut client, true).await;
+            }
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Stopped reading the AOF file on a malformed command: {e}");
+                break;
+            }
+        }
+
+        let position = content.len() - decoder.len();
+        add_loaded_bytes((position - consumed) as u64);
+        consumed = position;
+
+        if replayed % REPLAY_BATCH == 0 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    if !decoder.is_empty() {
+        warn!("The AOF file ends with an incomplete command, it was ignored");
+    }
+
+    Ok(())
+}
+
+/// Whether the AOF was written by older versions, one `<COMMAND> <args>`
+/// line per command instead of RESP arrays. `check_aof` tells if it really
+/// is one.
+pub fn is_legacy_aof(content: &[u8]) -> bool {
+    content.first().is_some_and(|&c| c != b'*')
+}
+
+/// Splits a text format AOF into commands. Arguments can't contain spaces or
+/// newlines in this format, so none are expected.
+pub fn parse_legacy_aof(content: &[u8]) -> Vec<Vec<Bytes>> {
+    content
+        .split(|&c| c == b'\n')
+        .map(legacy_command)
+        .filter(|command| !command.is_empty())
+        .collect()
+}
+
+fn legacy_command(line: &[u8]) -> Vec<Bytes> {
+    line.split(|c| c.is_ascii_whitespace())
+        .filter(|arg| !arg.is_empty())
+        .map(Bytes::copy_from_slice)
+        .collect()
 }
@@ -2,28 +2,131 @@ use thiserror::Error;
 
 #[derive(Debug, Clone, Error)]
 pub enum SiderError {
-    #[error("Invalid command")]
+    #[error("ERR unknown command")]
     InvalidCommand,
-    #[error("(nil)")]
-    Nil,
-    #[error("(empty array)")]
-    EmptyArray,
-    #[error("ERR WRONGTYPE Operation against a key holding the wrong kind of value")]
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
     WrongType,
-    #[error("ERR value is not an integer")]
+    #[error("ERR value is not an integer or out of range")]
     NotInt,
-    #[error("value is not an integer or out of range")]
+    #[error("ERR value is not an integer or out of range")]
     NotIntOrOutOfRange,
+    #[error("ERR value is out of range, must be positive")]
+    NotPositive,
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
     #[error("ERR unexpected database error")]
     DatabaseError,
     #[error("ERR regex error")]
     RegexError,
-    #[error("Error: TTL is required")]
+    #[error("ERR value is not an integer or out of range")]
     TTL,
+    #[error("ERR invalid expire time in '{0}' command")]
+    InvalidExpireTime(&'static str),
+    #[error("ERR NX and XX, GT or LT options at the same time are not compatible")]
+    ExpireNXAndOthers,
+    #[error("ERR GT and LT options at the same time are not compatible")]
+    ExpireGTAndLT,
+    #[error("ERR Unsupported option {0}")]
+    UnsupportedOption(String),
+    #[error("ERR syntax error")]
+    Syntax,
+    #[error("NOPROTO unsupported protocol version")]
+    NoProto,
+    #[error("WRONGPASS invalid username-password pair or user is disabled.")]
+    WrongPass,
     #[error("{0}")]
     Custom(String),
-    #[error("Wrong number of argument")]
+    #[error("ERR wrong number of arguments for command")]
     WrongNumberArgs,
+    #[error("ERR MULTI calls can not be nested")]
+    NestedMulti,
+    #[error("ERR EXEC without MULTI")]
+    ExecWithoutMulti,
+    #[error("ERR DISCARD without MULTI")]
+    DiscardWithoutMulti,
+    #[error("ERR WATCH inside MULTI is not allowed")]
+    WatchInsideMulti,
+    #[error("ERR Command not allowed inside a transaction")]
+    NotAllowedInMulti,
+    #[error("ERR only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING are allowed in this context")]
+    SubscribeMode,
+    #[error("ERR unknown subcommand")]
+    UnknownSubcommand,
+    #[error("ERR Unknown option or number of arguments for CONFIG SET - '{0}'")]
+    UnknownConfig(String),
+    #[error("ERR CONFIG SET failed (possibly related to argument '{0}') - Invalid argument")]
+    InvalidConfigValue(String),
+    #[error("ERR timeout is not a float or out of range")]
+    TimeoutNotFloat,
+    #[error("ERR timeout is negative")]
+    NegativeTimeout,
+    #[error("ERR value is not a valid float")]
+    NotFloat,
+    #[error("ERR min or max is not a float")]
+    MinMaxNotFloat,
+    #[error("ERR min or max not valid string range item")]
+    InvalidLexRange,
+    #[error("ERR resulting score is not a number (NaN)")]
+    ScoreNaN,
+    #[error("ERR XX and NX options at the same time are not compatible")]
+    XXAndNX,
+    #[error("ERR GT, LT, and/or NX options at the same time are not compatible")]
+    GTLTAndNX,
+    #[error("ERR INCR option supports a single increment-element pair")]
+    IncrSinglePair,
+    #[error("ERR timeout is not an integer or out of range")]
+    TimeoutNotInt,
+    #[error("ERR Invalid stream ID specified as stream command argument")]
+    InvalidStreamId,
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    StreamIdZero,
+    #[error(
+        "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+    )]
+    StreamIdTooSmall,
+    #[error("ERR The ID specified in XSETID is smaller than the target stream top item")]
+    StreamLastIdTooSmall,
+    #[error("ERR The MAXLEN argument must be >= 0.")]
+    NegativeMaxLen,
+    #[error("ERR syntax error, LIMIT cannot be used without the special ~ option")]
+    LimitWithoutApprox,
+    #[error(
+        "ERR Unbalanced '{command}' list of streams: for each stream key an ID or '{id}' must be specified."
+    )]
+    UnbalancedStreams {
+        command: &'static str,
+        id: &'static str,
+    },
+    #[error("NOGROUP No such key '{0}' or consumer group '{1}'")]
+    NoGroup(String, String),
+    #[error("BUSYGROUP Consumer Group name already exists")]
+    BusyGroup,
+    #[error(
+        "ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically."
+    )]
+    XGroupKeyMissing,
+    #[error("ERR no such key")]
+    NoSuchKey,
+    #[error("EXECABORT Transaction discarded because of previous errors.")]
+    ExecAbort,
+    #[error("OOM command not allowed when used memory > 'maxmemory'.")]
+    OutOfMemory,
+    #[error("ERR Background append only file rewriting already in progress")]
+    RewriteInProgress,
+    #[error("ERR Background save already in progress")]
+    SaveInProgress,
+    #[error("ERR Failed to save the snapshot: {0}")]
+    SaveFailed(String),
+    #[error("ERR Corrupt snapshot: {0}")]
+    CorruptSnapshot(String),
+    #[error("MISCONF Errors writing to the AOF file: {0}")]
+    AofWriteFailed(String),
+    #[error("ERR Error trying to load the RDB dump: {0}")]
+    InvalidRdb(String),
+    #[error("ERR Streams can't be saved to an RDB file, the dataset has {0} of them")]
+    RdbStreams(usize),
+    #[error("LOADING Sider is loading the dataset in memory")]
+    Loading,
 
     // RESP Parsing Errors
     #[error("ERR Protocol error: empty request")]
@@ -34,6 +137,12 @@ pub enum SiderError {
     InvalidArrayLength,
     #[error("ERR Protocol error: expected '$', got something else")]
     InvalidBulkStringPrefix,
+    #[error("ERR Protocol error: invalid bulk length")]
+    InvalidBulkLength,
+    #[error("ERR Protocol error: too big request line")]
+    LineTooLong,
+    #[error("ERR Protocol error: unbalanced quotes in request")]
+    UnbalancedQuotes,
     #[error("ERR Protocol error: wrong number of elements")]
     WrongElementCount,
 }
@@ -0,0 +1,110 @@
+//! Expired keys are deleted lazily, when a command accesses them, and by an
+//! active cycle sampling the keys with an expiry like Redis does, so keys
+//! nobody accesses anymore don't linger.
+
+use crate::shard::Shard;
+use crate::types::Db;
+use std::time::{Duration, Instant};
+use tokio::time;
+
+// The active cycle runs 10 times per second...
+const CYCLE_PERIOD: Duration = Duration::from_millis(100);
+// ...for at most a quarter of that time
+const CYCLE_TIME_LIMIT: Duration = Duration::from_millis(25);
+// Keys with an expiry sampled per batch, the lock is released in between
+const KEYS_PER_BATCH: usize = 20;
+// Sampling goes on while more than this percentage of the keys were expired
+const ACCEPTABLE_STALE_PERC: usize = 10;
+
+/// Expiry counters reported by INFO.
+#[derive(Debug, Default, Clone)]
+pub struct ExpireStats {
+    // Deleted because they expired, lazily or by the active cycle
+    pub expired_keys: u64,
+    // Estimate of the percentage of keys with an expiry that are expired
+    pub expired_stale_perc: f64,
+    // Active cycles stopped by the time limit while keys were still expiring
+    pub expired_time_cap_reached_count: u64,
+    pub expire_cycle_time: Duration,
+}
+
+impl Shard {
+    /// Samples up to `KEYS_PER_BATCH` keys with an expiry, deleting the
+    /// expired ones. Returns how many were sampled and deleted.
+    pub fn expire_batch(&mut self) -> (usize, usize) {
+        let sampled = self.volatile_len().min(KEYS_PER_BATCH);
+        let mut expired = 0;
+
+        for _ in 0..sampled {
+            if let Some(key) = self.random_volatile_key() {
+                if self.expire_if_needed(&key) {
+                    expired += 1;
+                }
+            }
+        }
+
+        (sampled, expired)
+    }
+}
+
+/// One run of the active cycle over every shard: batches are sampled until
+/// few of the sampled keys turn out expired, or the time limit is reached.
+/// The more keys are expired, the more batches it takes. A cycle running out
+/// of time is resumed from the next shard, so every shard gets its turn.
+pub async fn active_expire_cycle(db: &Db) {
+    let start = Instant::now();
+    let count = db.shards().len();
+    let first = db.expire_cursor();
+
+    for index in (first..count).chain(0..first) {
+        let shard = &db.shards()[index];
+        let shard_start = Instant::now();
+        let (mut total_sampled, mut total_expired) = (0, 0);
+
+        loop {
+            // Only this shard is locked, commands on the others go on
+            let mut shard = shard.write().await;
+            let (sampled, expired) = shard.expire_batch();
+            total_sampled += sampled;
+            total_expired += expired;
+
+            let done = expired * 100 <= sampled * ACCEPTABLE_STALE_PERC;
+            let timed_out = start.elapsed() >= CYCLE_TIME_LIMIT;
+
+            if done || timed_out {
+                let stats = shard.expire_stats_mut();
+                stats.expire_cycle_time += shard_start.elapsed();
+
+                if !done {
+                    stats.expired_time_cap_reached_count += 1;
+                }
+
+                // Smoothed over cycles, like Redis does
+                if total_sampled > 0 {
+                    let stale_perc = (total_expired * 100) as f64 / total_sampled as f64;
+                    stats.expired_stale_perc =
+                        stale_perc * 0.05 + stats.expired_stale_perc * 0.95;
+                }
+
+                if timed_out {
+                    db.set_expire_cursor(index + 1);
+                    return;
+                }
+                break;
+            }
+
+            // Let clients in between batches
+            drop(shard);
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+pub async fn expire_keys_periodically(db: Db) {
+    let mut interval = time::interval(CYCLE_PERIOD);
+
+    loop {
+        interval.tick().await;
+        active_expire_cycle(&db).await;
+    }
+}
@@ -1,8 +1,9 @@
-use crate::types::KeyBase;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::types::{Command, CommandArgs, CommandType, DbValue, KeyBase};
+use crate::utils::current_time_millis;
+use bytes::Bytes;
 
 impl<T> KeyBase<T> {
-    pub fn new(name: String, data: T, expires_at: Option<i64>) -> Self {
+    pub fn new(name: Bytes, data: T, expires_at: Option<i64>) -> Self {
         KeyBase {
             name,
             data,
@@ -10,27 +11,105 @@ impl<T> KeyBase<T> {
         }
     }
 
-    fn get_current_timestamp() -> i64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs() as i64
+    // A key without ttl returns -1 and is not expired
+    pub fn is_expired(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at <= current_time_millis(),
+            None => false,
+        }
     }
+}
 
-    pub fn get_ttl(&self) -> i64 {
-        self.expires_at
-            .map_or(-1, |expires_at| expires_at - Self::get_current_timestamp())
+impl DbValue {
+    pub fn is_expired(&self) -> bool {
+        match self {
+            DbValue::StringKey(key) => key.is_expired(),
+            DbValue::ListKey(key) => key.is_expired(),
+            DbValue::SetKey(key) => key.is_expired(),
+            DbValue::HashKey(key) => key.is_expired(),
+            DbValue::ZSetKey(key) => key.is_expired(),
+            DbValue::StreamKey(key) => key.is_expired(),
+        }
     }
 
-    pub fn set_ttl(&mut self, ttl: i64) {
-        self.expires_at = Some(Self::get_current_timestamp() + ttl);
+    pub fn expires_at(&self) -> Option<i64> {
+        match self {
+            DbValue::StringKey(key) => key.expires_at,
+            DbValue::ListKey(key) => key.expires_at,
+            DbValue::SetKey(key) => key.expires_at,
+            DbValue::HashKey(key) => key.expires_at,
+            DbValue::ZSetKey(key) => key.expires_at,
+            DbValue::StreamKey(key) => key.expires_at,
+        }
     }
 
-    // A key without ttl returns -1 and is not expired
-    pub fn is_expired(&self) -> bool {
-        match self.expires_at {
-            Some(expires_at) => expires_at <= Self::get_current_timestamp(),
-            None => false,
+    pub fn set_expires_at(&mut self, expires_at: Option<i64>) {
+        match self {
+            DbValue::StringKey(key) => key.expires_at = expires_at,
+            DbValue::ListKey(key) => key.expires_at = expires_at,
+            DbValue::SetKey(key) => key.expires_at = expires_at,
+            DbValue::HashKey(key) => key.expires_at = expires_at,
+            DbValue::ZSetKey(key) => key.expires_at = expires_at,
+            DbValue::StreamKey(key) => key.expires_at = expires_at,
+        }
+    }
+}
+
+impl CommandArgs {
+    /// Keys a command operates on. Only meaningful for write commands, KEYS
+    /// for instance carries a pattern and not a key.
+    pub fn keys(&self) -> Vec<Bytes> {
+        match self {
+            CommandArgs::SingleKey(key)
+            | CommandArgs::KeyWithValue { key, .. }
+            | CommandArgs::KeyWithValues { key, .. }
+            | CommandArgs::HashFields { key, .. } => vec![key.clone()],
+            CommandArgs::MultipleKeys(keys) => keys.clone(),
+            CommandArgs::NoArgs | CommandArgs::Hello { .. } => Vec::new(),
+        }
+    }
+}
+
+impl Command {
+    /// Like `CommandArgs::keys`, for the commands whose arguments mix keys
+    /// with other values.
+    pub fn keys(&self) -> Vec<Bytes> {
+        match (&self.command_type, &self.args) {
+            (CommandType::BLPOP | CommandType::BRPOP, CommandArgs::MultipleKeys(args)) => args
+                .split_last()
+                .map_or(Vec::new(), |(_timeout, keys)| keys.to_vec()),
+            (
+                CommandType::LMOVE | CommandType::BLMOVE,
+                CommandArgs::KeyWithValues { key, values },
+            ) => {
+                let mut keys = vec![key.clone()];
+                keys.extend(values.first().cloned());
+                keys
+            }
+            // The subcommand comes before the key
+            (CommandType::XGROUP, CommandArgs::KeyWithValues { values, .. }) => {
+                values.first().cloned().into_iter().collect()
+            }
+            (CommandType::XREAD | CommandType::XREADGROUP, CommandArgs::MultipleKeys(args)) => {
+                let streams = args
+                    .iter()
+                    .position(|arg| arg.eq_ignore_ascii_case(b"STREAMS"))
+                    .map_or(&args[..0], |position| &args[position + 1..]);
+
+                streams[..streams.len() / 2].to_vec()
+            }
+            // A pattern, a subcommand or sections
+            (CommandType::KEYS | CommandType::CONFIG | CommandType::INFO, _) => Vec::new(),
+            _ => self.args.keys(),
+        }
+    }
+
+    /// Keys whose shards are locked to run the command, `None` for the
+    /// commands going through every key.
+    pub fn shard_keys(&self) -> Option<Vec<Bytes>> {
+        match self.command_type {
+            CommandType::KEYS | CommandType::FLUSHDB | CommandType::INFO => None,
+            _ => Some(self.keys()),
         }
     }
 }
@@ -0,0 +1,501 @@
+use crate::expire::ExpireStats;
+use crate::response::SiderResponse;
+use crate::shard::{shard_index, Shard};
+use crate::types::{BlockingOperation, Command, DbValue};
+use bytes::Bytes;
+use indexmap::Equivalent;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::sync::{oneshot, OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+
+// Enough for writes on different keys to rarely wait on each other
+const DEFAULT_SHARDS: usize = 16;
+
+/// A client parked by BLPOP, BRPOP, BLMOVE, XREAD or XREADGROUP until one of
+/// its keys can serve it. The reply is sent through `reply` once it is.
+#[derive(Debug)]
+pub struct BlockedClient {
+    pub keys: Vec<Bytes>,
+    pub operation: BlockingOperation,
+    pub reply: oneshot::Sender<SiderResponse>,
+}
+
+// Shared by all the shards, a client can wait on keys from several of them.
+// Only locked for short moments, never while waiting on a shard.
+#[derive(Debug, Default)]
+struct BlockedClients {
+    blocked: HashMap<u64, BlockedClient>,
+    // Ids of the clients waiting on each key, oldest first
+    waiting: HashMap<Bytes, VecDeque<u64>>,
+}
+
+impl BlockedClients {
+    fn unblock(&mut self, client_id: u64) -> Option<BlockedClient> {
+        let blocked = self.blocked.remove(&client_id)?;
+
+        for key in blocked.keys.iter() {
+            if let Some(waiting) = self.waiting.get_mut(key) {
+                waiting.retain(|id| *id != client_id);
+
+                if waiting.is_empty() {
+                    self.waiting.remove(key);
+                }
+            }
+        }
+
+        Some(blocked)
+    }
+}
+
+fn lock_blocked(blocked: &Mutex<BlockedClients>) -> MutexGuard<'_, BlockedClients> {
+    // Nothing is left half updated by a panic while it is held
+    blocked.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The database: keys are spread over shards locked independently, so
+/// commands on keys of different shards run in parallel.
+///
+/// A command locks the shards of all its keys before running, always in
+/// ascending order so two commands can't each hold a shard the other waits
+/// on. Commands going through every key, like KEYS or FLUSHDB, lock all of
+/// them.
+#[derive(Debug)]
+pub struct Store {
+    shards: Vec<Arc<RwLock<Shard>>>,
+    blocked: Arc<Mutex<BlockedClients>>,
+    // Estimated bytes used by all the shards, checked without locking them
+    used_memory: Arc<AtomicUsize>,
+    // Shard the next active expire cycle starts from
+    expire_cursor: AtomicUsize,
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Store::new()
+    }
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Store::with_shards(DEFAULT_SHARDS)
+    }
+
+    /// A store with `count` shards, 1 behaves like a single global lock.
+    pub fn with_shards(count: usize) -> Self {
+        let used_memory = Arc::new(AtomicUsize::new(0));
+
+        Store {
+            shards: (0..count.max(1))
+                .map(|_| Shard::with_memory_counter(used_memory.clone()))
+                .map(|shard| Arc::new(RwLock::new(shard)))
+                .collect(),
+            blocked: Arc::default(),
+            used_memory,
+            expire_cursor: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn used_memory(&self) -> usize {
+        self.used_memory.load(Ordering::Relaxed)
+    }
+
+    /// Shard the active expire cycle resumes from, the one after where the
+    /// last cycle ran out of time.
+    pub fn expire_cursor(&self) -> usize {
+        self.expire_cursor.load(Ordering::Relaxed) % self.shards.len()
+    }
+
+    pub fn set_expire_cursor(&self, index: usize) {
+        self.expire_cursor
+            .store(index % self.shards.len(), Ordering::Relaxed);
+    }
+
+    pub fn shards(&self) -> &[Arc<RwLock<Shard>>] {
+        &self.shards
+    }
+
+    /// Locks the shards holding `keys`. Every shard is locked instead if a
+    /// client waits on one of the keys: serving it may touch any key, e.g.
+    /// the destination of BLMOVE.
+    pub async fn lock(&self, keys: &[Bytes]) -> Keyspace {
+        let mut indexes: Vec<usize> = keys
+            .iter()
+            .map(|key| shard_index(key, self.shards.len()))
+            .collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+
+        let keyspace = self.lock_shards(indexes).await;
+
+        // Checked once locked, clients block while holding their keys' shards
+        let waited_on = {
+            let blocked = lock_blocked(&self.blocked);
+            keys.iter().any(|key| blocked.waiting.contains_key(key))
+        };
+
+        if waited_on {
+            drop(keyspace);
+            return self.lock_all().await;
+        }
+
+        keyspace
+    }
+
+    pub async fn lock_all(&self) -> Keyspace {
+        self.lock_shards((0..self.shards.len()).collect()).await
+    }
+
+    // `indexes` must be sorted
+    async fn lock_shards(&self, indexes: Vec<usize>) -> Keyspace {
+        let mut shards = Vec::with_capacity(indexes.len());
+
+        for index in indexes {
+            shards.push((index, self.shards[index].clone().write_owned().await));
+        }
+
+        Keyspace {
+            shards,
+            shard_count: self.shards.len(),
+            blocked: self.blocked.clone(),
+            ready_keys: VecDeque::new(),
+            propagated: None,
+        }
+    }
+
+    /// Every shard for reading, e.g. to dump them all at a point in time.
+    pub async fn read_all(&self) -> Vec<OwnedRwLockReadGuard<Shard>> {
+        let mut shards = Vec::with_capacity(self.shards.len());
+
+        for shard in self.shards.iter() {
+            shards.push(shard.clone().read_owned().await);
+        }
+
+        shards
+    }
+
+    /// Stops waiting for the client, e.g. on timeout or disconnection.
+    /// `None` if it was already picked to be served.
+    pub fn unblock(&self, client_id: u64) -> Option<BlockedClient> {
+        lock_blocked(&self.blocked).unblock(client_id)
+    }
+
+    pub fn blocked_clients(&self) -> usize {
+        lock_blocked(&self.blocked).blocked.len()
+    }
+}
+
+/// The keys a command can access: the shards locked for it. Accessing a key
+/// of a shard that isn't locked is a bug and panics.
+#[derive(Debug)]
+pub struct Keyspace {
+    // Along with their index, usually only a few of them
+    shards: Vec<(usize, OwnedRwLockWriteGuard<Shard>)>,
+    shard_count: usize,
+    blocked: Arc<Mutex<BlockedClients>>,
+    // Keys that got elements while clients were waiting on them
+    ready_keys: VecDeque<Bytes>,
+    // What the running command is logged as in the AOF when it can't be
+    // replayed as is, e.g. XADD with the ID it generated
+    propagated: Option<Vec<Command>>,
+}
+
+impl Keyspace {
+    /// A standalone keyspace, with every shard of a store of its own locked.
+    pub fn new() -> Self {
+        let store = Store::new();
+        let shards = store
+            .shards
+            .iter()
+            .enumerate()
+            .map(|(index, shard)| {
+                let shard = shard.clone().try_write_owned();
+                (index, shard.expect("Nobody else has access to the store"))
+            })
+            .collect();
+
+        Keyspace {
+            shards,
+            shard_count: store.shards.len(),
+            blocked: store.blocked,
+            ready_keys: VecDeque::new(),
+            propagated: None,
+        }
+    }
+
+    fn shard<Q>(&self, key: &Q) -> &Shard
+    where
+        Q: ?Sized + Hash,
+    {
+        let index = shard_index(key, self.shard_count);
+        self.shards
+            .iter()
+            .find(|(locked, _)| *locked == index)
+            .map(|(_, shard)| &**shard)
+            .expect("Key of a shard not locked for the command")
+    }
+
+    fn shard_mut<Q>(&mut self, key: &Q) -> &mut Shard
+    where
+        Q: ?Sized + Hash,
+    {
+        let index = shard_index(key, self.shard_count);
+        self.shards
+            .iter_mut()
+            .find(|(locked, _)| *locked == index)
+            .map(|(_, shard)| &mut **shard)
+            .expect("Key of a shard not locked for the command")
+    }
+
+    /// The shards locked for the command.
+    pub fn locked_shards(&self) -> impl Iterator<Item = &Shard> {
+        self.shards.iter().map(|(_, shard)| &**shard)
+    }
+
+    fn locked_shards_mut(&mut self) -> impl Iterator<Item = &mut Shard> {
+        self.shards.iter_mut().map(|(_, shard)| &mut **shard)
+    }
+
+    fn blocked(&self) -> MutexGuard<'_, BlockedClients> {
+        lock_blocked(&self.blocked)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&DbValue>
+    where
+        Q: ?Sized + Hash + Equivalent<Bytes>,
+    {
+        self.shard(key).get(key)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut DbValue>
+    where
+        Q: ?Sized + Hash + Equivalent<Bytes>,
+    {
+        self.shard_mut(key).get_mut(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: ?Sized + Hash + Equivalent<Bytes>,
+    {
+        self.shard(key).contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: Bytes, value: DbValue) -> Option<DbValue> {
+        self.shard_mut(&key).insert(key, value)
+    }
+
+    pub fn swap_remove<Q>(&mut self, key: &Q) -> Option<DbValue>
+    where
+        Q: ?Sized + Hash + Equivalent<Bytes>,
+    {
+        self.shard_mut(key).swap_remove(key)
+    }
+
+    /// Keeps the keys of the locked shards `keep` returns true for.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&Bytes, &mut DbValue) -> bool,
+    {
+        for shard in self.locked_shards_mut() {
+            shard.retain(&mut keep);
+        }
+    }
+
+    /// Removes every key of the locked shards, blocked clients keep waiting.
+    pub fn clear(&mut self) {
+        for shard in self.locked_shards_mut() {
+            shard.clear();
+        }
+    }
+
+    pub fn watch(&mut self, key: &Bytes) -> u64 {
+        self.shard_mut(key).watch(key)
+    }
+
+    pub fn unwatch(&mut self, key: &[u8]) {
+        self.shard_mut(key).unwatch(key)
+    }
+
+    pub fn watched_version(&self, key: &[u8]) -> Option<u64> {
+        self.shard(key).watched_version(key)
+    }
+
+    /// Lets the clients watching the key know it changed.
+    pub fn signal_modified_key(&mut self, key: &[u8]) {
+        self.shard_mut(key).signal_modified_key(key)
+    }
+
+    /// Sets or removes the expiry of a key, false if it doesn't exist.
+    pub fn set_expires_at(&mut self, key: &Bytes, expires_at: Option<i64>) -> bool {
+        self.shard_mut(key).set_expires_at(key, expires_at)
+    }
+
+    /// Deletes the key if it has expired. Returns true if it was deleted.
+    pub fn expire_if_needed(&mut self, key: &[u8]) -> bool {
+        self.shard_mut(key).expire_if_needed(key)
+    }
+
+    /// Marks the key as just accessed for the LRU and LFU policies.
+    pub fn record_access(&mut self, key: &[u8]) {
+        self.shard_mut(key).record_access(key)
+    }
+
+    /// Measures the key again once a command changed its value in place.
+    pub fn refresh_memory_usage(&mut self, key: &[u8]) {
+        self.shard_mut(key).refresh_memory_usage(key)
+    }
+
+    /// Estimated bytes used by the keys of the locked shards.
+    pub fn used_memory(&self) -> usize {
+        self.locked_shards().map(|shard| shard.used_memory()).sum()
+    }
+
+    pub fn evicted_keys(&self) -> u64 {
+        self.locked_shards().map(|shard| shard.evicted_keys()).sum()
+    }
+
+    /// Number of keys with an expiry in the locked shards.
+    pub fn volatile_len(&self) -> usize {
+        self.locked_shards().map(|shard| shard.volatile_len()).sum()
+    }
+
+    /// Expiry counters of the locked shards added up.
+    pub fn expire_stats(&self) -> ExpireStats {
+        let mut stats = ExpireStats::default();
+        let mut shards = 0;
+
+        for shard in self.locked_shards() {
+            let shard = shard.expire_stats();
+            stats.expired_keys += shard.expired_keys;
+            stats.expired_stale_perc += shard.expired_stale_perc;
+            stats.expired_time_cap_reached_count += shard.expired_time_cap_reached_count;
+            stats.expire_cycle_time += shard.expire_cycle_time;
+            shards += 1;
+        }
+
+        if shards > 0 {
+            stats.expired_stale_perc /= shards as f64;
+        }
+
+        stats
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Bytes> {
+        self.locked_shards().flat_map(|shard| shard.keys())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, &DbValue)> {
+        self.locked_shards().flat_map(|shard| shard.iter())
+    }
+
+    pub fn len(&self) -> usize {
+        self.locked_shards().map(|shard| shard.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.locked_shards().all(|shard| shard.is_empty())
+    }
+
+    /// Parks a client on `keys`, behind the clients already waiting on them.
+    pub fn block(
+        &mut self,
+        client_id: u64,
+        keys: Vec<Bytes>,
+        operation: BlockingOperation,
+    ) -> oneshot::Receiver<SiderResponse> {
+        let (reply, receiver) = oneshot::channel();
+        let mut blocked = self.blocked();
+        blocked.unblock(client_id);
+
+        for key in keys.iter() {
+            let waiting = blocked.waiting.entry(key.clone()).or_default();
+
+            // BLPOP list list 0 waits only once
+            if !waiting.contains(&client_id) {
+                waiting.push_back(client_id);
+            }
+        }
+
+        blocked.blocked.insert(
+            client_id,
+            BlockedClient {
+                keys,
+                operation,
+                reply,
+            },
+        );
+
+        receiver
+    }
+
+    /// Stops waiting for the client, e.g. on timeout or disconnection.
+    pub fn unblock(&mut self, client_id: u64) -> Option<BlockedClient> {
+        self.blocked().unblock(client_id)
+    }
+
+    pub fn blocked_clients(&self) -> usize {
+        self.blocked().blocked.len()
+    }
+
+    /// Called when elements are pushed to a list or entries added to a
+    /// stream, the clients waiting on it are served once the command is done.
+    pub fn signal_key_as_ready(&mut self, key: &Bytes) {
+        let waited_on = self.blocked().waiting.contains_key(key);
+
+        if waited_on && !self.ready_keys.contains(key) {
+            self.ready_keys.push_back(key.clone());
+        }
+    }
+
+    /// Next client to serve along with the key it is served from: the oldest
+    /// one waiting on a ready key that can serve it, e.g. a list that still
+    /// holds elements. Clients whose connection is gone are dropped along
+    /// the way.
+    pub fn next_unblocked(&mut self) -> Option<(Bytes, BlockedClient)> {
+        while let Some(key) = self.ready_keys.front().cloned() {
+            let value = self.get(&key);
+            let mut blocked = self.blocked();
+
+            let next = blocked.waiting.get(&key).and_then(|waiting| {
+                waiting.iter().copied().find(|client_id| {
+                    blocked
+                        .blocked
+                        .get(client_id)
+                        .is_some_and(|blocked| blocked.operation.is_ready(&key, value))
+                })
+            });
+
+            let Some(client_id) = next else {
+                drop(blocked);
+                self.ready_keys.pop_front();
+                continue;
+            };
+
+            match blocked.unblock(client_id) {
+                Some(blocked) if !blocked.reply.is_closed() => return Some((key, blocked)),
+                _ => continue,
+            }
+        }
+
+        None
+    }
+
+    /// Logs `commands` to the AOF in place of the command being run, which
+    /// is not logged at all if `commands` is empty.
+    pub fn propagate_as(&mut self, commands: Vec<Command>) {
+        self.propagated = Some(commands);
+    }
+
+    /// What the last command is logged as, `None` to log it as is.
+    pub fn take_propagated(&mut self) -> Option<Vec<Command>> {
+        self.propagated.take()
+    }
+}
+
+impl Default for Keyspace {
+    fn default() -> Self {
+        Keyspace::new()
+    }
+}
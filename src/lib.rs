@@ -4,17 +4,33 @@
 #![deny(clippy::unwrap_used)]
 // False positive with None
 #![allow(non_snake_case)]
+// `is_multiple_of` needs Rust 1.87
+#![allow(clippy::manual_is_multiple_of)]
 #![deny(dead_code)]
 
 pub mod aof;
+pub mod client;
 pub mod commands;
 pub mod config;
+pub mod crc64;
 pub mod database;
 pub mod errors;
+pub mod expire;
 pub mod keys;
+pub mod keyspace;
+pub mod memory;
+pub mod notify;
 pub mod parser;
 pub mod process;
+pub mod pubsub;
+pub mod rdb;
 pub mod resp;
 pub mod response;
+pub mod rewrite;
 pub mod server;
+pub mod shard;
+pub mod snapshot;
+pub mod stream;
 pub mod types;
+pub mod utils;
+pub mod zset;
@@ -1,22 +1,25 @@
-use indexmap::IndexMap;
-use sider::aof::clean_up_db;
+use sider::aof::{set_aof_load_truncated, set_appendfsync, start_aof_writer, AppendFsync};
 use sider::config::get_config;
-use sider::database::delete_expired_keys;
-use sider::database::restore_from_aof;
+use sider::database::{read_saved_data, restore, start_loading};
+use sider::expire::expire_keys_periodically;
+use sider::memory::{parse_memory, set_maxmemory, set_maxmemory_policy, EvictionPolicy};
+use sider::notify::{parse_notify_flags, set_notify_flags};
+use sider::rewrite::rewrite_aof_periodically;
 use sider::server::handle_client;
-use sider::types::Db;
+use sider::snapshot::{parse_save_rules, save_periodically, set_save_rules};
+use sider::types::{Db, Store};
+use sider::utils::parse_yes_no;
 use std::error::Error;
 use std::net::Ipv4Addr;
 use std::sync::Arc;
 use tokio::net::TcpListener;
-use tokio::sync::RwLock;
 use tracing::{error, info};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     tracing_subscriber::fmt::init();
 
-    let db: Db = Arc::new(RwLock::new(IndexMap::new()));
+    let db: Db = Arc::new(Store::new());
 
     // Config
     let config = get_config();
@@ -24,19 +27,73 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let bind: Ipv4Addr = config.get("bind").expect("Bind is missing");
     let full_address = format!("{}:{}", bind, port);
 
+    let notify_keyspace_events: String = config
+        .get("notify-keyspace-events")
+        .expect("notify-keyspace-events is missing");
+    set_notify_flags(
+        parse_notify_flags(notify_keyspace_events.as_bytes())
+            .expect("Invalid notify-keyspace-events"),
+    );
+
+    let maxmemory: String = config.get("maxmemory").expect("maxmemory is missing");
+    set_maxmemory(parse_memory(maxmemory.as_bytes()).expect("Invalid maxmemory"));
+
+    let maxmemory_policy: String = config
+        .get("maxmemory-policy")
+        .expect("maxmemory-policy is missing");
+    set_maxmemory_policy(
+        EvictionPolicy::parse(maxmemory_policy.as_bytes()).expect("Invalid maxmemory-policy"),
+    );
+
+    let appendfsync: String = config.get("appendfsync").expect("appendfsync is missing");
+    set_appendfsync(AppendFsync::parse(appendfsync.as_bytes()).expect("Invalid appendfsync"));
+
+    let save: String = config.get("save").expect("save is missing");
+    set_save_rules(parse_save_rules(save.as_bytes()).expect("Invalid save"));
+
+    let aof_load_truncated: String = config
+        .get("aof-load-truncated")
+        .expect("aof-load-truncated is missing");
+    set_aof_load_truncated(
+        parse_yes_no(aof_load_truncated.as_bytes()).expect("Invalid aof-load-truncated"),
+    );
+
+    // Read before the AOF writer creates the file if it is missing
+    let saved = match read_saved_data().await {
+        Ok(saved) => saved,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    // Every write to the AOF goes through it, restoring included
+    start_aof_writer().await?;
+
     let listener = TcpListener::bind(full_address.to_string()).await?;
     let message = format!("Listening {}...", full_address);
 
     info!(message);
 
-    // Restoring DB from AOF file at start up
-    tokio::spawn(restore_from_aof(db.clone()));
+    // Restoring DB from the snapshot and the AOF file at start up, clients
+    // are answered with -LOADING until it is done
+    start_loading();
+    let restored = db.clone();
+    tokio::spawn(async move {
+        if let Err(e) = restore(restored, saved).await {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    });
+
+    // Delete expired keys nobody accesses
+    tokio::spawn(expire_keys_periodically(db.clone()));
 
-    // Delete expired keys every 60 seconds
-    tokio::spawn(delete_expired_keys(db.clone()));
+    // Rewrite the AOF when asked to or once it grew too much
+    tokio::spawn(rewrite_aof_periodically(db.clone()));
 
-    // Clean database every 60 seconds
-    tokio::spawn(clean_up_db(db.clone()));
+    // Take snapshots when asked to or once the save rules say so
+    tokio::spawn(save_periodically(db.clone()));
 
     loop {
         let (socket, addr) = listener.accept().await?;
@@ -0,0 +1,365 @@
+//! Memory accounting and eviction. Every entry keeps an estimate of the
+//! memory it uses, and once the total goes over `maxmemory` keys are evicted
+//! according to `maxmemory-policy` before running commands, like Redis does.
+
+use crate::aof::write_aof;
+use crate::errors::SiderError;
+use crate::types::{Command, CommandArgs, CommandType, DbValue, Store, StreamId};
+use crate::utils::{current_time_millis, random_index};
+use std::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
+
+// Rough cost of an entry besides its key and value: the hash table slot, the
+// value header and the expiry
+const ENTRY_OVERHEAD: usize = 64;
+// Same for an element of a list, set, hash, sorted set or stream
+const ELEMENT_OVERHEAD: usize = 16;
+// Elements of a collection measured to estimate its size, like the default
+// of Redis `MEMORY USAGE`
+const SIZE_SAMPLES: usize = 5;
+
+// LFU counters start there so new keys aren't evicted right away, and grow
+// logarithmically: the higher they are, the less likely an access bumps them
+const LFU_INIT_VAL: u8 = 5;
+const LFU_LOG_FACTOR: f64 = 10.0;
+// Counters are decremented by one every minute without access
+const LFU_DECAY_MINUTES: i64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    NoEviction,
+    AllKeysLru,
+    AllKeysLfu,
+    AllKeysRandom,
+    VolatileLru,
+    VolatileLfu,
+    VolatileRandom,
+    VolatileTtl,
+}
+
+const POLICIES: [(&str, EvictionPolicy); 8] = [
+    ("noeviction", EvictionPolicy::NoEviction),
+    ("allkeys-lru", EvictionPolicy::AllKeysLru),
+    ("allkeys-lfu", EvictionPolicy::AllKeysLfu),
+    ("allkeys-random", EvictionPolicy::AllKeysRandom),
+    ("volatile-lru", EvictionPolicy::VolatileLru),
+    ("volatile-lfu", EvictionPolicy::VolatileLfu),
+    ("volatile-random", EvictionPolicy::VolatileRandom),
+    ("volatile-ttl", EvictionPolicy::VolatileTtl),
+];
+
+impl EvictionPolicy {
+    pub fn parse(value: &[u8]) -> Option<Self> {
+        POLICIES
+            .iter()
+            .find(|(name, _)| name.as_bytes().eq_ignore_ascii_case(value))
+            .map(|(_, policy)| *policy)
+    }
+
+    pub fn name(&self) -> &'static str {
+        POLICIES
+            .iter()
+            .find(|(_, policy)| policy == self)
+            .map_or("noeviction", |(name, _)| name)
+    }
+
+    /// Whether only keys with an expiry can be evicted.
+    pub fn is_volatile(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::VolatileLru
+                | EvictionPolicy::VolatileLfu
+                | EvictionPolicy::VolatileRandom
+                | EvictionPolicy::VolatileTtl
+        )
+    }
+}
+
+// No limit by default, like Redis on 64-bit systems
+static MAXMEMORY: AtomicU64 = AtomicU64::new(0);
+static MAXMEMORY_POLICY: AtomicU8 = AtomicU8::new(0);
+static MAXMEMORY_SAMPLES: AtomicUsize = AtomicUsize::new(5);
+
+/// Memory limit in bytes, 0 if there is none.
+pub fn get_maxmemory() -> u64 {
+    MAXMEMORY.load(Ordering::Relaxed)
+}
+
+pub fn set_maxmemory(bytes: u64) {
+    MAXMEMORY.store(bytes, Ordering::Relaxed);
+}
+
+pub fn get_maxmemory_policy() -> EvictionPolicy {
+    POLICIES[MAXMEMORY_POLICY.load(Ordering::Relaxed) as usize].1
+}
+
+pub fn set_maxmemory_policy(policy: EvictionPolicy) {
+    let index = POLICIES
+        .iter()
+        .position(|(_, known)| *known == policy)
+        .unwrap_or(0);
+    MAXMEMORY_POLICY.store(index as u8, Ordering::Relaxed);
+}
+
+/// Keys sampled to pick the one to evict, more is closer to the exact policy.
+pub fn get_maxmemory_samples() -> usize {
+    MAXMEMORY_SAMPLES.load(Ordering::Relaxed)
+}
+
+pub fn set_maxmemory_samples(samples: usize) {
+    MAXMEMORY_SAMPLES.store(samples, Ordering::Relaxed);
+}
+
+/// Parses a memory amount the way Redis does: `100`, `10k` (1000 bytes),
+/// `10kb` (1024 bytes), `mb`, `m`, `gb` and `g`.
+pub fn parse_memory(value: &[u8]) -> Option<u64> {
+    let value = std::str::from_utf8(value).ok()?.to_ascii_lowercase();
+    let digits = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+
+    let unit: u64 = match &value[digits.len()..] {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+
+    digits.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// `1.50M` for 1572864 bytes, like the `_human` fields of Redis INFO.
+pub fn format_memory(bytes: u64) -> String {
+    const UNITS: [(&str, u64); 3] = [("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10)];
+
+    UNITS
+        .iter()
+        .find(|(_, size)| bytes >= *size)
+        .map_or(format!("{bytes}B"), |(unit, size)| {
+            format!("{:.2}{unit}", bytes as f64 / *size as f64)
+        })
+}
+
+// Estimated from up to `SIZE_SAMPLES` elements, so large collections don't
+// make every write slower
+fn sampled_size<I>(len: usize, elements: I) -> usize
+where
+    I: Iterator<Item = usize>,
+{
+    let (count, total) = elements
+        .take(SIZE_SAMPLES)
+        .fold((0, 0), |(count, total), size| (count + 1, total + size));
+
+    match count {
+        0 => 0,
+        _ => len * (total / count + ELEMENT_OVERHEAD),
+    }
+}
+
+impl DbValue {
+    /// Estimated bytes used by the value, not counting its key.
+    pub fn memory_usage(&self) -> usize {
+        let data = match self {
+            DbValue::StringKey(key) => key.data.as_ref().map_or(0, |data| data.len()),
+            DbValue::ListKey(key) => {
+                sampled_size(key.data.len(), key.data.iter().map(|value| value.len()))
+            }
+            DbValue::SetKey(key) => {
+                sampled_size(key.data.len(), key.data.iter().map(|member| member.len()))
+            }
+            DbValue::HashKey(key) => sampled_size(
+                key.data.len(),
+                key.data
+                    .iter()
+                    .map(|(field, value)| field.len() + value.len()),
+            ),
+            DbValue::ZSetKey(key) => sampled_size(
+                key.data.len(),
+                // The member is stored twice, in the index and in the tree
+                key.data.iter().map(|(member, _)| member.len() * 2 + 8),
+            ),
+            DbValue::StreamKey(key) => {
+                let entries =
+                    key.data
+                        .range(StreamId::MIN, StreamId::MAX)
+                        .map(|(_, fields)| {
+                            fields
+                                .iter()
+                                .map(|(field, value)| {
+                                    field.len() + value.len() + ELEMENT_OVERHEAD
+                                })
+                                .sum::<usize>()
+                        });
+                let pending: usize = key
+                    .data
+                    .groups
+                    .values()
+                    .map(|group| group.pending.len())
+                    .sum();
+
+                sampled_size(key.data.len(), entries) + pending * ELEMENT_OVERHEAD * 3
+            }
+        };
+
+        ENTRY_OVERHEAD + data
+    }
+}
+
+/// When a key was last accessed, used by the LRU and LFU policies.
+#[derive(Debug, Clone)]
+pub struct AccessInfo {
+    // Unix time in milliseconds
+    accessed_at: i64,
+    frequency: u8,
+    // Unix time in minutes the frequency was last decremented
+    decremented_at: i64,
+}
+
+impl Default for AccessInfo {
+    fn default() -> Self {
+        let now = current_time_millis();
+
+        AccessInfo {
+            accessed_at: now,
+            frequency: LFU_INIT_VAL,
+            decremented_at: now / 60_000,
+        }
+    }
+}
+
+impl AccessInfo {
+    pub fn record(&mut self) {
+        let now = current_time_millis();
+        self.frequency = self.decayed_frequency(now);
+        self.decremented_at = now / 60_000;
+        self.accessed_at = now;
+
+        // Bumped with a probability decreasing as the counter grows
+        if self.frequency < u8::MAX {
+            let base = self.frequency.saturating_sub(LFU_INIT_VAL) as f64;
+            let chance = 1.0 / (base * LFU_LOG_FACTOR + 1.0);
+            let draw = random_index(1 << 20) as f64 / (1 << 20) as f64;
+
+            if draw < chance {
+                self.frequency += 1;
+            }
+        }
+    }
+
+    fn decayed_frequency(&self, now: i64) -> u8 {
+        let periods = (now / 60_000 - self.decremented_at) / LFU_DECAY_MINUTES;
+        let periods = periods.clamp(0, u8::MAX as i64) as u8;
+
+        self.frequency.saturating_sub(periods)
+    }
+
+    /// How good a candidate for eviction the key is, the higher the better.
+    pub fn eviction_score(&self, policy: EvictionPolicy, value: &DbValue) -> u64 {
+        let now = current_time_millis();
+
+        match policy {
+            EvictionPolicy::AllKeysLru | EvictionPolicy::VolatileLru => {
+                (now - self.accessed_at).max(0) as u64
+            }
+            EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu => {
+                (u8::MAX - self.decayed_frequency(now)) as u64
+            }
+            // The closest to expire first
+            EvictionPolicy::VolatileTtl => {
+                u64::MAX - value.expires_at().map_or(u64::MAX, |at| at.max(0) as u64)
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Commands refused once the memory limit is reached and nothing can be
+/// evicted, the ones that may use more memory. Deleting stays possible.
+pub fn is_denyoom_command(cmd_type: CommandType) -> bool {
+    matches!(
+        cmd_type,
+        CommandType::SET
+            | CommandType::INCR
+            | CommandType::DECR
+            | CommandType::INCRBY
+            | CommandType::LPUSH
+            | CommandType::RPUSH
+            | CommandType::LMOVE
+            | CommandType::BLMOVE
+            | CommandType::HSET
+            | CommandType::SADD
+            | CommandType::ZADD
+            | CommandType::ZINCRBY
+            | CommandType::XADD
+            | CommandType::XGROUP
+    )
+}
+
+/// Evicts keys until the memory used is back under `maxmemory`. Fails if it
+/// can't: the policy is `noeviction` or no key qualifies.
+pub async fn perform_evictions(db: &Store) -> Result<(), SiderError> {
+    let maxmemory = get_maxmemory();
+
+    // The common case, checked without locking anything
+    if maxmemory == 0 || db.used_memory() as u64 <= maxmemory {
+        return Ok(());
+    }
+
+    evict_until(
+        db,
+        maxmemory,
+        get_maxmemory_policy(),
+        get_maxmemory_samples(),
+    )
+    .await
+}
+
+/// Evicts keys picked with `policy` among `samples` random ones until at
+/// most `maxmemory` bytes are used. Only one shard is locked at a time.
+pub async fn evict_until(
+    db: &Store,
+    maxmemory: u64,
+    policy: EvictionPolicy,
+    samples: usize,
+) -> Result<(), SiderError> {
+    let shards = db.shards();
+
+    while db.used_memory() as u64 > maxmemory {
+        if policy == EvictionPolicy::NoEviction {
+            return Err(SiderError::OutOfMemory);
+        }
+
+        let start = random_index(shards.len());
+        let mut evicted = false;
+
+        for i in 0..shards.len() {
+            let mut shard = shards[(start + i) % shards.len()].write().await;
+
+            if let Some(key) = shard.eviction_candidate(policy, samples.max(1)) {
+                shard.evict(&key);
+
+                // Replaying the AOF must not bring it back
+                let del = Command {
+                    command_type: CommandType::DEL,
+                    args: CommandArgs::MultipleKeys(vec![key]),
+                };
+                write_aof(&del)
+                    .await
+                    .map_err(|e| SiderError::AofWriteFailed(e.to_string()))?;
+
+                evicted = true;
+                break;
+            }
+        }
+
+        if !evicted {
+            return Err(SiderError::OutOfMemory);
+        }
+
+        // Let clients in between evictions
+        tokio::task::yield_now().await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use crate::pubsub::get_pubsub;
+use bytes::Bytes;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// Same classes and letters as Redis `notify-keyspace-events`
+pub const NOTIFY_KEYSPACE: u32 = 1 << 0; // K, __keyspace@0__:<key> channels
+pub const NOTIFY_KEYEVENT: u32 = 1 << 1; // E, __keyevent@0__:<event> channels
+pub const NOTIFY_GENERIC: u32 = 1 << 2; // g, DEL, EXPIRE, ...
+pub const NOTIFY_STRING: u32 = 1 << 3; // $
+pub const NOTIFY_LIST: u32 = 1 << 4; // l
+pub const NOTIFY_SET: u32 = 1 << 5; // s
+pub const NOTIFY_HASH: u32 = 1 << 6; // h
+pub const NOTIFY_ZSET: u32 = 1 << 7; // z
+pub const NOTIFY_EXPIRED: u32 = 1 << 8; // x
+pub const NOTIFY_EVICTED: u32 = 1 << 9; // e
+pub const NOTIFY_STREAM: u32 = 1 << 10; // t
+pub const NOTIFY_ALL: u32 = NOTIFY_GENERIC
+    | NOTIFY_STRING
+    | NOTIFY_LIST
+    | NOTIFY_SET
+    | NOTIFY_HASH
+    | NOTIFY_ZSET
+    | NOTIFY_EXPIRED
+    | NOTIFY_EVICTED
+    | NOTIFY_STREAM; // A
+
+const CLASSES: [(u8, u32); 11] = [
+    (b'K', NOTIFY_KEYSPACE),
+    (b'E', NOTIFY_KEYEVENT),
+    (b'g', NOTIFY_GENERIC),
+    (b'$', NOTIFY_STRING),
+    (b'l', NOTIFY_LIST),
+    (b's', NOTIFY_SET),
+    (b'h', NOTIFY_HASH),
+    (b'z', NOTIFY_ZSET),
+    (b'x', NOTIFY_EXPIRED),
+    (b'e', NOTIFY_EVICTED),
+    (b't', NOTIFY_STREAM),
+];
+
+// Disabled by default, like Redis
+static NOTIFY_FLAGS: AtomicU32 = AtomicU32::new(0);
+
+/// Parses a `notify-keyspace-events` value such as `KEA` or `Elx`.
+pub fn parse_notify_flags(value: &[u8]) -> Option<u32> {
+    value.iter().try_fold(0, |flags, c| match c {
+        b'A' => Some(flags | NOTIFY_ALL),
+        _ => CLASSES
+            .iter()
+            .find(|(letter, _)| letter == c)
+            .map(|(_, class)| flags | class),
+    })
+}
+
+pub fn format_notify_flags(flags: u32) -> String {
+    let mut value = String::new();
+
+    if flags & NOTIFY_ALL == NOTIFY_ALL {
+        value.push('A');
+    }
+
+    for (letter, class) in CLASSES {
+        let in_all = NOTIFY_ALL & class != 0 && flags & NOTIFY_ALL == NOTIFY_ALL;
+        if flags & class != 0 && !in_all {
+            value.push(letter as char);
+        }
+    }
+
+    value
+}
+
+pub fn get_notify_flags() -> u32 {
+    NOTIFY_FLAGS.load(Ordering::Relaxed)
+}
+
+pub fn set_notify_flags(flags: u32) {
+    NOTIFY_FLAGS.store(flags, Ordering::Relaxed);
+}
+
+/// Publishes `event` for `key` if its class is enabled: the event name on
+/// `__keyspace@0__:<key>` and the key name on `__keyevent@0__:<event>`.
+pub fn notify_keyspace_event(class: u32, event: &str, key: &[u8]) {
+    let flags = get_notify_flags();
+
+    if flags & class == 0 {
+        return;
+    }
+
+    let pubsub = get_pubsub();
+
+    if flags & NOTIFY_KEYSPACE != 0 {
+        let mut channel = b"__keyspace@0__:".to_vec();
+        channel.extend_from_slice(key);
+        pubsub.publish(
+            &Bytes::from(channel),
+            &Bytes::copy_from_slice(event.as_bytes()),
+        );
+    }
+
+    if flags & NOTIFY_KEYEVENT != 0 {
+        let channel = format!("__keyevent@0__:{}", event);
+        pubsub.publish(&Bytes::from(channel), &Bytes::copy_from_slice(key));
+    }
+}
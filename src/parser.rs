@@ -1,17 +1,14 @@
-use crate::aof::write_aof;
 use crate::commands::build::*;
 use crate::errors::SiderError;
 use crate::types::Command;
+use bytes::Bytes;
 
-pub async fn parse_command(
-    mut args: Vec<String>,
-    restore: bool,
-) -> Result<Command, SiderError> {
+pub fn parse_command(mut args: Vec<Bytes>) -> Result<Command, SiderError> {
     if args.is_empty() {
         return Err(SiderError::InvalidCommand);
     }
 
-    let command_type = args[0].to_uppercase();
+    let command_type = String::from_utf8_lossy(&args[0]).to_uppercase();
     args.remove(0);
 
     let command = match command_type.as_str() {
@@ -25,6 +22,13 @@ pub async fn parse_command(
         "EXISTS" => build_exists_command(&args),
         "EXPIRE" => build_expire_command(&args),
         "TTL" => build_ttl_command(&args),
+        "PEXPIRE" => build_pexpire_command(&args),
+        "PTTL" => build_pttl_command(&args),
+        "EXPIREAT" => build_expireat_command(&args),
+        "PEXPIREAT" => build_pexpireat_command(&args),
+        "EXPIRETIME" => build_expiretime_command(&args),
+        "PEXPIRETIME" => build_pexpiretime_command(&args),
+        "PERSIST" => build_persist_command(&args),
         "INCR" => build_incr_command(&args),
         "DECR" => build_decr_command(&args),
         "INCRBY" => build_incrby_command(&args),
@@ -33,6 +37,10 @@ pub async fn parse_command(
         "LRANGE" => build_lrange_command(&args),
         "LPOP" => build_lpop_command(&args),
         "RPOP" => build_rpop_command(&args),
+        "LMOVE" => build_lmove_command(&args),
+        "BLPOP" => build_blpop_command(&args),
+        "BRPOP" => build_brpop_command(&args),
+        "BLMOVE" => build_blmove_command(&args),
         "HSET" => build_hset_command(&args),
         "HGET" => build_hget_command(&args),
         "HGETALL" => build_hgetall_command(&args),
@@ -41,14 +49,54 @@ pub async fn parse_command(
         "SADD" => build_sadd_command(&args),
         "SMEMBERS" => build_smembers_command(&args),
         "SREM" => build_srem_command(&args),
+        "HELLO" => build_hello_command(&args),
+        "MULTI" => build_multi_command(&args),
+        "EXEC" => build_exec_command(&args),
+        "DISCARD" => build_discard_command(&args),
+        "WATCH" => build_watch_command(&args),
+        "UNWATCH" => build_unwatch_command(&args),
+        "SUBSCRIBE" => build_subscribe_command(&args),
+        "UNSUBSCRIBE" => build_unsubscribe_command(&args),
+        "PSUBSCRIBE" => build_psubscribe_command(&args),
+        "PUNSUBSCRIBE" => build_punsubscribe_command(&args),
+        "PUBLISH" => build_publish_command(&args),
+        "PUBSUB" => build_pubsub_command(&args),
+        "CONFIG" => build_config_command(&args),
+        "INFO" => build_info_command(&args),
+        "BGREWRITEAOF" => build_bgrewriteaof_command(&args),
+        "SAVE" => build_save_command(&args),
+        "BGSAVE" => build_bgsave_command(&args),
+        "LASTSAVE" => build_lastsave_command(&args),
+        "DEBUG" => build_debug_command(&args),
+        "ZADD" => build_zadd_command(&args),
+        "ZREM" => build_zrem_command(&args),
+        "ZSCORE" => build_zscore_command(&args),
+        "ZINCRBY" => build_zincrby_command(&args),
+        "ZCARD" => build_zcard_command(&args),
+        "ZRANK" => build_zrank_command(&args),
+        "ZREVRANK" => build_zrevrank_command(&args),
+        "ZRANGE" => build_zrange_command(&args),
+        "ZRANGEBYSCORE" => build_zrangebyscore_command(&args),
+        "ZCOUNT" => build_zcount_command(&args),
+        "ZPOPMIN" => build_zpopmin_command(&args),
+        "ZPOPMAX" => build_zpopmax_command(&args),
+        "ZREMRANGEBYSCORE" => build_zremrangebyscore_command(&args),
+        "XADD" => build_xadd_command(&args),
+        "XLEN" => build_xlen_command(&args),
+        "XRANGE" => build_xrange_command(&args),
+        "XREVRANGE" => build_xrevrange_command(&args),
+        "XREAD" => build_xread_command(&args),
+        "XDEL" => build_xdel_command(&args),
+        "XTRIM" => build_xtrim_command(&args),
+        "XSETID" => build_xsetid_command(&args),
+        "XGROUP" => build_xgroup_command(&args),
+        "XREADGROUP" => build_xreadgroup_command(&args),
+        "XACK" => build_xack_command(&args),
+        "XPENDING" => build_xpending_command(&args),
+        "XCLAIM" => build_xclaim_command(&args),
+        "XAUTOCLAIM" => build_xautoclaim_command(&args),
         _ => return Err(SiderError::InvalidCommand),
     }?;
 
-    if !restore {
-        write_aof(&command)
-            .await
-            .expect("Error writing to AOF file!");
-    }
-
     Ok(command)
 }
@@ -1,49 +1,389 @@
+use crate::aof::{aof_write_error, is_read_command, write_aof, write_aof_transaction};
+use crate::client::{BlockedWait, Client};
+use crate::database::is_loading;
 use crate::parser::parse_command;
+use crate::types::BlockingRequest;
+use crate::types::Command;
 use crate::types::CommandType;
 use crate::types::Db;
+use crate::types::Keyspace;
+use bytes::Bytes;
 
+use crate::commands::blocking::*;
+use crate::commands::config::*;
 use crate::commands::db::*;
 use crate::commands::hashsets::*;
+use crate::commands::info::*;
 use crate::commands::keys::*;
 use crate::commands::lists::*;
 use crate::commands::misc::*;
+use crate::commands::persistence::*;
+use crate::commands::pubsub::*;
 use crate::commands::sets::*;
+use crate::commands::streams::*;
+use crate::commands::transactions::*;
+use crate::commands::zsets::*;
 use crate::errors::SiderError;
+use crate::memory::{is_denyoom_command, perform_evictions};
 use crate::response::SiderResponse;
 
 pub async fn process_command(
-    command: Vec<String>,
+    command: Vec<Bytes>,
     db: &Db,
+    conn: &mut Client,
     restore: bool,
 ) -> Result<SiderResponse, SiderError> {
-    let command = parse_command(command, restore).await?;
+    let command = match parse_command(command) {
+        Ok(command) => command,
+        Err(e) => {
+            // A command that can't be queued makes the whole transaction fail
+            if conn.transaction.is_some() {
+                conn.transaction_aborted = true;
+            }
+            return Err(e);
+        }
+    };
 
+    // Nothing is served until the dataset is restored, but clients can set up
+    // their connection and check on the server and the loading progress
+    if !restore
+        && is_loading()
+        && !matches!(
+            command.command_type,
+            CommandType::PONG | CommandType::INFO | CommandType::HELLO | CommandType::CLIENT
+        )
+    {
+        if conn.transaction.is_some() {
+            conn.transaction_aborted = true;
+        }
+        return Err(SiderError::Loading);
+    }
+
+    // Memory is freed before running anything, commands that would use more
+    // are refused if it can't be. The AOF is replayed whatever the limit.
+    if !restore {
+        if let Err(e) = perform_evictions(db).await {
+            if is_denyoom_command(command.command_type.clone()) {
+                if conn.transaction.is_some() {
+                    conn.transaction_aborted = true;
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    // Writes are refused while the AOF can't be written, they would be lost
+    // on restart
+    if !restore && !is_read_command(command.command_type.clone()) {
+        if let Some(e) = aof_write_error() {
+            if conn.transaction.is_some() {
+                conn.transaction_aborted = true;
+            }
+            return Err(SiderError::AofWriteFailed(e));
+        }
+    }
+
+    if conn.in_subscribe_mode() {
+        match command.command_type {
+            // Replies are pushed like messages while subscribed
+            CommandType::PONG => {
+                return Ok(SiderResponse::Push(vec![
+                    SiderResponse::BulkString(Bytes::from_static(b"pong")),
+                    SiderResponse::BulkString(Bytes::new()),
+                ]))
+            }
+            CommandType::SUBSCRIBE
+            | CommandType::UNSUBSCRIBE
+            | CommandType::PSUBSCRIBE
+            | CommandType::PUNSUBSCRIBE => {}
+            _ => return Err(SiderError::SubscribeMode),
+        }
+    }
+
+    match command.command_type {
+        CommandType::SUBSCRIBE
+        | CommandType::UNSUBSCRIBE
+        | CommandType::PSUBSCRIBE
+        | CommandType::PUNSUBSCRIBE
+            if conn.transaction.is_some() =>
+        {
+            conn.transaction_aborted = true;
+            return Err(SiderError::NotAllowedInMulti);
+        }
+        CommandType::SUBSCRIBE => return subscribe(conn, command),
+        CommandType::UNSUBSCRIBE => return unsubscribe(conn, command),
+        CommandType::PSUBSCRIBE => return psubscribe(conn, command),
+        CommandType::PUNSUBSCRIBE => return punsubscribe(conn, command),
+        CommandType::SAVE if conn.transaction.is_none() => return save(db).await,
+        CommandType::DEBUG if conn.transaction.is_none() => return debug(db, command).await,
+        CommandType::MULTI => return multi(conn),
+        CommandType::EXEC => return exec(db, conn, restore).await,
+        CommandType::DISCARD => return discard(db, conn).await,
+        CommandType::UNWATCH if conn.transaction.is_none() => return unwatch(db, conn).await,
+        CommandType::WATCH if conn.transaction.is_some() => {
+            return Err(SiderError::WatchInsideMulti)
+        }
+        _ => {}
+    }
+
+    if let Some(queued) = conn.transaction.as_mut() {
+        queued.push(command);
+        return Ok(SiderResponse::SimpleString("QUEUED".to_string()));
+    }
+
+    let mut db = match command.shard_keys() {
+        Some(keys) => db.lock(&keys).await,
+        None => db.lock_all().await,
+    };
+
+    let result = match execute_or_block(&mut db, conn, command.clone()) {
+        Ok(Execution::Done(reply)) => Ok(reply),
+        Ok(Execution::Blocked(request)) => {
+            let reply = db.block(conn.id, request.keys, request.operation.clone());
+            conn.blocked = Some(BlockedWait {
+                reply,
+                timeout: request.timeout,
+            });
+            return Ok(request.operation.timeout_reply());
+        }
+        Err(e) => Err(e),
+    };
+
+    // Logged while holding the lock so the AOF follows the execution order
+    let logged = match result {
+        Ok(_) if !restore => log_command(&mut db, command).await,
+        _ => Ok(()),
+    };
+
+    serve_blocked_clients(&mut db, conn, restore).await;
+    logged?;
+    result
+}
+
+/// Commands an executed command is logged as: itself, or what it asked to
+/// be logged as instead, e.g. XADD with the ID it generated.
+pub fn logged_commands(db: &mut Keyspace, command: Command) -> Vec<Command> {
+    db.take_propagated().unwrap_or_else(|| vec![command])
+}
+
+// Fails if the AOF couldn't be written, the command was applied and its
+// write is retried, but isn't on disk
+async fn log_command(db: &mut Keyspace, command: Command) -> Result<(), SiderError> {
+    let logged = match logged_commands(db, command).as_slice() {
+        [] => Ok(()),
+        [command] => write_aof(command).await,
+        // Replayed all at once, like the command they stand for
+        commands => write_aof_transaction(commands).await,
+    };
+
+    logged.map_err(|e| SiderError::AofWriteFailed(e.to_string()))
+}
+
+/// Hands the elements pushed or the entries added by the last command to the
+/// clients blocked on those keys, longest waiting first.
+pub async fn serve_blocked_clients(db: &mut Keyspace, conn: &mut Client, restore: bool) {
+    while let Some((key, blocked)) = db.next_unblocked() {
+        let command = blocked.operation.command(key.clone());
+
+        // Run on the blocked client's behalf, the commands blocking ones
+        // amount to don't depend on the connection they come from
+        let reply = match execute_command(db, conn, command.clone()) {
+            Ok(reply) => {
+                let logged = if restore {
+                    Ok(())
+                } else {
+                    log_command(db, command).await
+                };
+
+                match logged {
+                    Ok(()) => blocked.operation.reply(key, reply),
+                    Err(e) => SiderResponse::Error(e),
+                }
+            }
+            Err(e) => SiderResponse::Error(e),
+        };
+
+        // The receiver is only gone if the client disconnected after being
+        // picked, there is nobody left to hand the element to
+        let _ = blocked.reply.send(reply);
+    }
+}
+
+/// Runs a parsed command against the keyspace. The caller holds the lock.
+pub fn execute_command(
+    db: &mut Keyspace,
+    conn: &mut Client,
+    command: Command,
+) -> Result<SiderResponse, SiderError> {
+    // Never blocks here, e.g. inside MULTI it behaves like LPOP, RPOP or LMOVE
+    match execute_or_block(db, conn, command)? {
+        Execution::Done(reply) => Ok(reply),
+        Execution::Blocked(request) => {
+            db.propagate_as(Vec::new());
+            Ok(request.operation.timeout_reply())
+        }
+    }
+}
+
+// What running a command amounted to, a blocking command with nothing to
+// serve it yet has to wait
+enum Execution {
+    Done(SiderResponse),
+    Blocked(BlockingRequest),
+}
+
+// Serves a blocking command right away if one of its keys has an element or
+// entries to read. The AOF gets the non-blocking command it amounted to.
+fn execute_or_block(
+    db: &mut Keyspace,
+    conn: &mut Client,
+    command: Command,
+) -> Result<Execution, SiderError> {
+    lookup_keys(db, &command);
+
+    let Some(request) = blocking_request(db, &command)? else {
+        return run_command(db, conn, command).map(Execution::Done);
+    };
+
+    let Some(key) = blocking_source(db, &request)? else {
+        return Ok(Execution::Blocked(request));
+    };
+
+    let served = request.operation.command(key.clone());
+    let reply = run_command(db, conn, served.clone())?;
+
+    let logged = logged_commands(db, served);
+    db.propagate_as(logged);
+
+    Ok(Execution::Done(request.operation.reply(key, reply)))
+}
+
+// The keys were already looked up, those of a command served for a blocking
+// one are among the blocking command's keys
+fn run_command(
+    db: &mut Keyspace,
+    conn: &mut Client,
+    command: Command,
+) -> Result<SiderResponse, SiderError> {
+    let is_write = !is_read_command(command.command_type.clone());
+    let keys = if is_write { command.keys() } else { Vec::new() };
+
+    // Left over by a command whose caller didn't log it
+    db.take_propagated();
+
+    let result = dispatch(db, conn, command);
+
+    // Watchers are signaled by the commands, only for the keys they changed
+    if is_write && result.is_ok() {
+        for key in keys {
+            db.refresh_memory_usage(&key);
+        }
+    }
+
+    result
+}
+
+// Lazy expiry: the keys a command accesses are deleted first if expired, so
+// no command ever sees them. The others are marked as accessed for eviction.
+fn lookup_keys(db: &mut Keyspace, command: &Command) {
+    for key in command.keys() {
+        if !db.expire_if_needed(&key) {
+            db.record_access(&key);
+        }
+    }
+}
+
+fn dispatch(
+    db: &mut Keyspace,
+    conn: &mut Client,
+    command: Command,
+) -> Result<SiderResponse, SiderError> {
     match command.command_type {
-        CommandType::DOCS => docs().await,
-        CommandType::PONG => pong().await,
-        CommandType::GET => get_key(db, command).await,
-        CommandType::SET => set_key(db, command).await,
-        CommandType::DEL => delete_key(db, command).await,
-        CommandType::FLUSHDB => flush_db(db).await,
-        CommandType::KEYS => get_keys(db, command).await,
-        CommandType::EXISTS => exists(db, command).await,
-        CommandType::EXPIRE => expire(db, command).await,
-        CommandType::TTL => ttl(db, command).await,
-        CommandType::INCR => incr(db, command).await,
-        CommandType::DECR => decr(db, command).await,
-        CommandType::INCRBY => incrby(db, command).await,
-        CommandType::LPUSH => lpush(db, command).await,
-        CommandType::LRANGE => lrange(db, command).await,
-        CommandType::RPUSH => rpush(db, command).await,
-        CommandType::LPOP => lpop(db, command).await,
-        CommandType::RPOP => rpop(db, command).await,
-        CommandType::HSET => hset(db, command).await,
-        CommandType::HGET => hget(db, command).await,
-        CommandType::HGETALL => hgetall(db, command).await,
-        CommandType::HDEL => hdel(db, command).await,
-        CommandType::CLIENT => client().await,
-        CommandType::SADD => sadd(db, command).await,
-        CommandType::SMEMBERS => smembers(db, command).await,
-        CommandType::SREM => srem(db, command).await,
+        CommandType::DOCS => docs(),
+        CommandType::PONG => pong(),
+        CommandType::GET => get_key(db, command),
+        CommandType::SET => set_key(db, command),
+        CommandType::DEL => delete_key(db, command),
+        CommandType::FLUSHDB => flush_db(db),
+        CommandType::KEYS => get_keys(db, command),
+        CommandType::EXISTS => exists(db, command),
+        CommandType::EXPIRE => expire(db, command),
+        CommandType::TTL => ttl(db, command),
+        CommandType::PEXPIRE => pexpire(db, command),
+        CommandType::PTTL => pttl(db, command),
+        CommandType::EXPIREAT => expireat(db, command),
+        CommandType::PEXPIREAT => pexpireat(db, command),
+        CommandType::EXPIRETIME => expiretime(db, command),
+        CommandType::PEXPIRETIME => pexpiretime(db, command),
+        CommandType::PERSIST => persist(db, command),
+        CommandType::INCR => incr(db, command),
+        CommandType::DECR => decr(db, command),
+        CommandType::INCRBY => incrby(db, command),
+        CommandType::LPUSH => lpush(db, command),
+        CommandType::LRANGE => lrange(db, command),
+        CommandType::RPUSH => rpush(db, command),
+        CommandType::LPOP => lpop(db, command),
+        CommandType::RPOP => rpop(db, command),
+        CommandType::LMOVE => lmove(db, command),
+        CommandType::HSET => hset(db, command),
+        CommandType::HGET => hget(db, command),
+        CommandType::HGETALL => hgetall(db, command),
+        CommandType::HDEL => hdel(db, command),
+        CommandType::CLIENT => client(),
+        CommandType::SADD => sadd(db, command),
+        CommandType::SMEMBERS => smembers(db, command),
+        CommandType::SREM => srem(db, command),
+        CommandType::HELLO => hello(conn, command),
+        CommandType::WATCH => watch(db, conn, command),
+        // Queued in a transaction, EXEC already stopped watching the keys
+        CommandType::UNWATCH => Ok(SiderResponse::Ok),
+        CommandType::PUBLISH => publish(command),
+        CommandType::PUBSUB => pubsub(command),
+        CommandType::CONFIG => config(command),
+        CommandType::INFO => info(db, command),
+        CommandType::BGREWRITEAOF => bgrewriteaof(),
+        CommandType::BGSAVE => bgsave(),
+        CommandType::LASTSAVE => lastsave(),
+        // Wait for the whole store
+        CommandType::SAVE | CommandType::DEBUG => Err(SiderError::NotAllowedInMulti),
+        CommandType::ZADD => zadd(db, command),
+        CommandType::ZREM => zrem(db, command),
+        CommandType::ZSCORE => zscore(db, command),
+        CommandType::ZINCRBY => zincrby(db, command),
+        CommandType::ZCARD => zcard(db, command),
+        CommandType::ZRANK => zrank(db, command),
+        CommandType::ZREVRANK => zrevrank(db, command),
+        CommandType::ZRANGE => zrange(db, command),
+        CommandType::ZRANGEBYSCORE => zrangebyscore(db, command),
+        CommandType::ZCOUNT => zcount(db, command),
+        CommandType::ZPOPMIN => zpopmin(db, command),
+        CommandType::ZPOPMAX => zpopmax(db, command),
+        CommandType::ZREMRANGEBYSCORE => zremrangebyscore(db, command),
+        CommandType::XADD => xadd(db, command),
+        CommandType::XLEN => xlen(db, command),
+        CommandType::XRANGE => xrange(db, command),
+        CommandType::XREVRANGE => xrevrange(db, command),
+        CommandType::XREAD => xread(db, command),
+        CommandType::XDEL => xdel(db, command),
+        CommandType::XTRIM => xtrim(db, command),
+        CommandType::XSETID => xsetid(db, command),
+        CommandType::XGROUP => xgroup(db, command),
+        CommandType::XREADGROUP => xreadgroup(db, command),
+        CommandType::XACK => xack(db, command),
+        CommandType::XPENDING => xpending(db, command),
+        CommandType::XCLAIM => xclaim(db, command),
+        CommandType::XAUTOCLAIM => xautoclaim(db, command),
+        // Handled by process_command before anything is queued, or by
+        // execute_or_block for the blocking ones
+        CommandType::BLPOP
+        | CommandType::BRPOP
+        | CommandType::BLMOVE
+        | CommandType::MULTI
+        | CommandType::EXEC
+        | CommandType::DISCARD
+        | CommandType::SUBSCRIBE
+        | CommandType::UNSUBSCRIBE
+        | CommandType::PSUBSCRIBE
+        | CommandType::PUNSUBSCRIBE => Err(SiderError::InvalidCommand),
     }
 }
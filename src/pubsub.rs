@@ -0,0 +1,169 @@
+use crate::commands::keys::convert_redis_pattern_to_regex;
+use crate::errors::SiderError;
+use crate::response::SiderResponse;
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+use regex::bytes::Regex;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Where a client receives its messages, drained by its connection task.
+pub type MessageSender = UnboundedSender<SiderResponse>;
+
+struct PatternSubscribers {
+    regex: Regex,
+    subscribers: HashMap<u64, MessageSender>,
+}
+
+#[derive(Default)]
+struct Registry {
+    // Subscribers are indexed by client id
+    channels: HashMap<Bytes, HashMap<u64, MessageSender>>,
+    patterns: HashMap<Bytes, PatternSubscribers>,
+}
+
+/// Channel and pattern subscriptions of every connection.
+///
+/// Publishing only queues the message for each subscriber, the connection
+/// tasks write them out as push frames.
+#[derive(Default)]
+pub struct PubSub {
+    registry: Mutex<Registry>,
+}
+
+static PUBSUB: Lazy<PubSub> = Lazy::new(PubSub::default);
+
+pub fn get_pubsub() -> &'static PubSub {
+    &PUBSUB
+}
+
+impl PubSub {
+    fn registry(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().expect("Pub/sub registry poisoned")
+    }
+
+    pub fn subscribe(&self, channel: Bytes, client_id: u64, sender: MessageSender) {
+        self.registry()
+            .channels
+            .entry(channel)
+            .or_default()
+            .insert(client_id, sender);
+    }
+
+    pub fn unsubscribe(&self, channel: &[u8], client_id: u64) {
+        let mut registry = self.registry();
+
+        if let Some(subscribers) = registry.channels.get_mut(channel) {
+            subscribers.remove(&client_id);
+
+            if subscribers.is_empty() {
+                registry.channels.remove(channel);
+            }
+        }
+    }
+
+    pub fn psubscribe(
+        &self,
+        pattern: Bytes,
+        client_id: u64,
+        sender: MessageSender,
+    ) -> Result<(), SiderError> {
+        let mut registry = self.registry();
+
+        if let Some(existing) = registry.patterns.get_mut(&pattern) {
+            existing.subscribers.insert(client_id, sender);
+            return Ok(());
+        }
+
+        let regex = Regex::new(&convert_redis_pattern_to_regex(&pattern))
+            .map_err(|_| SiderError::RegexError)?;
+
+        registry.patterns.insert(
+            pattern,
+            PatternSubscribers {
+                regex,
+                subscribers: HashMap::from([(client_id, sender)]),
+            },
+        );
+
+        Ok(())
+    }
+
+    pub fn punsubscribe(&self, pattern: &[u8], client_id: u64) {
+        let mut registry = self.registry();
+
+        if let Some(existing) = registry.patterns.get_mut(pattern) {
+            existing.subscribers.remove(&client_id);
+
+            if existing.subscribers.is_empty() {
+                registry.patterns.remove(pattern);
+            }
+        }
+    }
+
+    /// Sends the message to the channel subscribers and to the clients with a
+    /// matching pattern. Returns the number of clients that received it.
+    pub fn publish(&self, channel: &Bytes, message: &Bytes) -> i64 {
+        let registry = self.registry();
+        let mut receivers = 0;
+
+        if let Some(subscribers) = registry.channels.get(channel) {
+            let frame = SiderResponse::Push(vec![
+                SiderResponse::BulkString(Bytes::from_static(b"message")),
+                SiderResponse::BulkString(channel.clone()),
+                SiderResponse::BulkString(message.clone()),
+            ]);
+
+            for sender in subscribers.values() {
+                // The connection may be closing, it is unsubscribed on drop
+                if sender.send(frame.clone()).is_ok() {
+                    receivers += 1;
+                }
+            }
+        }
+
+        for (pattern, existing) in registry.patterns.iter() {
+            if !existing.regex.is_match(channel) {
+                continue;
+            }
+
+            let frame = SiderResponse::Push(vec![
+                SiderResponse::BulkString(Bytes::from_static(b"pmessage")),
+                SiderResponse::BulkString(pattern.clone()),
+                SiderResponse::BulkString(channel.clone()),
+                SiderResponse::BulkString(message.clone()),
+            ]);
+
+            for sender in existing.subscribers.values() {
+                if sender.send(frame.clone()).is_ok() {
+                    receivers += 1;
+                }
+            }
+        }
+
+        receivers
+    }
+
+    /// Channels with at least one subscriber, optionally filtered.
+    pub fn channels(&self, filter: Option<&Regex>) -> Vec<Bytes> {
+        self.registry()
+            .channels
+            .keys()
+            .filter(|channel| filter.is_none_or(|regex| regex.is_match(channel)))
+            .cloned()
+            .collect()
+    }
+
+    pub fn numsub(&self, channel: &[u8]) -> i64 {
+        self.registry()
+            .channels
+            .get(channel)
+            .map_or(0, |subscribers| subscribers.len() as i64)
+    }
+
+    /// Number of distinct patterns subscribed to, by any client.
+    pub fn numpat(&self) -> i64 {
+        self.registry().patterns.len() as i64
+    }
+}
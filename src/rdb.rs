@@ -0,0 +1,857 @@
+//! Redis RDB files, to move datasets between Redis and Sider. Files are
+//! written in RDB version 9 with the plain encodings, which Redis loads
+//! since 5.0. Versions up to 11 are read, with the compact encodings Redis
+//! uses for small collections: ziplists, listpacks, intsets and quicklists,
+//! and LZF compressed strings.
+//!
+//! Only the data types Sider shares with Redis are converted: streams and
+//! function libraries are left out with a warning, and files with module
+//! data or functions of Redis 7.0 release candidates are rejected.
+
+use crate::aof::{append_aof, get_aof_log_dir, replace_aof};
+use crate::crc64::crc64;
+use crate::errors::SiderError;
+use crate::resp::encode_command;
+use crate::rewrite::push_key_dump;
+use crate::shard::Shard;
+use crate::snapshot::add_changes;
+use crate::types::{DbValue, Key, KeyHash, KeyList, KeySet, KeyZSet, Keyspace};
+use crate::utils::current_time_millis;
+use crate::zset::SortedSet;
+use bytes::Bytes;
+use indexmap::IndexMap;
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+const MAGIC: &[u8] = b"REDIS";
+/// Version of the files written.
+pub const RDB_VERSION: u32 = 9;
+// Redis 7.2, newer versions may use encodings unknown here
+const MAX_RDB_VERSION: u32 = 11;
+
+const OPCODE_FUNCTION2: u8 = 0xF5;
+const OPCODE_FUNCTION_PRE_GA: u8 = 0xF6;
+const OPCODE_MODULE_AUX: u8 = 0xF7;
+const OPCODE_IDLE: u8 = 0xF8;
+const OPCODE_FREQ: u8 = 0xF9;
+const OPCODE_AUX: u8 = 0xFA;
+const OPCODE_RESIZEDB: u8 = 0xFB;
+const OPCODE_EXPIRETIME_MS: u8 = 0xFC;
+const OPCODE_EXPIRETIME: u8 = 0xFD;
+const OPCODE_SELECTDB: u8 = 0xFE;
+const OPCODE_EOF: u8 = 0xFF;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+const TYPE_LIST_ZIPLIST: u8 = 10;
+const TYPE_SET_INTSET: u8 = 11;
+const TYPE_ZSET_ZIPLIST: u8 = 12;
+const TYPE_HASH_ZIPLIST: u8 = 13;
+const TYPE_LIST_QUICKLIST: u8 = 14;
+const TYPE_HASH_LISTPACK: u8 = 16;
+const TYPE_ZSET_LISTPACK: u8 = 17;
+const TYPE_LIST_QUICKLIST_2: u8 = 18;
+const TYPE_SET_LISTPACK: u8 = 20;
+const TYPE_STREAM_LISTPACKS: u8 = 15;
+const TYPE_STREAM_LISTPACKS_2: u8 = 19;
+const TYPE_STREAM_LISTPACKS_3: u8 = 21;
+
+// Strings stored as integers or compressed instead of their bytes
+const ENCODING_INT8: u8 = 0;
+const ENCODING_INT16: u8 = 1;
+const ENCODING_INT32: u8 = 2;
+const ENCODING_LZF: u8 = 3;
+
+// Quicklist nodes holding a single big element instead of a listpack
+const QUICKLIST_NODE_PLAIN: usize = 1;
+
+pub fn get_rdb_file() -> PathBuf {
+    get_aof_log_dir().join("dump.rdb")
+}
+
+/// Writes the keys of the locked shards to `dump.rdb`, replacing the
+/// previous file once complete. Fails if there are streams, they would be
+/// missing from the file.
+pub async fn save_rdb(keyspace: &Keyspace) -> Result<(), SiderError> {
+    let streams = keyspace.locked_shards().map(count_streams).sum();
+    if streams > 0 {
+        return Err(SiderError::RdbStreams(streams));
+    }
+    let output = encode_rdb(keyspace.locked_shards());
+
+    let path = get_aof_log_dir().join("temp-dump.rdb");
+    let saved = async {
+        let mut file = fs::File::create(&path).await?;
+        file.write_all(&output).await?;
+        file.sync_all().await?;
+        fs::rename(&path, get_rdb_file()).await
+    }
+    .await;
+
+    if let Err(e) = saved {
+        let _ = fs::remove_file(&path).await;
+        return Err(SiderError::SaveFailed(e.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Loads `dump.rdb` in place of the dataset, or on top of it if `merge`,
+/// and logs the keys loaded to the AOF. Every shard must be locked until the
+/// AOF has them, so no write lands in between. Returns the number of keys
+/// loaded.
+pub async fn load_rdb(keyspace: &mut Keyspace, merge: bool) -> Result<usize, SiderError> {
+    let data = fs::read(get_rdb_file())
+        .await
+        .map_err(|e| SiderError::InvalidRdb(e.to_string()))?;
+    let entries: Vec<_> = decode_rdb(&data)?
+        .into_iter()
+        .filter(|(_, value)| !value.is_expired())
+        .collect();
+
+    let mut logged = Vec::new();
+    for (key, value) in &entries {
+        if merge {
+            encode_command(&mut logged, &[&b"DEL"[..], &key[..]]);
+        }
+        push_key_dump(&mut logged, key, value);
+    }
+
+    // On disk before the dataset changes, nothing is lost if it can't be
+    let logged = if merge {
+        append_aof(logged).await
+    } else {
+        replace_aof_with(logged).await
+    };
+    logged.map_err(|e| SiderError::InvalidRdb(format!("failed to log the keys: {e}")))?;
+
+    if !merge {
+        keyspace.clear();
+    }
+
+    let loaded = entries.len();
+    for (key, value) in entries {
+        keyspace.insert(key, value);
+    }
+
+    add_changes(loaded as u64);
+    info!("Loaded {loaded} keys from the RDB file");
+    Ok(loaded)
+}
+
+// Written to a temporary file renamed over the AOF, a crash leaves either
+// the old AOF or the new one whole
+async fn replace_aof_with(content: Vec<u8>) -> std::io::Result<()> {
+    let path = get_aof_log_dir().join("temp-reload.aof");
+
+    let replaced = async {
+        let mut file = fs::File::create(&path).await?;
+        file.write_all(&content).await?;
+        file.sync_all().await?;
+        replace_aof(path.clone()).await
+    }
+    .await;
+
+    if replaced.is_err() {
+        let _ = fs::remove_file(&path).await;
+    }
+    replaced
+}
+
+/// Keys of the shard `encode_rdb` leaves out as it can't write them.
+pub fn count_streams(shard: &Shard) -> usize {
+    shard
+        .iter()
+        .filter(|(_, value)| matches!(value, DbValue::StreamKey(_)) && !value.is_expired())
+        .count()
+}
+
+/// A whole RDB file with the keys of the shards, expired ones and streams
+/// left out.
+pub fn encode_rdb<'a, I>(shards: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = &'a Shard>,
+{
+    let mut output = format!("REDIS{RDB_VERSION:04}").into_bytes();
+
+    push_aux(&mut output, "redis-bits", &(usize::BITS).to_string());
+    push_aux(
+        &mut output,
+        "ctime",
+        &(current_time_millis() / 1000).to_string(),
+    );
+    push_aux(&mut output, "sider-ver", env!("CARGO_PKG_VERSION"));
+
+    output.push(OPCODE_SELECTDB);
+    push_length(&mut output, 0);
+
+    let mut skipped = 0;
+
+    for shard in shards {
+        for (key, value) in shard.iter().filter(|(_, value)| !value.is_expired()) {
+            if matches!(value, DbValue::StreamKey(_)) {
+                skipped += 1;
+                continue;
+            }
+
+            push_key(&mut output, key, value);
+        }
+    }
+
+    if skipped > 0 {
+        warn!("{skipped} stream keys were left out of the RDB file");
+    }
+
+    output.push(OPCODE_EOF);
+    let crc = crc64(0, &output);
+    output.extend_from_slice(&crc.to_le_bytes());
+    output
+}
+
+fn push_aux(output: &mut Vec<u8>, name: &str, value: &str) {
+    output.push(OPCODE_AUX);
+    push_string(output, name.as_bytes());
+    push_string(output, value.as_bytes());
+}
+
+fn push_key(output: &mut Vec<u8>, key: &[u8], value: &DbValue) {
+    if let Some(expires_at) = value.expires_at() {
+        output.push(OPCODE_EXPIRETIME_MS);
+        output.extend_from_slice(&expires_at.to_le_bytes());
+    }
+
+    match value {
+        DbValue::StringKey(string_key) => {
+            output.push(TYPE_STRING);
+            push_string(output, key);
+            push_string(output, string_key.data.as_deref().unwrap_or_default());
+        }
+        DbValue::ListKey(list_key) => {
+            output.push(TYPE_LIST);
+            push_string(output, key);
+            push_length(output, list_key.data.len());
+            for value in list_key.data.iter() {
+                push_string(output, value);
+            }
+        }
+        DbValue::SetKey(set_key) => {
+            output.push(TYPE_SET);
+            push_string(output, key);
+            push_length(output, set_key.data.len());
+            for member in set_key.data.iter() {
+                push_string(output, member);
+            }
+        }
+        DbValue::HashKey(hash_key) => {
+            output.push(TYPE_HASH);
+            push_string(output, key);
+            push_length(output, hash_key.data.len());
+            for (field, value) in hash_key.data.iter() {
+                push_string(output, field);
+                push_string(output, value);
+            }
+        }
+        DbValue::ZSetKey(zset_key) => {
+            output.push(TYPE_ZSET_2);
+            push_string(output, key);
+            push_length(output, zset_key.data.len());
+            for (member, score) in zset_key.data.iter() {
+                push_string(output, member);
+                output.extend_from_slice(&score.to_le_bytes());
+            }
+        }
+        DbValue::StreamKey(_) => {}
+    }
+}
+
+// 6 bits, 14 bits or a 32 or 64 bits big endian length
+fn push_length(output: &mut Vec<u8>, len: usize) {
+    if len < 1 << 6 {
+        output.push(len as u8);
+    } else if len < 1 << 14 {
+        output.push(0x40 | (len >> 8) as u8);
+        output.push(len as u8);
+    } else if let Ok(len) = u32::try_from(len) {
+        output.push(0x80);
+        output.extend_from_slice(&len.to_be_bytes());
+    } else {
+        output.push(0x81);
+        output.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+}
+
+fn push_string(output: &mut Vec<u8>, value: &[u8]) {
+    push_length(output, value.len());
+    output.extend_from_slice(value);
+}
+
+/// Reads the keys of an RDB file, checking its checksum. Keys of databases
+/// other than 0 are left out, Sider has a single one.
+pub fn decode_rdb(data: &[u8]) -> Result<Vec<(Bytes, DbValue)>, SiderError> {
+    let mut reader = Reader::new(data);
+
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(invalid("not an RDB file"));
+    }
+
+    let version = std::str::from_utf8(reader.bytes(4)?)
+        .ok()
+        .and_then(|version| version.parse::<u32>().ok())
+        .ok_or_else(|| invalid("invalid version"))?;
+    if version == 0 || version > MAX_RDB_VERSION {
+        return Err(invalid(&format!("unsupported version {version}")));
+    }
+
+    let mut entries = Vec::new();
+    let mut database = 0;
+    let mut skipped = 0;
+    let mut streams = 0;
+    let mut expires_at = None;
+
+    loop {
+        match reader.byte()? {
+            OPCODE_EOF => break,
+            OPCODE_SELECTDB => database = reader.length()?,
+            OPCODE_RESIZEDB => {
+                reader.length()?;
+                reader.length()?;
+            }
+            OPCODE_AUX => {
+                reader.string()?;
+                reader.string()?;
+            }
+            OPCODE_EXPIRETIME_MS => expires_at = Some(reader.u64_le()? as i64),
+            OPCODE_EXPIRETIME => expires_at = Some(reader.u32_le()? as i64 * 1000),
+            // Eviction hints, the keys start over as new
+            OPCODE_IDLE => {
+                reader.length()?;
+            }
+            OPCODE_FREQ => {
+                reader.byte()?;
+            }
+            OPCODE_FUNCTION2 => {
+                reader.string()?;
+                warn!("A function library of the RDB file was left out");
+            }
+            OPCODE_FUNCTION_PRE_GA | OPCODE_MODULE_AUX => {
+                return Err(invalid("modules and functions are not supported"))
+            }
+            value_type @ (TYPE_STREAM_LISTPACKS
+            | TYPE_STREAM_LISTPACKS_2
+            | TYPE_STREAM_LISTPACKS_3) => {
+                reader.string()?;
+                reader.skip_stream(value_type)?;
+                expires_at = None;
+                streams += 1;
+            }
+            value_type => {
+                let key = reader.string()?;
+                let value = reader.value(value_type, key.clone(), expires_at.take())?;
+
+                if database == 0 {
+                    entries.push((key, value));
+                } else {
+                    skipped += 1;
+                }
+            }
+        }
+    }
+
+    // Files written by versions before 5 have no checksum, and a 0 one
+    // means it was disabled
+    if version >= 5 {
+        let end = reader.position;
+        let checksum = reader.u64_le()?;
+
+        if checksum != 0 && checksum != crc64(0, &data[..end]) {
+            return Err(invalid("checksum mismatch"));
+        }
+    }
+
+    if skipped > 0 {
+        warn!("{skipped} keys of databases other than 0 were left out");
+    }
+    if streams > 0 {
+        warn!("{streams} stream keys of the RDB file were left out");
+    }
+
+    Ok(entries)
+}
+
+fn invalid(reason: &str) -> SiderError {
+    SiderError::InvalidRdb(reason.to_string())
+}
+
+// An RDB length, or the encoding of a string stored otherwise
+enum Length {
+    Plain(usize),
+    Encoded(u8),
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, position: 0 }
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn peek(&self) -> Result<u8, SiderError> {
+        self.data
+            .get(self.position)
+            .copied()
+            .ok_or_else(|| invalid("unexpected end"))
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], SiderError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| invalid("unexpected end"))?;
+
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SiderError> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.bytes(N)?);
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, SiderError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16_le(&mut self) -> Result<u16, SiderError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32_le(&mut self) -> Result<u32, SiderError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64_le(&mut self) -> Result<u64, SiderError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    // Sign extended from the 24 bits
+    fn i24_le(&mut self) -> Result<i64, SiderError> {
+        let [a, b, c] = self.array()?;
+        Ok((i32::from_le_bytes([0, a, b, c]) >> 8) as i64)
+    }
+
+    fn length_or_encoding(&mut self) -> Result<Length, SiderError> {
+        let first = self.byte()?;
+
+        let len = match first >> 6 {
+            0 => (first & 0x3f) as usize,
+            1 => ((first & 0x3f) as usize) << 8 | self.byte()? as usize,
+            2 if first == 0x80 => u32::from_be_bytes(self.array()?) as usize,
+            2 if first == 0x81 => usize::try_from(u64::from_be_bytes(self.array()?))
+                .map_err(|_| invalid("length too big"))?,
+            2 => return Err(invalid("invalid length")),
+            _ => return Ok(Length::Encoded(first & 0x3f)),
+        };
+
+        Ok(Length::Plain(len))
+    }
+
+    fn length(&mut self) -> Result<usize, SiderError> {
+        match self.length_or_encoding()? {
+            Length::Plain(len) => Ok(len),
+            Length::Encoded(_) => Err(invalid("expected a length")),
+        }
+    }
+
+    // Never more than the bytes left, whatever a corrupt length says
+    fn capacity(&mut self) -> Result<usize, SiderError> {
+        Ok(self.length()?.min(self.data.len() - self.position))
+    }
+
+    fn string(&mut self) -> Result<Bytes, SiderError> {
+        let string = match self.length_or_encoding()? {
+            Length::Plain(len) => Bytes::copy_from_slice(self.bytes(len)?),
+            Length::Encoded(ENCODING_INT8) => integer(self.byte()? as i8 as i64),
+            Length::Encoded(ENCODING_INT16) => integer(self.u16_le()? as i16 as i64),
+            Length::Encoded(ENCODING_INT32) => integer(self.u32_le()? as i32 as i64),
+            Length::Encoded(ENCODING_LZF) => {
+                let compressed_len = self.length()?;
+                let len = self.length()?;
+                let compressed = self.bytes(compressed_len)?;
+                Bytes::from(lzf_decompress(compressed, len)?)
+            }
+            Length::Encoded(other) => {
+                return Err(invalid(&format!("unknown string encoding {other}")))
+            }
+        };
+
+        Ok(string)
+    }
+
+    // Scores of the first sorted set type, as text with special lengths
+    // for NaN and infinities
+    fn text_double(&mut self) -> Result<f64, SiderError> {
+        match self.byte()? {
+            253 => Ok(f64::NAN),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            len => {
+                let text = self.bytes(len as usize)?;
+                parse_double(text)
+            }
+        }
+    }
+
+    // Streams are not converted, they are only read past. Newer types add
+    // the first and max deleted IDs, the entries added and read counts, and
+    // the last time consumers were active
+    fn skip_stream(&mut self, value_type: u8) -> Result<(), SiderError> {
+        let (ids, group_ids, consumer_times) = match value_type {
+            TYPE_STREAM_LISTPACKS => (3, 2, 8),
+            TYPE_STREAM_LISTPACKS_2 => (8, 3, 8),
+            _ => (8, 3, 16),
+        };
+
+        // Node keys and their listpack of entries
+        for _ in 0..self.length()? {
+            self.string()?;
+            self.string()?;
+        }
+        for _ in 0..ids {
+            self.length()?;
+        }
+
+        for _ in 0..self.length()? {
+            self.string()?;
+            for _ in 0..group_ids {
+                self.length()?;
+            }
+            // Pending entries, with their delivery time and count
+            for _ in 0..self.length()? {
+                self.bytes(16 + 8)?;
+                self.length()?;
+            }
+            for _ in 0..self.length()? {
+                self.string()?;
+                self.bytes(consumer_times)?;
+                let pending = self.length()?;
+                self.bytes(
+                    pending
+                        .checked_mul(16)
+                        .ok_or_else(|| invalid("length too big"))?,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn value(
+        &mut self,
+        value_type: u8,
+        key: Bytes,
+        expires_at: Option<i64>,
+    ) -> Result<DbValue, SiderError> {
+        let value = match value_type {
+            TYPE_STRING => DbValue::StringKey(Key::new(key, Some(self.string()?), expires_at)),
+            TYPE_LIST => {
+                let len = self.capacity()?;
+                let mut values = VecDeque::with_capacity(len);
+                for _ in 0..len {
+                    values.push_back(self.string()?);
+                }
+                DbValue::ListKey(KeyList::new(key, values, expires_at))
+            }
+            TYPE_SET => {
+                let len = self.capacity()?;
+                let mut members = HashSet::with_capacity(len);
+                for _ in 0..len {
+                    members.insert(self.string()?);
+                }
+                DbValue::SetKey(KeySet::new(key, members, expires_at))
+            }
+            TYPE_ZSET | TYPE_ZSET_2 => {
+                let mut members = SortedSet::default();
+                for _ in 0..self.capacity()? {
+                    let member = self.string()?;
+                    let score = if value_type == TYPE_ZSET {
+                        self.text_double()?
+                    } else {
+                        f64::from_bits(self.u64_le()?)
+                    };
+                    members.insert(member, score);
+                }
+                DbValue::ZSetKey(KeyZSet::new(key, members, expires_at))
+            }
+            TYPE_HASH => {
+                let len = self.capacity()?;
+                let mut fields = IndexMap::with_capacity(len);
+                for _ in 0..len {
+                    fields.insert(self.string()?, self.string()?);
+                }
+                DbValue::HashKey(KeyHash::new(key, fields, expires_at))
+            }
+            TYPE_LIST_ZIPLIST => {
+                let values = ziplist_entries(&self.string()?)?;
+                DbValue::ListKey(KeyList::new(key, values.into(), expires_at))
+            }
+            TYPE_LIST_QUICKLIST | TYPE_LIST_QUICKLIST_2 => {
+                let mut values = VecDeque::new();
+                for _ in 0..self.capacity()? {
+                    if value_type == TYPE_LIST_QUICKLIST {
+                        values.extend(ziplist_entries(&self.string()?)?);
+                    } else if self.length()? == QUICKLIST_NODE_PLAIN {
+                        values.push_back(self.string()?);
+                    } else {
+                        values.extend(listpack_entries(&self.string()?)?);
+                    }
+                }
+                DbValue::ListKey(KeyList::new(key, values, expires_at))
+            }
+            TYPE_SET_INTSET => {
+                let members = intset_entries(&self.string()?)?;
+                DbValue::SetKey(KeySet::new(key, members.into_iter().collect(), expires_at))
+            }
+            TYPE_SET_LISTPACK => {
+                let members = listpack_entries(&self.string()?)?;
+                DbValue::SetKey(KeySet::new(key, members.into_iter().collect(), expires_at))
+            }
+            TYPE_HASH_ZIPLIST | TYPE_HASH_LISTPACK => {
+                let blob = self.string()?;
+                let entries = if value_type == TYPE_HASH_ZIPLIST {
+                    ziplist_entries(&blob)?
+                } else {
+                    listpack_entries(&blob)?
+                };
+                let fields = pairs(entries)?.collect();
+                DbValue::HashKey(KeyHash::new(key, fields, expires_at))
+            }
+            TYPE_ZSET_ZIPLIST | TYPE_ZSET_LISTPACK => {
+                let blob = self.string()?;
+                let entries = if value_type == TYPE_ZSET_ZIPLIST {
+                    ziplist_entries(&blob)?
+                } else {
+                    listpack_entries(&blob)?
+                };
+                let mut members = SortedSet::default();
+                for (member, score) in pairs(entries)? {
+                    members.insert(member, parse_double(&score)?);
+                }
+                DbValue::ZSetKey(KeyZSet::new(key, members, expires_at))
+            }
+            other => return Err(invalid(&format!("unsupported value type {other}"))),
+        };
+
+        Ok(value)
+    }
+}
+
+fn integer(value: i64) -> Bytes {
+    Bytes::from(value.to_string())
+}
+
+fn parse_double(text: &[u8]) -> Result<f64, SiderError> {
+    std::str::from_utf8(text)
+        .ok()
+        .and_then(|text| text.parse::<f64>().ok())
+        .ok_or_else(|| invalid("invalid score"))
+}
+
+// Hash fields and sorted set members alternate with their value or score
+fn pairs(entries: Vec<Bytes>) -> Result<impl Iterator<Item = (Bytes, Bytes)>, SiderError> {
+    if entries.len() % 2 != 0 {
+        return Err(invalid("odd number of elements"));
+    }
+
+    let mut entries = entries.into_iter();
+    Ok(std::iter::from_fn(move || {
+        Some((entries.next()?, entries.next()?))
+    }))
+}
+
+/// Decompresses LZF data into `len` bytes, the compression of long strings
+/// in RDB files.
+fn lzf_decompress(input: &[u8], len: usize) -> Result<Vec<u8>, SiderError> {
+    let corrupt = || invalid("corrupt compressed string");
+    let mut output = Vec::with_capacity(len);
+    let mut position = 0;
+
+    while position < input.len() {
+        let control = input[position] as usize;
+        position += 1;
+
+        if control < 32 {
+            // Literal run of control + 1 bytes
+            let literal = input
+                .get(position..position + control + 1)
+                .ok_or_else(corrupt)?;
+            output.extend_from_slice(literal);
+            position += control + 1;
+        } else {
+            // Back reference, the bytes can overlap what they copy
+            let mut run = control >> 5;
+            if run == 7 {
+                run += *input.get(position).ok_or_else(corrupt)? as usize;
+                position += 1;
+            }
+            let offset =
+                ((control & 0x1f) << 8) + *input.get(position).ok_or_else(corrupt)? as usize;
+            position += 1;
+
+            let start = output.len().checked_sub(offset + 1).ok_or_else(corrupt)?;
+            for i in 0..run + 2 {
+                output.push(output[start + i]);
+            }
+        }
+
+        if output.len() > len {
+            return Err(corrupt());
+        }
+    }
+
+    if output.len() != len {
+        return Err(corrupt());
+    }
+
+    Ok(output)
+}
+
+// Header of 4 bytes of size, 4 of offset of the last entry and 2 of count,
+// then entries prefixed with the length of the previous one, until 0xFF
+fn ziplist_entries(blob: &[u8]) -> Result<Vec<Bytes>, SiderError> {
+    let mut reader = Reader::new(blob);
+    reader.bytes(10)?;
+    let mut entries = Vec::new();
+
+    while reader.peek()? != 0xFF {
+        if reader.byte()? == 0xFE {
+            reader.bytes(4)?;
+        }
+
+        let encoding = reader.byte()?;
+        let entry = match encoding >> 6 {
+            0 => Bytes::copy_from_slice(reader.bytes((encoding & 0x3f) as usize)?),
+            1 => {
+                let len = ((encoding & 0x3f) as usize) << 8 | reader.byte()? as usize;
+                Bytes::copy_from_slice(reader.bytes(len)?)
+            }
+            2 => {
+                let len = u32::from_be_bytes(reader.array()?) as usize;
+                Bytes::copy_from_slice(reader.bytes(len)?)
+            }
+            _ => integer(match encoding {
+                0xC0 => reader.u16_le()? as i16 as i64,
+                0xD0 => reader.u32_le()? as i32 as i64,
+                0xE0 => reader.u64_le()? as i64,
+                0xF0 => reader.i24_le()?,
+                0xFE => reader.byte()? as i8 as i64,
+                // 4 bits immediate, from 1 for 0 up to 13 for 12
+                0xF1..=0xFD => (encoding & 0x0f) as i64 - 1,
+                _ => return Err(invalid("invalid ziplist entry")),
+            }),
+        };
+
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+// Header of 4 bytes of size and 2 of count, then entries followed by their
+// own length, until 0xFF
+fn listpack_entries(blob: &[u8]) -> Result<Vec<Bytes>, SiderError> {
+    let mut reader = Reader::new(blob);
+    reader.bytes(6)?;
+    let mut entries = Vec::new();
+
+    while reader.peek()? != 0xFF {
+        let start = reader.position;
+        let encoding = reader.byte()?;
+
+        let entry = if encoding & 0x80 == 0 {
+            integer((encoding & 0x7f) as i64)
+        } else if encoding & 0xC0 == 0x80 {
+            Bytes::copy_from_slice(reader.bytes((encoding & 0x3f) as usize)?)
+        } else if encoding & 0xE0 == 0xC0 {
+            // 13 bits two's complement
+            let value = ((encoding & 0x1f) as i64) << 8 | reader.byte()? as i64;
+            integer(if value >= 1 << 12 {
+                value - (1 << 13)
+            } else {
+                value
+            })
+        } else if encoding & 0xF0 == 0xE0 {
+            let len = ((encoding & 0x0f) as usize) << 8 | reader.byte()? as usize;
+            Bytes::copy_from_slice(reader.bytes(len)?)
+        } else {
+            match encoding {
+                0xF0 => {
+                    let len = reader.u32_le()? as usize;
+                    Bytes::copy_from_slice(reader.bytes(len)?)
+                }
+                0xF1 => integer(reader.u16_le()? as i16 as i64),
+                0xF2 => integer(reader.i24_le()?),
+                0xF3 => integer(reader.u32_le()? as i32 as i64),
+                0xF4 => integer(reader.u64_le()? as i64),
+                _ => return Err(invalid("invalid listpack entry")),
+            }
+        };
+
+        reader.bytes(backlen_size(reader.position - start))?;
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+// Bytes taken by the length of a listpack entry, written 7 bits per byte
+fn backlen_size(len: usize) -> usize {
+    match len {
+        0..=127 => 1,
+        128..=16382 => 2,
+        16383..=2097150 => 3,
+        2097151..=268435454 => 4,
+        _ => 5,
+    }
+}
+
+// Header of the integer size, 2, 4 or 8, and the count, then the sorted
+// integers
+fn intset_entries(blob: &[u8]) -> Result<Vec<Bytes>, SiderError> {
+    let mut reader = Reader::new(blob);
+    let size = reader.u32_le()?;
+    let count = reader.u32_le()?;
+    let mut entries = Vec::new();
+
+    for _ in 0..count {
+        let value = match size {
+            2 => reader.u16_le()? as i16 as i64,
+            4 => reader.u32_le()? as i32 as i64,
+            8 => reader.u64_le()? as i64,
+            _ => return Err(invalid("invalid intset encoding")),
+        };
+        entries.push(integer(value));
+    }
+
+    if !reader.is_at_end() {
+        return Err(invalid("invalid intset length"));
+    }
+
+    Ok(entries)
+}
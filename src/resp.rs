@@ -1,28 +1,282 @@
 use crate::errors::SiderError;
+use crate::utils::parse_number;
+use bytes::{Buf, Bytes, BytesMut};
+use std::ops::Range;
 
-pub fn parse_resp_command(resp_command: &str) -> Result<Vec<Vec<String>>, SiderError> {
-    let lines = resp_command.split_terminator("\r\n");
-    let mut commands: Vec<Vec<String>> = Vec::new();
-    let mut cmd_nb: Option<usize> = None;
-
-    for line in lines {
-        // Check if the line starts with '*' and is followed immediately by a number
-        if let Some(rest) = line.strip_prefix('*') {
-            if !rest.is_empty() && rest.chars().all(|c| c.is_numeric()) {
-                commands.push(Vec::new());
-                cmd_nb = Some(commands.len() - 1);
-                continue;
+// Same limits as Redis: 1M elements per request, 512MB per bulk string and
+// 64KB for a header line.
+const MAX_MULTIBULK_LEN: i64 = 1024 * 1024;
+const MAX_BULK_LEN: i64 = 512 * 1024 * 1024;
+const MAX_INLINE_LEN: usize = 64 * 1024;
+
+/// A complete request found at the start of the buffer.
+enum Frame {
+    // `*<n>` array of bulk strings, args are positions in the frame
+    MultiBulk { len: usize, args: Vec<Range<usize>> },
+    // Space separated line, as typed in telnet or netcat
+    Inline { len: usize, args: Vec<Bytes> },
+}
+
+/// Incremental RESP request decoder.
+///
+/// Bytes are fed in as they come off the socket and complete commands are
+/// pulled out one at a time. Whatever is left over (a command split across
+/// two reads) stays buffered until the next `feed`. Inline commands can be
+/// mixed with RESP arrays on the same connection.
+#[derive(Debug, Default)]
+pub struct RespDecoder {
+    buffer: BytesMut,
+}
+
+impl RespDecoder {
+    pub fn new() -> Self {
+        RespDecoder::default()
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Bytes fed that weren't decoded yet.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns the next complete command, `None` if more bytes are needed.
+    pub fn decode(&mut self) -> Result<Option<Vec<Bytes>>, SiderError> {
+        loop {
+            let Some(frame) = self.parse_frame()? else {
+                return Ok(None);
+            };
+
+            let command: Vec<Bytes> = match frame {
+                Frame::MultiBulk { len, args } => {
+                    // Arguments are slices of the frame, no copy is made
+                    let data = self.buffer.split_to(len).freeze();
+                    args.into_iter().map(|r| data.slice(r)).collect()
+                }
+                Frame::Inline { len, args } => {
+                    self.buffer.advance(len);
+                    args
+                }
+            };
+
+            // `*0`, `*-1` and blank lines are valid but empty requests, skip them
+            if !command.is_empty() {
+                return Ok(Some(command));
             }
         }
+    }
+
+    fn parse_frame(&self) -> Result<Option<Frame>, SiderError> {
+        let buf = &self.buffer[..];
+
+        if buf.is_empty() {
+            return Ok(None);
+        }
+
+        if buf[0] != b'*' {
+            return parse_inline(buf);
+        }
+
+        let Some((header, mut pos)) = read_line(buf, 0)? else {
+            return Ok(None);
+        };
+
+        let nb_elements =
+            parse_number::<i64>(&header[1..]).ok_or(SiderError::InvalidArrayLength)?;
 
-        if line.starts_with('$') {
-            continue;
+        if nb_elements > MAX_MULTIBULK_LEN {
+            return Err(SiderError::InvalidArrayLength);
         }
 
-        if let Some(idx) = cmd_nb {
-            commands[idx].push(line.to_string());
+        let mut args = Vec::with_capacity(nb_elements.max(0) as usize);
+
+        for _ in 0..nb_elements {
+            let Some((line, next)) = read_line(buf, pos)? else {
+                return Ok(None);
+            };
+
+            if line.first() != Some(&b'$') {
+                return Err(SiderError::InvalidBulkStringPrefix);
+            }
+
+            let len = match parse_number::<i64>(&line[1..]) {
+                Some(len) if (0..=MAX_BULK_LEN).contains(&len) => len as usize,
+                _ => return Err(SiderError::InvalidBulkLength),
+            };
+
+            // Payload plus its trailing CRLF
+            if buf.len() < next + len + 2 {
+                return Ok(None);
+            }
+
+            if &buf[next + len..next + len + 2] != b"\r\n" {
+                return Err(SiderError::InvalidBulkLength);
+            }
+
+            args.push(next..next + len);
+            pos = next + len + 2;
         }
+
+        Ok(Some(Frame::MultiBulk { len: pos, args }))
+    }
+}
+
+/// Reads a CRLF terminated line starting at `start`. Returns the line without
+/// its terminator and the position right after it.
+fn read_line(buf: &[u8], start: usize) -> Result<Option<(&[u8], usize)>, SiderError> {
+    let rest = &buf[start..];
+
+    match rest.windows(2).position(|w| w == b"\r\n") {
+        Some(end) => Ok(Some((&rest[..end], start + end + 2))),
+        None if rest.len() > MAX_INLINE_LEN => Err(SiderError::LineTooLong),
+        None => Ok(None),
+    }
+}
+
+fn parse_inline(buf: &[u8]) -> Result<Option<Frame>, SiderError> {
+    let Some(end) = buf.iter().position(|&c| c == b'\n') else {
+        if buf.len() > MAX_INLINE_LEN {
+            return Err(SiderError::LineTooLong);
+        }
+        return Ok(None);
+    };
+
+    // Both "\r\n" and a bare "\n" terminate the line
+    let line = buf[..end].strip_suffix(b"\r").unwrap_or(&buf[..end]);
+
+    Ok(Some(Frame::Inline {
+        len: end + 1,
+        args: split_inline_args(line)?,
+    }))
+}
+
+/// Splits an inline command into arguments with the same quoting rules as
+/// redis-cli: `"..."` understands `\n`, `\r`, `\t`, `\b`, `\a`, `\\`, `\"`
+/// and `\xHH` escapes, `'...'` only understands `\'`. A closing quote must
+/// be followed by a space or the end of the line.
+pub fn split_inline_args(line: &[u8]) -> Result<Vec<Bytes>, SiderError> {
+    let mut args = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+
+        if i == line.len() {
+            return Ok(args);
+        }
+
+        let mut arg = Vec::new();
+
+        match line[i] {
+            b'"' => {
+                i += 1;
+                loop {
+                    match line.get(i..) {
+                        Some([b'\\', b'x', h, l, ..]) if hex_value(*h, *l).is_some() => {
+                            arg.extend(hex_value(*h, *l));
+                            i += 4;
+                        }
+                        Some([b'\\', c, ..]) => {
+                            arg.push(match c {
+                                b'n' => b'\n',
+                                b'r' => b'\r',
+                                b't' => b'\t',
+                                b'b' => 0x08,
+                                b'a' => 0x07,
+                                other => *other,
+                            });
+                            i += 2;
+                        }
+                        Some([b'"', ..]) => {
+                            i += 1;
+                            break;
+                        }
+                        Some([c, ..]) => {
+                            arg.push(*c);
+                            i += 1;
+                        }
+                        _ => return Err(SiderError::UnbalancedQuotes),
+                    }
+                }
+            }
+            b'\'' => {
+                i += 1;
+                loop {
+                    match line.get(i..) {
+                        Some([b'\\', b'\'', ..]) => {
+                            arg.push(b'\'');
+                            i += 2;
+                        }
+                        Some([b'\'', ..]) => {
+                            i += 1;
+                            break;
+                        }
+                        Some([c, ..]) => {
+                            arg.push(*c);
+                            i += 1;
+                        }
+                        _ => return Err(SiderError::UnbalancedQuotes),
+                    }
+                }
+            }
+            _ => {
+                while i < line.len() && !line[i].is_ascii_whitespace() {
+                    arg.push(line[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        // "foo"bar is not a valid argument
+        if i < line.len() && !line[i].is_ascii_whitespace() {
+            return Err(SiderError::UnbalancedQuotes);
+        }
+
+        args.push(Bytes::from(arg));
+    }
+}
+
+fn hex_value(high: u8, low: u8) -> Option<u8> {
+    let high = (high as char).to_digit(16)?;
+    let low = (low as char).to_digit(16)?;
+    Some((high * 16 + low) as u8)
+}
+
+/// Decodes a buffer holding only complete commands, such as a file. A
+/// truncated trailing command is reported as `WrongElementCount`.
+pub fn parse_resp_command(resp_command: &[u8]) -> Result<Vec<Vec<Bytes>>, SiderError> {
+    let mut decoder = RespDecoder::new();
+    decoder.feed(resp_command);
+
+    let mut commands: Vec<Vec<Bytes>> = Vec::new();
+
+    while let Some(command) = decoder.decode()? {
+        commands.push(command);
+    }
+
+    if !decoder.is_empty() {
+        return Err(SiderError::WrongElementCount);
     }
 
     Ok(commands)
 }
+
+/// Encodes a command the way clients send it, as an array of bulk strings.
+pub fn encode_command<A: AsRef<[u8]>>(buf: &mut Vec<u8>, args: &[A]) {
+    buf.extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+
+    for arg in args {
+        let arg = arg.as_ref();
+        buf.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        buf.extend_from_slice(arg);
+        buf.extend_from_slice(b"\r\n");
+    }
+}
@@ -1,34 +1,145 @@
+use crate::client::Protocol;
 use crate::errors::SiderError;
-use std::fmt;
+use bytes::Bytes;
 
+/// A reply, encoded as RESP2 or RESP3 depending on the connection.
+///
+/// RESP3-only types fall back to their RESP2 equivalent when the client
+/// didn't switch protocol with HELLO: maps are flattened into arrays,
+/// booleans become integers, doubles and big numbers become bulk strings.
 #[derive(Debug, Clone)]
 pub enum SiderResponse {
-    Ok,                   // "OK"
-    Int(i64),             // "(integer) 123"
-    SimpleString(String), // "foo"
-    List(Vec<String>),    // "1) foo\n2) bar\n"
-    Nil,                  // "(nil)"
-    EmptyArray,           // "(empty array)"
-    Error(SiderError),    // Handles errors gracefully
+    Ok,                        // "+OK"
+    SimpleString(String),      // "+PONG", must not contain CR or LF
+    Integer(i64),              // ":123"
+    BulkString(Bytes),         // "$3\r\nfoo", binary safe
+    NullBulk,                  // "$-1", e.g. GET on a missing key
+    Array(Vec<SiderResponse>), // "*2\r\n...", elements can be of any type
+    NullArray,                 // "*-1", e.g. LPOP key count on a missing key
+    Error(SiderError),         // "-ERR ..."
+
+    // RESP3
+    Map(Vec<(SiderResponse, SiderResponse)>), // "%1\r\n...", e.g. HGETALL
+    Set(Vec<SiderResponse>),                  // "~2\r\n...", e.g. SMEMBERS
+    Double(f64),                              // ",3.14"
+    Boolean(bool),                            // "#t"
+    BigNumber(String),                        // "(3492890328409238509324850943850943825024385"
+    Verbatim { format: String, text: Bytes }, // "=15\r\ntxt:Some string"
+    Push(Vec<SiderResponse>),                 // ">3\r\n...", out of band messages
+
+    // Several replies sent back to back, e.g. one per channel for SUBSCRIBE
+    Sequence(Vec<SiderResponse>),
 }
 
-impl fmt::Display for SiderResponse {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl SiderResponse {
+    /// Builds an array of bulk strings, the most common multi-value reply.
+    pub fn bulk_strings<I>(values: I) -> Self
+    where
+        I: IntoIterator<Item = Bytes>,
+    {
+        SiderResponse::Array(values.into_iter().map(SiderResponse::BulkString).collect())
+    }
+
+    /// Encodes the response as RESP2.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode_for(Protocol::Resp2)
+    }
+
+    pub fn encode_for(&self, protocol: Protocol) -> Vec<u8> {
+        let mut response = Vec::new();
+        self.encode(protocol, &mut response);
+        response
+    }
+
+    /// Encodes the response as it is sent on the wire. Values are written as
+    /// raw bytes so they go out exactly as they were stored.
+    pub fn encode(&self, protocol: Protocol, buf: &mut Vec<u8>) {
+        let resp3 = protocol == Protocol::Resp3;
+
         match self {
-            SiderResponse::Ok => write!(f, "+OK\r\n"),
-            SiderResponse::Int(value) => write!(f, "+(integer) {}\r\n", value),
-            SiderResponse::SimpleString(value) => write!(f, "+{}\r\n", value),
-            SiderResponse::List(values) => {
-                let mut response = format!("*{}\r\n", values.len());
-
-                for value in values {
-                    response.push_str(&format!("${}\r\n{}\r\n", value.len(), value));
+            SiderResponse::Ok => buf.extend_from_slice(b"+OK\r\n"),
+            SiderResponse::SimpleString(value) => push_line(buf, b'+', value.as_bytes()),
+            SiderResponse::Integer(value) => push_line(buf, b':', value.to_string().as_bytes()),
+            SiderResponse::BulkString(value) => push_blob(buf, b'$', value),
+            SiderResponse::NullBulk | SiderResponse::NullArray if resp3 => {
+                buf.extend_from_slice(b"_\r\n")
+            }
+            SiderResponse::NullBulk => buf.extend_from_slice(b"$-1\r\n"),
+            SiderResponse::Array(values) => push_aggregate(buf, b'*', values, protocol),
+            SiderResponse::NullArray => buf.extend_from_slice(b"*-1\r\n"),
+            SiderResponse::Error(err) => push_line(buf, b'-', err.to_string().as_bytes()),
+            SiderResponse::Map(pairs) => {
+                let prefix = if resp3 { b'%' } else { b'*' };
+                let len = if resp3 { pairs.len() } else { pairs.len() * 2 };
+                push_line(buf, prefix, len.to_string().as_bytes());
+
+                for (key, value) in pairs {
+                    key.encode(protocol, buf);
+                    value.encode(protocol, buf);
+                }
+            }
+            SiderResponse::Set(values) => {
+                push_aggregate(buf, if resp3 { b'~' } else { b'*' }, values, protocol)
+            }
+            SiderResponse::Double(value) if resp3 => {
+                push_line(buf, b',', format_double(*value).as_bytes())
+            }
+            SiderResponse::Double(value) => {
+                push_blob(buf, b'$', format_double(*value).as_bytes())
+            }
+            SiderResponse::Boolean(value) if resp3 => {
+                push_line(buf, b'#', if *value { b"t" } else { b"f" })
+            }
+            SiderResponse::Boolean(value) => {
+                push_line(buf, b':', if *value { b"1" } else { b"0" })
+            }
+            SiderResponse::BigNumber(value) if resp3 => push_line(buf, b'(', value.as_bytes()),
+            SiderResponse::BigNumber(value) => push_blob(buf, b'$', value.as_bytes()),
+            SiderResponse::Verbatim { format, text } if resp3 => {
+                let mut payload = format!("{}:", format).into_bytes();
+                payload.extend_from_slice(text);
+                push_blob(buf, b'=', &payload);
+            }
+            SiderResponse::Verbatim { text, .. } => push_blob(buf, b'$', text),
+            SiderResponse::Push(values) => {
+                push_aggregate(buf, if resp3 { b'>' } else { b'*' }, values, protocol)
+            }
+            SiderResponse::Sequence(replies) => {
+                for reply in replies {
+                    reply.encode(protocol, buf);
                 }
-                write!(f, "{}", response)
             }
-            SiderResponse::Nil => write!(f, "+(nil)\r\n"),
-            SiderResponse::EmptyArray => write!(f, "+(empty array)\r\n"),
-            SiderResponse::Error(err) => write!(f, "-{}\r\n", err),
         }
     }
 }
+
+fn push_line(buf: &mut Vec<u8>, prefix: u8, line: &[u8]) {
+    buf.push(prefix);
+    buf.extend_from_slice(line);
+    buf.extend_from_slice(b"\r\n");
+}
+
+fn push_blob(buf: &mut Vec<u8>, prefix: u8, value: &[u8]) {
+    push_line(buf, prefix, value.len().to_string().as_bytes());
+    buf.extend_from_slice(value);
+    buf.extend_from_slice(b"\r\n");
+}
+
+fn push_aggregate(buf: &mut Vec<u8>, prefix: u8, values: &[SiderResponse], protocol: Protocol) {
+    push_line(buf, prefix, values.len().to_string().as_bytes());
+
+    for value in values {
+        value.encode(protocol, buf);
+    }
+}
+
+// Same spelling as Redis for the special values
+fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
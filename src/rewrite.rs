@@ -0,0 +1,316 @@
+//! AOF rewrites. The AOF only grows as writes are logged, so once it got
+//! big enough it is replaced by the shortest list of commands rebuilding the
+//! dataset, like Redis `BGREWRITEAOF`. Clients are only held up while their
+//! shard waits to be dumped.
+
+use crate::aof::{
+    abort_aof_rewrite, finish_aof_rewrite, get_aof_base_size, get_aof_current_size,
+    get_aof_log_dir, start_aof_rewrite,
+};
+use crate::database::is_loading;
+use crate::resp::encode_command;
+use crate::shard::Shard;
+use crate::types::{Db, DbValue, Stream, StreamId};
+use bytes::Bytes;
+use std::io::Error;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio::time::{self, Duration};
+use tracing::{info, warn};
+
+// How often automatic and scheduled rewrites are checked for
+const CRON_PERIOD: Duration = Duration::from_millis(100);
+// Elements per command for collections, so none gets too long to replay
+const ITEMS_PER_COMMAND: usize = 64;
+
+// Rewrites once the AOF doubled since the last one and is at least 64MB,
+// like Redis
+static AUTO_REWRITE_PERCENTAGE: AtomicU64 = AtomicU64::new(100);
+static AUTO_REWRITE_MIN_SIZE: AtomicU64 = AtomicU64::new(64 * 1024 * 1024);
+
+static REWRITE_SCHEDULED: AtomicBool = AtomicBool::new(false);
+static REWRITE_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static LAST_REWRITE_OK: AtomicBool = AtomicBool::new(true);
+static REWRITES: AtomicU64 = AtomicU64::new(0);
+
+/// Growth of the AOF since the last rewrite, in percent, triggering a new
+/// one. 0 disables automatic rewrites.
+pub fn get_auto_rewrite_percentage() -> u64 {
+    AUTO_REWRITE_PERCENTAGE.load(Ordering::Relaxed)
+}
+
+pub fn set_auto_rewrite_percentage(percentage: u64) {
+    AUTO_REWRITE_PERCENTAGE.store(percentage, Ordering::Relaxed);
+}
+
+/// Size in bytes under which the AOF is never rewritten automatically.
+pub fn get_auto_rewrite_min_size() -> u64 {
+    AUTO_REWRITE_MIN_SIZE.load(Ordering::Relaxed)
+}
+
+pub fn set_auto_rewrite_min_size(bytes: u64) {
+    AUTO_REWRITE_MIN_SIZE.store(bytes, Ordering::Relaxed);
+}
+
+/// Has the next check start a rewrite, `BGREWRITEAOF`.
+pub fn schedule_rewrite() {
+    REWRITE_SCHEDULED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_rewrite_scheduled() -> bool {
+    REWRITE_SCHEDULED.load(Ordering::Relaxed)
+}
+
+pub fn is_rewrite_in_progress() -> bool {
+    REWRITE_IN_PROGRESS.load(Ordering::Relaxed)
+}
+
+pub fn last_rewrite_ok() -> bool {
+    LAST_REWRITE_OK.load(Ordering::Relaxed)
+}
+
+/// Rewrites completed since startup.
+pub fn rewrites() -> u64 {
+    REWRITES.load(Ordering::Relaxed)
+}
+
+/// Whether the AOF grew enough since the last rewrite to be rewritten.
+pub fn should_auto_rewrite(current_size: u64, base_size: u64) -> bool {
+    let percentage = get_auto_rewrite_percentage();
+
+    if percentage == 0 || current_size < get_auto_rewrite_min_size() {
+        return false;
+    }
+
+    let base_size = base_size.max(1);
+    let growth = current_size.saturating_sub(base_size) * 100 / base_size;
+    growth >= percentage
+}
+
+/// Runs the rewrites asked for with `BGREWRITEAOF` and the automatic ones.
+pub async fn rewrite_aof_periodically(db: Db) {
+    let mut interval = time::interval(CRON_PERIOD);
+
+    loop {
+        interval.tick().await;
+
+        // The dump would miss what isn't replayed yet
+        if is_loading() {
+            continue;
+        }
+
+        let scheduled = REWRITE_SCHEDULED.swap(false, Ordering::Relaxed);
+        if scheduled || should_auto_rewrite(get_aof_current_size(), get_aof_base_size()) {
+            let _ = rewrite_aof(&db).await;
+        }
+    }
+}
+
+/// Replaces the AOF with the commands rebuilding the dataset, followed by
+/// the writes made in the meantime. Fails if a rewrite is in progress.
+pub async fn rewrite_aof(db: &Db) -> std::io::Result<()> {
+    if REWRITE_IN_PROGRESS.swap(true, Ordering::Relaxed) {
+        return Err(Error::other("A rewrite is already in progress"));
+    }
+
+    info!("Rewriting the AOF file");
+    let path = get_aof_log_dir().join("temp-rewriteaof.aof");
+    let result = write_rewritten_aof(db, &path).await;
+
+    match &result {
+        Ok(()) => {
+            REWRITES.fetch_add(1, Ordering::Relaxed);
+            info!("AOF file rewritten");
+        }
+        Err(e) => {
+            warn!("Failed to rewrite the AOF file: {e}");
+            let _ = fs::remove_file(&path).await;
+        }
+    }
+
+    LAST_REWRITE_OK.store(result.is_ok(), Ordering::Relaxed);
+    REWRITE_IN_PROGRESS.store(false, Ordering::Relaxed);
+    result
+}
+
+async fn write_rewritten_aof(db: &Db, path: &std::path::Path) -> std::io::Result<()> {
+    let mut file = File::create(path).await?;
+
+    // The snapshot is taken once every shard is locked, writes made after
+    // that are kept by the AOF writer to be appended to it
+    let shards = db.read_all().await;
+    start_aof_rewrite().await?;
+
+    // Dumped in memory, each shard is released as soon as it is dumped and
+    // none is held while writing the file
+    let dumps: Vec<Vec<u8>> = shards.into_iter().map(|shard| dump_shard(&shard)).collect();
+
+    let dumped = async {
+        for output in dumps {
+            file.write_all(&output).await?;
+        }
+
+        file.flush().await
+    }
+    .await;
+
+    if let Err(e) = dumped {
+        let _ = abort_aof_rewrite().await;
+        return Err(e);
+    }
+
+    drop(file);
+    finish_aof_rewrite(path.to_path_buf()).await
+}
+
+/// The commands rebuilding the keys of the shard, expired ones left out.
+pub fn dump_shard(shard: &Shard) -> Vec<u8> {
+    let mut output = Vec::new();
+
+    for (key, value) in shard.iter().filter(|(_, value)| !value.is_expired()) {
+        push_key_dump(&mut output, key, value);
+    }
+
+    output
+}
+
+/// Appends the commands rebuilding the key to `output`.
+pub fn push_key_dump(output: &mut Vec<u8>, key: &Bytes, value: &DbValue) {
+    match value {
+        DbValue::StringKey(string_key) => {
+            if let Some(data) = &string_key.data {
+                push_dump_line(output, "SET", &[key, data]);
+            }
+        }
+        // Pushed at the tail to keep the order
+        DbValue::ListKey(list_key) => {
+            let values: Vec<&[u8]> = list_key.data.iter().map(|value| &value[..]).collect();
+            for chunk in values.chunks(ITEMS_PER_COMMAND) {
+                push_collection_line(output, "RPUSH", key, chunk);
+            }
+        }
+        DbValue::SetKey(set_key) => {
+            let members: Vec<&[u8]> = set_key.data.iter().map(|member| &member[..]).collect();
+            for chunk in members.chunks(ITEMS_PER_COMMAND) {
+                push_collection_line(output, "SADD", key, chunk);
+            }
+        }
+        DbValue::HashKey(hash_key) => {
+            let fields: Vec<&[u8]> = hash_key
+                .data
+                .iter()
+                .flat_map(|(field, value)| [&field[..], &value[..]])
+                .collect();
+            for chunk in fields.chunks(ITEMS_PER_COMMAND * 2) {
+                push_collection_line(output, "HSET", key, chunk);
+            }
+        }
+        DbValue::ZSetKey(zset_key) => {
+            let scores: Vec<String> = zset_key
+                .data
+                .iter()
+                .map(|(_, score)| score.to_string())
+                .collect();
+            let members: Vec<&[u8]> = zset_key
+                .data
+                .iter()
+                .zip(scores.iter())
+                .flat_map(|((member, _), score)| [score.as_bytes(), &member[..]])
+                .collect();
+            for chunk in members.chunks(ITEMS_PER_COMMAND * 2) {
+                push_collection_line(output, "ZADD", key, chunk);
+            }
+        }
+        DbValue::StreamKey(stream_key) => push_stream_dump(output, key, &stream_key.data),
+    }
+
+    if let Some(expires_at) = value.expires_at() {
+        let expires_at = expires_at.to_string();
+        push_dump_line(output, "PEXPIREAT", &[key, expires_at.as_bytes()]);
+    }
+}
+
+/// Appends a `<COMMAND> <args>` RESP array to the dump.
+fn push_dump_line(output: &mut Vec<u8>, command: &str, args: &[&[u8]]) {
+    let mut command_args: Vec<&[u8]> = vec![command.as_bytes()];
+    command_args.extend_from_slice(args);
+
+    encode_command(output, &command_args);
+}
+
+fn push_collection_line(output: &mut Vec<u8>, command: &str, key: &[u8], items: &[&[u8]]) {
+    let mut args: Vec<&[u8]> = vec![key];
+    args.extend_from_slice(items);
+
+    push_dump_line(output, command, &args);
+}
+
+// Entries with their IDs, then the consumer groups along with their
+// consumers and pending entries
+fn push_stream_dump(output: &mut Vec<u8>, key: &[u8], stream: &Stream) {
+    if stream.is_empty() {
+        // Creates the stream, the entry is trimmed right away
+        let id = stream.last_id.max(StreamId::new(0, 1)).to_string();
+        push_dump_line(
+            output,
+            "XADD",
+            &[key, b"MAXLEN", b"0", id.as_bytes(), b"field", b"value"],
+        );
+    }
+
+    for (id, fields) in stream.range(StreamId::MIN, StreamId::MAX) {
+        let id = id.to_string();
+        let mut args: Vec<&[u8]> = vec![key, id.as_bytes()];
+        for (field, value) in fields.iter() {
+            args.push(field);
+            args.push(value);
+        }
+        push_dump_line(output, "XADD", &args);
+    }
+
+    // Deleted entries may have had greater IDs
+    let last_id = stream.last_id.to_string();
+    push_dump_line(output, "XSETID", &[key, last_id.as_bytes()]);
+
+    for (name, group) in stream.groups.iter() {
+        let last_delivered = group.last_delivered.to_string();
+        push_dump_line(
+            output,
+            "XGROUP",
+            &[b"CREATE", key, name, last_delivered.as_bytes()],
+        );
+
+        for consumer in group.consumers.keys() {
+            push_dump_line(output, "XGROUP", &[b"CREATECONSUMER", key, name, consumer]);
+        }
+
+        // Pending entries deleted from the stream can't be claimed, they are
+        // dropped like XAUTOCLAIM would
+        for (id, pending) in group.pending.iter() {
+            let (id, delivered_at, delivery_count) = (
+                id.to_string(),
+                pending.delivered_at.to_string(),
+                pending.delivery_count.to_string(),
+            );
+
+            push_dump_line(
+                output,
+                "XCLAIM",
+                &[
+                    key,
+                    name,
+                    &pending.consumer,
+                    b"0",
+                    id.as_bytes(),
+                    b"TIME",
+                    delivered_at.as_bytes(),
+                    b"RETRYCOUNT",
+                    delivery_count.as_bytes(),
+                    b"FORCE",
+                    b"JUSTID",
+                ],
+            );
+        }
+    }
+}
@@ -1,47 +1,170 @@
+use crate::client::{BlockedWait, Client};
+use crate::commands::transactions::unwatch_all_keys;
 use crate::errors::format_redis_error;
 use crate::process::process_command;
-use crate::resp::parse_resp_command;
+use crate::resp::RespDecoder;
+use crate::response::SiderResponse;
 use crate::types::Db;
 use std::error::Error;
+use std::future;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::time::{self, Instant};
 
-pub async fn handle_client(mut socket: TcpStream, db: Db) -> Result<(), Box<dyn Error>> {
-    let mut buffer = [0; 1024];
+// Replies are written once every command of a read has been processed, or
+// earlier if a huge pipeline makes the output grow past this size.
+const MAX_PENDING_OUTPUT: usize = 1024 * 1024;
+
+pub async fn handle_client(
+    socket: TcpStream,
+    db: Db,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut client = Client::new();
+    let served = serve_client(socket, &db, &mut client).await;
+
+    // Shards only track the keys watched by connected clients
+    unwatch_all_keys(&db, &mut client).await;
+    served
+}
+
+async fn serve_client(
+    mut socket: TcpStream,
+    db: &Db,
+    client: &mut Client,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut buffer = [0; 16 * 1024];
+    let mut decoder = RespDecoder::new();
+    let mut output: Vec<u8> = Vec::new();
+
+    // Replies are already batched, don't let Nagle delay them further
+    socket.set_nodelay(true)?;
 
     loop {
-        let bytes_read = socket.read(&mut buffer).await?;
+        let bytes_read = tokio::select! {
+            read = socket.read(&mut buffer) => read?,
+            Some(message) = client.messages.recv() => {
+                message.encode(client.protocol, &mut output);
+
+                // Send whatever else was published meanwhile in the same write
+                while let Ok(message) = client.messages.try_recv() {
+                    message.encode(client.protocol, &mut output);
+                }
+
+                socket.write_all(&output).await?;
+                socket.flush().await?;
+                output.clear();
+                continue;
+            }
+        };
 
         if bytes_read == 0 {
             // Client disconnected
             return Ok(());
         }
 
-        let raw_command = String::from_utf8_lossy(&buffer[..bytes_read])
-            .trim()
-            .to_string();
+        decoder.feed(&buffer[..bytes_read]);
 
-        let parsed_commands = match parse_resp_command(&raw_command) {
-            Ok(parsed) => parsed,
-            Err(e) => {
-                let error_response = format_redis_error(e);
-                socket.write_all(error_response.as_bytes()).await?;
-                socket.flush().await?;
-                continue;
+        loop {
+            let parsed = match decoder.decode() {
+                Ok(Some(parsed)) => parsed,
+                // Wait for the rest of the pipeline
+                Ok(None) => break,
+                Err(e) => {
+                    // The stream can't be resynchronised after a protocol
+                    // error, reply and close the connection like Redis does
+                    output.extend_from_slice(format_redis_error(e).as_bytes());
+                    socket.write_all(&output).await?;
+                    socket.flush().await?;
+                    return Ok(());
+                }
+            };
+
+            let result = process_command(parsed, db, client, false).await;
+
+            let result = match client.blocked.take() {
+                Some(wait) => {
+                    // Replies to the commands before it shouldn't wait too
+                    socket.write_all(&output).await?;
+                    socket.flush().await?;
+                    output.clear();
+
+                    let timeout_reply = result.unwrap_or(SiderResponse::NullArray);
+                    let wait = wait_until_served(
+                        &mut socket,
+                        &mut buffer,
+                        &mut decoder,
+                        db,
+                        client.id,
+                        wait,
+                        timeout_reply,
+                    );
+
+                    match wait.await? {
+                        Some(reply) => Ok(reply),
+                        // Disconnected while blocked
+                        None => return Ok(()),
+                    }
+                }
+                None => result,
+            };
+
+            match result {
+                Ok(resp) => resp.encode(client.protocol, &mut output),
+                Err(e) => output.extend_from_slice(format_redis_error(e).as_bytes()),
+            }
+
+            if output.len() >= MAX_PENDING_OUTPUT {
+                socket.write_all(&output).await?;
+                output.clear();
+            }
+        }
+
+        if !output.is_empty() {
+            socket.write_all(&output).await?;
+            socket.flush().await?;
+            output.clear();
+        }
+    }
+}
+
+// Waits for the reply of a blocking command until it times out. Anything the
+// client sends meanwhile is kept for after, `None` if it disconnects.
+async fn wait_until_served(
+    socket: &mut TcpStream,
+    buffer: &mut [u8],
+    decoder: &mut RespDecoder,
+    db: &Db,
+    client_id: u64,
+    wait: BlockedWait,
+    timeout_reply: SiderResponse,
+) -> Result<Option<SiderResponse>, Box<dyn Error + Send + Sync>> {
+    let BlockedWait { mut reply, timeout } = wait;
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        let timed_out = async {
+            match deadline {
+                Some(deadline) => time::sleep_until(deadline).await,
+                None => future::pending().await,
             }
         };
 
-        for parsed in parsed_commands {
-            match process_command(parsed, &db, false).await {
-                Ok(resp) => {
-                    let response = resp.to_string();
-                    socket.write_all(response.as_bytes()).await?;
-                    socket.flush().await?;
+        tokio::select! {
+            served = &mut reply => return Ok(Some(served.unwrap_or(timeout_reply))),
+            _ = timed_out => {
+                // Already picked, the reply is on its way
+                if db.unblock(client_id).is_none() {
+                    return Ok(Some(reply.await.unwrap_or(timeout_reply)));
                 }
-                Err(e) => {
-                    let error_response = format_redis_error(e);
-                    socket.write_all(error_response.as_bytes()).await?;
-                    socket.flush().await?;
+
+                return Ok(Some(timeout_reply));
+            }
+            read = socket.read(buffer) => match read {
+                Ok(bytes_read) if bytes_read > 0 => decoder.feed(&buffer[..bytes_read]),
+                read => {
+                    db.unblock(client_id);
+                    read?;
+                    return Ok(None);
                 }
             }
         }
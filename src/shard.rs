@@ -0,0 +1,359 @@
+use crate::expire::ExpireStats;
+use crate::memory::{AccessInfo, EvictionPolicy};
+use crate::notify::{notify_keyspace_event, NOTIFY_EVICTED, NOTIFY_EXPIRED};
+use crate::types::DbValue;
+use crate::utils::random_index;
+use bytes::Bytes;
+use indexmap::{Equivalent, IndexMap, IndexSet};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Shard a key belongs to among `count`. The hash is not keyed, so a key
+/// lands in the same shard for as long as the shard count doesn't change.
+pub fn shard_index<Q>(key: &Q, count: usize) -> usize
+where
+    Q: ?Sized + Hash,
+{
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % count as u64) as usize
+}
+
+// Versions are unique across keys, a key watched again once nobody watches
+// it never gets back the version it had before
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(1);
+
+fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug)]
+struct WatchedKey {
+    // Bumped every time the key is modified, created or deleted
+    version: u64,
+    // Clients watching it, the key is forgotten once none is left
+    watchers: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: DbValue,
+    // Estimated bytes used by the key and value, counted in `used_memory`
+    size: usize,
+    access: AccessInfo,
+}
+
+fn entry_size(key: &[u8], value: &DbValue) -> usize {
+    key.len() + value.memory_usage()
+}
+
+/// A slice of the keys of the database, locked independently of the others.
+#[derive(Debug, Default)]
+pub struct Shard {
+    entries: IndexMap<Bytes, Entry>,
+    // Keys with an expiry, sampled by the active expiry cycle. Kept in sync
+    // by every method adding, removing or changing the expiry of a key.
+    volatile: IndexSet<Bytes>,
+    expire_stats: ExpireStats,
+    evicted_keys: u64,
+    used_memory: usize,
+    // Memory used by all the shards of the store
+    total_memory: Arc<AtomicUsize>,
+    // Keys given to WATCH, whether they exist or not, like the watched_keys
+    // dict of Redis
+    watched_keys: HashMap<Bytes, WatchedKey>,
+}
+
+impl Shard {
+    pub fn new() -> Self {
+        Shard::default()
+    }
+
+    /// A shard of a store, adding the memory it uses to `total_memory`.
+    pub fn with_memory_counter(total_memory: Arc<AtomicUsize>) -> Self {
+        Shard {
+            total_memory,
+            ..Shard::default()
+        }
+    }
+
+    fn add_memory(&mut self, added: usize, removed: usize) {
+        self.used_memory = self.used_memory + added - removed;
+
+        if added >= removed {
+            self.total_memory
+                .fetch_add(added - removed, Ordering::Relaxed);
+        } else {
+            self.total_memory
+                .fetch_sub(removed - added, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&DbValue>
+    where
+        Q: ?Sized + Hash + Equivalent<Bytes>,
+    {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut DbValue>
+    where
+        Q: ?Sized + Hash + Equivalent<Bytes>,
+    {
+        self.entries.get_mut(key).map(|entry| &mut entry.value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: ?Sized + Hash + Equivalent<Bytes>,
+    {
+        self.entries.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: Bytes, value: DbValue) -> Option<DbValue> {
+        match value.expires_at() {
+            Some(_) => self.volatile.insert(key.clone()),
+            None => self.volatile.swap_remove(&key),
+        };
+
+        // An overwritten key keeps its access history for eviction
+        let access = self
+            .entries
+            .get(&key)
+            .map_or_else(AccessInfo::default, |entry| entry.access.clone());
+
+        let size = entry_size(&key, &value);
+        let entry = Entry {
+            value,
+            size,
+            access,
+        };
+
+        self.signal_modified_key(&key);
+        let previous = self.entries.insert(key, entry);
+        let removed = previous.as_ref().map_or(0, |previous| previous.size);
+        self.add_memory(size, removed);
+
+        previous.map(|previous| previous.value)
+    }
+
+    pub fn swap_remove<Q>(&mut self, key: &Q) -> Option<DbValue>
+    where
+        Q: ?Sized + Hash + Equivalent<Bytes>,
+    {
+        self.volatile.swap_remove(key);
+        let (_, key, entry) = self.entries.swap_remove_full(key)?;
+        self.add_memory(0, entry.size);
+        self.signal_modified_key(&key);
+
+        Some(entry.value)
+    }
+
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&Bytes, &mut DbValue) -> bool,
+    {
+        let mut freed = 0;
+        let mut removed = Vec::new();
+        self.entries.retain(|key, entry| {
+            let kept = keep(key, &mut entry.value);
+            if !kept {
+                freed += entry.size;
+                removed.push(key.clone());
+            }
+            kept
+        });
+        self.add_memory(0, freed);
+
+        for key in removed {
+            self.signal_modified_key(&key);
+        }
+
+        let entries = &self.entries;
+        self.volatile.retain(|key| entries.contains_key(key));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.volatile.clear();
+        self.add_memory(0, self.used_memory);
+
+        // Redis counts them all as modified, even the ones that didn't exist
+        for watched in self.watched_keys.values_mut() {
+            watched.version = next_version();
+        }
+    }
+
+    /// Sets or removes the expiry of a key, false if it doesn't exist.
+    pub fn set_expires_at(&mut self, key: &Bytes, expires_at: Option<i64>) -> bool {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return false;
+        };
+
+        entry.value.set_expires_at(expires_at);
+        match expires_at {
+            Some(_) => self.volatile.insert(key.clone()),
+            None => self.volatile.swap_remove(key),
+        };
+        self.signal_modified_key(key);
+
+        true
+    }
+
+    /// Starts tracking the changes of a key for a client and returns its
+    /// current version.
+    pub fn watch(&mut self, key: &Bytes) -> u64 {
+        let watched = self
+            .watched_keys
+            .entry(key.clone())
+            .or_insert_with(|| WatchedKey {
+                version: next_version(),
+                watchers: 0,
+            });
+        watched.watchers += 1;
+        watched.version
+    }
+
+    /// Undoes one `watch` of the key.
+    pub fn unwatch(&mut self, key: &[u8]) {
+        if let Some(watched) = self.watched_keys.get_mut(key) {
+            watched.watchers -= 1;
+            if watched.watchers == 0 {
+                self.watched_keys.remove(key);
+            }
+        }
+    }
+
+    /// Version of a watched key, `None` if nobody watches it.
+    pub fn watched_version(&self, key: &[u8]) -> Option<u64> {
+        self.watched_keys.get(key).map(|watched| watched.version)
+    }
+
+    /// Makes the EXEC of the clients watching the key fail. Called by every
+    /// change to a key, commands changing a value in place call it too.
+    pub fn signal_modified_key(&mut self, key: &[u8]) {
+        if let Some(watched) = self.watched_keys.get_mut(key) {
+            watched.version = next_version();
+        }
+    }
+
+    /// Deletes the key if it has expired. Returns true if it was deleted.
+    pub fn expire_if_needed(&mut self, key: &[u8]) -> bool {
+        if !self
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.value.is_expired())
+        {
+            return false;
+        }
+
+        self.swap_remove(key);
+        self.expire_stats.expired_keys += 1;
+        notify_keyspace_event(NOTIFY_EXPIRED, "expired", key);
+
+        true
+    }
+
+    /// Number of keys with an expiry.
+    pub fn volatile_len(&self) -> usize {
+        self.volatile.len()
+    }
+
+    /// A key with an expiry picked at random, `None` if there is none.
+    pub fn random_volatile_key(&self) -> Option<Bytes> {
+        if self.volatile.is_empty() {
+            return None;
+        }
+
+        self.volatile
+            .get_index(random_index(self.volatile.len()))
+            .cloned()
+    }
+
+    pub fn expire_stats(&self) -> &ExpireStats {
+        &self.expire_stats
+    }
+
+    pub fn expire_stats_mut(&mut self) -> &mut ExpireStats {
+        &mut self.expire_stats
+    }
+
+    /// Marks the key as just accessed for the LRU and LFU policies.
+    pub fn record_access(&mut self, key: &[u8]) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.access.record();
+        }
+    }
+
+    /// Measures the key again once a command changed its value in place.
+    pub fn refresh_memory_usage(&mut self, key: &[u8]) {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return;
+        };
+
+        let (size, removed) = (entry_size(key, &entry.value), entry.size);
+        entry.size = size;
+        self.add_memory(size, removed);
+    }
+
+    /// Estimated bytes used by the keys of the shard.
+    pub fn used_memory(&self) -> usize {
+        self.used_memory
+    }
+
+    /// The best key to evict among `samples` picked at random according to
+    /// `policy`, `None` if no key qualifies.
+    pub fn eviction_candidate(&self, policy: EvictionPolicy, samples: usize) -> Option<Bytes> {
+        let mut best: Option<(&Bytes, u64)> = None;
+
+        for _ in 0..samples {
+            let sampled = if policy.is_volatile() {
+                let key = self
+                    .volatile
+                    .get_index(random_index(self.volatile.len().max(1)));
+                key.and_then(|key| self.entries.get_key_value(key))
+            } else {
+                self.entries
+                    .get_index(random_index(self.entries.len().max(1)))
+            };
+            let (key, entry) = sampled?;
+
+            let score = entry.access.eviction_score(policy, &entry.value);
+            if best.is_none_or(|(_, best)| score > best) {
+                best = Some((key, score));
+            }
+        }
+
+        best.map(|(key, _)| key.clone())
+    }
+
+    /// Deletes a key to free memory.
+    pub fn evict(&mut self, key: &Bytes) {
+        if self.swap_remove(key).is_some() {
+            self.evicted_keys += 1;
+            notify_keyspace_event(NOTIFY_EVICTED, "evicted", key);
+        }
+    }
+
+    pub fn evicted_keys(&self) -> u64 {
+        self.evicted_keys
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Bytes> {
+        self.entries.keys()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, &DbValue)> {
+        self.entries.iter().map(|(key, entry)| (key, &entry.value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
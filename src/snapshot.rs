@@ -0,0 +1,596 @@
+//! Point-in-time snapshots of the dataset in a compact binary format, like
+//! Redis RDB files, taken with `SAVE`, `BGSAVE` or the `save` rules. A
+//! snapshot remembers where the AOF stood when it was taken, so restarting
+//! only replays what was logged after it.
+//!
+//! The file starts with `SIDER` and the format version, then the size and
+//! checksum of the AOF at that point. Keys follow, each with its type and
+//! optionally its expiry, until the EOF opcode and the CRC64 of everything
+//! before it.
+
+use crate::aof::{aof_position, get_aof_log_dir, AofPosition};
+use crate::crc64::crc64;
+use crate::database::is_loading;
+use crate::errors::SiderError;
+use crate::shard::Shard;
+use crate::stream::{Consumer, ConsumerGroup, PendingEntry, Stream};
+use crate::types::{
+    Db, DbValue, Key, KeyHash, KeyList, KeySet, KeyStream, KeyZSet, Store, StreamId,
+};
+use crate::utils::current_time_millis;
+use crate::zset::SortedSet;
+use bytes::Bytes;
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::RwLock;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio::time::{self, Duration};
+use tracing::{info, warn};
+
+const MAGIC: &[u8] = b"SIDER";
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+const OPCODE_EXPIRE_MS: u8 = 0xFC;
+const OPCODE_EOF: u8 = 0xFF;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_HASH: u8 = 3;
+const TYPE_ZSET: u8 = 4;
+const TYPE_STREAM: u8 = 5;
+
+// How often the save rules and scheduled saves are checked for
+const CRON_PERIOD: Duration = Duration::from_millis(100);
+// Seconds before a failed save is retried by the rules, like Redis
+const SAVE_RETRY_DELAY: i64 = 5;
+
+/// Save once `changes` writes were made in the last `seconds`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveRule {
+    pub seconds: u64,
+    pub changes: u64,
+}
+
+// Same default as Redis: after an hour if a key changed, 5 minutes if 100
+// did and a minute if 10000 did
+static SAVE_RULES: Lazy<RwLock<Vec<SaveRule>>> =
+    Lazy::new(|| RwLock::new(parse_save_rules(b"3600 1 300 100 60 10000").unwrap_or_default()));
+
+// Writes made since the last successful save
+static CHANGES: AtomicU64 = AtomicU64::new(0);
+// Unix time in seconds of the last successful save, and of the last try
+static LAST_SAVE: AtomicI64 = AtomicI64::new(0);
+static LAST_SAVE_ATTEMPT: AtomicI64 = AtomicI64::new(0);
+static LAST_SAVE_OK: AtomicBool = AtomicBool::new(true);
+static SAVE_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static SAVE_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+/// Parses a `save` value such as `3600 1 300 100`, empty disables saving.
+pub fn parse_save_rules(value: &[u8]) -> Option<Vec<SaveRule>> {
+    let numbers = value
+        .split(|c| c.is_ascii_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(|number| std::str::from_utf8(number).ok()?.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    if numbers.len() % 2 != 0 {
+        return None;
+    }
+
+    let rules = numbers.chunks(2).map(|rule| SaveRule {
+        seconds: rule[0],
+        changes: rule[1],
+    });
+    Some(rules.collect())
+}
+
+pub fn format_save_rules(rules: &[SaveRule]) -> String {
+    rules
+        .iter()
+        .map(|rule| format!("{} {}", rule.seconds, rule.changes))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn get_save_rules() -> Vec<SaveRule> {
+    SAVE_RULES.read().expect("Save rules lock poisoned").clone()
+}
+
+pub fn set_save_rules(rules: Vec<SaveRule>) {
+    *SAVE_RULES.write().expect("Save rules lock poisoned") = rules;
+}
+
+/// Counts writes towards the save rules.
+pub fn add_changes(count: u64) {
+    CHANGES.fetch_add(count, Ordering::Relaxed);
+}
+
+pub fn changes_since_last_save() -> u64 {
+    CHANGES.load(Ordering::Relaxed)
+}
+
+/// Unix time in seconds of the last successful save, or of the startup.
+pub fn get_last_save() -> i64 {
+    LAST_SAVE.load(Ordering::Relaxed)
+}
+
+pub fn last_save_ok() -> bool {
+    LAST_SAVE_OK.load(Ordering::Relaxed)
+}
+
+pub fn is_save_in_progress() -> bool {
+    SAVE_IN_PROGRESS.load(Ordering::Relaxed)
+}
+
+/// Has the next check take a snapshot, `BGSAVE`.
+pub fn schedule_save() {
+    SAVE_SCHEDULED.store(true, Ordering::Relaxed);
+}
+
+/// Whether one of the rules asks for a save, `elapsed` seconds and
+/// `changes` writes after the last one.
+pub fn save_rule_reached(rules: &[SaveRule], changes: u64, elapsed: u64) -> bool {
+    rules
+        .iter()
+        .any(|rule| changes >= rule.changes && elapsed >= rule.seconds)
+}
+
+pub fn get_snapshot_file() -> PathBuf {
+    get_aof_log_dir().join("dump.sider")
+}
+
+/// Takes the snapshots asked for with `BGSAVE` and by the save rules.
+pub async fn save_periodically(db: Db) {
+    LAST_SAVE.store(current_time_millis() / 1000, Ordering::Relaxed);
+    let mut interval = time::interval(CRON_PERIOD);
+
+    loop {
+        interval.tick().await;
+
+        // The snapshot would miss what isn't restored yet
+        if is_loading() {
+            continue;
+        }
+
+        let now = current_time_millis() / 1000;
+        let elapsed = (now - get_last_save()).max(0) as u64;
+        let retry = last_save_ok()
+            || now - LAST_SAVE_ATTEMPT.load(Ordering::Relaxed) >= SAVE_RETRY_DELAY;
+        let rules =
+            retry && save_rule_reached(&get_save_rules(), changes_since_last_save(), elapsed);
+
+        if SAVE_SCHEDULED.swap(false, Ordering::Relaxed) || rules {
+            let _ = save_snapshot(&db).await;
+        }
+    }
+}
+
+/// Writes a snapshot of the dataset and replaces the previous one with it.
+/// Shards are only locked while they are encoded in memory, the file is
+/// written once they are all released.
+pub async fn save_snapshot(db: &Store) -> Result<(), SiderError> {
+    if SAVE_IN_PROGRESS.swap(true, Ordering::Relaxed) {
+        return Err(SiderError::SaveInProgress);
+    }
+
+    info!("Saving a snapshot");
+    LAST_SAVE_ATTEMPT.store(current_time_millis() / 1000, Ordering::Relaxed);
+    let path = get_aof_log_dir().join("temp-snapshot.sider");
+    let result = write_snapshot(db, &path).await;
+
+    match &result {
+        Ok(changes) => {
+            CHANGES.fetch_sub(*changes, Ordering::Relaxed);
+            LAST_SAVE.store(current_time_millis() / 1000, Ordering::Relaxed);
+            info!("Snapshot saved");
+        }
+        Err(e) => {
+            warn!("Failed to save a snapshot: {e}");
+            let _ = fs::remove_file(&path).await;
+        }
+    }
+
+    LAST_SAVE_OK.store(result.is_ok(), Ordering::Relaxed);
+    SAVE_IN_PROGRESS.store(false, Ordering::Relaxed);
+    result
+        .map(|_| ())
+        .map_err(|e| SiderError::SaveFailed(e.to_string()))
+}
+
+// Returns the changes the snapshot covers
+async fn write_snapshot(db: &Store, path: &Path) -> std::io::Result<u64> {
+    let mut file = File::create(path).await?;
+
+    // Taken once every shard is locked, like the AOF rewrite
+    let shards = db.read_all().await;
+    let changes = changes_since_last_save();
+    let mut output = encode_header(aof_position().await?);
+
+    // Each shard is released as soon as it is encoded
+    for shard in shards {
+        encode_shard(&shard, &mut output);
+    }
+
+    let crc = crc64(0, &output);
+    file.write_all(&output).await?;
+    file.write_all(&encode_trailer(crc)).await?;
+    file.flush().await?;
+    file.sync_all().await?;
+    drop(file);
+
+    fs::rename(path, get_snapshot_file()).await?;
+    Ok(changes)
+}
+
+/// A whole snapshot of the shards, as written by `SAVE`.
+pub fn encode_snapshot<'a, I>(aof: AofPosition, shards: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = &'a Shard>,
+{
+    let mut output = encode_header(aof);
+
+    for shard in shards {
+        encode_shard(shard, &mut output);
+    }
+
+    let crc = crc64(0, &output);
+    output.extend_from_slice(&encode_trailer(crc));
+    output
+}
+
+fn encode_header(aof: AofPosition) -> Vec<u8> {
+    let mut output = MAGIC.to_vec();
+    output.push(SNAPSHOT_VERSION);
+    output.extend_from_slice(&aof.size.to_le_bytes());
+    output.extend_from_slice(&aof.crc.to_le_bytes());
+    output
+}
+
+// `crc` covers everything written before
+fn encode_trailer(crc: u64) -> Vec<u8> {
+    let crc = crc64(crc, &[OPCODE_EOF]);
+
+    let mut output = vec![OPCODE_EOF];
+    output.extend_from_slice(&crc.to_le_bytes());
+    output
+}
+
+// Expired keys are left out
+fn encode_shard(shard: &Shard, output: &mut Vec<u8>) {
+    for (key, value) in shard.iter().filter(|(_, value)| !value.is_expired()) {
+        if let Some(expires_at) = value.expires_at() {
+            output.push(OPCODE_EXPIRE_MS);
+            output.extend_from_slice(&expires_at.to_le_bytes());
+        }
+
+        match value {
+            DbValue::StringKey(string_key) => {
+                output.push(TYPE_STRING);
+                push_string(output, key);
+                push_string(output, string_key.data.as_deref().unwrap_or_default());
+            }
+            DbValue::ListKey(list_key) => {
+                output.push(TYPE_LIST);
+                push_string(output, key);
+                push_length(output, list_key.data.len());
+                for value in list_key.data.iter() {
+                    push_string(output, value);
+                }
+            }
+            DbValue::SetKey(set_key) => {
+                output.push(TYPE_SET);
+                push_string(output, key);
+                push_length(output, set_key.data.len());
+                for member in set_key.data.iter() {
+                    push_string(output, member);
+                }
+            }
+            DbValue::HashKey(hash_key) => {
+                output.push(TYPE_HASH);
+                push_string(output, key);
+                push_length(output, hash_key.data.len());
+                for (field, value) in hash_key.data.iter() {
+                    push_string(output, field);
+                    push_string(output, value);
+                }
+            }
+            DbValue::ZSetKey(zset_key) => {
+                output.push(TYPE_ZSET);
+                push_string(output, key);
+                push_length(output, zset_key.data.len());
+                for (member, score) in zset_key.data.iter() {
+                    push_string(output, member);
+                    output.extend_from_slice(&score.to_le_bytes());
+                }
+            }
+            DbValue::StreamKey(stream_key) => {
+                output.push(TYPE_STREAM);
+                push_string(output, key);
+                push_stream(output, &stream_key.data);
+            }
+        }
+    }
+}
+
+fn push_stream(output: &mut Vec<u8>, stream: &Stream) {
+    push_length(output, stream.len());
+    for (id, fields) in stream.range(StreamId::MIN, StreamId::MAX) {
+        push_stream_id(output, id);
+        push_length(output, fields.len());
+        for (field, value) in fields.iter() {
+            push_string(output, field);
+            push_string(output, value);
+        }
+    }
+
+    push_stream_id(output, &stream.last_id);
+
+    push_length(output, stream.groups.len());
+    for (name, group) in stream.groups.iter() {
+        push_string(output, name);
+        push_stream_id(output, &group.last_delivered);
+
+        push_length(output, group.consumers.len());
+        for (name, consumer) in group.consumers.iter() {
+            push_string(output, name);
+            output.extend_from_slice(&consumer.seen_at.to_le_bytes());
+        }
+
+        push_length(output, group.pending.len());
+        for (id, pending) in group.pending.iter() {
+            push_stream_id(output, id);
+            push_string(output, &pending.consumer);
+            output.extend_from_slice(&pending.delivered_at.to_le_bytes());
+            push_length(output, pending.delivery_count as usize);
+        }
+    }
+}
+
+// Lengths are varints, 7 bits per byte, so short strings only take one
+fn push_length(output: &mut Vec<u8>, len: usize) {
+    let mut len = len as u64;
+
+    while len >= 0x80 {
+        output.push((len as u8 & 0x7f) | 0x80);
+        len >>= 7;
+    }
+
+    output.push(len as u8);
+}
+
+fn push_string(output: &mut Vec<u8>, value: &[u8]) {
+    push_length(output, value.len());
+    output.extend_from_slice(value);
+}
+
+fn push_stream_id(output: &mut Vec<u8>, id: &StreamId) {
+    push_length(output, id.ms as usize);
+    push_length(output, id.seq as usize);
+}
+
+/// A snapshot read back: where the AOF stood and the keys.
+#[derive(Debug)]
+pub struct Snapshot {
+    pub aof: AofPosition,
+    pub entries: Vec<(Bytes, DbValue)>,
+}
+
+impl Snapshot {
+    /// Whether `aof` is the AOF the snapshot was taken with, maybe with more
+    /// writes appended since. It wasn't rewritten or flushed in between.
+    pub fn matches_aof(&self, aof: &[u8]) -> bool {
+        let Ok(size) = usize::try_from(self.aof.size) else {
+            return false;
+        };
+
+        aof.len() >= size && crc64(0, &aof[..size]) == self.aof.crc
+    }
+}
+
+/// Reads a snapshot, checking its checksum first.
+pub fn decode_snapshot(data: &[u8]) -> Result<Snapshot, SiderError> {
+    let corrupt = |reason: &str| SiderError::CorruptSnapshot(reason.to_string());
+
+    if data.len() < MAGIC.len() + 1 + 8 || !data.starts_with(MAGIC) {
+        return Err(corrupt("not a snapshot"));
+    }
+
+    let (content, checksum) = data.split_at(data.len() - 8);
+    if crc64(0, content).to_le_bytes() != checksum {
+        return Err(corrupt("checksum mismatch"));
+    }
+
+    let mut reader = Reader {
+        data: content,
+        position: MAGIC.len(),
+    };
+
+    let version = reader.byte()?;
+    if version > SNAPSHOT_VERSION {
+        return Err(corrupt(&format!("unsupported version {version}")));
+    }
+
+    let aof = AofPosition {
+        size: reader.u64()?,
+        crc: reader.u64()?,
+    };
+    let mut entries = Vec::new();
+    let mut expires_at = None;
+
+    loop {
+        match reader.byte()? {
+            OPCODE_EOF => break,
+            OPCODE_EXPIRE_MS => expires_at = Some(reader.u64()? as i64),
+            value_type => {
+                let key = reader.string()?;
+                let value = reader.value(value_type, key.clone(), expires_at.take())?;
+                entries.push((key, value));
+            }
+        }
+    }
+
+    if reader.position != content.len() {
+        return Err(corrupt("data after the end"));
+    }
+
+    Ok(Snapshot { aof, entries })
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> Result<&[u8], SiderError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| SiderError::CorruptSnapshot("unexpected end".to_string()))?;
+
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, SiderError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, SiderError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn f64(&mut self) -> Result<f64, SiderError> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+
+    fn length(&mut self) -> Result<usize, SiderError> {
+        let mut len: u64 = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            len |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return usize::try_from(len)
+                    .map_err(|_| SiderError::CorruptSnapshot("length too big".to_string()));
+            }
+        }
+
+        Err(SiderError::CorruptSnapshot("length too big".to_string()))
+    }
+
+    // Never more than the bytes left, whatever a corrupt length says
+    fn capacity(&mut self) -> Result<usize, SiderError> {
+        Ok(self.length()?.min(self.data.len() - self.position))
+    }
+
+    fn string(&mut self) -> Result<Bytes, SiderError> {
+        let len = self.length()?;
+        Ok(Bytes::copy_from_slice(self.bytes(len)?))
+    }
+
+    fn stream_id(&mut self) -> Result<StreamId, SiderError> {
+        Ok(StreamId::new(self.length()? as u64, self.length()? as u64))
+    }
+
+    fn value(
+        &mut self,
+        value_type: u8,
+        key: Bytes,
+        expires_at: Option<i64>,
+    ) -> Result<DbValue, SiderError> {
+        let value = match value_type {
+            TYPE_STRING => DbValue::StringKey(Key::new(key, Some(self.string()?), expires_at)),
+            TYPE_LIST => {
+                let len = self.capacity()?;
+                let mut values = VecDeque::with_capacity(len);
+                for _ in 0..len {
+                    values.push_back(self.string()?);
+                }
+                DbValue::ListKey(KeyList::new(key, values, expires_at))
+            }
+            TYPE_SET => {
+                let len = self.capacity()?;
+                let mut members = HashSet::with_capacity(len);
+                for _ in 0..len {
+                    members.insert(self.string()?);
+                }
+                DbValue::SetKey(KeySet::new(key, members, expires_at))
+            }
+            TYPE_HASH => {
+                let len = self.capacity()?;
+                let mut fields = IndexMap::with_capacity(len);
+                for _ in 0..len {
+                    fields.insert(self.string()?, self.string()?);
+                }
+                DbValue::HashKey(KeyHash::new(key, fields, expires_at))
+            }
+            TYPE_ZSET => {
+                let mut members = SortedSet::default();
+                for _ in 0..self.capacity()? {
+                    let member = self.string()?;
+                    members.insert(member, self.f64()?);
+                }
+                DbValue::ZSetKey(KeyZSet::new(key, members, expires_at))
+            }
+            TYPE_STREAM => DbValue::StreamKey(KeyStream::new(key, self.stream()?, expires_at)),
+            other => {
+                return Err(SiderError::CorruptSnapshot(format!(
+                    "unknown value type {other}"
+                )))
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn stream(&mut self) -> Result<Stream, SiderError> {
+        let mut stream = Stream::default();
+
+        for _ in 0..self.capacity()? {
+            let id = self.stream_id()?;
+            let mut fields = Vec::new();
+            for _ in 0..self.capacity()? {
+                fields.push((self.string()?, self.string()?));
+            }
+            stream.insert(id, fields);
+        }
+
+        stream.last_id = self.stream_id()?;
+
+        for _ in 0..self.capacity()? {
+            let name = self.string()?;
+            let mut group = ConsumerGroup::new(self.stream_id()?);
+
+            for _ in 0..self.capacity()? {
+                let name = self.string()?;
+                let seen_at = self.u64()? as i64;
+                group.consumers.insert(name, Consumer { seen_at });
+            }
+
+            for _ in 0..self.capacity()? {
+                let id = self.stream_id()?;
+                let pending = PendingEntry {
+                    consumer: self.string()?,
+                    delivered_at: self.u64()? as i64,
+                    delivery_count: self.length()? as u64,
+                };
+                group.pending.insert(id, pending);
+            }
+
+            stream.groups.insert(name, group);
+        }
+
+        Ok(stream)
+    }
+}
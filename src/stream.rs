@@ -0,0 +1,216 @@
+use bytes::Bytes;
+use indexmap::IndexMap;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Bound;
+
+/// A `<milliseconds>-<sequence>` stream entry ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    /// Parses `ms-seq`, or `ms` alone with `missing_seq` as its sequence.
+    pub fn parse(value: &[u8], missing_seq: u64) -> Option<StreamId> {
+        let value = std::str::from_utf8(value).ok()?;
+
+        let (ms, seq) = match value.split_once('-') {
+            Some((ms, seq)) => (ms, seq.parse().ok()?),
+            None => (value, missing_seq),
+        };
+
+        Some(StreamId::new(ms.parse().ok()?, seq))
+    }
+
+    /// The smallest ID greater than this one.
+    pub fn next(&self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    /// The greatest ID smaller than this one.
+    pub fn prev(&self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(self.to_string())
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+pub type StreamFields = Vec<(Bytes, Bytes)>;
+
+/// An entry delivered to a consumer and not acknowledged yet.
+#[derive(Debug, Clone)]
+pub struct PendingEntry {
+    pub consumer: Bytes,
+    // Unix time in milliseconds of the last delivery
+    pub delivered_at: i64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Consumer {
+    // Unix time in milliseconds the consumer last read or claimed entries
+    pub seen_at: i64,
+}
+
+/// Entries are delivered to the consumers of a group only once, and stay in
+/// the pending entries list (PEL) until they are acknowledged.
+#[derive(Debug, Clone, Default)]
+pub struct ConsumerGroup {
+    pub last_delivered: StreamId,
+    pub pending: BTreeMap<StreamId, PendingEntry>,
+    pub consumers: IndexMap<Bytes, Consumer>,
+}
+
+impl ConsumerGroup {
+    pub fn new(last_delivered: StreamId) -> Self {
+        ConsumerGroup {
+            last_delivered,
+            ..Default::default()
+        }
+    }
+
+    /// Marks the consumer as active, creating it if needed. Returns true if
+    /// it was created.
+    pub fn see_consumer(&mut self, name: &Bytes, now: i64) -> bool {
+        match self.consumers.get_mut(name) {
+            Some(consumer) => {
+                consumer.seen_at = now;
+                false
+            }
+            None => {
+                self.consumers
+                    .insert(name.clone(), Consumer { seen_at: now });
+                true
+            }
+        }
+    }
+
+    /// Number of entries delivered to the consumer and not acknowledged.
+    pub fn pending_count(&self, consumer: &[u8]) -> usize {
+        self.pending
+            .values()
+            .filter(|pending| pending.consumer == consumer)
+            .count()
+    }
+}
+
+/// An append-only log of entries ordered by ID, along with its consumer
+/// groups. Unlike other types a stream is kept when it becomes empty.
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, StreamFields>,
+    // Greatest ID ever added, new entries must be above it even once deleted
+    pub last_id: StreamId,
+    pub groups: IndexMap<Bytes, ConsumerGroup>,
+}
+
+impl Stream {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, id: &StreamId) -> Option<&StreamFields> {
+        self.entries.get(id)
+    }
+
+    pub fn first_id(&self) -> Option<StreamId> {
+        self.entries.keys().next().copied()
+    }
+
+    pub fn last_entry_id(&self) -> Option<StreamId> {
+        self.entries.keys().next_back().copied()
+    }
+
+    /// ID given to an entry added at `now` with `*`, `None` once IDs are
+    /// exhausted.
+    pub fn next_id(&self, now: u64) -> Option<StreamId> {
+        if now > self.last_id.ms {
+            Some(StreamId::new(now, 0))
+        } else {
+            self.last_id.next()
+        }
+    }
+
+    /// Adds an entry, the caller checked `id` is above `last_id`.
+    pub fn insert(&mut self, id: StreamId, fields: StreamFields) {
+        self.entries.insert(id, fields);
+        self.last_id = id;
+    }
+
+    pub fn remove(&mut self, id: &StreamId) -> bool {
+        self.entries.remove(id).is_some()
+    }
+
+    /// Entries with an ID within `start` and `end`, oldest first.
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+    ) -> impl DoubleEndedIterator<Item = (&StreamId, &StreamFields)> {
+        // `BTreeMap::range` panics on a reversed range
+        let bounds = (start <= end).then_some((Bound::Included(start), Bound::Included(end)));
+        bounds
+            .into_iter()
+            .flat_map(|bounds| self.entries.range(bounds))
+    }
+
+    /// Removes the oldest entries until at most `max_len` are left, or until
+    /// `limit` entries were removed. Returns how many were removed.
+    pub fn trim_max_len(&mut self, max_len: usize, limit: Option<usize>) -> usize {
+        let excess = self.len().saturating_sub(max_len);
+        let count = limit.map_or(excess, |limit| excess.min(limit));
+
+        for _ in 0..count {
+            self.entries.pop_first();
+        }
+
+        count
+    }
+
+    /// Removes the entries with an ID below `min_id`, at most `limit` of them.
+    /// Returns how many were removed.
+    pub fn trim_min_id(&mut self, min_id: StreamId, limit: Option<usize>) -> usize {
+        let mut count = 0;
+
+        while let Some(entry) = self.entries.first_entry() {
+            if *entry.key() >= min_id || limit.is_some_and(|limit| count >= limit) {
+                break;
+            }
+
+            entry.remove();
+            count += 1;
+        }
+
+        count
+    }
+}
@@ -1,10 +1,14 @@
+use bytes::Bytes;
 use indexmap::IndexMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
 
-pub type Db = Arc<RwLock<IndexMap<String, DbValue>>>;
+pub use crate::keyspace::{Keyspace, Store};
+pub use crate::stream::{Stream, StreamId};
+pub use crate::zset::SortedSet;
+pub type Db = Arc<Store>;
 
 #[derive(Debug, Clone)]
 pub struct Command {
@@ -24,6 +28,13 @@ pub enum CommandType {
     EXISTS,
     EXPIRE,
     TTL,
+    PEXPIRE,
+    PTTL,
+    EXPIREAT,
+    PEXPIREAT,
+    EXPIRETIME,
+    PEXPIRETIME,
+    PERSIST,
     INCR,
     DECR,
     INCRBY,
@@ -32,6 +43,10 @@ pub enum CommandType {
     RPUSH,
     LPOP,
     RPOP,
+    LMOVE,
+    BLPOP,
+    BRPOP,
+    BLMOVE,
     HSET,
     HGET,
     HGETALL,
@@ -40,38 +55,92 @@ pub enum CommandType {
     SADD,
     SMEMBERS,
     SREM,
+    HELLO,
+    MULTI,
+    EXEC,
+    DISCARD,
+    WATCH,
+    UNWATCH,
+    SUBSCRIBE,
+    UNSUBSCRIBE,
+    PSUBSCRIBE,
+    PUNSUBSCRIBE,
+    PUBLISH,
+    PUBSUB,
+    CONFIG,
+    INFO,
+    BGREWRITEAOF,
+    SAVE,
+    BGSAVE,
+    LASTSAVE,
+    DEBUG,
+    ZADD,
+    ZREM,
+    ZSCORE,
+    ZINCRBY,
+    ZCARD,
+    ZRANK,
+    ZREVRANK,
+    ZRANGE,
+    ZRANGEBYSCORE,
+    ZCOUNT,
+    ZPOPMIN,
+    ZPOPMAX,
+    ZREMRANGEBYSCORE,
+    XADD,
+    XLEN,
+    XRANGE,
+    XREVRANGE,
+    XREAD,
+    XDEL,
+    XTRIM,
+    XSETID,
+    XGROUP,
+    XREADGROUP,
+    XACK,
+    XPENDING,
+    XCLAIM,
+    XAUTOCLAIM,
 }
 
 #[derive(Debug, Clone)]
 pub enum CommandArgs {
-    NoArgs,                    // PONG, FLUSHDB
-    SingleKey(String),         // GET key
-    MultipleKeys(Vec<String>), // DEL key1 key2 key3
+    NoArgs,                   // PONG, FLUSHDB, MULTI
+    SingleKey(Bytes),         // GET key
+    MultipleKeys(Vec<Bytes>), // DEL key1 key2 key3
     KeyWithValue {
-        key: String,
-        value: String,
+        key: Bytes,
+        value: Bytes,
     }, // SET key value
     KeyWithValues {
-        key: String,
-        values: Vec<String>,
+        key: Bytes,
+        values: Vec<Bytes>,
     },
     HashFields {
-        key: String,
-        fields: IndexMap<String, String>,
+        key: Bytes,
+        fields: IndexMap<Bytes, Bytes>,
     }, // HSET key field1 value1 field2 value2
+    Hello {
+        protover: Option<i64>,
+        auth: Option<(Bytes, Bytes)>,
+        setname: Option<Bytes>,
+    }, // HELLO 3 AUTH user pass SETNAME name
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct KeyBase<T> {
-    pub name: String,
+    pub name: Bytes,
     pub data: T,
+    // Unix time in milliseconds
     pub expires_at: Option<i64>,
 }
 
-pub type Key = KeyBase<Option<String>>;
-pub type KeyList = KeyBase<VecDeque<String>>;
-pub type KeySet = KeyBase<HashSet<String>>;
-pub type KeyHash = KeyBase<IndexMap<String, String>>;
+pub type Key = KeyBase<Option<Bytes>>;
+pub type KeyList = KeyBase<VecDeque<Bytes>>;
+pub type KeySet = KeyBase<HashSet<Bytes>>;
+pub type KeyHash = KeyBase<IndexMap<Bytes, Bytes>>;
+pub type KeyZSet = KeyBase<SortedSet>;
+pub type KeyStream = KeyBase<Stream>;
 
 #[derive(Debug, Clone)]
 pub enum DbValue {
@@ -79,6 +148,8 @@ pub enum DbValue {
     ListKey(KeyList),
     SetKey(KeySet),
     HashKey(KeyHash),
+    ZSetKey(KeyZSet),
+    StreamKey(KeyStream),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -92,3 +163,36 @@ pub enum PopType {
     LPOP,
     RPOP,
 }
+
+/// What BLPOP, BRPOP and BLMOVE do once one of their keys has an element,
+/// or XREAD and XREADGROUP once one of their streams has new entries.
+#[derive(Debug, Clone)]
+pub enum BlockingOperation {
+    Pop(PopType),
+    Move {
+        destination: Bytes,
+        from: PopType,
+        to: ListPushType,
+    },
+    // Entries after the ID given for each stream, `$` already resolved
+    ReadStreams {
+        streams: Vec<(Bytes, StreamId)>,
+        count: Option<usize>,
+    },
+    // Entries never delivered to the group
+    ReadGroup {
+        group: Bytes,
+        consumer: Bytes,
+        keys: Vec<Bytes>,
+        count: Option<usize>,
+        noack: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockingRequest {
+    pub keys: Vec<Bytes>,
+    // `None` waits forever
+    pub timeout: Option<Duration>,
+    pub operation: BlockingOperation,
+}
@@ -0,0 +1,56 @@
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Parses a number sent as raw bytes, e.g. an `INCRBY` increment or a TTL.
+pub fn parse_number<T: FromStr>(value: &[u8]) -> Option<T> {
+    std::str::from_utf8(value).ok()?.parse::<T>().ok()
+}
+
+/// Parses a `yes` or `no` setting, e.g. `aof-load-truncated`.
+pub fn parse_yes_no(value: &[u8]) -> Option<bool> {
+    if value.eq_ignore_ascii_case(b"yes") {
+        Some(true)
+    } else if value.eq_ignore_ascii_case(b"no") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+pub fn format_yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Unix time in milliseconds.
+pub fn current_time_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as i64
+}
+
+thread_local! {
+    // xorshift64* state, seeded from the randomly keyed std hasher
+    static RANDOM_STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+}
+
+/// A random index below `len`, which must not be 0. Good enough to sample
+/// keys, not for anything security related.
+pub fn random_index(len: usize) -> usize {
+    RANDOM_STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) % len as u64) as usize
+    })
+}
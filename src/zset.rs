@@ -0,0 +1,501 @@
+use crate::utils::random_index;
+use bytes::Bytes;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::Bound;
+
+// Scores are never NaN, `total_cmp` gives them the order the list needs
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// A `ZRANGE ... BYLEX` bound: `-`, `+`, `[member` or `(member`.
+#[derive(Debug, Clone)]
+pub enum LexBound {
+    Min,
+    Max,
+    Included(Bytes),
+    Excluded(Bytes),
+}
+
+impl LexBound {
+    fn is_below(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Included(bound) => bound[..] <= *member,
+            LexBound::Excluded(bound) => bound[..] < *member,
+        }
+    }
+
+    fn is_above(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Included(bound) => bound[..] >= *member,
+            LexBound::Excluded(bound) => bound[..] > *member,
+        }
+    }
+}
+
+const MAX_LEVEL: usize = 32;
+
+// Node 0 is the head, linked to the first node at every level
+const HEAD: usize = 0;
+
+#[derive(Debug, Clone)]
+struct Link {
+    forward: Option<usize>,
+    // Members the link skips over, the node it points to included
+    span: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: Bytes,
+    score: f64,
+    backward: Option<usize>,
+    links: Vec<Link>,
+}
+
+impl Node {
+    fn new(member: Bytes, score: f64, level: usize) -> Self {
+        Node {
+            member,
+            score,
+            backward: None,
+            links: vec![
+                Link {
+                    forward: None,
+                    span: 0,
+                };
+                level
+            ],
+        }
+    }
+
+    fn precedes(&self, score: f64, member: &[u8]) -> bool {
+        (Score(self.score), &self.member[..]) < (Score(score), member)
+    }
+}
+
+/// A skip list whose links count the members they skip, like the one Redis
+/// keeps sorted sets in, so ranks are found in O(log N). Nodes live in a
+/// vector and point to each other by index, freed slots are reused.
+#[derive(Debug, Clone)]
+struct SkipList {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    tail: Option<usize>,
+    level: usize,
+    len: usize,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        SkipList {
+            nodes: vec![Node::new(Bytes::new(), 0.0, MAX_LEVEL)],
+            free: Vec::new(),
+            tail: None,
+            level: 1,
+            len: 0,
+        }
+    }
+}
+
+// Each level has a quarter of the nodes of the one below
+fn random_level() -> usize {
+    let mut level = 1;
+    while level < MAX_LEVEL && random_index(4) == 0 {
+        level += 1;
+    }
+    level
+}
+
+impl SkipList {
+    fn link(&self, node: usize, level: usize) -> &Link {
+        &self.nodes[node].links[level]
+    }
+
+    fn link_mut(&mut self, node: usize, level: usize) -> &mut Link {
+        &mut self.nodes[node].links[level]
+    }
+
+    // The last node preceding (score, member) at every level, along with the
+    // rank of those nodes
+    fn predecessors(
+        &self,
+        score: f64,
+        member: &[u8],
+    ) -> ([usize; MAX_LEVEL], [usize; MAX_LEVEL]) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut node = HEAD;
+
+        for level in (0..self.level).rev() {
+            rank[level] = rank.get(level + 1).copied().unwrap_or(0);
+
+            while let Some(next) = self.link(node, level).forward {
+                if !self.nodes[next].precedes(score, member) {
+                    break;
+                }
+                rank[level] += self.link(node, level).span;
+                node = next;
+            }
+
+            update[level] = node;
+        }
+
+        (update, rank)
+    }
+
+    /// Adds a member that isn't in the list yet.
+    fn insert(&mut self, member: Bytes, score: f64) {
+        let (mut update, mut rank) = self.predecessors(score, &member);
+        let level = random_level();
+
+        if level > self.level {
+            for above in self.level..level {
+                rank[above] = 0;
+                update[above] = HEAD;
+                self.link_mut(HEAD, above).span = self.len;
+            }
+            self.level = level;
+        }
+
+        let node = Node::new(member, score, level);
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+
+        for level in 0..level {
+            let previous = update[level];
+            let skipped = rank[0] - rank[level];
+
+            self.link_mut(index, level).forward = self.link(previous, level).forward;
+            self.link_mut(index, level).span = self.link(previous, level).span - skipped;
+            self.link_mut(previous, level).forward = Some(index);
+            self.link_mut(previous, level).span = skipped + 1;
+        }
+
+        // The links above the new node now skip it too
+        for (above, previous) in update.iter().enumerate().take(self.level).skip(level) {
+            self.link_mut(*previous, above).span += 1;
+        }
+
+        self.nodes[index].backward = Some(update[0]).filter(|previous| *previous != HEAD);
+        match self.link(index, 0).forward {
+            Some(next) => self.nodes[next].backward = Some(index),
+            None => self.tail = Some(index),
+        }
+
+        self.len += 1;
+    }
+
+    /// Removes the member, `score` being its current score.
+    fn remove(&mut self, member: &[u8], score: f64) -> bool {
+        let (update, _) = self.predecessors(score, member);
+
+        let Some(index) = self.link(update[0], 0).forward else {
+            return false;
+        };
+
+        let node = &self.nodes[index];
+        if Score(node.score) != Score(score) || node.member[..] != *member {
+            return false;
+        }
+
+        for (level, &previous) in update.iter().enumerate().take(self.level) {
+            if self.link(previous, level).forward == Some(index) {
+                let link = self.link(index, level).clone();
+                self.link_mut(previous, level).forward = link.forward;
+                self.link_mut(previous, level).span += link.span;
+            }
+            self.link_mut(previous, level).span -= 1;
+        }
+
+        let backward = self.nodes[index].backward;
+        match self.link(index, 0).forward {
+            Some(next) => self.nodes[next].backward = backward,
+            None => self.tail = backward,
+        }
+
+        while self.level > 1 && self.link(HEAD, self.level - 1).forward.is_none() {
+            self.level -= 1;
+        }
+
+        // Drops the member, the slot is reused by the next insert
+        self.nodes[index] = Node::new(Bytes::new(), 0.0, 0);
+        self.free.push(index);
+        self.len -= 1;
+        true
+    }
+
+    // Number of nodes `before` holds for, it must hold for a prefix of the
+    // list
+    fn position<F>(&self, before: F) -> usize
+    where
+        F: Fn(&Node) -> bool,
+    {
+        let mut node = HEAD;
+        let mut rank = 0;
+
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.link(node, level).forward {
+                if !before(&self.nodes[next]) {
+                    break;
+                }
+                rank += self.link(node, level).span;
+                node = next;
+            }
+        }
+
+        rank
+    }
+
+    // The node at the rank, starting from 0
+    fn node_at(&self, rank: usize) -> Option<usize> {
+        if rank >= self.len {
+            return None;
+        }
+
+        let mut node = HEAD;
+        let mut traversed = 0;
+
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.link(node, level).forward {
+                if traversed + self.link(node, level).span > rank + 1 {
+                    break;
+                }
+                traversed += self.link(node, level).span;
+                node = next;
+            }
+
+            if traversed == rank + 1 {
+                return Some(node);
+            }
+        }
+
+        None
+    }
+
+    // Members from rank `start` to `end` excluded
+    fn range(&self, start: usize, end: usize) -> Iter<'_> {
+        let end = end.min(self.len);
+        let start = start.min(end);
+
+        Iter {
+            list: self,
+            front: self.node_at(start),
+            back: end.checked_sub(1).and_then(|last| self.node_at(last)),
+            start,
+            end,
+        }
+    }
+}
+
+/// Members of a sorted set in order. Skipping members jumps to their rank
+/// instead of walking them.
+pub struct Iter<'a> {
+    list: &'a SkipList,
+    front: Option<usize>,
+    back: Option<usize>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> Iter<'a> {
+    fn entry(&self, index: usize) -> (&'a Bytes, f64) {
+        let node = &self.list.nodes[index];
+        (&node.member, node.score)
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a Bytes, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+
+        let index = self.front?;
+        self.front = self.list.link(index, 0).forward;
+        self.start += 1;
+        Some(self.entry(index))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n >= self.len() {
+            self.start = self.end;
+            return None;
+        }
+
+        if n > 0 {
+            self.start += n;
+            self.front = self.list.node_at(self.start);
+        }
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+
+    fn count(self) -> usize {
+        self.len()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+
+        let index = self.back?;
+        self.back = self.list.nodes[index].backward;
+        self.end -= 1;
+        Some(self.entry(index))
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        if n >= self.len() {
+            self.end = self.start;
+            return None;
+        }
+
+        if n > 0 {
+            self.end -= n;
+            self.back = self.list.node_at(self.end - 1);
+        }
+        self.next_back()
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// Members ordered by score, then by member for equal scores like Redis,
+/// with a member to score index for lookups.
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    scores: HashMap<Bytes, f64>,
+    ordered: SkipList,
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Adds the member or updates its score. Returns true if it was added.
+    pub fn insert(&mut self, member: Bytes, score: f64) -> bool {
+        // -0 and 0 are the same score
+        let score = score + 0.0;
+
+        match self.scores.insert(member.clone(), score) {
+            Some(previous) => {
+                self.ordered.remove(&member, previous);
+                self.ordered.insert(member, score);
+                false
+            }
+            None => {
+                self.ordered.insert(member, score);
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let (member, score) = self.scores.remove_entry(member)?;
+        self.ordered.remove(&member, score);
+        Some(score)
+    }
+
+    /// Position starting from the lowest score.
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.ordered.position(|node| node.precedes(score, member)))
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        self.ordered.range(0, self.len())
+    }
+
+    /// Members with a score within `min` and `max`, lowest first.
+    pub fn range_by_score(&self, min: Bound<f64>, max: Bound<f64>) -> Iter<'_> {
+        let below = |node: &Node, bound: Bound<f64>| match bound {
+            Bound::Included(score) => Score(node.score) < Score(score + 0.0),
+            Bound::Excluded(score) => Score(node.score) <= Score(score + 0.0),
+            Bound::Unbounded => false,
+        };
+
+        let within = |node: &Node, bound: Bound<f64>| match bound {
+            Bound::Included(score) => Score(node.score) <= Score(score + 0.0),
+            Bound::Excluded(score) => Score(node.score) < Score(score + 0.0),
+            Bound::Unbounded => true,
+        };
+
+        let start = self.ordered.position(|node| below(node, min));
+        let end = self.ordered.position(|node| within(node, max));
+        self.ordered.range(start, end)
+    }
+
+    /// Members within `min` and `max` by lexicographical order. Like Redis
+    /// this is only meaningful when every member has the same score.
+    pub fn range_by_lex(&self, min: LexBound, max: LexBound) -> Iter<'_> {
+        let start = self.ordered.position(|node| !min.is_below(&node.member));
+        let end = self.ordered.position(|node| max.is_above(&node.member));
+        self.ordered.range(start, end)
+    }
+
+    pub fn pop_first(&mut self) -> Option<(Bytes, f64)> {
+        let (member, _) = self.iter().next()?;
+        let member = member.clone();
+        self.remove(&member).map(|score| (member, score))
+    }
+
+    pub fn pop_last(&mut self) -> Option<(Bytes, f64)> {
+        let (member, _) = self.iter().next_back()?;
+        let member = member.clone();
+        self.remove(&member).map(|score| (member, score))
+    }
+}
@@ -0,0 +1,268 @@
+use super::utils::{
+    restart_server, send_command, spawn_loading_server, spawn_server, start_server,
+    stop_server, wait_for_loading,
+};
+use redis::{Client, Commands, Connection};
+use sider::aof::get_aof_file;
+use sider::resp::encode_command;
+use sider::snapshot::get_snapshot_file;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+
+fn connect() -> Connection {
+    let client = Client::open("redis://127.0.0.1:6379/").expect("Failed to connect to Redis");
+    client
+        .get_connection()
+        .expect("Failed to get Redis connection")
+}
+
+#[test]
+fn test_aof_restores_values_with_spaces() {
+    let mut server = start_server();
+
+    let mut conn = connect();
+    let value = "hello world\r\nsecond line";
+    let _: () = conn.set("aof:greeting", value).unwrap();
+    let _: () = conn.hset("aof:user", "full name", "Ada Lovelace").unwrap();
+    send_command("RPUSH aof:done last");
+
+    let mut server = restart_server(&mut server);
+
+    // The AOF is replayed in the background
+    for _ in 0..20 {
+        if send_command("LRANGE aof:done 0 -1") != "(empty array)" {
+            break;
+        }
+        sleep(Duration::from_millis(250));
+    }
+
+    let mut conn = connect();
+    let restored: String = conn.get("aof:greeting").unwrap();
+    assert_eq!(restored, value);
+    let restored: String = conn.hget("aof:user", "full name").unwrap();
+    assert_eq!(restored, "Ada Lovelace");
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_appendfsync_always() {
+    let mut server = start_server();
+
+    let response = send_command("CONFIG SET appendfsync sometimes");
+    assert!(response.contains("Invalid argument"));
+
+    let response = send_command("CONFIG SET appendfsync always");
+    assert_eq!(response, "OK");
+    let response = send_command("CONFIG GET appendfsync");
+    assert!(response.contains("always"));
+
+    // Replied once on disk, the server can be killed right away
+    for i in 0..10 {
+        send_command(&format!("RPUSH aof:synced {i}"));
+    }
+
+    let mut server = restart_server(&mut server);
+
+    let mut restored = String::new();
+    for _ in 0..20 {
+        restored = send_command("LRANGE aof:synced 0 -1");
+        if restored != "(empty array)" {
+            break;
+        }
+        sleep(Duration::from_millis(250));
+    }
+    assert_eq!(
+        restored,
+        r#"["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"]"#
+    );
+
+    stop_server(&mut server);
+}
+
+fn aof_rewrites() -> u64 {
+    send_command("INFO persistence")
+        .lines()
+        .find_map(|line| line.strip_prefix("aof_rewrites:"))
+        .and_then(|value| value.parse().ok())
+        .unwrap()
+}
+
+#[test]
+fn test_bgrewriteaof() {
+    let mut server = start_server();
+
+    send_command("RPUSH aof:list a b c");
+    send_command("HSET aof:hash field value");
+    send_command("EXPIRE aof:hash 1000");
+
+    let response = send_command("BGREWRITEAOF");
+    assert!(response.contains("rewriting scheduled"));
+
+    for _ in 0..20 {
+        if aof_rewrites() > 0 {
+            break;
+        }
+        sleep(Duration::from_millis(250));
+    }
+    assert_eq!(aof_rewrites(), 1);
+
+    // Appended to the rewritten file
+    send_command("RPUSH aof:list d");
+
+    let mut server = restart_server(&mut server);
+
+    let mut restored = String::new();
+    for _ in 0..20 {
+        restored = send_command("LRANGE aof:list 0 -1");
+        if restored != "(empty array)" {
+            break;
+        }
+        sleep(Duration::from_millis(250));
+    }
+    assert_eq!(restored, r#"["a", "b", "c", "d"]"#);
+
+    let ttl = send_command("TTL aof:hash");
+    assert!(ttl.starts_with("(integer) 9"), "{ttl}");
+
+    stop_server(&mut server);
+}
+
+// What a crash in the middle of writing a command leaves
+fn append_torn_command() {
+    let mut aof = OpenOptions::new()
+        .append(true)
+        .open(get_aof_file())
+        .unwrap();
+    aof.write_all(b"*3\r\n$5\r\nRPUSH\r\n$8\r\naof:torn\r\n$1\r\n")
+        .unwrap();
+}
+
+fn check_aof(args: &[&str]) -> bool {
+    Command::new(env!("CARGO_BIN_EXE_sider-check-aof"))
+        .args(args)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap()
+        .success()
+}
+
+fn wait_for_list(key: &str) -> String {
+    let mut restored = String::new();
+    for _ in 0..20 {
+        restored = send_command(&format!("LRANGE {key} 0 -1"));
+        if restored != "(empty array)" {
+            break;
+        }
+        sleep(Duration::from_millis(250));
+    }
+    restored
+}
+
+#[test]
+fn test_torn_aof_is_truncated_on_startup() {
+    let mut server = start_server();
+
+    let response = send_command("CONFIG GET aof-load-truncated");
+    assert!(response.contains("yes"), "{response}");
+
+    send_command("RPUSH aof:torn a b");
+    stop_server(&mut server);
+    append_torn_command();
+
+    let mut server = spawn_server();
+    assert_eq!(wait_for_list("aof:torn"), r#"["a", "b"]"#);
+    stop_server(&mut server);
+
+    // Dropped from the file too
+    assert!(check_aof(&[]));
+}
+
+#[test]
+fn test_torn_aof_refused_and_fixed() {
+    let mut server = start_server();
+
+    send_command("RPUSH aof:torn a b");
+    stop_server(&mut server);
+    append_torn_command();
+
+    let mut refused = Command::new(env!("CARGO_BIN_EXE_sider"))
+        .args(["--aof-load-truncated", "no"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut status = None;
+    for _ in 0..20 {
+        status = refused.try_wait().unwrap();
+        if status.is_some() {
+            break;
+        }
+        sleep(Duration::from_millis(250));
+    }
+    if status.is_none() {
+        stop_server(&mut refused);
+    }
+    assert!(status.is_some_and(|status| !status.success()));
+
+    assert!(!check_aof(&[]));
+    assert!(check_aof(&["--fix"]));
+    assert!(check_aof(&[]));
+
+    let mut server = spawn_server();
+    assert_eq!(wait_for_list("aof:torn"), r#"["a", "b"]"#);
+
+    stop_server(&mut server);
+}
+
+// Enough for the restore to still run once clients are let in
+const LOADING_COMMANDS: usize = 300_000;
+
+#[test]
+fn test_commands_refused_while_loading() {
+    let mut server = start_server();
+    stop_server(&mut server);
+
+    let mut aof = Vec::new();
+    for i in 0..LOADING_COMMANDS {
+        encode_command(&mut aof, &["RPUSH", "aof:loading", &i.to_string()]);
+    }
+    fs::write(get_aof_file(), &aof).unwrap();
+    let _ = fs::remove_file(get_snapshot_file());
+
+    let mut server = spawn_loading_server();
+
+    assert_eq!(send_command("PING"), "PONG");
+    let hello = send_command("HELLO 2");
+    assert!(hello.contains("proto"), "{hello}");
+    let response = send_command("LRANGE aof:loading 0 0");
+    assert!(response.starts_with("LOADING"), "{response}");
+
+    let info = send_command("INFO persistence");
+    assert!(info.contains("loading:1"), "{info}");
+    assert!(
+        info.contains(&format!("loading_total_bytes:{}", aof.len())),
+        "{info}"
+    );
+    assert!(info.contains("loading_eta_seconds:"), "{info}");
+
+    wait_for_loading();
+    let last = format!("LRANGE aof:loading {} -1", LOADING_COMMANDS - 1);
+    let expected = format!(r#"["{}"]"#, LOADING_COMMANDS - 1);
+    assert_eq!(send_command(&last), expected);
+
+    // Replayed commands aren't logged again, the list isn't doubled on the
+    // next restart
+    assert_eq!(
+        fs::metadata(get_aof_file()).unwrap().len(),
+        aof.len() as u64
+    );
+
+    let mut server = restart_server(&mut server);
+    assert_eq!(send_command(&last), expected);
+
+    stop_server(&mut server);
+}
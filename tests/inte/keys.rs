@@ -23,6 +23,32 @@ fn test_set_get() {
     stop_server(&mut server);
 }
 
+#[test]
+fn test_set_lock() {
+    let mut server = start_server();
+
+    let response = send_command("SET lock token1 NX PX 30000");
+    assert_eq!(response, "OK");
+
+    // Someone else holds the lock
+    let response = send_command("SET lock token2 NX PX 30000");
+    assert_eq!(response, "(nil)");
+
+    let response = send_command("TTL lock");
+    assert_eq!(response, "(integer) 30");
+
+    let response = send_command("SET lock token3 XX GET KEEPTTL");
+    assert_eq!(response, "token1");
+
+    let response = send_command("TTL lock");
+    assert_eq!(response, "(integer) 30");
+
+    let response = send_command("SET lock token4 EX 10 KEEPTTL");
+    assert_eq!(response, "ERR syntax error");
+
+    stop_server(&mut server);
+}
+
 #[test]
 fn test_delete_key() {
     let mut server = start_server();
@@ -144,21 +170,76 @@ fn test_ttl() {
     stop_server(&mut server);
 }
 
+#[test]
+fn test_pexpire() {
+    let mut server = start_server();
+
+    send_command("SET rate:1 5");
+    let response = send_command("PEXPIRE rate:1 300");
+    assert_eq!(response, "(integer) 1");
+
+    let pttl: i64 = send_command("PTTL rate:1")
+        .trim_start_matches("(integer) ")
+        .parse()
+        .expect("Failed to parse PTTL value");
+    assert!(pttl > 0 && pttl <= 300);
+
+    std::thread::sleep(std::time::Duration::from_millis(400));
+
+    let response = send_command("PTTL rate:1");
+    assert_eq!(response, "(integer) -2");
+
+    let response = send_command("GET rate:1");
+    assert_eq!(response, "(nil)");
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_persist() {
+    let mut server = start_server();
+
+    send_command("RPUSH queue job");
+    send_command("EXPIREAT queue 4102444800");
+
+    let response = send_command("EXPIRETIME queue");
+    assert_eq!(response, "(integer) 4102444800");
+
+    let response = send_command("PERSIST queue");
+    assert_eq!(response, "(integer) 1");
+
+    let response = send_command("TTL queue");
+    assert_eq!(response, "(integer) -1");
+
+    stop_server(&mut server);
+}
+
 #[test]
 fn test_background_delete() {
     let mut server = start_server();
 
+    let expired_keys = || {
+        let response = send_command("INFO stats");
+        response
+            .lines()
+            .find_map(|line| line.strip_prefix("expired_keys:"))
+            .and_then(|count| count.parse::<u64>().ok())
+            .unwrap()
+    };
+    let before = expired_keys();
+
     send_command("SET name Smith");
-    send_command("EXPIRE name 10");
+    send_command("PEXPIRE name 500");
 
     send_command("LPUSH list:expire Alice Bob Charlie");
-    send_command("EXPIRE list:expire 3");
+    send_command("PEXPIRE list:expire 500");
 
     send_command("HSET hash:expire name Smith first_name John age 21");
-    send_command("EXPIRE hash:expire 3");
+    send_command("PEXPIRE hash:expire 500");
 
-    // Background delete occurs every 60 secs
-    std::thread::sleep(std::time::Duration::from_secs(70));
+    // The active expiry deletes them without anyone accessing them
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    assert!(expired_keys() >= before + 3);
 
     let response = send_command("EXISTS name");
     assert!(response.contains("(integer) 0"));
@@ -229,3 +310,23 @@ fn test_incrby() {
 
     stop_server(&mut server);
 }
+
+#[test]
+fn test_large_pipeline() {
+    let mut server = start_server();
+
+    let client = redis::Client::open("redis://127.0.0.1:6379/").expect("Failed to connect");
+    let mut conn = client.get_connection().expect("Failed to get connection");
+
+    // Large enough to span many socket reads on the server side
+    let mut pipe = redis::pipe();
+    for i in 0..10_000 {
+        pipe.cmd("SET").arg(format!("pipe:{}", i)).arg(i).ignore();
+        pipe.cmd("GET").arg(format!("pipe:{}", i));
+    }
+
+    let values: Vec<i64> = pipe.query(&mut conn).expect("Pipeline failed");
+    assert_eq!(values, (0..10_000).collect::<Vec<i64>>());
+
+    stop_server(&mut server);
+}
@@ -1,4 +1,6 @@
-use super::utils::{send_command, start_server, stop_server};
+use super::utils::{connection, send_command, start_server, stop_server};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[test]
 fn test_lpush() {
@@ -109,6 +111,12 @@ fn test_lpop() {
     let response = send_command("LPOP lpoplist");
     assert!(response.contains("(nil)"));
 
+    let response = send_command("LPOP lpoplist 2");
+    assert!(response.contains("(nil)"));
+
+    let response = send_command("EXISTS lpoplist");
+    assert_eq!(response, "(integer) 0");
+
     stop_server(&mut server);
 }
 
@@ -130,3 +138,95 @@ fn test_rpop() {
 
     stop_server(&mut server);
 }
+
+#[test]
+fn test_brpop_waits_for_push() {
+    let mut server = start_server();
+
+    let waiter = thread::spawn(|| {
+        let mut conn = connection();
+        redis::cmd("BRPOP")
+            .arg("jobs:empty")
+            .arg("jobs:queue")
+            .arg(5)
+            .query::<Option<(String, String)>>(&mut conn)
+    });
+
+    // Give the waiter time to block
+    thread::sleep(Duration::from_millis(500));
+    let response = send_command("LPUSH jobs:queue job1");
+    assert_eq!(response, "(integer) 1");
+
+    let popped = waiter.join().unwrap().unwrap();
+    assert_eq!(popped, Some(("jobs:queue".to_string(), "job1".to_string())));
+
+    let response = send_command("EXISTS jobs:queue");
+    assert_eq!(response, "(integer) 0");
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_blpop_timeout() {
+    let mut server = start_server();
+
+    let mut conn = connection();
+    let start = Instant::now();
+    let popped: Option<(String, String)> = redis::cmd("BLPOP")
+        .arg("blpop:missing")
+        .arg(0.3)
+        .query(&mut conn)
+        .unwrap();
+    assert_eq!(popped, None);
+    assert!(start.elapsed() >= Duration::from_millis(300));
+
+    // The connection keeps working after the timeout
+    let pong: String = redis::cmd("PING").query(&mut conn).unwrap();
+    assert_eq!(pong, "PONG");
+
+    // A client that timed out doesn't take elements pushed later
+    send_command("RPUSH blpop:missing a");
+    let response = send_command("LPOP blpop:missing");
+    assert_eq!(response, "a");
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_blmove_waits_for_push() {
+    let mut server = start_server();
+
+    // This client goes away while blocked, it must not consume the element
+    let gone = thread::spawn(|| {
+        let mut conn = connection();
+        conn.set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        let _ = redis::cmd("BLPOP")
+            .arg("blmove:source")
+            .arg(0)
+            .query::<Option<(String, String)>>(&mut conn);
+    });
+    gone.join().unwrap();
+
+    let waiter = thread::spawn(|| {
+        let mut conn = connection();
+        redis::cmd("BLMOVE")
+            .arg("blmove:source")
+            .arg("blmove:destination")
+            .arg("LEFT")
+            .arg("RIGHT")
+            .arg(5)
+            .query::<Option<String>>(&mut conn)
+    });
+
+    thread::sleep(Duration::from_millis(500));
+    send_command("RPUSH blmove:source a");
+
+    let moved = waiter.join().unwrap().unwrap();
+    assert_eq!(moved, Some("a".to_string()));
+
+    let response = send_command("LRANGE blmove:destination 0 -1");
+    assert_eq!(response, "[\"a\"]");
+
+    stop_server(&mut server);
+}
@@ -0,0 +1,67 @@
+use super::utils::{send_command, start_server, stop_server};
+
+fn info_field(section: &str, field: &str) -> u64 {
+    let response = send_command(&format!("INFO {section}"));
+    response
+        .lines()
+        .find_map(|line| line.strip_prefix(&format!("{field}:")))
+        .and_then(|value| value.parse().ok())
+        .unwrap()
+}
+
+#[test]
+fn test_maxmemory_noeviction() {
+    let mut server = start_server();
+
+    send_command("CONFIG SET maxmemory-policy noeviction");
+    let used = info_field("memory", "used_memory");
+    let response = send_command(&format!("CONFIG SET maxmemory {}", used + 1));
+    assert_eq!(response, "OK");
+
+    // Allowed while under the limit, which it goes over
+    send_command("SET oom:first some-value");
+
+    let response = send_command("SET oom:second some-value");
+    assert_eq!(
+        response,
+        "OOM command not allowed when used memory > 'maxmemory'."
+    );
+
+    // Reading and deleting are still fine
+    let response = send_command("GET oom:first");
+    assert_eq!(response, "some-value");
+    let response = send_command("DEL oom:first");
+    assert_eq!(response, "(integer) 1");
+
+    send_command("CONFIG SET maxmemory 0");
+    let response = send_command("SET oom:second some-value");
+    assert_eq!(response, "OK");
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_maxmemory_eviction() {
+    let mut server = start_server();
+
+    let response = send_command("CONFIG SET maxmemory-policy allkeys-lru");
+    assert_eq!(response, "OK");
+
+    let used = info_field("memory", "used_memory");
+    let maxmemory = used + 4096;
+    send_command(&format!("CONFIG SET maxmemory {maxmemory}"));
+
+    let value = "x".repeat(200);
+    for i in 0..100 {
+        let response = send_command(&format!("SET evict:{i} {value}"));
+        assert_eq!(response, "OK");
+    }
+
+    assert!(info_field("stats", "evicted_keys") > 0);
+    // Evicted before each command, the last one may go over
+    assert!(info_field("memory", "used_memory") <= maxmemory + 512);
+
+    send_command("CONFIG SET maxmemory 0");
+
+    stop_server(&mut server);
+}
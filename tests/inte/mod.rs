@@ -1,5 +1,15 @@
+pub mod aof;
 pub mod hsets;
 pub mod keys;
 pub mod lists;
+pub mod memory;
+pub mod notify;
+pub mod pubsub;
+pub mod rdb;
+pub mod resp3;
 pub mod sets;
+pub mod snapshot;
+pub mod streams;
+pub mod transactions;
 pub mod utils;
+pub mod zsets;
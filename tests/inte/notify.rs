@@ -0,0 +1,48 @@
+use super::utils::{send_command, start_server, stop_server};
+use redis::Client;
+use std::time::Duration;
+
+#[test]
+fn test_keyspace_notifications() {
+    let mut server = start_server();
+
+    let response = send_command("CONFIG GET notify-keyspace-events");
+    assert!(response.contains("notify-keyspace-events"));
+
+    let response = send_command("CONFIG SET notify-keyspace-events KEA");
+    assert!(response.contains("OK"));
+
+    let response = send_command("CONFIG GET notify-*");
+    assert!(response.contains("AKE"));
+
+    let client = Client::open("redis://127.0.0.1:6379/").expect("Failed to connect to Redis");
+    let mut conn = client
+        .get_connection()
+        .expect("Failed to get Redis connection");
+    conn.set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+
+    let mut pubsub = conn.as_pubsub();
+    pubsub.psubscribe("__keyspace@0__:user:*").unwrap();
+    pubsub.subscribe("__keyevent@0__:del").unwrap();
+
+    send_command("SET user:1 John");
+    send_command("DEL user:1");
+
+    let message = pubsub.get_message().unwrap();
+    assert_eq!(message.get_channel_name(), "__keyspace@0__:user:1");
+    assert_eq!(message.get_payload::<String>().unwrap(), "set");
+
+    let message = pubsub.get_message().unwrap();
+    assert_eq!(message.get_channel_name(), "__keyspace@0__:user:1");
+    assert_eq!(message.get_payload::<String>().unwrap(), "del");
+
+    let message = pubsub.get_message().unwrap();
+    assert_eq!(message.get_channel_name(), "__keyevent@0__:del");
+    assert_eq!(message.get_payload::<String>().unwrap(), "user:1");
+
+    let response = send_command("CONFIG SET notify-keyspace-events K?");
+    assert!(response.contains("ERR"));
+
+    stop_server(&mut server);
+}
@@ -0,0 +1,60 @@
+use super::utils::{connection, send_command, start_server, stop_server};
+use std::time::Duration;
+
+#[test]
+fn test_subscribe_publish() {
+    let mut server = start_server();
+
+    let mut conn = connection();
+    let mut pubsub = conn.as_pubsub();
+    pubsub.subscribe("news").unwrap();
+    pubsub.psubscribe("sport:*").unwrap();
+
+    let response = send_command("PUBLISH news hello");
+    assert!(response.contains("(integer) 1"));
+
+    let message = pubsub.get_message().unwrap();
+    assert_eq!(message.get_channel_name(), "news");
+    assert_eq!(message.get_payload::<String>().unwrap(), "hello");
+
+    let response = send_command("PUBLISH sport:tennis match");
+    assert!(response.contains("(integer) 1"));
+
+    let message = pubsub.get_message().unwrap();
+    assert_eq!(message.get_pattern::<String>().unwrap(), "sport:*");
+    assert_eq!(message.get_channel_name(), "sport:tennis");
+    assert_eq!(message.get_payload::<String>().unwrap(), "match");
+
+    let response = send_command("PUBSUB NUMPAT");
+    assert!(response.contains("(integer) 1"));
+
+    let response = send_command("PUBSUB CHANNELS");
+    assert!(response.contains("news"));
+
+    drop(pubsub);
+    drop(conn);
+
+    // Give the server a moment to notice the connection is gone
+    std::thread::sleep(Duration::from_millis(200));
+
+    let response = send_command("PUBLISH news hello");
+    assert!(response.contains("(integer) 0"));
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_subscribe_mode_rejects_commands() {
+    let mut server = start_server();
+
+    let mut conn = connection();
+    let _: redis::Value = redis::cmd("SUBSCRIBE")
+        .arg("news")
+        .query(&mut conn)
+        .unwrap();
+    let response: redis::RedisResult<redis::Value> =
+        redis::cmd("GET").arg("key").query(&mut conn);
+    assert!(response.is_err());
+
+    stop_server(&mut server);
+}
@@ -0,0 +1,128 @@
+use super::utils::{
+    restart_server, send_command, spawn_server, start_server, stop_server, wait_for_restore,
+};
+use sider::crc64::crc64;
+use sider::rdb::get_rdb_file;
+use std::fs;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::Duration;
+
+// What Redis writes for a small hash: rdb:hash with f => 5, as a listpack
+fn redis_rdb() -> Vec<u8> {
+    let mut data = b"REDIS0011".to_vec();
+    data.extend_from_slice(&[0xFE, 0x00, 0x10, 0x08]);
+    data.extend_from_slice(b"rdb:hash");
+    data.extend_from_slice(&[0x0C, 0x0C, 0x00, 0x00, 0x00, 0x02, 0x00]);
+    data.extend_from_slice(&[0x81, b'f', 0x02, 0x05, 0x01, 0xFF, 0xFF]);
+    let crc = crc64(0, &data);
+    data.extend_from_slice(&crc.to_le_bytes());
+    data
+}
+
+fn sider_rdb(args: &[&str]) -> bool {
+    Command::new(env!("CARGO_BIN_EXE_sider-rdb"))
+        .args(args)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .expect("Failed to run sider-rdb")
+        .success()
+}
+
+#[test]
+fn test_debug_reload() {
+    let mut server = start_server();
+
+    send_command("RPUSH rdb:list a b c");
+    send_command("SET rdb:string value");
+    send_command("EXPIRE rdb:string 1000");
+
+    // Saved and loaded back
+    let response = send_command("DEBUG RELOAD");
+    assert_eq!(response, "OK");
+    let list = send_command("LRANGE rdb:list 0 -1");
+    assert_eq!(list, r#"["a", "b", "c"]"#);
+    let ttl = send_command("TTL rdb:string");
+    assert!(
+        ttl.starts_with("(integer) 9") || ttl == "(integer) 1000",
+        "{ttl}"
+    );
+
+    fs::write(get_rdb_file(), redis_rdb()).unwrap();
+
+    let response = send_command("DEBUG RELOAD NOSAVE MERGE");
+    assert_eq!(response, "OK");
+    assert_eq!(send_command("HGET rdb:hash f"), "5");
+    assert_eq!(send_command("GET rdb:string"), "value");
+
+    let response = send_command("DEBUG RELOAD NOSAVE");
+    assert_eq!(response, "OK");
+    assert_eq!(send_command("GET rdb:string"), "(nil)");
+
+    let response = send_command("DEBUG RELOAD SOMETIMES");
+    assert!(response.contains("syntax error"), "{response}");
+    let response = send_command("DEBUG SLEEP 0");
+    assert!(response.contains("unknown subcommand"), "{response}");
+
+    // The AOF has the keys loaded
+    let mut server = restart_server(&mut server);
+    for _ in 0..20 {
+        if send_command("HGET rdb:hash f") == "5" {
+            break;
+        }
+        sleep(Duration::from_millis(250));
+    }
+    assert_eq!(send_command("HGET rdb:hash f"), "5");
+    assert_eq!(send_command("LRANGE rdb:list 0 -1"), "(empty array)");
+
+    let _ = fs::remove_file(get_rdb_file());
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_debug_reload_refused_with_streams() {
+    let mut server = start_server();
+
+    send_command("XADD rdb:stream 1-1 field value");
+
+    let response = send_command("DEBUG RELOAD");
+    assert!(response.contains("Streams can't be saved"), "{response}");
+    assert_eq!(send_command("XLEN rdb:stream"), "(integer) 1");
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_sider_rdb_export_and_import() {
+    let mut server = start_server();
+    let rdb = std::env::temp_dir().join("sider-test-export.rdb");
+
+    send_command("RPUSH rdb:exported x y z");
+    assert_eq!(send_command("SAVE"), "OK");
+
+    assert!(sider_rdb(&["export", rdb.to_str().unwrap()]));
+    assert!(fs::read(&rdb).unwrap().starts_with(b"REDIS0009"));
+
+    send_command("DEL rdb:exported");
+
+    // The snapshot misses the DEL
+    assert!(!sider_rdb(&["export", rdb.to_str().unwrap()]));
+    assert!(sider_rdb(&["export", "--force", rdb.to_str().unwrap()]));
+
+    stop_server(&mut server);
+
+    // The AOF isn't empty, it has the writes since the server started
+    assert!(!sider_rdb(&["import", rdb.to_str().unwrap()]));
+    assert!(sider_rdb(&["import", "--force", rdb.to_str().unwrap()]));
+
+    let mut server = spawn_server();
+    assert_eq!(wait_for_restore("rdb:exported"), r#"["x", "y", "z"]"#);
+
+    // Still there once restarted from what the server saved since
+    let mut server = restart_server(&mut server);
+    assert_eq!(wait_for_restore("rdb:exported"), r#"["x", "y", "z"]"#);
+
+    let _ = fs::remove_file(&rdb);
+    stop_server(&mut server);
+}
@@ -0,0 +1,55 @@
+use super::utils::{send_command, start_server, stop_server};
+use redis::{Client, Value};
+
+fn resp3_connection() -> redis::Connection {
+    let client = Client::open("redis://127.0.0.1:6379/?protocol=resp3")
+        .expect("Failed to connect to Redis");
+    client
+        .get_connection()
+        .expect("Failed to get Redis connection")
+}
+
+#[test]
+fn test_hgetall_returns_map() {
+    let mut server = start_server();
+
+    send_command("HSET resp3hash name Smith");
+
+    let mut conn = resp3_connection();
+    let response: Value = redis::cmd("HGETALL")
+        .arg("resp3hash")
+        .query(&mut conn)
+        .unwrap();
+
+    assert_eq!(
+        response,
+        Value::Map(vec![(
+            Value::BulkString(b"name".to_vec()),
+            Value::BulkString(b"Smith".to_vec())
+        )])
+    );
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_smembers_returns_set() {
+    let mut server = start_server();
+
+    send_command("SADD resp3set Hello");
+
+    let mut conn = resp3_connection();
+    let response: Value = redis::cmd("SMEMBERS")
+        .arg("resp3set")
+        .query(&mut conn)
+        .unwrap();
+    assert_eq!(
+        response,
+        Value::Set(vec![Value::BulkString(b"Hello".to_vec())])
+    );
+
+    let response: Value = redis::cmd("GET").arg("missing").query(&mut conn).unwrap();
+    assert_eq!(response, Value::Nil);
+
+    stop_server(&mut server);
+}
@@ -0,0 +1,81 @@
+use super::utils::{
+    connection, restart_server, send_command, start_server, stop_server, wait_for_restore,
+};
+use redis::{RedisResult, Value};
+use std::thread::sleep;
+use std::time::Duration;
+
+fn persistence_field(field: &str) -> String {
+    send_command("INFO persistence")
+        .lines()
+        .find_map(|line| line.strip_prefix(&format!("{field}:")).map(String::from))
+        .unwrap()
+}
+
+#[test]
+fn test_save_and_restore_with_aof_tail() {
+    let mut server = start_server();
+
+    send_command("RPUSH snapshot:list a b");
+    send_command("HSET snapshot:hash field value");
+    send_command("EXPIRE snapshot:hash 1000");
+    assert_ne!(persistence_field("rdb_changes_since_last_save"), "0");
+
+    let response = send_command("SAVE");
+    assert_eq!(response, "OK");
+    assert_eq!(persistence_field("rdb_changes_since_last_save"), "0");
+    assert_eq!(persistence_field("rdb_last_bgsave_status"), "ok");
+
+    let lastsave = send_command("LASTSAVE");
+    assert!(lastsave.starts_with("(integer) "), "{lastsave}");
+    assert_eq!(
+        lastsave.trim_start_matches("(integer) "),
+        persistence_field("rdb_last_save_time")
+    );
+
+    let mut conn = connection();
+    let _: () = redis::cmd("MULTI").query(&mut conn).unwrap();
+    let _: () = redis::cmd("SAVE").query(&mut conn).unwrap();
+    let response: RedisResult<Value> = redis::cmd("EXEC").query(&mut conn);
+    assert!(response.is_err());
+
+    // Only in the AOF, replayed after loading the snapshot
+    send_command("RPUSH snapshot:list c");
+
+    let mut server = restart_server(&mut server);
+
+    assert_eq!(wait_for_restore("snapshot:list"), r#"["a", "b", "c"]"#);
+    let value = send_command("HGET snapshot:hash field");
+    assert_eq!(value, "value");
+    let ttl = send_command("TTL snapshot:hash");
+    assert!(ttl.starts_with("(integer) 9"), "{ttl}");
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_bgsave() {
+    let mut server = start_server();
+
+    send_command("RPUSH snapshot:queue x y z");
+
+    let response = send_command("BGSAVE");
+    assert!(response.contains("saving scheduled"), "{response}");
+    let response = send_command("BGSAVE SOMETIMES");
+    assert!(response.contains("syntax error"), "{response}");
+
+    for _ in 0..20 {
+        if persistence_field("rdb_changes_since_last_save") == "0" {
+            break;
+        }
+        sleep(Duration::from_millis(250));
+    }
+    assert_eq!(persistence_field("rdb_changes_since_last_save"), "0");
+    assert_eq!(persistence_field("rdb_bgsave_in_progress"), "0");
+
+    let mut server = restart_server(&mut server);
+
+    assert_eq!(wait_for_restore("snapshot:queue"), r#"["x", "y", "z"]"#);
+
+    stop_server(&mut server);
+}
@@ -0,0 +1,158 @@
+use super::utils::{connection, send_command, start_server, stop_server};
+use std::thread;
+use std::time::Duration;
+
+type Entries = Vec<(String, Vec<String>)>;
+type StreamsReply = Option<Vec<(String, Entries)>>;
+
+#[test]
+fn test_xadd_xrange() {
+    let mut server = start_server();
+
+    let response = send_command("XADD events 1-1 type login user alice");
+    assert_eq!(response, "1-1");
+
+    let response = send_command("XADD events 1-* type logout user alice");
+    assert_eq!(response, "1-2");
+
+    let response = send_command("XADD events 1-0 type login user bob");
+    assert_eq!(
+        response,
+        "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+    );
+
+    let response = send_command("XLEN events");
+    assert_eq!(response, "(integer) 2");
+
+    let mut conn = connection();
+    let entries: Entries = redis::cmd("XRANGE")
+        .arg("events")
+        .arg("(1-1")
+        .arg("+")
+        .query(&mut conn)
+        .unwrap();
+    assert_eq!(
+        entries,
+        vec![(
+            "1-2".to_string(),
+            vec![
+                "type".to_string(),
+                "logout".to_string(),
+                "user".to_string(),
+                "alice".to_string()
+            ]
+        )]
+    );
+
+    let response = send_command("XTRIM events MAXLEN 0");
+    assert_eq!(response, "(integer) 2");
+
+    // An emptied stream is kept, unlike lists
+    let response = send_command("EXISTS events");
+    assert_eq!(response, "(integer) 1");
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_xread_waits_for_xadd() {
+    let mut server = start_server();
+
+    let waiter = thread::spawn(|| {
+        let mut conn = connection();
+        redis::cmd("XREAD")
+            .arg("BLOCK")
+            .arg(5000)
+            .arg("STREAMS")
+            .arg("xread:stream")
+            .arg("$")
+            .query::<StreamsReply>(&mut conn)
+    });
+
+    // Give the waiter time to block
+    thread::sleep(Duration::from_millis(500));
+    let response = send_command("XADD xread:stream 7-0 field value");
+    assert_eq!(response, "7-0");
+
+    let read = waiter.join().unwrap().unwrap();
+    assert_eq!(
+        read,
+        Some(vec![(
+            "xread:stream".to_string(),
+            vec![(
+                "7-0".to_string(),
+                vec!["field".to_string(), "value".to_string()]
+            )]
+        )])
+    );
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_consumer_group() {
+    let mut server = start_server();
+
+    let response = send_command("XGROUP CREATE jobs workers $ MKSTREAM");
+    assert_eq!(response, "OK");
+
+    send_command("XADD jobs 1-0 job a");
+    send_command("XADD jobs 2-0 job b");
+
+    let mut conn = connection();
+    let read = |conn: &mut redis::Connection, consumer: &str| {
+        redis::cmd("XREADGROUP")
+            .arg("GROUP")
+            .arg("workers")
+            .arg(consumer)
+            .arg("COUNT")
+            .arg(1)
+            .arg("STREAMS")
+            .arg("jobs")
+            .arg(">")
+            .query::<StreamsReply>(conn)
+            .unwrap()
+    };
+
+    // Each entry goes to a single consumer of the group
+    let first = read(&mut conn, "alice").unwrap();
+    assert_eq!(first[0].1[0].0, "1-0");
+    let second = read(&mut conn, "bob").unwrap();
+    assert_eq!(second[0].1[0].0, "2-0");
+    assert_eq!(read(&mut conn, "bob"), None);
+
+    let response = send_command("XACK jobs workers 1-0");
+    assert_eq!(response, "(integer) 1");
+
+    // bob went away, alice takes over what it didn't acknowledge
+    let claimed: Vec<String> = redis::cmd("XCLAIM")
+        .arg("jobs")
+        .arg("workers")
+        .arg("alice")
+        .arg(0)
+        .arg("2-0")
+        .arg("JUSTID")
+        .query(&mut conn)
+        .unwrap();
+    assert_eq!(claimed, vec!["2-0".to_string()]);
+
+    let (count, first, last, consumers): (u64, String, String, Vec<(String, String)>) =
+        redis::cmd("XPENDING")
+            .arg("jobs")
+            .arg("workers")
+            .query(&mut conn)
+            .unwrap();
+    assert_eq!(
+        (count, first, last),
+        (1, "2-0".to_string(), "2-0".to_string())
+    );
+    assert_eq!(consumers, vec![("alice".to_string(), "1".to_string())]);
+
+    let response = send_command("XREADGROUP GROUP missing alice STREAMS jobs >");
+    assert_eq!(
+        response,
+        "NOGROUP No such key 'jobs' or consumer group 'missing'"
+    );
+
+    stop_server(&mut server);
+}
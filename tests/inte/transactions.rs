@@ -0,0 +1,137 @@
+use super::utils::{connection, send_command, start_server, stop_server};
+use redis::{RedisResult, Value};
+
+#[test]
+fn test_multi_exec() {
+    let mut server = start_server();
+
+    let mut conn = connection();
+    let (set, incr, get): (String, i64, String) = redis::pipe()
+        .atomic()
+        .cmd("SET")
+        .arg("counter")
+        .arg("10")
+        .cmd("INCR")
+        .arg("counter")
+        .cmd("GET")
+        .arg("counter")
+        .query(&mut conn)
+        .unwrap();
+
+    assert_eq!(set, "OK");
+    assert_eq!(incr, 11);
+    assert_eq!(get, "11");
+
+    let response = send_command("GET counter");
+    assert!(response.contains("11"));
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_exec_keeps_runtime_errors() {
+    let mut server = start_server();
+
+    send_command("LPUSH mylist a");
+
+    let mut conn = connection();
+    let _: () = redis::cmd("MULTI").query(&mut conn).unwrap();
+    let _: () = redis::cmd("INCR").arg("mylist").query(&mut conn).unwrap();
+    let _: () = redis::cmd("SET")
+        .arg("after")
+        .arg("1")
+        .query(&mut conn)
+        .unwrap();
+    let response: RedisResult<Value> = redis::cmd("EXEC").query(&mut conn);
+
+    // The client surfaces the error of the failing INCR
+    assert_eq!(response.unwrap_err().code(), Some("WRONGTYPE"));
+
+    // But it doesn't prevent the SET from running
+
+    let response = send_command("GET after");
+    assert!(response.contains("1"));
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_exec_abort() {
+    let mut server = start_server();
+
+    let mut conn = connection();
+    let _: () = redis::cmd("MULTI").query(&mut conn).unwrap();
+    let _: () = redis::cmd("SET")
+        .arg("key")
+        .arg("value")
+        .query(&mut conn)
+        .unwrap();
+    let response: RedisResult<()> = redis::cmd("GET").query(&mut conn);
+    assert!(response.is_err());
+
+    let response: RedisResult<Value> = redis::cmd("EXEC").query(&mut conn);
+    let error = response.unwrap_err();
+    assert_eq!(error.code(), Some("EXECABORT"));
+
+    let response = send_command("EXISTS key");
+    assert!(response.contains("(integer) 0"));
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_discard() {
+    let mut server = start_server();
+
+    let mut conn = connection();
+    let _: () = redis::cmd("MULTI").query(&mut conn).unwrap();
+    let _: () = redis::cmd("SET")
+        .arg("key")
+        .arg("value")
+        .query(&mut conn)
+        .unwrap();
+    let _: () = redis::cmd("DISCARD").query(&mut conn).unwrap();
+
+    let response = send_command("EXISTS key");
+    assert!(response.contains("(integer) 0"));
+
+    let response = send_command("EXEC");
+    assert!(response.contains("ERR EXEC without MULTI"));
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_watch() {
+    let mut server = start_server();
+
+    send_command("SET stock 10");
+
+    let mut conn = connection();
+    let _: () = redis::cmd("WATCH").arg("stock").query(&mut conn).unwrap();
+
+    // Another client changes the key between WATCH and EXEC
+    send_command("DECR stock");
+
+    let response: Option<(i64,)> = redis::pipe()
+        .atomic()
+        .cmd("DECR")
+        .arg("stock")
+        .query(&mut conn)
+        .unwrap();
+    assert_eq!(response, None);
+
+    let response = send_command("GET stock");
+    assert!(response.contains("9"));
+
+    // EXEC dropped the watched keys, the next transaction goes through
+    let response: Option<(i64,)> = redis::pipe()
+        .atomic()
+        .cmd("DECR")
+        .arg("stock")
+        .query(&mut conn)
+        .unwrap();
+    assert_eq!(response, Some((8,)));
+
+    stop_server(&mut server);
+}
@@ -5,25 +5,61 @@ use std::thread::sleep;
 use std::time::Duration;
 
 pub fn start_server() -> Child {
-    let child = Command::new("cargo")
-        .args(["run"])
+    let child = spawn_server();
+    send_command("FLUSHDB");
+    child
+}
+
+/// Restarts the server without flushing, the dataset is restored from disk.
+pub fn restart_server(server: &mut Child) -> Child {
+    stop_server(server);
+    spawn_server()
+}
+
+/// Starts the server on the data left on disk, without flushing it, and
+/// waits until it is restored.
+pub fn spawn_server() -> Child {
+    let child = spawn_loading_server();
+    wait_for_loading();
+    child
+}
+
+/// Starts the server on the data left on disk and returns as soon as it
+/// accepts connections, it may still be loading the dataset.
+pub fn spawn_loading_server() -> Child {
+    // Run the binary directly, killing `cargo run` would leave the server
+    // running and the next test would talk to a stale instance
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sider"))
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
         .spawn()
         .expect("Failed to start Redis-like server");
 
     // Actively check if the server is ready before continuing
-    for _ in 0..20 {
+    for _ in 0..200 {
         if TcpStream::connect("127.0.0.1:6379").is_ok() {
-            send_command("FLUSHDB");
             return child;
         }
-        sleep(Duration::from_secs(1)); // Wait before retrying
+        sleep(Duration::from_millis(100)); // Wait before retrying
     }
 
+    stop_server(&mut child);
     panic!("Server did not start in time");
 }
 
+/// Waits until the server restored the dataset, commands get `-LOADING`
+/// until then.
+pub fn wait_for_loading() {
+    for _ in 0..600 {
+        if send_command("INFO persistence").contains("loading:0") {
+            return;
+        }
+        sleep(Duration::from_millis(100));
+    }
+
+    panic!("Server did not load the dataset in time");
+}
+
 pub fn stop_server(server: &mut Child) {
     if let Err(e) = server.kill() {
         eprintln!("Warning: Failed to kill server: {:?}", e);
@@ -32,6 +68,31 @@ pub fn stop_server(server: &mut Child) {
     sleep(Duration::from_secs(1)); // Give OS time to release the port
 }
 
+/// A connection for commands `send_command` can't render, reads give up
+/// after a few seconds instead of hanging the test.
+pub fn connection() -> redis::Connection {
+    let client = Client::open("redis://127.0.0.1:6379/").expect("Failed to connect to Redis");
+    let conn = client
+        .get_connection()
+        .expect("Failed to get Redis connection");
+    conn.set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    conn
+}
+
+/// Waits until the list at `key` is restored and returns it.
+pub fn wait_for_restore(key: &str) -> String {
+    let mut restored = String::new();
+    for _ in 0..20 {
+        restored = send_command(&format!("LRANGE {key} 0 -1"));
+        if restored != "(empty array)" {
+            break;
+        }
+        sleep(Duration::from_millis(250));
+    }
+    restored
+}
+
 pub fn send_command(command: &str) -> String {
     let client = Client::open("redis://127.0.0.1:6379/").expect("Failed to connect to Redis");
     let mut conn = client
@@ -50,6 +111,7 @@ pub fn send_command(command: &str) -> String {
         Ok(Value::Okay) => "OK".to_string(), // Handle OK response
         Ok(Value::Int(int_value)) => format!("(integer) {}", int_value), // Handle integers (e.g., LPUSH, LLEN)
         Ok(Value::BulkString(bytes)) => String::from_utf8_lossy(&bytes).to_string(), // Handle bulk string responses
+        Ok(Value::Array(items)) if items.is_empty() => "(empty array)".to_string(),
         Ok(Value::Array(items)) => {
             let strings: Vec<String> = items
                 .into_iter()
@@ -59,7 +121,11 @@ pub fn send_command(command: &str) -> String {
         }
         Ok(Value::SimpleString(s)) => s, // Handle simple string responses
         Ok(Value::Nil) => "(nil)".to_string(), // Handle nil responses
-        Err(e) => format!("{}", e),      // Handle errors
+        Err(e) => match (e.code(), e.detail()) {
+            // Render server errors like redis-cli does, e.g. "WRONGTYPE Operation..."
+            (Some(code), Some(detail)) => format!("{} {}", code, detail),
+            _ => format!("{}", e), // Handle errors
+        },
         _ => "-ERR Unexpected response\r\n".to_string(),
     }
 }
@@ -0,0 +1,58 @@
+use super::utils::{send_command, start_server, stop_server};
+
+#[test]
+fn test_zadd_zrange() {
+    let mut server = start_server();
+
+    let response = send_command("ZADD leaderboard 10 alice 20 bob 15 carol");
+    assert_eq!(response, "(integer) 3");
+
+    let response = send_command("ZADD leaderboard XX CH 25 alice 5 dave");
+    assert_eq!(response, "(integer) 1");
+
+    let response = send_command("ZRANGE leaderboard 0 -1 WITHSCORES");
+    assert_eq!(
+        response,
+        "[\"carol\", \"15\", \"bob\", \"20\", \"alice\", \"25\"]"
+    );
+
+    let response = send_command("ZRANGE leaderboard +inf (15 BYSCORE REV LIMIT 0 1");
+    assert_eq!(response, "[\"alice\"]");
+
+    let response = send_command("ZREVRANK leaderboard carol");
+    assert_eq!(response, "(integer) 2");
+
+    let response = send_command("ZINCRBY leaderboard 2.5 carol");
+    assert_eq!(response, "17.5");
+
+    let response = send_command("ZCOUNT leaderboard 17.5 20");
+    assert_eq!(response, "(integer) 2");
+
+    let response = send_command("GET leaderboard");
+    assert!(
+        response.contains("WRONGTYPE Operation against a key holding the wrong kind of value")
+    );
+
+    stop_server(&mut server);
+}
+
+#[test]
+fn test_delayed_jobs() {
+    let mut server = start_server();
+
+    send_command("ZADD jobs 300 job3 100 job1 200 job2");
+
+    let response = send_command("ZRANGEBYSCORE jobs -inf 200");
+    assert_eq!(response, "[\"job1\", \"job2\"]");
+
+    let response = send_command("ZREMRANGEBYSCORE jobs -inf 200");
+    assert_eq!(response, "(integer) 2");
+
+    let response = send_command("ZPOPMIN jobs");
+    assert_eq!(response, "[\"job3\", \"300\"]");
+
+    let response = send_command("EXISTS jobs");
+    assert_eq!(response, "(integer) 0");
+
+    stop_server(&mut server);
+}
@@ -0,0 +1,205 @@
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use indexmap::IndexMap;
+    use sider::aof::{format_aof_command, AppendFsync};
+    use sider::database::{
+        check_aof, is_legacy_aof, parse_legacy_aof, AofCheck, LoadingProgress,
+    };
+    use sider::resp::{encode_command, parse_resp_command};
+    use sider::types::{Command, CommandArgs, CommandType};
+
+    #[test]
+    fn test_format_aof_command() {
+        let command = Command {
+            command_type: CommandType::SET,
+            args: CommandArgs::KeyWithValue {
+                key: Bytes::from("name"),
+                value: Bytes::from("Alice"),
+            },
+        };
+
+        assert_eq!(
+            format_aof_command(&command),
+            b"*3\r\n$3\r\nSET\r\n$4\r\nname\r\n$5\r\nAlice\r\n"
+        );
+    }
+
+    #[test]
+    fn test_aof_command_round_trip() {
+        let mut fields = IndexMap::new();
+        fields.insert(Bytes::from("bio"), Bytes::from("line one\r\nline two"));
+        fields.insert(Bytes::from("empty"), Bytes::new());
+
+        let commands = [
+            Command {
+                command_type: CommandType::SET,
+                args: CommandArgs::KeyWithValues {
+                    key: Bytes::from("greeting"),
+                    values: vec![Bytes::from("hello world"), "PX".into(), "1000".into()],
+                },
+            },
+            Command {
+                command_type: CommandType::HSET,
+                args: CommandArgs::HashFields {
+                    key: Bytes::from("user 1"),
+                    fields,
+                },
+            },
+        ];
+
+        let mut content = Vec::new();
+        for command in &commands {
+            content.extend_from_slice(&format_aof_command(command));
+        }
+
+        let parsed = parse_resp_command(&content).unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                vec![
+                    Bytes::from("SET"),
+                    "greeting".into(),
+                    "hello world".into(),
+                    "PX".into(),
+                    "1000".into(),
+                ],
+                vec![
+                    Bytes::from("HSET"),
+                    "user 1".into(),
+                    "bio".into(),
+                    "line one\r\nline two".into(),
+                    "empty".into(),
+                    "".into(),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_command() {
+        let mut buf = Vec::new();
+        encode_command(&mut buf, &["MULTI"]);
+        encode_command(&mut buf, &[&b"DEL"[..], b"a key"]);
+
+        assert_eq!(
+            buf,
+            b"*1\r\n$5\r\nMULTI\r\n*2\r\n$3\r\nDEL\r\n$5\r\na key\r\n"
+        );
+    }
+
+    #[test]
+    fn test_legacy_aof_is_detected() {
+        assert!(is_legacy_aof(b"SET name Alice\n"));
+        assert!(!is_legacy_aof(
+            b"*3\r\n$3\r\nSET\r\n$4\r\nname\r\n$5\r\nAlice\r\n"
+        ));
+        assert!(!is_legacy_aof(b""));
+    }
+
+    #[test]
+    fn test_parse_legacy_aof() {
+        let content = b"MULTI\nSET name Alice\nLPUSH  queue a b\n\nEXEC\n";
+
+        assert_eq!(
+            parse_legacy_aof(content),
+            vec![
+                vec![Bytes::from("MULTI")],
+                vec![Bytes::from("SET"), "name".into(), "Alice".into()],
+                vec![Bytes::from("LPUSH"), "queue".into(), "a".into(), "b".into()],
+                vec![Bytes::from("EXEC")],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_appendfsync() {
+        assert_eq!(AppendFsync::parse(b"always"), Some(AppendFsync::Always));
+        assert_eq!(AppendFsync::parse(b"EVERYSEC"), Some(AppendFsync::EverySec));
+        assert_eq!(AppendFsync::parse(b"no"), Some(AppendFsync::No));
+        assert_eq!(AppendFsync::parse(b"sometimes"), None);
+        assert_eq!(AppendFsync::EverySec.name(), "everysec");
+    }
+
+    #[test]
+    fn test_check_aof() {
+        let set = b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n";
+        let mut content = set.to_vec();
+        content.extend_from_slice(b"*1\r\n$5\r\nMULTI\r\n");
+        content.extend_from_slice(set);
+        content.extend_from_slice(b"*1\r\n$4\r\nEXEC\r\n");
+        assert_eq!(check_aof(&content), AofCheck::Valid);
+        assert_eq!(check_aof(b""), AofCheck::Valid);
+
+        // Cut in the middle of the last command
+        let mut torn = content.clone();
+        torn.extend_from_slice(&set[..10]);
+        assert_eq!(
+            check_aof(&torn),
+            AofCheck::Truncated {
+                offset: content.len()
+            }
+        );
+
+        // A transaction without its EXEC is dropped whole
+        let mut torn = set.to_vec();
+        torn.extend_from_slice(b"*1\r\n$5\r\nMULTI\r\n");
+        torn.extend_from_slice(set);
+        assert_eq!(check_aof(&torn), AofCheck::Truncated { offset: set.len() });
+
+        let mut corrupt = set.to_vec();
+        corrupt.extend_from_slice(b"*1\r\n$4\r\nEXEC\r\n");
+        assert!(matches!(
+            check_aof(&corrupt),
+            AofCheck::Corrupt { offset, .. } if offset == set.len()
+        ));
+
+        let mut corrupt = set.to_vec();
+        corrupt.extend_from_slice(b"*1\r\n$9\r\nPING\r\n");
+        corrupt.extend_from_slice(set);
+        assert!(matches!(
+            check_aof(&corrupt),
+            AofCheck::Corrupt { offset, .. } if offset == set.len()
+        ));
+
+        // Only files with a command on every line are in the text format
+        assert_eq!(check_aof(b"SET name Alice\n\nDEL name\n"), AofCheck::Valid);
+        assert!(matches!(
+            check_aof(b"SET name Alice\nnot a command\n"),
+            AofCheck::Corrupt { offset: 15, .. }
+        ));
+        assert!(matches!(
+            check_aof(b"REDIS0011\xfa\x09redis-ver"),
+            AofCheck::Corrupt { offset: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn test_loading_progress() {
+        let mut progress = LoadingProgress {
+            start: 10_000,
+            total_bytes: 1000,
+            loaded_bytes: 0,
+        };
+
+        // Nothing to go by yet
+        assert_eq!(progress.loaded_percent(), 0.0);
+        assert_eq!(progress.eta_seconds(12_000), 1);
+
+        // A quarter in 2 seconds, 6 more for the rest
+        progress.loaded_bytes = 250;
+        assert_eq!(progress.loaded_percent(), 25.0);
+        assert_eq!(progress.eta_seconds(12_000), 6);
+
+        progress.loaded_bytes = 1000;
+        assert_eq!(progress.loaded_percent(), 100.0);
+        assert_eq!(progress.eta_seconds(20_000), 0);
+
+        let empty = LoadingProgress {
+            start: 10_000,
+            total_bytes: 0,
+            loaded_bytes: 0,
+        };
+        assert_eq!(empty.loaded_percent(), 0.0);
+    }
+}
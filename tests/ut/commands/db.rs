@@ -1,51 +1,40 @@
 #[cfg(test)]
 mod tests {
-    use indexmap::IndexMap;
+    use bytes::Bytes;
     use sider::commands::db::*;
     use sider::types::*;
-    use std::sync::Arc;
-    use tokio::sync::RwLock;
 
-    async fn setup_db() -> Db {
-        Arc::new(RwLock::new(IndexMap::new()))
+    fn setup_db() -> Keyspace {
+        Keyspace::new()
     }
 
-    #[tokio::test]
-    async fn test_flush_db() {
-        let db = setup_db().await;
+    #[test]
+    fn test_flush_db() {
+        let mut db = setup_db();
 
-        {
-            let mut db_write = db.write().await;
-            db_write.insert(
-                "key1".to_string(),
-                DbValue::StringKey(Key {
-                    name: "key1".to_string(),
-                    data: Some("value1".to_string()),
-                    expires_at: None,
-                }),
-            );
-            db_write.insert(
-                "key2".to_string(),
-                DbValue::StringKey(Key {
-                    name: "key2".to_string(),
-                    data: Some("value2".to_string()),
-                    expires_at: None,
-                }),
-            );
-        }
+        db.insert(
+            Bytes::from("key1"),
+            DbValue::StringKey(Key {
+                name: Bytes::from("key1"),
+                data: Some(Bytes::from("value1")),
+                expires_at: None,
+            }),
+        );
+        db.insert(
+            Bytes::from("key2"),
+            DbValue::StringKey(Key {
+                name: Bytes::from("key2"),
+                data: Some(Bytes::from("value2")),
+                expires_at: None,
+            }),
+        );
 
-        {
-            let db_read = db.read().await;
-            assert!(!db_read.is_empty());
-        }
+        assert!(!db.is_empty());
 
-        let result = flush_db(&db).await.unwrap().to_string();
+        let result = flush_db(&mut db).unwrap().to_bytes();
 
-        {
-            let db_read = db.read().await;
-            assert!(db_read.is_empty());
-        }
+        assert!(db.is_empty());
 
-        assert_eq!(result, "+OK\r\n");
+        assert_eq!(result, b"+OK\r\n");
     }
 }
@@ -1,142 +1,144 @@
 #[cfg(test)]
 mod tests {
+    use bytes::Bytes;
     use indexmap::IndexMap;
     use sider::commands::hashsets::*;
     use sider::types::*;
-    use std::sync::Arc;
-    use tokio::sync::RwLock;
 
-    async fn setup_db() -> Db {
-        Arc::new(RwLock::new(IndexMap::new()))
+    fn setup_db() -> Keyspace {
+        Keyspace::new()
     }
 
-    #[tokio::test]
-    async fn test_hset_new_hash() {
-        let db = setup_db().await;
+    #[test]
+    fn test_hset_new_hash() {
+        let mut db = setup_db();
         let fields = IndexMap::from([
-            ("name".to_string(), "Smith".to_string()),
-            ("first_name".to_string(), "John".to_string()),
+            (Bytes::from("name"), Bytes::from("Smith")),
+            (Bytes::from("first_name"), Bytes::from("John")),
         ]);
 
         let command = Command {
             command_type: CommandType::HSET,
             args: CommandArgs::HashFields {
-                key: "user:1".to_string(),
+                key: Bytes::from("user:1"),
                 fields,
             },
         };
 
-        let result = hset(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 2\r\n");
+        let result = hset(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":2\r\n");
 
-        let db_read = db.read().await;
-        assert!(db_read.contains_key("user:1"));
+        assert!(db.contains_key(b"user:1".as_slice()));
     }
 
-    #[tokio::test]
-    async fn test_hset_add_new_fields() {
-        let db = setup_db().await;
+    #[test]
+    fn test_hset_add_new_fields() {
+        let mut db = setup_db();
 
-        let fields = IndexMap::from([("name".to_string(), "Doe".to_string())]);
+        let fields = IndexMap::from([(Bytes::from("name"), Bytes::from("Doe"))]);
         let command = Command {
             command_type: CommandType::HSET,
             args: CommandArgs::HashFields {
-                key: "user:2".to_string(),
+                key: Bytes::from("user:2"),
                 fields,
             },
         };
 
-        let result = hset(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 1\r\n");
+        let result = hset(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":1\r\n");
 
-        let fields = IndexMap::from([("age".to_string(), "30".to_string())]);
+        let fields = IndexMap::from([(Bytes::from("age"), Bytes::from("30"))]);
         let command = Command {
             command_type: CommandType::HSET,
             args: CommandArgs::HashFields {
-                key: "user:2".to_string(),
+                key: Bytes::from("user:2"),
                 fields,
             },
         };
 
-        let result = hset(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 1\r\n");
+        let result = hset(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":1\r\n");
 
-        let db_read = db.read().await;
-        let stored_hash = match db_read.get("user:2") {
+        let stored_hash = match db.get(b"user:2".as_slice()) {
             Some(DbValue::HashKey(hash)) => hash,
             _ => panic!("Expected HashKey"),
         };
 
-        assert_eq!(stored_hash.data.get("name"), Some(&"Doe".to_string()));
-        assert_eq!(stored_hash.data.get("age"), Some(&"30".to_string()));
+        assert_eq!(
+            stored_hash.data.get(b"name".as_slice()),
+            Some(&Bytes::from("Doe"))
+        );
+        assert_eq!(
+            stored_hash.data.get(b"age".as_slice()),
+            Some(&Bytes::from("30"))
+        );
     }
 
-    #[tokio::test]
-    async fn test_hdel() {
-        let db = setup_db().await;
+    #[test]
+    fn test_hdel() {
+        let mut db = setup_db();
 
         let fields = IndexMap::from([
-            ("last_name".to_string(), "Smith".to_string()),
-            ("first_name".to_string(), "John".to_string()),
-            ("age".to_string(), "21".to_string()),
+            (Bytes::from("last_name"), Bytes::from("Smith")),
+            (Bytes::from("first_name"), Bytes::from("John")),
+            (Bytes::from("age"), Bytes::from("21")),
         ]);
 
         let command = Command {
             command_type: CommandType::HSET,
             args: CommandArgs::HashFields {
-                key: "hdelhash".to_string(),
+                key: Bytes::from("hdelhash"),
                 fields,
             },
         };
 
-        let result = hset(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 3\r\n");
+        let result = hset(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":3\r\n");
 
         let command = Command {
             command_type: CommandType::HDEL,
             args: CommandArgs::KeyWithValues {
-                key: "hdelhash".to_string(),
-                values: vec!["last_name".to_string(), "first_name".to_string()],
+                key: Bytes::from("hdelhash"),
+                values: vec![Bytes::from("last_name"), Bytes::from("first_name")],
             },
         };
 
-        let result = hdel(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 2\r\n");
+        let result = hdel(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":2\r\n");
 
         let command = Command {
             command_type: CommandType::HDEL,
             args: CommandArgs::KeyWithValues {
-                key: "hdelhash".to_string(),
-                values: vec!["non_existent_field".to_string()],
+                key: Bytes::from("hdelhash"),
+                values: vec![Bytes::from("non_existent_field")],
             },
         };
 
-        let result = hdel(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 0\r\n");
+        let result = hdel(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":0\r\n");
 
         let command = Command {
             command_type: CommandType::HDEL,
             args: CommandArgs::KeyWithValues {
-                key: "unknownhash".to_string(),
-                values: vec!["some_field".to_string()],
+                key: Bytes::from("unknownhash"),
+                values: vec![Bytes::from("some_field")],
             },
         };
 
-        let result = hdel(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 0\r\n");
+        let result = hdel(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":0\r\n");
 
         let command = Command {
             command_type: CommandType::HDEL,
             args: CommandArgs::KeyWithValues {
-                key: "hdelhash".to_string(),
-                values: vec!["age".to_string()],
+                key: Bytes::from("hdelhash"),
+                values: vec![Bytes::from("age")],
             },
         };
 
-        let result = hdel(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 1\r\n");
+        let result = hdel(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":1\r\n");
 
-        let db_read = db.read().await;
-        assert!(!db_read.contains_key("hdelhash"));
+        assert!(!db.contains_key(b"hdelhash".as_slice()));
     }
 }
@@ -1,196 +1,502 @@
 #[cfg(test)]
 mod tests {
-    use indexmap::IndexMap;
+    use crate::ut::utils::run;
+    use bytes::Bytes;
+    use sider::client::Client;
     use sider::commands::keys::*;
+    use sider::errors::SiderError;
+    use sider::parser::parse_command;
+    use sider::process::{execute_command, logged_commands};
     use sider::types::*;
-    use std::sync::Arc;
-    use tokio::sync::RwLock;
+    use sider::utils::current_time_millis;
 
-    async fn setup_db() -> Db {
-        Arc::new(RwLock::new(IndexMap::new()))
+    fn setup_db() -> Keyspace {
+        Keyspace::new()
     }
 
-    #[tokio::test]
-    async fn test_set_key() {
-        let db = setup_db().await;
+    fn expires_at(db: &Keyspace, key: &str) -> Option<i64> {
+        db.get(key.as_bytes()).and_then(|value| value.expires_at())
+    }
+
+    #[test]
+    fn test_set_key() {
+        let mut db = setup_db();
         let command = Command {
             command_type: CommandType::SET,
             args: CommandArgs::KeyWithValue {
-                key: "my_key".to_string(),
-                value: "value".to_string(),
+                key: Bytes::from("my_key"),
+                value: Bytes::from("value"),
             },
         };
 
-        let result = set_key(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+OK\r\n");
+        let result = set_key(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b"+OK\r\n");
 
-        let db_read = db.read().await;
-        assert!(db_read.contains_key("my_key"));
+        assert!(db.contains_key(b"my_key".as_slice()));
     }
 
-    #[tokio::test]
-    async fn test_delete_key() {
-        let db = setup_db().await;
-        let key_name = "key_to_delete".to_string();
+    #[test]
+    fn test_delete_key() {
+        let mut db = setup_db();
+        let key_name = Bytes::from("key_to_delete");
 
-        {
-            let mut db_write = db.write().await;
-            db_write.insert(
-                key_name.clone(),
-                DbValue::StringKey(Key {
-                    name: key_name.clone(),
-                    data: Some("value".to_string()),
-                    expires_at: None,
-                }),
-            );
-        }
+        db.insert(
+            key_name.clone(),
+            DbValue::StringKey(Key {
+                name: key_name.clone(),
+                data: Some(Bytes::from("value")),
+                expires_at: None,
+            }),
+        );
 
         let command = Command {
             command_type: CommandType::DEL,
             args: CommandArgs::SingleKey(key_name.clone()),
         };
 
-        let result = delete_key(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 1\r\n");
+        let result = delete_key(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":1\r\n");
 
-        let db_read = db.read().await;
-        assert!(!db_read.contains_key(&key_name));
+        assert!(!db.contains_key(&key_name));
     }
 
-    #[tokio::test]
-    async fn test_incr_new_key() {
-        let db = setup_db().await;
+    #[test]
+    fn test_incr_new_key() {
+        let mut db = setup_db();
         let command = Command {
             command_type: CommandType::INCR,
-            args: CommandArgs::SingleKey("counter".to_string()),
+            args: CommandArgs::SingleKey(Bytes::from("counter")),
         };
 
-        let result = incr(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 1\r\n");
-    }
-
-    #[tokio::test]
-    async fn test_incr_existing_key() {
-        let db = setup_db().await;
-        let key_name = "counter".to_string();
-
-        {
-            let mut db_write = db.write().await;
-            db_write.insert(
-                key_name.clone(),
-                DbValue::StringKey(Key {
-                    name: key_name.clone(),
-                    data: Some("5".to_string()),
-                    expires_at: None,
-                }),
-            );
-        }
+        let result = incr(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":1\r\n");
+    }
+
+    #[test]
+    fn test_incr_existing_key() {
+        let mut db = setup_db();
+        let key_name = Bytes::from("counter");
+
+        db.insert(
+            key_name.clone(),
+            DbValue::StringKey(Key {
+                name: key_name.clone(),
+                data: Some(Bytes::from("5")),
+                expires_at: None,
+            }),
+        );
 
         let command = Command {
             command_type: CommandType::INCR,
             args: CommandArgs::SingleKey(key_name),
         };
 
-        let result = incr(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 6\r\n");
+        let result = incr(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":6\r\n");
     }
 
-    #[tokio::test]
-    async fn test_decr_new_key() {
-        let db = setup_db().await;
+    #[test]
+    fn test_decr_new_key() {
+        let mut db = setup_db();
         let command = Command {
             command_type: CommandType::DECR,
-            args: CommandArgs::SingleKey("counter".to_string()),
+            args: CommandArgs::SingleKey(Bytes::from("counter")),
         };
 
-        let result = decr(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) -1\r\n");
-    }
-
-    #[tokio::test]
-    async fn test_incrby() {
-        let db = setup_db().await;
-        let key_name = "counter".to_string();
-
-        {
-            let mut db_write = db.write().await;
-            db_write.insert(
-                key_name.clone(),
-                DbValue::StringKey(Key {
-                    name: key_name.clone(),
-                    data: Some("10".to_string()),
-                    expires_at: None,
-                }),
-            );
-        }
+        let result = decr(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":-1\r\n");
+    }
+
+    #[test]
+    fn test_incrby() {
+        let mut db = setup_db();
+        let key_name = Bytes::from("counter");
+
+        db.insert(
+            key_name.clone(),
+            DbValue::StringKey(Key {
+                name: key_name.clone(),
+                data: Some(Bytes::from("10")),
+                expires_at: None,
+            }),
+        );
 
         let command = Command {
             command_type: CommandType::INCRBY,
             args: CommandArgs::KeyWithValue {
                 key: key_name,
-                value: "5".to_string(),
+                value: Bytes::from("5"),
             },
         };
 
-        let result = incrby(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 15\r\n");
-    }
-
-    #[tokio::test]
-    async fn test_get_keys() {
-        let db = setup_db().await;
-
-        {
-            let mut db_write = db.write().await;
-            db_write.insert(
-                "foo".to_string(),
-                DbValue::StringKey(Key {
-                    name: "foo".to_string(),
-                    data: Some("bar".to_string()),
-                    expires_at: None,
-                }),
-            );
-            db_write.insert(
-                "foobar".to_string(),
-                DbValue::StringKey(Key {
-                    name: "foobar".to_string(),
-                    data: Some("baz".to_string()),
-                    expires_at: None,
-                }),
-            );
-        }
+        let result = incrby(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":15\r\n");
+    }
+
+    #[test]
+    fn test_get_keys() {
+        let mut db = setup_db();
+
+        db.insert(
+            Bytes::from("foo"),
+            DbValue::StringKey(Key {
+                name: Bytes::from("foo"),
+                data: Some(Bytes::from("bar")),
+                expires_at: None,
+            }),
+        );
+        db.insert(
+            Bytes::from("foobar"),
+            DbValue::StringKey(Key {
+                name: Bytes::from("foobar"),
+                data: Some(Bytes::from("baz")),
+                expires_at: None,
+            }),
+        );
 
         let command = Command {
             command_type: CommandType::KEYS,
-            args: CommandArgs::SingleKey("foo*".to_string()),
+            args: CommandArgs::SingleKey(Bytes::from("foo*")),
         };
 
-        let result = get_keys(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "*2\r\n$3\r\nfoo\r\n$6\r\nfoobar\r\n");
+        let result = get_keys(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b"*2\r\n$3\r\nfoo\r\n$6\r\nfoobar\r\n");
     }
 
-    #[tokio::test]
-    async fn test_exists() {
-        let db = setup_db().await;
+    #[test]
+    fn test_exists() {
+        let mut db = setup_db();
 
-        {
-            let mut db_write = db.write().await;
-            db_write.insert(
-                "key1".to_string(),
-                DbValue::StringKey(Key {
-                    name: "key1".to_string(),
-                    data: Some("val1".to_string()),
-                    expires_at: None,
-                }),
-            );
-        }
+        db.insert(
+            Bytes::from("key1"),
+            DbValue::StringKey(Key {
+                name: Bytes::from("key1"),
+                data: Some(Bytes::from("val1")),
+                expires_at: None,
+            }),
+        );
 
         let command = Command {
             command_type: CommandType::EXISTS,
-            args: CommandArgs::MultipleKeys(vec!["key1".to_string(), "key2".to_string()]),
+            args: CommandArgs::MultipleKeys(vec![Bytes::from("key1"), Bytes::from("key2")]),
+        };
+
+        let result = exists(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b":1\r\n");
+    }
+
+    #[test]
+    fn test_set_get_binary_value() {
+        let mut db = setup_db();
+        let key = Bytes::from_static(b"proto:\x00\xff");
+        let value = Bytes::from_static(b"\x08\x96\x01\r\n\xc3\x28 ");
+
+        let command = Command {
+            command_type: CommandType::SET,
+            args: CommandArgs::KeyWithValue {
+                key: key.clone(),
+                value: value.clone(),
+            },
+        };
+        set_key(&mut db, command).unwrap();
+
+        let command = Command {
+            command_type: CommandType::GET,
+            args: CommandArgs::SingleKey(key),
+        };
+
+        let result = get_key(&mut db, command).unwrap().to_bytes();
+        let mut expected = format!("${}\r\n", value.len()).into_bytes();
+        expected.extend_from_slice(&value);
+        expected.extend_from_slice(b"\r\n");
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_get_keys_binary_pattern() {
+        let mut db = setup_db();
+
+        for name in [&b"bin:\xff\x01"[..], &b"bin:a-b"[..], &b"other"[..]] {
+            let name = Bytes::copy_from_slice(name);
+            db.insert(name.clone(), DbValue::StringKey(Key::new(name, None, None)));
+        }
+
+        let command = Command {
+            command_type: CommandType::KEYS,
+            args: CommandArgs::SingleKey(Bytes::from_static(b"bin:\xff*")),
+        };
+
+        let result = get_keys(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b"*1\r\n$6\r\nbin:\xff\x01\r\n");
+
+        let command = Command {
+            command_type: CommandType::KEYS,
+            args: CommandArgs::SingleKey(Bytes::from_static(b"bin:[a-z]-?")),
+        };
+
+        let result = get_keys(&mut db, command).unwrap().to_bytes();
+        assert_eq!(result, b"*1\r\n$7\r\nbin:a-b\r\n");
+    }
+
+    #[test]
+    fn test_set_nx_xx() {
+        let mut db = setup_db();
+
+        assert_eq!(run(&mut db, "SET lock a NX").unwrap(), b"+OK\r\n");
+        assert_eq!(run(&mut db, "SET lock b NX").unwrap(), b"$-1\r\n");
+        assert_eq!(run(&mut db, "GET lock").unwrap(), b"$1\r\na\r\n");
+
+        assert_eq!(run(&mut db, "SET missing a XX").unwrap(), b"$-1\r\n");
+        assert!(!db.contains_key(b"missing".as_slice()));
+        assert_eq!(run(&mut db, "SET lock b xx").unwrap(), b"+OK\r\n");
+        assert_eq!(run(&mut db, "GET lock").unwrap(), b"$1\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_set_get() {
+        let mut db = setup_db();
+
+        assert_eq!(run(&mut db, "SET name alice GET").unwrap(), b"$-1\r\n");
+        assert_eq!(
+            run(&mut db, "SET name bob GET").unwrap(),
+            b"$5\r\nalice\r\n"
+        );
+
+        // The old value is returned even if the condition fails
+        assert_eq!(
+            run(&mut db, "SET name carol NX GET").unwrap(),
+            b"$3\r\nbob\r\n"
+        );
+        assert_eq!(run(&mut db, "GET name").unwrap(), b"$3\r\nbob\r\n");
+
+        run(&mut db, "LPUSH list a").unwrap();
+        let result = run(&mut db, "SET list value GET");
+        assert!(matches!(result, Err(SiderError::WrongType)));
+
+        // Without GET any type is overwritten
+        assert_eq!(run(&mut db, "SET list value").unwrap(), b"+OK\r\n");
+        assert_eq!(run(&mut db, "GET list").unwrap(), b"$5\r\nvalue\r\n");
+    }
+
+    #[test]
+    fn test_set_expiry() {
+        let mut db = setup_db();
+        let now = current_time_millis();
+
+        run(&mut db, "SET session token EX 100").unwrap();
+        let session = expires_at(&db, "session").unwrap();
+        assert!((now + 100_000..now + 101_000).contains(&session));
+        assert_eq!(run(&mut db, "TTL session").unwrap(), b":100\r\n");
+
+        run(&mut db, "SET session other PX 1500").unwrap();
+        let session = expires_at(&db, "session").unwrap();
+        assert!((now + 1500..now + 2500).contains(&session));
+
+        run(&mut db, "SET session kept KEEPTTL").unwrap();
+        assert_eq!(expires_at(&db, "session"), Some(session));
+
+        // A plain SET drops the TTL
+        run(&mut db, "SET session plain").unwrap();
+        assert_eq!(expires_at(&db, "session"), None);
+
+        run(&mut db, "SET at value EXAT 4102444800").unwrap();
+        assert_eq!(expires_at(&db, "at"), Some(4_102_444_800_000));
+        run(&mut db, "SET at value PXAT 4102444800123").unwrap();
+        assert_eq!(expires_at(&db, "at"), Some(4_102_444_800_123));
+
+        // Already expired
+        run(&mut db, "SET old value PXAT 1").unwrap();
+        assert_eq!(run(&mut db, "GET old").unwrap(), b"$-1\r\n");
+        assert_eq!(run(&mut db, "SET old value XX").unwrap(), b"$-1\r\n");
+    }
+
+    #[test]
+    fn test_set_option_errors() {
+        let mut db = setup_db();
+
+        for command in [
+            "SET key value NX XX",
+            "SET key value EX 10 PX 100",
+            "SET key value KEEPTTL EX 10",
+            "SET key value EX",
+            "SET key value FOO",
+        ] {
+            let result = run(&mut db, command);
+            assert!(matches!(result, Err(SiderError::Syntax)), "{}", command);
+        }
+
+        let result = run(&mut db, "SET key value EX ten");
+        assert!(matches!(result, Err(SiderError::NotInt)));
+
+        for command in ["SET key value EX 0", "SET key value PX -5"] {
+            let result = run(&mut db, command);
+            assert!(matches!(result, Err(SiderError::InvalidExpireTime("set"))));
+        }
+        let result = run(&mut db, &format!("SET key value EX {}", i64::MAX));
+        assert!(matches!(result, Err(SiderError::InvalidExpireTime("set"))));
+
+        let result = run(&mut db, "SET key");
+        assert!(matches!(result, Err(SiderError::WrongNumberArgs)));
+
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn test_set_is_logged_with_absolute_expiry() {
+        let mut db = setup_db();
+        let args = ["SET", "lock", "token", "NX", "PX", "30000"]
+            .map(|arg| Bytes::copy_from_slice(arg.as_bytes()));
+        let command = parse_command(args.to_vec()).unwrap();
+
+        execute_command(&mut db, &mut Client::new(), command.clone()).unwrap();
+        let expires_at = expires_at(&db, "lock").unwrap();
+
+        let logged = logged_commands(&mut db, command.clone());
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].command_type, CommandType::SET);
+
+        let CommandArgs::KeyWithValues { key, values } = &logged[0].args else {
+            panic!("SET is logged with its options");
+        };
+        assert_eq!(key, "lock");
+        assert_eq!(
+            values,
+            &vec![
+                Bytes::from("token"),
+                Bytes::from("PXAT"),
+                Bytes::from(expires_at.to_string()),
+            ]
+        );
+
+        // A SET that didn't happen isn't logged
+        execute_command(&mut db, &mut Client::new(), command.clone()).unwrap();
+        assert!(logged_commands(&mut db, command).is_empty());
+    }
+
+    #[test]
+    fn test_expire_variants() {
+        let mut db = setup_db();
+        let now = current_time_millis();
+
+        run(&mut db, "SET key value").unwrap();
+
+        assert_eq!(run(&mut db, "PEXPIRE key 1500").unwrap(), b":1\r\n");
+        let key = expires_at(&db, "key").unwrap();
+        assert!((now + 1500..now + 2500).contains(&key));
+
+        assert_eq!(run(&mut db, "EXPIREAT key 4102444800").unwrap(), b":1\r\n");
+        assert_eq!(
+            run(&mut db, "PEXPIRETIME key").unwrap(),
+            b":4102444800000\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "PEXPIREAT key 4102444800999").unwrap(),
+            b":1\r\n"
+        );
+        assert_eq!(run(&mut db, "EXPIRETIME key").unwrap(), b":4102444800\r\n");
+
+        assert_eq!(run(&mut db, "PERSIST key").unwrap(), b":1\r\n");
+        assert_eq!(run(&mut db, "PERSIST key").unwrap(), b":0\r\n");
+        assert_eq!(run(&mut db, "TTL key").unwrap(), b":-1\r\n");
+        assert_eq!(run(&mut db, "PTTL key").unwrap(), b":-1\r\n");
+        assert_eq!(run(&mut db, "EXPIRETIME key").unwrap(), b":-1\r\n");
+
+        assert_eq!(run(&mut db, "PEXPIRE missing 100").unwrap(), b":0\r\n");
+        assert_eq!(run(&mut db, "PTTL missing").unwrap(), b":-2\r\n");
+        assert_eq!(run(&mut db, "PEXPIRETIME missing").unwrap(), b":-2\r\n");
+        assert_eq!(run(&mut db, "PERSIST missing").unwrap(), b":0\r\n");
+
+        // A time in the past deletes the key
+        assert_eq!(run(&mut db, "PEXPIREAT key 1").unwrap(), b":1\r\n");
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn test_ttl_all_types() {
+        let mut db = setup_db();
+
+        for command in [
+            "RPUSH list a",
+            "SADD set a",
+            "HSET hash field value",
+            "ZADD zset 1 a",
+            "XADD stream 1-1 field value",
+        ] {
+            run(&mut db, command).unwrap();
+        }
+
+        for key in ["list", "set", "hash", "zset", "stream"] {
+            assert_eq!(run(&mut db, &format!("TTL {}", key)).unwrap(), b":-1\r\n");
+            run(&mut db, &format!("EXPIRE {} 100", key)).unwrap();
+            assert_eq!(run(&mut db, &format!("TTL {}", key)).unwrap(), b":100\r\n");
+
+            let pttl = run(&mut db, &format!("PTTL {}", key)).unwrap();
+            let pttl: i64 = std::str::from_utf8(&pttl[1..pttl.len() - 2])
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert!((99_000..=100_000).contains(&pttl));
+        }
+    }
+
+    #[test]
+    fn test_expire_flags() {
+        let mut db = setup_db();
+        run(&mut db, "SET key value").unwrap();
+
+        // Without expiry the TTL counts as infinite
+        assert_eq!(run(&mut db, "EXPIRE key 100 XX").unwrap(), b":0\r\n");
+        assert_eq!(run(&mut db, "EXPIRE key 100 GT").unwrap(), b":0\r\n");
+        assert_eq!(run(&mut db, "EXPIRE key 100 LT").unwrap(), b":1\r\n");
+        assert_eq!(run(&mut db, "EXPIRE key 50 NX").unwrap(), b":0\r\n");
+
+        assert_eq!(run(&mut db, "EXPIRE key 200 lt").unwrap(), b":0\r\n");
+        assert_eq!(run(&mut db, "EXPIRE key 200 GT").unwrap(), b":1\r\n");
+        assert_eq!(run(&mut db, "EXPIRE key 50 XX LT").unwrap(), b":1\r\n");
+        assert_eq!(run(&mut db, "TTL key").unwrap(), b":50\r\n");
+
+        run(&mut db, "PERSIST key").unwrap();
+        assert_eq!(run(&mut db, "EXPIRE key 50 NX").unwrap(), b":1\r\n");
+
+        let result = run(&mut db, "EXPIRE key 10 NX XX");
+        assert!(matches!(result, Err(SiderError::ExpireNXAndOthers)));
+        let result = run(&mut db, "EXPIRE key 10 GT LT");
+        assert!(matches!(result, Err(SiderError::ExpireGTAndLT)));
+        let result = run(&mut db, "EXPIRE key 10 FOO");
+        assert!(matches!(result, Err(SiderError::UnsupportedOption(_))));
+        let result = run(&mut db, "EXPIRE key ten");
+        assert!(matches!(result, Err(SiderError::NotInt)));
+        let result = run(&mut db, &format!("EXPIRE key {}", i64::MAX));
+        assert!(matches!(
+            result,
+            Err(SiderError::InvalidExpireTime("expire"))
+        ));
+    }
+
+    #[test]
+    fn test_expire_is_logged_with_absolute_time() {
+        let mut db = setup_db();
+        run(&mut db, "SET key value").unwrap();
+
+        let args = ["EXPIRE", "key", "100"].map(|arg| Bytes::copy_from_slice(arg.as_bytes()));
+        let command = parse_command(args.to_vec()).unwrap();
+        execute_command(&mut db, &mut Client::new(), command.clone()).unwrap();
+        let expires_at = expires_at(&db, "key").unwrap();
+
+        let logged = logged_commands(&mut db, command);
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].command_type, CommandType::PEXPIREAT);
+        let CommandArgs::KeyWithValue { value, .. } = &logged[0].args else {
+            panic!("PEXPIREAT is logged with the key and the time");
         };
+        assert_eq!(value, &Bytes::from(expires_at.to_string()));
+
+        // Expiring in the past is a deletion
+        let args = ["PEXPIRE", "key", "-1"].map(|arg| Bytes::copy_from_slice(arg.as_bytes()));
+        let command = parse_command(args.to_vec()).unwrap();
+        execute_command(&mut db, &mut Client::new(), command.clone()).unwrap();
 
-        let result = exists(&db, command).await.unwrap().to_string();
-        assert_eq!(result, "+(integer) 1\r\n");
+        let logged = logged_commands(&mut db, command);
+        assert_eq!(logged[0].command_type, CommandType::DEL);
     }
 }
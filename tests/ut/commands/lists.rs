@@ -0,0 +1,236 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::{process, served};
+    use sider::client::Client;
+    use sider::errors::SiderError;
+    use sider::types::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn setup_db() -> Db {
+        Arc::new(Store::new())
+    }
+
+    #[tokio::test]
+    async fn test_blpop_pops_right_away() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "RPUSH second a b").await.unwrap();
+
+        let result = process(&db, &mut client, "BLPOP first second 0")
+            .await
+            .unwrap();
+        assert_eq!(result.to_bytes(), b"*2\r\n$6\r\nsecond\r\n$1\r\na\r\n");
+        assert!(client.blocked.is_none());
+
+        let result = process(&db, &mut client, "BRPOP first second 0")
+            .await
+            .unwrap();
+        assert_eq!(result.to_bytes(), b"*2\r\n$6\r\nsecond\r\n$1\r\nb\r\n");
+
+        assert!(db.lock_all().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_blpop_is_served_by_push() {
+        let db = setup_db();
+        let mut waiter = Client::new();
+        let mut pusher = Client::new();
+
+        let result = process(&db, &mut waiter, "BLPOP first second 1.5")
+            .await
+            .unwrap();
+        assert_eq!(result.to_bytes(), b"*-1\r\n");
+
+        let wait = waiter.blocked.as_ref().unwrap();
+        assert_eq!(wait.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(served(&mut waiter), None);
+
+        // The pusher sees the list it created, the element goes to the waiter
+        let result = process(&db, &mut pusher, "RPUSH second a").await.unwrap();
+        assert_eq!(result.to_bytes(), b":1\r\n");
+
+        assert_eq!(
+            served(&mut waiter).unwrap(),
+            b"*2\r\n$6\r\nsecond\r\n$1\r\na\r\n"
+        );
+
+        let db = db.lock_all().await;
+        assert!(db.is_empty());
+        assert_eq!(db.blocked_clients(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_waiters_are_served_in_order() {
+        let db = setup_db();
+        let mut first = Client::new();
+        let mut second = Client::new();
+        let mut third = Client::new();
+        let mut pusher = Client::new();
+
+        process(&db, &mut first, "BRPOP jobs 0").await.unwrap();
+        process(&db, &mut second, "BLPOP other jobs 0")
+            .await
+            .unwrap();
+        process(&db, &mut third, "BRPOP jobs 0").await.unwrap();
+
+        process(&db, &mut pusher, "LPUSH jobs 1 2").await.unwrap();
+
+        assert_eq!(
+            served(&mut first).unwrap(),
+            b"*2\r\n$4\r\njobs\r\n$1\r\n1\r\n"
+        );
+        assert_eq!(
+            served(&mut second).unwrap(),
+            b"*2\r\n$4\r\njobs\r\n$1\r\n2\r\n"
+        );
+        assert_eq!(served(&mut third), None);
+
+        process(&db, &mut pusher, "LPUSH jobs 3").await.unwrap();
+        assert_eq!(
+            served(&mut third).unwrap(),
+            b"*2\r\n$4\r\njobs\r\n$1\r\n3\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unblocked_client_is_not_served() {
+        let db = setup_db();
+        let mut gone = Client::new();
+        let mut timed_out = Client::new();
+        let mut waiter = Client::new();
+        let mut pusher = Client::new();
+
+        process(&db, &mut gone, "BLPOP jobs 0").await.unwrap();
+        process(&db, &mut timed_out, "BLPOP jobs 1").await.unwrap();
+        process(&db, &mut waiter, "BLPOP jobs 0").await.unwrap();
+
+        // A disconnected client drops its end, a timed out one is removed
+        drop(gone);
+        db.unblock(timed_out.id);
+        assert_eq!(served(&mut timed_out), None);
+
+        process(&db, &mut pusher, "RPUSH jobs a").await.unwrap();
+        assert_eq!(
+            served(&mut waiter).unwrap(),
+            b"*2\r\n$4\r\njobs\r\n$1\r\na\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blmove() {
+        let db = setup_db();
+        let mut waiter = Client::new();
+        let mut chained = Client::new();
+        let mut pusher = Client::new();
+
+        let result = process(&db, &mut waiter, "BLMOVE source destination RIGHT LEFT 0")
+            .await
+            .unwrap();
+        assert_eq!(result.to_bytes(), b"$-1\r\n");
+
+        // Moving to a list another client waits on serves that client too
+        process(&db, &mut chained, "BLPOP destination 0")
+            .await
+            .unwrap();
+        process(&db, &mut pusher, "RPUSH source a b").await.unwrap();
+
+        assert_eq!(served(&mut waiter).unwrap(), b"$1\r\nb\r\n");
+        assert_eq!(
+            served(&mut chained).unwrap(),
+            b"*2\r\n$11\r\ndestination\r\n$1\r\nb\r\n"
+        );
+
+        let result = process(&db, &mut pusher, "LMOVE source source LEFT RIGHT")
+            .await
+            .unwrap();
+        assert_eq!(result.to_bytes(), b"$1\r\na\r\n");
+
+        let result = process(&db, &mut pusher, "LMOVE missing source LEFT RIGHT")
+            .await
+            .unwrap();
+        assert_eq!(result.to_bytes(), b"$-1\r\n");
+
+        process(&db, &mut pusher, "SET string 1").await.unwrap();
+        let result = process(&db, &mut pusher, "LMOVE source string LEFT RIGHT").await;
+        assert!(matches!(result, Err(SiderError::WrongType)));
+
+        let result = process(&db, &mut pusher, "LMOVE source destination UP RIGHT").await;
+        assert!(matches!(result, Err(SiderError::Syntax)));
+
+        // Nothing was popped by the failed moves
+        let result = process(&db, &mut pusher, "LRANGE source 0 -1")
+            .await
+            .unwrap();
+        assert_eq!(result.to_bytes(), b"*1\r\n$1\r\na\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_blocking_errors() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "SET string 1").await.unwrap();
+
+        let result = process(&db, &mut client, "BLPOP missing string 0").await;
+        assert!(matches!(result, Err(SiderError::WrongType)));
+
+        let result = process(&db, &mut client, "BLPOP list -1").await;
+        assert!(matches!(result, Err(SiderError::NegativeTimeout)));
+
+        let result = process(&db, &mut client, "BRPOP list soon").await;
+        assert!(matches!(result, Err(SiderError::TimeoutNotFloat)));
+
+        let result = process(&db, &mut client, "BLPOP list").await;
+        assert!(matches!(result, Err(SiderError::WrongNumberArgs)));
+
+        assert!(client.blocked.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_blocking_inside_multi_does_not_block() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        process(&db, &mut client, "BLPOP jobs 0").await.unwrap();
+        process(&db, &mut client, "RPUSH jobs a").await.unwrap();
+        process(&db, &mut client, "BRPOP jobs 0").await.unwrap();
+        process(&db, &mut client, "BLMOVE jobs other LEFT LEFT 0")
+            .await
+            .unwrap();
+
+        let result = process(&db, &mut client, "EXEC").await.unwrap();
+        assert_eq!(
+            result.to_bytes(),
+            b"*4\r\n*-1\r\n:1\r\n*2\r\n$4\r\njobs\r\n$1\r\na\r\n$-1\r\n"
+        );
+        assert!(client.blocked.is_none());
+        assert_eq!(db.blocked_clients(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_exec_serves_blocked_clients() {
+        let db = setup_db();
+        let mut waiter = Client::new();
+        let mut client = Client::new();
+
+        process(&db, &mut waiter, "BLPOP jobs 0").await.unwrap();
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        process(&db, &mut client, "RPUSH jobs a b").await.unwrap();
+        process(&db, &mut client, "LRANGE jobs 0 -1").await.unwrap();
+
+        // The waiter is served once the whole transaction ran
+        let result = process(&db, &mut client, "EXEC").await.unwrap();
+        assert_eq!(
+            result.to_bytes(),
+            b"*2\r\n:2\r\n*2\r\n$1\r\na\r\n$1\r\nb\r\n"
+        );
+        assert_eq!(
+            served(&mut waiter).unwrap(),
+            b"*2\r\n$4\r\njobs\r\n$1\r\na\r\n"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use sider::client::{Client, Protocol};
+    use sider::commands::build::build_hello_command;
+    use sider::commands::misc::*;
+    use sider::errors::SiderError;
+
+    #[test]
+    fn test_hello_switches_protocol() {
+        let mut client = Client::new();
+        assert_eq!(client.protocol, Protocol::Resp2);
+
+        let args = [
+            Bytes::from("3"),
+            Bytes::from("AUTH"),
+            Bytes::from("default"),
+            Bytes::from("secret"),
+            Bytes::from("SETNAME"),
+            Bytes::from("worker-1"),
+        ];
+        let command = build_hello_command(&args).unwrap();
+
+        let result = hello(&mut client, command).unwrap();
+        assert_eq!(client.protocol, Protocol::Resp3);
+        assert_eq!(client.name, Some(Bytes::from("worker-1")));
+
+        let encoded = result.encode_for(client.protocol);
+        assert!(encoded.starts_with(b"%7\r\n$6\r\nserver\r\n$5\r\nsider\r\n"));
+        assert!(encoded
+            .windows(b"$5\r\nproto\r\n:3\r\n".len())
+            .any(|w| w == b"$5\r\nproto\r\n:3\r\n"));
+    }
+
+    #[test]
+    fn test_hello_errors() {
+        let mut client = Client::new();
+
+        let command = build_hello_command(&[Bytes::from("4")]).unwrap();
+        let result = hello(&mut client, command);
+        assert!(matches!(result, Err(SiderError::NoProto)));
+        assert_eq!(client.protocol, Protocol::Resp2);
+
+        let args = [
+            Bytes::from("3"),
+            Bytes::from("AUTH"),
+            Bytes::from("admin"),
+            Bytes::from("secret"),
+        ];
+        let command = build_hello_command(&args).unwrap();
+        let result = hello(&mut client, command);
+        assert!(matches!(result, Err(SiderError::WrongPass)));
+
+        let result = build_hello_command(&[Bytes::from("3"), Bytes::from("AUTH")]);
+        assert!(matches!(result, Err(SiderError::Syntax)));
+    }
+}
@@ -1,3 +1,9 @@
 pub mod db;
 pub mod hashsets;
 pub mod keys;
+pub mod lists;
+pub mod misc;
+pub mod pubsub;
+pub mod streams;
+pub mod transactions;
+pub mod zsets;
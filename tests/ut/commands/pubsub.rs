@@ -0,0 +1,229 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::process;
+    use sider::client::{Client, Protocol};
+    use sider::errors::SiderError;
+    use sider::response::SiderResponse;
+    use sider::types::*;
+    use std::sync::Arc;
+
+    // Subscriptions are server wide, each test uses its own channel names
+    fn setup_db() -> Db {
+        Arc::new(Store::new())
+    }
+
+    fn next_message(client: &mut Client) -> Vec<u8> {
+        client.messages.try_recv().unwrap().to_bytes()
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_and_publish() {
+        let db = setup_db();
+        let mut subscriber = Client::new();
+        let mut publisher = Client::new();
+
+        let result = process(&db, &mut subscriber, "SUBSCRIBE ut:news ut:sport")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            result,
+            b"*3\r\n$9\r\nsubscribe\r\n$7\r\nut:news\r\n:1\r\n*3\r\n$9\r\nsubscribe\r\n$8\r\nut:sport\r\n:2\r\n"
+        );
+
+        let result = process(&db, &mut publisher, "PUBLISH ut:news hello")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":1\r\n");
+        assert_eq!(
+            next_message(&mut subscriber),
+            b"*3\r\n$7\r\nmessage\r\n$7\r\nut:news\r\n$5\r\nhello\r\n"
+        );
+
+        let result = process(&db, &mut publisher, "PUBLISH ut:nobody hello")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":0\r\n");
+        assert!(subscriber.messages.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_psubscribe() {
+        let db = setup_db();
+        let mut subscriber = Client::new();
+        let mut publisher = Client::new();
+
+        process(&db, &mut subscriber, "PSUBSCRIBE ut:pattern:*")
+            .await
+            .unwrap();
+
+        let result = process(&db, &mut publisher, "PUBLISH ut:pattern:1 hi")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":1\r\n");
+        assert_eq!(
+            next_message(&mut subscriber),
+            b"*4\r\n$8\r\npmessage\r\n$12\r\nut:pattern:*\r\n$12\r\nut:pattern:1\r\n$2\r\nhi\r\n"
+        );
+
+        let result = process(&db, &mut subscriber, "PUNSUBSCRIBE")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            result,
+            b"*3\r\n$12\r\npunsubscribe\r\n$12\r\nut:pattern:*\r\n:0\r\n"
+        );
+
+        let result = process(&db, &mut publisher, "PUBLISH ut:pattern:1 hi")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":0\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_mode() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "SUBSCRIBE ut:mode")
+            .await
+            .unwrap();
+
+        let result = process(&db, &mut client, "GET key").await;
+        assert!(matches!(result, Err(SiderError::SubscribeMode)));
+
+        let result = process(&db, &mut client, "PING").await.unwrap().to_bytes();
+        assert_eq!(result, b"*2\r\n$4\r\npong\r\n$0\r\n\r\n");
+
+        let result = process(&db, &mut client, "UNSUBSCRIBE")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            result,
+            b"*3\r\n$11\r\nunsubscribe\r\n$7\r\nut:mode\r\n:0\r\n"
+        );
+
+        let result = process(&db, &mut client, "GET key")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"$-1\r\n");
+
+        // Nothing left to unsubscribe from
+        let result = process(&db, &mut client, "UNSUBSCRIBE")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"*3\r\n$11\r\nunsubscribe\r\n$-1\r\n:0\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_resp3_commands_while_subscribed() {
+        let db = setup_db();
+        let mut client = Client::new();
+        client.protocol = Protocol::Resp3;
+
+        let result = process(&db, &mut client, "SUBSCRIBE ut:resp3")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.encode_for(Protocol::Resp3),
+            b">3\r\n$9\r\nsubscribe\r\n$8\r\nut:resp3\r\n:1\r\n"
+        );
+
+        let result = process(&db, &mut client, "SET key value")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"+OK\r\n");
+
+        process(&db, &mut client, "PUBLISH ut:resp3 self")
+            .await
+            .unwrap();
+        let message = client.messages.try_recv().unwrap();
+        assert_eq!(
+            message.encode_for(Protocol::Resp3),
+            b">3\r\n$7\r\nmessage\r\n$8\r\nut:resp3\r\n$4\r\nself\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pubsub_introspection() {
+        let db = setup_db();
+        let mut first = Client::new();
+        let mut second = Client::new();
+        let mut other = Client::new();
+
+        process(&db, &mut first, "SUBSCRIBE ut:intro:a ut:intro:b")
+            .await
+            .unwrap();
+        process(&db, &mut second, "SUBSCRIBE ut:intro:a")
+            .await
+            .unwrap();
+
+        let result = process(
+            &db,
+            &mut other,
+            "PUBSUB NUMSUB ut:intro:a ut:intro:b ut:intro:c",
+        )
+        .await
+        .unwrap()
+        .encode_for(Protocol::Resp3);
+        assert_eq!(
+            result,
+            b"*6\r\n$10\r\nut:intro:a\r\n:2\r\n$10\r\nut:intro:b\r\n:1\r\n$10\r\nut:intro:c\r\n:0\r\n"
+        );
+
+        let result = process(&db, &mut other, "PUBSUB CHANNELS ut:intro:*")
+            .await
+            .unwrap();
+        let SiderResponse::Array(mut channels) = result else {
+            panic!("expected an array");
+        };
+        channels.sort_by_key(|channel| channel.to_bytes());
+        assert_eq!(
+            SiderResponse::Array(channels).to_bytes(),
+            b"*2\r\n$10\r\nut:intro:a\r\n$10\r\nut:intro:b\r\n"
+        );
+
+        // Closing a connection drops its subscriptions
+        drop(first);
+        let result = process(&db, &mut other, "PUBSUB NUMSUB ut:intro:a ut:intro:b")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            result,
+            b"*4\r\n$10\r\nut:intro:a\r\n:1\r\n$10\r\nut:intro:b\r\n:0\r\n"
+        );
+
+        let result = process(&db, &mut other, "PUBSUB HELP").await;
+        assert!(matches!(result, Err(SiderError::UnknownSubcommand)));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_inside_multi() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        let result = process(&db, &mut client, "SUBSCRIBE ut:multi").await;
+        assert!(matches!(result, Err(SiderError::NotAllowedInMulti)));
+
+        // PUBLISH can be queued, but the transaction fails as a whole
+        let result = process(&db, &mut client, "PUBLISH ut:multi hello")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"+QUEUED\r\n");
+        let result = process(&db, &mut client, "EXEC").await;
+        assert!(matches!(result, Err(SiderError::ExecAbort)));
+        assert!(client.channels.is_empty());
+    }
+}
@@ -0,0 +1,458 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::{process, served};
+    use bytes::Bytes;
+    use sider::client::Client;
+    use sider::errors::SiderError;
+    use sider::process::{execute_command, logged_commands};
+    use sider::types::*;
+    use std::sync::Arc;
+
+    fn setup_db() -> Db {
+        Arc::new(Store::new())
+    }
+
+    #[test]
+    fn test_stream_id() {
+        assert_eq!(StreamId::parse(b"5-3", 0), Some(StreamId::new(5, 3)));
+        assert_eq!(
+            StreamId::parse(b"5", u64::MAX),
+            Some(StreamId::new(5, u64::MAX))
+        );
+        assert_eq!(StreamId::parse(b"5-x", 0), None);
+        assert_eq!(StreamId::parse(b"-1", 0), None);
+
+        assert_eq!(StreamId::new(5, u64::MAX).next(), Some(StreamId::new(6, 0)));
+        assert_eq!(StreamId::new(6, 0).prev(), Some(StreamId::new(5, u64::MAX)));
+        assert_eq!(StreamId::MAX.next(), None);
+        assert_eq!(StreamId::MIN.prev(), None);
+        assert_eq!(StreamId::new(1, 2).to_bytes(), Bytes::from("1-2"));
+    }
+
+    #[tokio::test]
+    async fn test_xadd_xrange() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        let result = process(&db, &mut client, "XADD s 1-1 a 1")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"$3\r\n1-1\r\n");
+        let result = process(&db, &mut client, "XADD s 1-* b 2")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"$3\r\n1-2\r\n");
+        let result = process(&db, &mut client, "XADD s 3 c 3")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"$3\r\n3-0\r\n");
+
+        let result = process(&db, &mut client, "XADD s 2-0 d 4").await;
+        assert!(matches!(result, Err(SiderError::StreamIdTooSmall)));
+        let result = process(&db, &mut client, "XADD t 0-0 d 4").await;
+        assert!(matches!(result, Err(SiderError::StreamIdZero)));
+        let result = process(&db, &mut client, "XADD s 4-0 d").await;
+        assert!(matches!(result, Err(SiderError::WrongNumberArgs)));
+        let result = process(&db, &mut client, "XADD s NOMKSTREAM 4-x d 4").await;
+        assert!(matches!(result, Err(SiderError::InvalidStreamId)));
+
+        let result = process(&db, &mut client, "XADD t NOMKSTREAM * d 4")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"$-1\r\n");
+        assert_eq!(
+            process(&db, &mut client, "XLEN s")
+                .await
+                .unwrap()
+                .to_bytes(),
+            b":3\r\n"
+        );
+        assert_eq!(
+            process(&db, &mut client, "XLEN t")
+                .await
+                .unwrap()
+                .to_bytes(),
+            b":0\r\n"
+        );
+
+        let result = process(&db, &mut client, "XRANGE s (1-1 +")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            result,
+            b"*2\r\n*2\r\n$3\r\n1-2\r\n*2\r\n$1\r\nb\r\n$1\r\n2\r\n\
+              *2\r\n$3\r\n3-0\r\n*2\r\n$1\r\nc\r\n$1\r\n3\r\n"
+        );
+
+        let result = process(&db, &mut client, "XREVRANGE s + - COUNT 1")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            result,
+            b"*1\r\n*2\r\n$3\r\n3-0\r\n*2\r\n$1\r\nc\r\n$1\r\n3\r\n"
+        );
+
+        // Milliseconds alone cover every sequence number
+        let result = process(&db, &mut client, "XRANGE s 1 1")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(&result[..4], b"*2\r\n");
+        let result = process(&db, &mut client, "XRANGE s 3 1")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"*0\r\n");
+
+        let result = process(&db, &mut client, "XDEL s 1-2 9-9")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":1\r\n");
+
+        // The stream is kept once empty, and IDs keep growing
+        process(&db, &mut client, "XDEL s 1-1 3-0").await.unwrap();
+        assert_eq!(
+            process(&db, &mut client, "XLEN s")
+                .await
+                .unwrap()
+                .to_bytes(),
+            b":0\r\n"
+        );
+        let result = process(&db, &mut client, "XADD s 3-0 d 4").await;
+        assert!(matches!(result, Err(SiderError::StreamIdTooSmall)));
+
+        process(&db, &mut client, "SET str value").await.unwrap();
+        let result = process(&db, &mut client, "XADD str * a 1").await;
+        assert!(matches!(result, Err(SiderError::WrongType)));
+    }
+
+    #[tokio::test]
+    async fn test_xadd_xtrim() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        for id in 1..=5 {
+            let command = format!("XADD s MAXLEN 3 {}-0 f v", id);
+            process(&db, &mut client, &command).await.unwrap();
+        }
+        let result = process(&db, &mut client, "XRANGE s - + COUNT 1")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert!(result.starts_with(b"*1\r\n*2\r\n$3\r\n3-0\r\n"));
+
+        let result = process(&db, &mut client, "XTRIM s MINID 5")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":2\r\n");
+        let result = process(&db, &mut client, "XTRIM s MAXLEN = 0")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":1\r\n");
+
+        let result = process(&db, &mut client, "XTRIM s MAXLEN 0 LIMIT 10").await;
+        assert!(matches!(result, Err(SiderError::LimitWithoutApprox)));
+        let result = process(&db, &mut client, "XTRIM s MAXLEN -1").await;
+        assert!(matches!(result, Err(SiderError::NegativeMaxLen)));
+
+        process(&db, &mut client, "XADD s 6-0 f v").await.unwrap();
+        let result = process(&db, &mut client, "XSETID s 5-0").await;
+        assert!(matches!(result, Err(SiderError::StreamLastIdTooSmall)));
+        let result = process(&db, &mut client, "XSETID s 9-0")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"+OK\r\n");
+        let result = process(&db, &mut client, "XADD s 8-0 f v").await;
+        assert!(matches!(result, Err(SiderError::StreamIdTooSmall)));
+    }
+
+    #[test]
+    fn test_xadd_is_logged_with_its_id() {
+        let mut db = Keyspace::new();
+        let args = ["XADD", "s", "MAXLEN", "~", "10", "*", "f", "v"]
+            .map(|arg| Bytes::copy_from_slice(arg.as_bytes()));
+        let command = sider::parser::parse_command(args.to_vec()).unwrap();
+
+        let reply = execute_command(&mut db, &mut Client::new(), command.clone()).unwrap();
+        let logged = logged_commands(&mut db, command);
+        assert_eq!(logged.len(), 1);
+
+        // Replaying the AOF must add the entry with the same ID
+        let CommandArgs::KeyWithValues { values, .. } = &logged[0].args else {
+            panic!("XADD is logged with its arguments");
+        };
+        let id = values[values.len() - 3].clone();
+        assert_eq!(
+            reply.to_bytes(),
+            format!("${}\r\n{}\r\n", id.len(), id.escape_ascii()).as_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_consumer_group() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        let result = process(&db, &mut client, "XGROUP CREATE s g $").await;
+        assert!(matches!(result, Err(SiderError::XGroupKeyMissing)));
+        let result = process(&db, &mut client, "XGROUP CREATE s g $ MKSTREAM")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"+OK\r\n");
+        let result = process(&db, &mut client, "XGROUP CREATE s g 0").await;
+        assert!(matches!(result, Err(SiderError::BusyGroup)));
+
+        process(&db, &mut client, "XADD s 1-0 a 1").await.unwrap();
+        process(&db, &mut client, "XADD s 2-0 b 2").await.unwrap();
+
+        let result = process(
+            &db,
+            &mut client,
+            "XREADGROUP GROUP g alice COUNT 1 STREAMS s >",
+        )
+        .await
+        .unwrap()
+        .to_bytes();
+        assert_eq!(
+            result,
+            b"*1\r\n*2\r\n$1\r\ns\r\n*1\r\n*2\r\n$3\r\n1-0\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n"
+        );
+        let result = process(&db, &mut client, "XREADGROUP GROUP g bob STREAMS s >")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert!(result.ends_with(b"*1\r\n*2\r\n$3\r\n2-0\r\n*2\r\n$1\r\nb\r\n$1\r\n2\r\n"));
+
+        // Nothing new to deliver
+        let result = process(&db, &mut client, "XREADGROUP GROUP g bob STREAMS s >")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"*-1\r\n");
+
+        let result = process(&db, &mut client, "XPENDING s g")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            result,
+            b"*4\r\n:2\r\n$3\r\n1-0\r\n$3\r\n2-0\r\n\
+              *2\r\n*2\r\n$5\r\nalice\r\n$1\r\n1\r\n*2\r\n$3\r\nbob\r\n$1\r\n1\r\n"
+        );
+
+        // The history of a consumer is its pending entries
+        let result = process(&db, &mut client, "XREADGROUP GROUP g alice STREAMS s 0")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert!(result.ends_with(b"*1\r\n*2\r\n$3\r\n1-0\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n"));
+
+        // Deleted entries stay pending, without their fields
+        process(&db, &mut client, "XDEL s 1-0").await.unwrap();
+        let result = process(&db, &mut client, "XREADGROUP GROUP g alice STREAMS s 0")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert!(result.ends_with(b"*1\r\n*2\r\n$3\r\n1-0\r\n*-1\r\n"));
+
+        let result = process(&db, &mut client, "XACK s g 1-0 2-0 3-0")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":2\r\n");
+        let result = process(&db, &mut client, "XACK s missing 1-0")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":0\r\n");
+        let result = process(&db, &mut client, "XPENDING s g")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"*4\r\n:0\r\n$-1\r\n$-1\r\n*-1\r\n");
+
+        let result = process(&db, &mut client, "XREADGROUP GROUP missing c STREAMS s >").await;
+        assert!(matches!(result, Err(SiderError::NoGroup(..))));
+
+        let result = process(&db, &mut client, "XGROUP DELCONSUMER s g alice")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":0\r\n");
+        let result = process(&db, &mut client, "XGROUP DESTROY s g")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":1\r\n");
+        let result = process(&db, &mut client, "XGROUP DESTROY s g")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b":0\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_xclaim_xautoclaim() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "XGROUP CREATE s g 0 MKSTREAM")
+            .await
+            .unwrap();
+        for id in 1..=3 {
+            let command = format!("XADD s {}-0 f v", id);
+            process(&db, &mut client, &command).await.unwrap();
+        }
+        process(&db, &mut client, "XREADGROUP GROUP g alice STREAMS s >")
+            .await
+            .unwrap();
+
+        // Not idle for long enough yet
+        let result = process(&db, &mut client, "XCLAIM s g bob 100000 1-0")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"*0\r\n");
+
+        let result = process(&db, &mut client, "XCLAIM s g bob 0 1-0 JUSTID")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"*1\r\n$3\r\n1-0\r\n");
+
+        let result = process(&db, &mut client, "XPENDING s g - + 10 bob")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert!(result.starts_with(b"*1\r\n*4\r\n$3\r\n1-0\r\n$3\r\nbob\r\n"));
+
+        let result = process(&db, &mut client, "XAUTOCLAIM s g carol 0 0 COUNT 1 JUSTID")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"*3\r\n$3\r\n2-0\r\n*1\r\n$3\r\n1-0\r\n*0\r\n");
+
+        // Claimed entries that were deleted are dropped from the PEL
+        process(&db, &mut client, "XDEL s 2-0").await.unwrap();
+        let result = process(
+            &db,
+            &mut client,
+            "XAUTOCLAIM s g carol 0 2-0 COUNT 1 JUSTID",
+        )
+        .await
+        .unwrap()
+        .to_bytes();
+        assert_eq!(
+            result,
+            b"*3\r\n$3\r\n0-0\r\n*1\r\n$3\r\n3-0\r\n*1\r\n$3\r\n2-0\r\n"
+        );
+
+        let result = process(&db, &mut client, "XPENDING s g")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert!(result.starts_with(b"*4\r\n:2\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_xread() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "XADD a 1-0 f 1").await.unwrap();
+        process(&db, &mut client, "XADD b 2-0 f 2").await.unwrap();
+
+        let result = process(&db, &mut client, "XREAD COUNT 1 STREAMS a b 0 1-0")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            result,
+            b"*2\r\n*2\r\n$1\r\na\r\n*1\r\n*2\r\n$3\r\n1-0\r\n*2\r\n$1\r\nf\r\n$1\r\n1\r\n\
+              *2\r\n$1\r\nb\r\n*1\r\n*2\r\n$3\r\n2-0\r\n*2\r\n$1\r\nf\r\n$1\r\n2\r\n"
+        );
+
+        let result = process(&db, &mut client, "XREAD STREAMS a b $ $")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"*-1\r\n");
+
+        let result = process(&db, &mut client, "XREAD STREAMS a b 0").await;
+        assert!(matches!(result, Err(SiderError::UnbalancedStreams { .. })));
+        let result = process(&db, &mut client, "XREAD BLOCK -1 STREAMS a 0").await;
+        assert!(matches!(result, Err(SiderError::NegativeTimeout)));
+    }
+
+    #[tokio::test]
+    async fn test_xread_block_is_served_by_xadd() {
+        let db = setup_db();
+        let mut reader = Client::new();
+        let mut group_reader = Client::new();
+        let mut writer = Client::new();
+
+        process(&db, &mut writer, "XGROUP CREATE s g $ MKSTREAM")
+            .await
+            .unwrap();
+
+        let result = process(&db, &mut reader, "XREAD BLOCK 0 STREAMS s $")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"*-1\r\n");
+        assert_eq!(reader.blocked.as_ref().unwrap().timeout, None);
+
+        let command = "XREADGROUP GROUP g c BLOCK 100 STREAMS s >";
+        process(&db, &mut group_reader, command).await.unwrap();
+        assert_eq!(served(&mut group_reader), None);
+
+        process(&db, &mut writer, "XADD s 1-0 f v").await.unwrap();
+
+        // Both are served the same entry, unlike list waiters
+        let entry =
+            b"*1\r\n*2\r\n$1\r\ns\r\n*1\r\n*2\r\n$3\r\n1-0\r\n*2\r\n$1\r\nf\r\n$1\r\nv\r\n";
+        assert_eq!(served(&mut reader).unwrap(), entry);
+        assert_eq!(served(&mut group_reader).unwrap(), entry);
+
+        assert_eq!(db.blocked_clients(), 0);
+
+        let result = process(&db, &mut writer, "XPENDING s g")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert!(result.starts_with(b"*4\r\n:1\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_xreadgroup_block_on_destroyed_group() {
+        let db = setup_db();
+        let mut reader = Client::new();
+        let mut writer = Client::new();
+
+        process(&db, &mut writer, "XGROUP CREATE s g $ MKSTREAM")
+            .await
+            .unwrap();
+        process(&db, &mut reader, "XREADGROUP GROUP g c BLOCK 0 STREAMS s >")
+            .await
+            .unwrap();
+
+        process(&db, &mut writer, "XGROUP DESTROY s g")
+            .await
+            .unwrap();
+        assert_eq!(
+            served(&mut reader).unwrap(),
+            b"-NOGROUP No such key 's' or consumer group 'g'\r\n"
+        );
+    }
+}
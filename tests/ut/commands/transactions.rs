@@ -0,0 +1,265 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::process;
+    use sider::client::Client;
+    use sider::errors::SiderError;
+    use sider::types::*;
+    use std::sync::Arc;
+
+    fn setup_db() -> Db {
+        Arc::new(Store::new())
+    }
+
+    #[tokio::test]
+    async fn test_exec_runs_queued_commands() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        let result = process(&db, &mut client, "MULTI").await.unwrap().to_bytes();
+        assert_eq!(result, b"+OK\r\n");
+
+        let result = process(&db, &mut client, "SET key 1")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"+QUEUED\r\n");
+        let result = process(&db, &mut client, "INCR key")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"+QUEUED\r\n");
+
+        // Nothing runs before EXEC
+        assert!(db.lock_all().await.is_empty());
+
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(result, b"*2\r\n+OK\r\n:2\r\n");
+        assert!(client.transaction.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_exec_reports_runtime_errors() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "RPUSH list a").await.unwrap();
+        process(&db, &mut client, "MULTI").await.unwrap();
+        process(&db, &mut client, "INCR list").await.unwrap();
+        process(&db, &mut client, "SET other value").await.unwrap();
+
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(
+            result,
+            b"*2\r\n-WRONGTYPE Operation against a key holding the wrong kind of value\r\n+OK\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exec_abort() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        process(&db, &mut client, "SET key value").await.unwrap();
+
+        let result = process(&db, &mut client, "LPUSH key").await;
+        assert!(matches!(result, Err(SiderError::WrongNumberArgs)));
+
+        let result = process(&db, &mut client, "EXEC").await;
+        assert!(matches!(result, Err(SiderError::ExecAbort)));
+        assert!(db.lock_all().await.is_empty());
+
+        // The connection is usable again
+        let result = process(&db, &mut client, "SET key value")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"+OK\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_discard() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        process(&db, &mut client, "SET key value").await.unwrap();
+
+        let result = process(&db, &mut client, "DISCARD")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"+OK\r\n");
+        assert!(db.lock_all().await.is_empty());
+
+        let result = process(&db, &mut client, "DISCARD").await;
+        assert!(matches!(result, Err(SiderError::DiscardWithoutMulti)));
+    }
+
+    #[tokio::test]
+    async fn test_transaction_errors() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        let result = process(&db, &mut client, "EXEC").await;
+        assert!(matches!(result, Err(SiderError::ExecWithoutMulti)));
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        let result = process(&db, &mut client, "MULTI").await;
+        assert!(matches!(result, Err(SiderError::NestedMulti)));
+
+        // A nested MULTI doesn't abort the transaction
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(result, b"*0\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_watch_untouched_key() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "SET stock 10").await.unwrap();
+        process(&db, &mut client, "WATCH stock missing")
+            .await
+            .unwrap();
+        process(&db, &mut client, "MULTI").await.unwrap();
+        process(&db, &mut client, "DECR stock").await.unwrap();
+
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(result, b"*1\r\n:9\r\n");
+        assert!(client.watched.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch_modified_key() {
+        let db = setup_db();
+        let mut client = Client::new();
+        let mut other = Client::new();
+
+        process(&db, &mut client, "SET stock 10").await.unwrap();
+        process(&db, &mut client, "WATCH stock").await.unwrap();
+
+        // Same value, but the key was still written to
+        process(&db, &mut other, "SET stock 10").await.unwrap();
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        process(&db, &mut client, "DECR stock").await.unwrap();
+
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(result, b"*-1\r\n");
+
+        let result = process(&db, &mut client, "GET stock")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"$2\r\n10\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_watch_created_and_flushed_keys() {
+        let db = setup_db();
+        let mut client = Client::new();
+        let mut other = Client::new();
+
+        process(&db, &mut client, "WATCH created").await.unwrap();
+        process(&db, &mut other, "RPUSH created a").await.unwrap();
+        process(&db, &mut client, "MULTI").await.unwrap();
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(result, b"*-1\r\n");
+
+        process(&db, &mut client, "WATCH created").await.unwrap();
+        process(&db, &mut other, "FLUSHDB").await.unwrap();
+        process(&db, &mut client, "MULTI").await.unwrap();
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(result, b"*-1\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_watch_missing_key_set_then_deleted() {
+        let db = setup_db();
+        let mut client = Client::new();
+        let mut other = Client::new();
+
+        process(&db, &mut client, "WATCH stock").await.unwrap();
+
+        // Missing again by EXEC, but it was changed meanwhile
+        process(&db, &mut other, "SET stock 1").await.unwrap();
+        process(&db, &mut other, "DEL stock").await.unwrap();
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        process(&db, &mut client, "SET stock 10").await.unwrap();
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(result, b"*-1\r\n");
+
+        // Nobody watches it anymore
+        assert_eq!(db.lock_all().await.watched_version(b"stock"), None);
+    }
+
+    #[tokio::test]
+    async fn test_watch_ignores_writes_that_change_nothing() {
+        let db = setup_db();
+        let mut client = Client::new();
+        let mut other = Client::new();
+
+        process(&db, &mut client, "SET stock 5").await.unwrap();
+        process(&db, &mut client, "SADD tags a").await.unwrap();
+        process(&db, &mut client, "WATCH stock tags").await.unwrap();
+
+        process(&db, &mut other, "SET stock 1 NX").await.unwrap();
+        process(&db, &mut other, "SADD tags a").await.unwrap();
+        process(&db, &mut other, "SREM tags b").await.unwrap();
+        process(&db, &mut other, "EXPIRE stock 100 XX")
+            .await
+            .unwrap();
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        process(&db, &mut client, "INCR stock").await.unwrap();
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(result, b"*1\r\n:6\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_watch_expired_key() {
+        let db = setup_db();
+        let mut client = Client::new();
+
+        process(&db, &mut client, "SET session token")
+            .await
+            .unwrap();
+        process(&db, &mut client, "WATCH session").await.unwrap();
+
+        // Expire it without going through a command
+        if let Some(DbValue::StringKey(key)) =
+            db.lock_all().await.get_mut(b"session".as_slice())
+        {
+            key.expires_at = Some(0);
+        }
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(result, b"*-1\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_unwatch() {
+        let db = setup_db();
+        let mut client = Client::new();
+        let mut other = Client::new();
+
+        process(&db, &mut client, "WATCH stock").await.unwrap();
+        process(&db, &mut other, "SET stock 1").await.unwrap();
+
+        let result = process(&db, &mut client, "UNWATCH")
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(result, b"+OK\r\n");
+
+        process(&db, &mut client, "MULTI").await.unwrap();
+        let result = process(&db, &mut client, "WATCH stock").await;
+        assert!(matches!(result, Err(SiderError::WatchInsideMulti)));
+
+        let result = process(&db, &mut client, "EXEC").await.unwrap().to_bytes();
+        assert_eq!(result, b"*0\r\n");
+    }
+}
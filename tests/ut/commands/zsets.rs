@@ -0,0 +1,319 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::run;
+    use bytes::Bytes;
+    use sider::errors::SiderError;
+    use sider::types::*;
+    use sider::zset::{LexBound, SortedSet};
+    use std::collections::HashMap;
+    use std::ops::Bound;
+
+    fn setup_db() -> Keyspace {
+        Keyspace::new()
+    }
+
+    fn members(entries: Vec<(&Bytes, f64)>) -> Vec<(String, f64)> {
+        entries
+            .into_iter()
+            .map(|(member, score)| (String::from_utf8_lossy(member).to_string(), score))
+            .collect()
+    }
+
+    #[test]
+    fn test_sorted_set_order() {
+        let mut zset = SortedSet::default();
+        assert!(zset.insert(Bytes::from("b"), 2.0));
+        assert!(zset.insert(Bytes::from("a"), 2.0));
+        assert!(zset.insert(Bytes::from("c"), -0.0));
+        assert!(zset.insert(Bytes::from("d"), 1.0));
+        assert!(!zset.insert(Bytes::from("d"), 3.0));
+
+        assert_eq!(
+            members(zset.iter().collect()),
+            vec![
+                ("c".to_string(), 0.0),
+                ("a".to_string(), 2.0),
+                ("b".to_string(), 2.0),
+                ("d".to_string(), 3.0)
+            ]
+        );
+        assert_eq!(zset.rank(b"b"), Some(2));
+        assert_eq!(zset.remove(b"a"), Some(2.0));
+        assert_eq!(zset.rank(b"b"), Some(1));
+        assert_eq!(zset.len(), 3);
+    }
+
+    #[test]
+    fn test_sorted_set_ranks() {
+        let mut zset = SortedSet::default();
+        let mut model = HashMap::new();
+        for i in 0..1000 {
+            let score = (i * 7919 % 101) as f64;
+            zset.insert(Bytes::from(format!("m{i}")), score);
+            model.insert(format!("m{i}"), score);
+        }
+        for i in (0..1000).step_by(3) {
+            zset.remove(format!("m{i}").as_bytes());
+            model.remove(&format!("m{i}"));
+        }
+        for i in (0..1000).step_by(7) {
+            zset.insert(Bytes::from(format!("m{i}")), -(i as f64));
+            model.insert(format!("m{i}"), -(i as f64) + 0.0);
+        }
+
+        let mut expected: Vec<(String, f64)> = model.into_iter().collect();
+        expected.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        assert_eq!(members(zset.iter().collect()), expected);
+        assert_eq!(zset.len(), expected.len());
+
+        for (rank, (member, _)) in expected.iter().enumerate() {
+            assert_eq!(zset.rank(member.as_bytes()), Some(rank));
+        }
+
+        // Skipping jumps to the rank, from either end
+        let nth = zset.iter().nth(500).map(|(member, _)| member.clone());
+        assert_eq!(nth, Some(Bytes::from(expected[500].0.clone())));
+        let nth = zset.iter().rev().nth(10).map(|(member, _)| member.clone());
+        let last = expected.len() - 11;
+        assert_eq!(nth, Some(Bytes::from(expected[last].0.clone())));
+        assert_eq!(
+            members(zset.iter().skip(20).take(5).collect()),
+            expected[20..25].to_vec()
+        );
+
+        let in_range = expected
+            .iter()
+            .filter(|(_, score)| (10.0..=50.0).contains(score))
+            .count();
+        let range = zset.range_by_score(Bound::Included(10.0), Bound::Included(50.0));
+        assert_eq!(range.count(), in_range);
+
+        while zset.pop_first().is_some() {}
+        assert!(zset.is_empty());
+        assert_eq!(zset.iter().next(), None);
+    }
+
+    #[test]
+    fn test_sorted_set_ranges() {
+        let mut zset = SortedSet::default();
+        for (member, score) in [("a", 1.0), ("b", 2.0), ("c", 2.0), ("d", f64::INFINITY)] {
+            zset.insert(Bytes::from(member), score);
+        }
+
+        let range = |min, max| members(zset.range_by_score(min, max).collect());
+
+        assert_eq!(range(Bound::Included(2.0), Bound::Included(2.0)).len(), 2);
+        assert_eq!(
+            range(Bound::Excluded(1.0), Bound::Excluded(f64::INFINITY)).len(),
+            2
+        );
+        assert_eq!(
+            range(Bound::Included(f64::INFINITY), Bound::Unbounded).len(),
+            1
+        );
+        assert_eq!(
+            range(Bound::Excluded(f64::INFINITY), Bound::Unbounded).len(),
+            0
+        );
+        assert_eq!(range(Bound::Included(3.0), Bound::Included(1.0)).len(), 0);
+        assert_eq!(range(Bound::Excluded(2.0), Bound::Excluded(2.0)).len(), 0);
+
+        let lex = zset.range_by_lex(LexBound::Excluded(Bytes::from("a")), LexBound::Max);
+        assert_eq!(lex.count(), 3);
+        let lex = zset.range_by_lex(LexBound::Max, LexBound::Min);
+        assert_eq!(lex.count(), 0);
+    }
+
+    #[test]
+    fn test_zadd() {
+        let mut db = setup_db();
+
+        assert_eq!(run(&mut db, "ZADD board 1 alice 2 bob").unwrap(), b":2\r\n");
+        assert_eq!(
+            run(&mut db, "ZADD board 3 alice 1 carol").unwrap(),
+            b":1\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZADD board CH 4 alice 1 carol").unwrap(),
+            b":1\r\n"
+        );
+
+        // NX only adds, XX only updates
+        assert_eq!(
+            run(&mut db, "ZADD board NX 9 alice 5 dave").unwrap(),
+            b":1\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZADD board XX CH 6 dave 5 eve").unwrap(),
+            b":1\r\n"
+        );
+        assert_eq!(run(&mut db, "ZSCORE board alice").unwrap(), b"$1\r\n4\r\n");
+        assert_eq!(run(&mut db, "ZSCORE board eve").unwrap(), b"$-1\r\n");
+
+        // GT and LT only restrict updates
+        assert_eq!(
+            run(&mut db, "ZADD board GT CH 1 alice 7 dave").unwrap(),
+            b":1\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZADD board LT CH 3 alice 0 frank").unwrap(),
+            b":2\r\n"
+        );
+        assert_eq!(run(&mut db, "ZSCORE board dave").unwrap(), b"$1\r\n7\r\n");
+
+        assert_eq!(
+            run(&mut db, "ZADD board INCR 2.5 bob").unwrap(),
+            b"$3\r\n4.5\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZADD board NX INCR 1 bob").unwrap(),
+            b"$-1\r\n"
+        );
+        assert_eq!(run(&mut db, "ZADD missing XX 1 bob").unwrap(), b":0\r\n");
+        assert!(!db.contains_key(b"missing".as_slice()));
+
+        assert_eq!(run(&mut db, "ZCARD board").unwrap(), b":5\r\n");
+        assert_eq!(run(&mut db, "ZCARD missing").unwrap(), b":0\r\n");
+    }
+
+    #[test]
+    fn test_zadd_errors() {
+        let mut db = setup_db();
+
+        let result = run(&mut db, "ZADD board NX XX 1 a");
+        assert!(matches!(result, Err(SiderError::XXAndNX)));
+        let result = run(&mut db, "ZADD board GT LT 1 a");
+        assert!(matches!(result, Err(SiderError::GTLTAndNX)));
+        let result = run(&mut db, "ZADD board INCR 1 a 2 b");
+        assert!(matches!(result, Err(SiderError::IncrSinglePair)));
+        let result = run(&mut db, "ZADD board 1 a 2");
+        assert!(matches!(result, Err(SiderError::Syntax)));
+        let result = run(&mut db, "ZADD board 1 a nan b");
+        assert!(matches!(result, Err(SiderError::NotFloat)));
+        assert!(db.is_empty());
+
+        run(&mut db, "ZADD board inf a").unwrap();
+        let result = run(&mut db, "ZINCRBY board -inf a");
+        assert!(matches!(result, Err(SiderError::ScoreNaN)));
+
+        run(&mut db, "SET string 1").unwrap();
+        let result = run(&mut db, "ZADD string 1 a");
+        assert!(matches!(result, Err(SiderError::WrongType)));
+    }
+
+    #[test]
+    fn test_zincrby_zrem_zrank() {
+        let mut db = setup_db();
+
+        assert_eq!(
+            run(&mut db, "ZINCRBY board 2 alice").unwrap(),
+            b"$1\r\n2\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZINCRBY board 1.5 alice").unwrap(),
+            b"$3\r\n3.5\r\n"
+        );
+        run(&mut db, "ZADD board 1 bob 5 carol").unwrap();
+
+        assert_eq!(run(&mut db, "ZRANK board bob").unwrap(), b":0\r\n");
+        assert_eq!(run(&mut db, "ZREVRANK board bob").unwrap(), b":2\r\n");
+        assert_eq!(run(&mut db, "ZRANK board dave").unwrap(), b"$-1\r\n");
+
+        assert_eq!(run(&mut db, "ZREM board bob dave").unwrap(), b":1\r\n");
+        assert_eq!(run(&mut db, "ZREM board alice carol").unwrap(), b":2\r\n");
+        assert!(!db.contains_key(b"board".as_slice()));
+    }
+
+    #[test]
+    fn test_zrange() {
+        let mut db = setup_db();
+        run(&mut db, "ZADD board 1 a 2 b 3 c 4 d").unwrap();
+
+        assert_eq!(
+            run(&mut db, "ZRANGE board 0 -1").unwrap(),
+            b"*4\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n$1\r\nd\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE board 0 1 REV WITHSCORES").unwrap(),
+            b"*4\r\n$1\r\nd\r\n$1\r\n4\r\n$1\r\nc\r\n$1\r\n3\r\n"
+        );
+        assert_eq!(run(&mut db, "ZRANGE board 5 10").unwrap(), b"*0\r\n");
+
+        assert_eq!(
+            run(&mut db, "ZRANGE board (1 3 BYSCORE").unwrap(),
+            b"*2\r\n$1\r\nb\r\n$1\r\nc\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE board +inf -inf BYSCORE REV LIMIT 1 2").unwrap(),
+            b"*2\r\n$1\r\nc\r\n$1\r\nb\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGEBYSCORE board -inf 2 WITHSCORES").unwrap(),
+            b"*4\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$1\r\n2\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGEBYSCORE board 2 +inf LIMIT 0 1").unwrap(),
+            b"*1\r\n$1\r\nb\r\n"
+        );
+
+        run(&mut db, "ZADD names 0 alice 0 bob 0 carol").unwrap();
+        assert_eq!(
+            run(&mut db, "ZRANGE names [b + BYLEX").unwrap(),
+            b"*2\r\n$3\r\nbob\r\n$5\r\ncarol\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE names (carol - BYLEX REV").unwrap(),
+            b"*2\r\n$3\r\nbob\r\n$5\r\nalice\r\n"
+        );
+
+        let result = run(&mut db, "ZRANGE board 0 1 LIMIT 0 1");
+        assert!(matches!(result, Err(SiderError::Syntax)));
+        let result = run(&mut db, "ZRANGE names - + BYLEX WITHSCORES");
+        assert!(matches!(result, Err(SiderError::Syntax)));
+        let result = run(&mut db, "ZRANGE names a + BYLEX");
+        assert!(matches!(result, Err(SiderError::InvalidLexRange)));
+        let result = run(&mut db, "ZRANGEBYSCORE board one 2");
+        assert!(matches!(result, Err(SiderError::MinMaxNotFloat)));
+    }
+
+    #[test]
+    fn test_zcount_zremrangebyscore() {
+        let mut db = setup_db();
+        run(&mut db, "ZADD board 1 a 2 b 3 c 4 d").unwrap();
+
+        assert_eq!(run(&mut db, "ZCOUNT board 2 3").unwrap(), b":2\r\n");
+        assert_eq!(run(&mut db, "ZCOUNT board (2 +inf").unwrap(), b":2\r\n");
+        assert_eq!(run(&mut db, "ZCOUNT missing 0 1").unwrap(), b":0\r\n");
+
+        assert_eq!(
+            run(&mut db, "ZREMRANGEBYSCORE board -inf (3").unwrap(),
+            b":2\r\n"
+        );
+        assert_eq!(run(&mut db, "ZCARD board").unwrap(), b":2\r\n");
+        assert_eq!(
+            run(&mut db, "ZREMRANGEBYSCORE board 0 10").unwrap(),
+            b":2\r\n"
+        );
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn test_zpopmin_zpopmax() {
+        let mut db = setup_db();
+        run(&mut db, "ZADD board 1 a 2 b 3 c").unwrap();
+
+        assert_eq!(
+            run(&mut db, "ZPOPMIN board").unwrap(),
+            b"*2\r\n$1\r\na\r\n$1\r\n1\r\n"
+        );
+        assert_eq!(
+            run(&mut db, "ZPOPMAX board 5").unwrap(),
+            b"*4\r\n$1\r\nc\r\n$1\r\n3\r\n$1\r\nb\r\n$1\r\n2\r\n"
+        );
+        assert!(db.is_empty());
+        assert_eq!(run(&mut db, "ZPOPMIN board").unwrap(), b"*0\r\n");
+
+        let result = run(&mut db, "ZPOPMIN board -1");
+        assert!(matches!(result, Err(SiderError::NotPositive)));
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::run;
+    use bytes::Bytes;
+    use sider::expire::active_expire_cycle;
+    use sider::shard::Shard;
+    use sider::types::*;
+    use sider::utils::current_time_millis;
+    use std::sync::Arc;
+
+    // Expired a long time ago, the keys are still there until deleted
+    fn expire_now(db: &mut Keyspace, key: &str) {
+        assert!(db.set_expires_at(&Bytes::copy_from_slice(key.as_bytes()), Some(1)));
+    }
+
+    #[test]
+    fn test_volatile_index() {
+        let mut db = Keyspace::new();
+
+        run(&mut db, "SET session abc EX 100").unwrap();
+        run(&mut db, "SET name Smith").unwrap();
+        run(&mut db, "RPUSH queue a b").unwrap();
+        run(&mut db, "EXPIRE queue 100").unwrap();
+        assert_eq!(db.volatile_len(), 2);
+
+        run(&mut db, "PERSIST queue").unwrap();
+        assert_eq!(db.volatile_len(), 1);
+
+        // Overwriting without KEEPTTL drops the expiry
+        run(&mut db, "SET session def").unwrap();
+        assert_eq!(db.volatile_len(), 0);
+
+        run(&mut db, "SET session abc PX 100000").unwrap();
+        run(&mut db, "DEL session").unwrap();
+        assert_eq!(db.volatile_len(), 0);
+    }
+
+    #[test]
+    fn test_lazy_expiry() {
+        let mut db = Keyspace::new();
+
+        run(&mut db, "SET name Smith").unwrap();
+        run(&mut db, "RPUSH queue a b").unwrap();
+        run(&mut db, "HSET user name Smith").unwrap();
+        expire_now(&mut db, "name");
+        expire_now(&mut db, "queue");
+        expire_now(&mut db, "user");
+
+        assert_eq!(run(&mut db, "GET name").unwrap(), b"$-1\r\n");
+        assert_eq!(run(&mut db, "LRANGE queue 0 -1").unwrap(), b"*0\r\n");
+        // Written to as a new key, the old fields are gone
+        assert_eq!(run(&mut db, "HSET user age 21").unwrap(), b":1\r\n");
+        assert_eq!(run(&mut db, "HGET user name").unwrap(), b"$-1\r\n");
+
+        assert_eq!(db.expire_stats().expired_keys, 3);
+        assert_eq!(db.volatile_len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_active_expire_cycle() {
+        let db: Db = Arc::new(Store::new());
+
+        {
+            let mut db = db.lock_all().await;
+            for i in 0..50 {
+                run(&mut db, &format!("SET expired:{i} value")).unwrap();
+                expire_now(&mut db, &format!("expired:{i}"));
+            }
+            run(&mut db, "SET persistent value").unwrap();
+        }
+
+        // Resumed from a later shard, the cycle wraps around to the first
+        // ones. Every sampled key is expired, sampling goes on until none is
+        // left.
+        db.set_expire_cursor(7);
+        active_expire_cycle(&db).await;
+        assert_eq!(db.expire_cursor(), 7);
+
+        let db = db.lock_all().await;
+        assert_eq!(db.len(), 1);
+        assert_eq!(db.volatile_len(), 0);
+        assert_eq!(db.expire_stats().expired_keys, 50);
+        assert!(db.expire_stats().expired_stale_perc > 0.0);
+    }
+
+    #[test]
+    fn test_expire_batch_keeps_live_keys() {
+        let mut shard = Shard::new();
+
+        for i in 0..10 {
+            let key = Bytes::from(format!("live:{i}"));
+            let value = DbValue::StringKey(Key {
+                name: key.clone(),
+                data: Some(Bytes::from("value")),
+                expires_at: Some(current_time_millis() + 100_000),
+            });
+            shard.insert(key, value);
+        }
+
+        assert_eq!(shard.expire_batch(), (10, 0));
+        assert_eq!(shard.volatile_len(), 10);
+    }
+
+    #[test]
+    fn test_info() {
+        let mut db = Keyspace::new();
+
+        run(&mut db, "SET name Smith").unwrap();
+        run(&mut db, "SET session abc EX 100").unwrap();
+        run(&mut db, "SET gone abc").unwrap();
+        expire_now(&mut db, "gone");
+        run(&mut db, "GET gone").unwrap();
+
+        let info = String::from_utf8(run(&mut db, "INFO keyspace stats").unwrap()).unwrap();
+        assert!(info.contains("# Stats\r\nexpired_keys:1\r\n"));
+        assert!(info.contains("# Keyspace\r\ndb0:keys=2,expires=1\r\n"));
+        assert!(!info.contains("# Server"));
+
+        let info = String::from_utf8(run(&mut db, "INFO").unwrap()).unwrap();
+        assert!(info.contains("# Server\r\nsider_version:"));
+    }
+}
@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::process;
+    use bytes::Bytes;
+    use sider::client::Client;
+    use sider::shard::shard_index;
+    use sider::types::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    // Two keys landing in different shards
+    fn keys_of_different_shards(db: &Store) -> (Bytes, Bytes) {
+        let count = db.shards().len();
+        let first = Bytes::from("shard:0");
+        let second = (1..)
+            .map(|i| Bytes::from(format!("shard:{i}")))
+            .find(|key| shard_index(key, count) != shard_index(&first, count))
+            .unwrap();
+
+        (first, second)
+    }
+
+    #[tokio::test]
+    async fn test_shards_are_locked_independently() {
+        let db: Db = Arc::new(Store::new());
+        let (first, second) = keys_of_different_shards(&db);
+
+        let held = db.lock(std::slice::from_ref(&first)).await;
+
+        // Another shard is free while the first one is held...
+        let mut other = timeout(
+            Duration::from_secs(1),
+            db.lock(std::slice::from_ref(&second)),
+        )
+        .await
+        .unwrap();
+        assert!(other.get(&second).is_none());
+        drop(other);
+
+        // ...unlike the same one
+        assert!(timeout(
+            Duration::from_millis(50),
+            db.lock(std::slice::from_ref(&first))
+        )
+        .await
+        .is_err());
+        drop(held);
+
+        other = db.lock(&[second, first]).await;
+        assert!(other.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_multi_key_commands_dont_deadlock() {
+        let db: Db = Arc::new(Store::new());
+        let keys: Vec<String> = (0..8).map(|i| format!("multi:{i}")).collect();
+
+        // Every client goes through the same keys in another order
+        let clients: Vec<_> = (0..8)
+            .map(|client_number| {
+                let db = db.clone();
+                let mut keys = keys.clone();
+                keys.rotate_left(client_number);
+                if client_number % 2 == 1 {
+                    keys.reverse();
+                }
+
+                tokio::spawn(async move {
+                    let mut client = Client::new();
+                    let keys = keys.join(" ");
+
+                    for _ in 0..200 {
+                        process(&db, &mut client, &format!("EXISTS {keys}"))
+                            .await
+                            .unwrap();
+                        process(&db, &mut client, &format!("DEL {keys}"))
+                            .await
+                            .unwrap();
+                        process(&db, &mut client, "MULTI").await.unwrap();
+                        for key in keys.split(' ') {
+                            process(&db, &mut client, &format!("SET {key} value"))
+                                .await
+                                .unwrap();
+                        }
+                        process(&db, &mut client, "EXEC").await.unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for client in clients {
+            timeout(Duration::from_secs(30), client)
+                .await
+                .expect("Clients are deadlocked")
+                .unwrap();
+        }
+
+        let mut client = Client::new();
+        let exists = format!("EXISTS {}", keys.join(" "));
+        assert_eq!(
+            process(&db, &mut client, &exists).await.unwrap().to_bytes(),
+            b":8\r\n"
+        );
+    }
+}
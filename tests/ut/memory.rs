@@ -0,0 +1,179 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::run;
+    use bytes::Bytes;
+    use sider::errors::SiderError;
+    use sider::memory::*;
+    use sider::shard::Shard;
+    use sider::types::*;
+    use sider::utils::current_time_millis;
+    use std::time::Duration;
+
+    fn string_value(key: &str, expires_at: Option<i64>) -> (Bytes, DbValue) {
+        let key = Bytes::copy_from_slice(key.as_bytes());
+        let value = DbValue::StringKey(Key::new(
+            key.clone(),
+            Some(Bytes::from("value")),
+            expires_at,
+        ));
+
+        (key, value)
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory(b"100"), Some(100));
+        assert_eq!(parse_memory(b"1k"), Some(1000));
+        assert_eq!(parse_memory(b"1kb"), Some(1024));
+        assert_eq!(parse_memory(b"2MB"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_memory(b"1gb"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_memory(b"10x"), None);
+        assert_eq!(parse_memory(b"mb"), None);
+
+        assert_eq!(format_memory(100), "100B");
+        assert_eq!(format_memory(1572864), "1.50M");
+
+        assert_eq!(
+            EvictionPolicy::parse(b"ALLKEYS-LRU"),
+            Some(EvictionPolicy::AllKeysLru)
+        );
+        assert_eq!(EvictionPolicy::VolatileTtl.name(), "volatile-ttl");
+        assert_eq!(EvictionPolicy::parse(b"lru"), None);
+    }
+
+    #[test]
+    fn test_memory_accounting() {
+        let mut db = Keyspace::new();
+        assert_eq!(db.used_memory(), 0);
+
+        run(&mut db, "SET name Smith").unwrap();
+        let string = db.used_memory();
+        assert!(string > 0);
+
+        // Changed in place, measured again once the command is done
+        run(&mut db, "RPUSH queue a").unwrap();
+        let one_element = db.used_memory();
+        run(&mut db, "RPUSH queue b c d e f g h").unwrap();
+        assert!(db.used_memory() > one_element);
+
+        run(&mut db, "DEL queue").unwrap();
+        assert_eq!(db.used_memory(), string);
+
+        run(&mut db, "FLUSHDB").unwrap();
+        assert_eq!(db.used_memory(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_store_memory() {
+        let db = Store::new();
+
+        let mut keyspace = db.lock_all().await;
+        run(&mut keyspace, "SET name Smith").unwrap();
+        run(&mut keyspace, "SADD tags a b c").unwrap();
+        let used = keyspace.used_memory();
+        drop(keyspace);
+
+        // Added up across shards without locking them
+        assert_eq!(db.used_memory(), used);
+    }
+
+    #[test]
+    fn test_eviction_candidate() {
+        let now = current_time_millis();
+        let mut shard = Shard::new();
+
+        for (key, expires_at) in [
+            ("later", Some(now + 50_000)),
+            ("soon", Some(now + 1_000)),
+            ("never", None),
+        ] {
+            let (key, value) = string_value(key, expires_at);
+            shard.insert(key, value);
+        }
+
+        // Enough samples to see every key
+        let candidate = shard.eviction_candidate(EvictionPolicy::VolatileTtl, 200);
+        assert_eq!(candidate, Some(Bytes::from("soon")));
+
+        let candidate = shard.eviction_candidate(EvictionPolicy::VolatileRandom, 200);
+        assert_ne!(candidate, Some(Bytes::from("never")));
+
+        shard.evict(&Bytes::from("soon"));
+        shard.evict(&Bytes::from("later"));
+        assert_eq!(shard.evicted_keys(), 2);
+        assert_eq!(
+            shard.eviction_candidate(EvictionPolicy::VolatileLru, 200),
+            None
+        );
+        assert_eq!(
+            shard.eviction_candidate(EvictionPolicy::AllKeysRandom, 200),
+            Some(Bytes::from("never"))
+        );
+    }
+
+    #[test]
+    fn test_lru_and_lfu() {
+        let mut shard = Shard::new();
+
+        for key in ["idle", "busy"] {
+            let (key, value) = string_value(key, None);
+            shard.insert(key, value);
+        }
+
+        std::thread::sleep(Duration::from_millis(5));
+        for _ in 0..10 {
+            shard.record_access(b"busy");
+        }
+
+        let idle = Some(Bytes::from("idle"));
+        assert_eq!(
+            shard.eviction_candidate(EvictionPolicy::AllKeysLru, 200),
+            idle
+        );
+        assert_eq!(
+            shard.eviction_candidate(EvictionPolicy::AllKeysLfu, 200),
+            idle
+        );
+    }
+
+    #[test]
+    fn test_overwrite_keeps_access() {
+        let mut shard = Shard::new();
+
+        for key in ["idle", "busy"] {
+            let (key, value) = string_value(key, None);
+            shard.insert(key, value);
+        }
+
+        for _ in 0..10 {
+            shard.record_access(b"busy");
+        }
+
+        let (key, value) = string_value("busy", None);
+        shard.insert(key, value);
+
+        assert_eq!(
+            shard.eviction_candidate(EvictionPolicy::AllKeysLfu, 200),
+            Some(Bytes::from("idle"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_out_of_memory() {
+        let db = Store::new();
+        run(&mut db.lock_all().await, "SET name Smith").unwrap();
+
+        let evicted = evict_until(&db, 1, EvictionPolicy::NoEviction, 5).await;
+        assert!(matches!(evicted, Err(SiderError::OutOfMemory)));
+
+        // Nothing qualifies, the key has no expiry
+        let evicted = evict_until(&db, 1, EvictionPolicy::VolatileLru, 5).await;
+        assert!(matches!(evicted, Err(SiderError::OutOfMemory)));
+
+        assert!(evict_until(&db, u64::MAX, EvictionPolicy::NoEviction, 5)
+            .await
+            .is_ok());
+        assert!(is_denyoom_command(CommandType::SET));
+        assert!(!is_denyoom_command(CommandType::DEL));
+    }
+}
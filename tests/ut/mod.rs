@@ -1 +1,12 @@
+pub mod aof;
 pub mod commands;
+pub mod expire;
+pub mod keyspace;
+pub mod memory;
+pub mod notify;
+pub mod rdb;
+pub mod resp;
+pub mod response;
+pub mod rewrite;
+pub mod snapshot;
+pub mod utils;
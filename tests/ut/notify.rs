@@ -0,0 +1,156 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::process;
+    use sider::client::Client;
+    use sider::notify::*;
+    use sider::types::*;
+    use std::sync::Arc;
+
+    fn setup_db() -> Db {
+        Arc::new(Store::new())
+    }
+
+    // Returns the payload of every message received so far. Other tests
+    // also emit events, only the ones about `ut:notify:` keys are kept.
+    fn received(client: &mut Client) -> Vec<String> {
+        let mut payloads = vec![];
+
+        while let Ok(message) = client.messages.try_recv() {
+            let encoded = String::from_utf8(message.to_bytes()).unwrap();
+            let channel = encoded.split("\r\n").nth(4).unwrap();
+            let payload = encoded.rsplit("\r\n").nth(1).unwrap();
+
+            if channel.contains("ut:notify:") || payload.starts_with("ut:notify:") {
+                payloads.push(payload.to_string());
+            }
+        }
+
+        payloads
+    }
+
+    #[test]
+    fn test_parse_notify_flags() {
+        assert_eq!(parse_notify_flags(b""), Some(0));
+        assert_eq!(
+            parse_notify_flags(b"Kx"),
+            Some(NOTIFY_KEYSPACE | NOTIFY_EXPIRED)
+        );
+        assert_eq!(
+            parse_notify_flags(b"EA"),
+            Some(NOTIFY_KEYEVENT | NOTIFY_ALL)
+        );
+        assert_eq!(parse_notify_flags(b"KE?"), None);
+
+        assert_eq!(
+            format_notify_flags(NOTIFY_KEYSPACE | NOTIFY_KEYEVENT | NOTIFY_ALL),
+            "AKE"
+        );
+        assert_eq!(
+            format_notify_flags(NOTIFY_KEYEVENT | NOTIFY_LIST | NOTIFY_EXPIRED),
+            "Elx"
+        );
+    }
+
+    // Every test enables the same events, they run in parallel on the
+    // process wide settings
+    #[tokio::test]
+    async fn test_keyspace_events() {
+        set_notify_flags(parse_notify_flags(b"KEA").unwrap());
+
+        let db = setup_db();
+        let mut subscriber = Client::new();
+        let mut client = Client::new();
+
+        process(
+            &db,
+            &mut subscriber,
+            "SUBSCRIBE __keyspace@0__:ut:notify:list",
+        )
+        .await
+        .unwrap();
+        received(&mut subscriber);
+
+        process(&db, &mut client, "RPUSH ut:notify:list a b")
+            .await
+            .unwrap();
+        process(&db, &mut client, "LPOP ut:notify:list")
+            .await
+            .unwrap();
+        process(&db, &mut client, "EXPIRE ut:notify:list 100")
+            .await
+            .unwrap();
+        process(&db, &mut client, "LPOP ut:notify:list")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            received(&mut subscriber),
+            vec!["rpush", "lpop", "expire", "lpop", "del"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keyevent_events() {
+        set_notify_flags(parse_notify_flags(b"KEA").unwrap());
+
+        let db = setup_db();
+        let mut subscriber = Client::new();
+        let mut client = Client::new();
+
+        process(&db, &mut subscriber, "SUBSCRIBE __keyevent@0__:del")
+            .await
+            .unwrap();
+        received(&mut subscriber);
+
+        process(&db, &mut client, "SET ut:notify:a 1")
+            .await
+            .unwrap();
+        process(&db, &mut client, "HSET ut:notify:b field value")
+            .await
+            .unwrap();
+        process(&db, &mut client, "DEL ut:notify:a ut:notify:missing")
+            .await
+            .unwrap();
+        process(&db, &mut client, "HDEL ut:notify:b field")
+            .await
+            .unwrap();
+        process(&db, &mut client, "SADD ut:notify:c member")
+            .await
+            .unwrap();
+        process(&db, &mut client, "FLUSHDB").await.unwrap();
+
+        assert_eq!(
+            received(&mut subscriber),
+            vec!["ut:notify:a", "ut:notify:b", "ut:notify:c"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_events() {
+        set_notify_flags(parse_notify_flags(b"KEA").unwrap());
+
+        let db = setup_db();
+        let mut subscriber = Client::new();
+        let mut client = Client::new();
+
+        process(&db, &mut subscriber, "SUBSCRIBE __keyevent@0__:expired")
+            .await
+            .unwrap();
+        received(&mut subscriber);
+
+        process(&db, &mut client, "SET ut:notify:session token")
+            .await
+            .unwrap();
+        process(&db, &mut client, "PEXPIRE ut:notify:session 1")
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        // Expired on access
+        process(&db, &mut client, "GET ut:notify:session")
+            .await
+            .unwrap();
+
+        assert_eq!(received(&mut subscriber), vec!["ut:notify:session"]);
+    }
+}
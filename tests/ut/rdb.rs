@@ -0,0 +1,260 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::process;
+    use bytes::Bytes;
+    use sider::client::Client;
+    use sider::crc64::crc64;
+    use sider::errors::SiderError;
+    use sider::rdb::*;
+    use sider::types::*;
+    use std::sync::Arc;
+
+    // A version 11 file with `body` as content
+    fn rdb_file(body: &[u8]) -> Vec<u8> {
+        let mut data = b"REDIS0011".to_vec();
+        data.extend_from_slice(body);
+        data.push(0xFF);
+        let crc = crc64(0, &data);
+        data.extend_from_slice(&crc.to_le_bytes());
+        data
+    }
+
+    fn find<'a>(entries: &'a [(Bytes, DbValue)], key: &str) -> &'a DbValue {
+        entries
+            .iter()
+            .find(|(name, _)| name == key.as_bytes())
+            .map(|(_, value)| value)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rdb_round_trip() {
+        let db: Db = Arc::new(Store::new());
+        let commands = [
+            "SET name Ada",
+            "PEXPIREAT name 4102444800000",
+            "RPUSH queue a b c",
+            "SADD tags x",
+            "HSET user name Ada lang rust",
+            "ZADD scores 1.5 alice -inf bob",
+            "XADD events 1-1 type click",
+        ];
+        for command in commands {
+            process(&db, &mut Client::new(), command).await.unwrap();
+        }
+
+        let data = {
+            let shards = db.read_all().await;
+            encode_rdb(shards.iter().map(|shard| &**shard))
+        };
+        assert!(data.starts_with(b"REDIS0009"));
+
+        // Streams are left out
+        let entries = decode_rdb(&data).unwrap();
+        assert_eq!(entries.len(), 5);
+
+        let restored: Db = Arc::new(Store::new());
+        {
+            let mut keyspace = restored.lock_all().await;
+            for (key, value) in entries {
+                keyspace.insert(key, value);
+            }
+        }
+
+        let queries = [
+            "GET name",
+            "PEXPIRETIME name",
+            "LRANGE queue 0 -1",
+            "SMEMBERS tags",
+            "HGETALL user",
+            "ZRANGE scores 0 -1 WITHSCORES",
+        ];
+        for query in queries {
+            assert_eq!(
+                process(&restored, &mut Client::new(), query)
+                    .await
+                    .unwrap()
+                    .to_bytes(),
+                process(&db, &mut Client::new(), query)
+                    .await
+                    .unwrap()
+                    .to_bytes(),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_encoded_strings() {
+        let mut body = vec![0xFA, 0x05];
+        body.extend_from_slice(b"ctime");
+        body.extend_from_slice(&[0xC2, 0x00, 0x00, 0x00, 0x66]);
+
+        // Expiry in milliseconds then an int16 string
+        body.push(0xFC);
+        body.extend_from_slice(&4102444800000u64.to_le_bytes());
+        body.extend_from_slice(&[0x00, 0x01, b'n', 0xC1, 0x0C, 0xFE]);
+
+        // Expiry in seconds then an int8 string
+        body.push(0xFD);
+        body.extend_from_slice(&4102444800u32.to_le_bytes());
+        body.extend_from_slice(&[0x00, 0x01, b'm', 0xC0, 0x07]);
+
+        // "a" followed by a back reference repeating it 9 times
+        body.extend_from_slice(&[0x00, 0x03, b'l', b'z', b'f', 0xC3, 0x05, 0x0A]);
+        body.extend_from_slice(&[0x00, b'a', 0xE0, 0x00, 0x00]);
+
+        let entries = decode_rdb(&rdb_file(&body)).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        match find(&entries, "n") {
+            DbValue::StringKey(key) => {
+                assert_eq!(key.data.as_deref(), Some(&b"-500"[..]));
+                assert_eq!(key.expires_at, Some(4102444800000));
+            }
+            other => panic!("{other:?}"),
+        }
+        match find(&entries, "m") {
+            DbValue::StringKey(key) => {
+                assert_eq!(key.data.as_deref(), Some(&b"7"[..]));
+                assert_eq!(key.expires_at, Some(4102444800000));
+            }
+            other => panic!("{other:?}"),
+        }
+        match find(&entries, "lzf") {
+            DbValue::StringKey(key) => {
+                assert_eq!(key.data.as_deref(), Some(&b"aaaaaaaaaa"[..]))
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_decode_compact_encodings() {
+        let mut body = Vec::new();
+
+        // Hash as a listpack: f => 5 as a 7 bits integer, g => -3 as a 13
+        // bits one
+        body.extend_from_slice(&[0x10, 0x01, b'h', 0x12]);
+        body.extend_from_slice(&[0x12, 0x00, 0x00, 0x00, 0x04, 0x00]);
+        body.extend_from_slice(&[0x81, b'f', 0x02, 0x05, 0x01]);
+        body.extend_from_slice(&[0x81, b'g', 0x02, 0xDF, 0xFD, 0x02, 0xFF]);
+
+        // Set as an intset of int16
+        body.extend_from_slice(&[0x0B, 0x01, b's', 0x0E]);
+        body.extend_from_slice(&[0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00]);
+        body.extend_from_slice(&[0xFE, 0xFF, 0x01, 0x00, 0x2C, 0x01]);
+
+        // Sorted set as a ziplist: a => "1.5", b => 2 as an immediate
+        body.extend_from_slice(&[0x0C, 0x01, b'z', 0x18]);
+        body.extend_from_slice(&[0x18, 0x00, 0x00, 0x00, 0x15, 0x00, 0x00, 0x00, 0x04, 0x00]);
+        body.extend_from_slice(&[0x00, 0x01, b'a', 0x03, 0x03, b'1', b'.', b'5']);
+        body.extend_from_slice(&[0x05, 0x01, b'b', 0x03, 0xF3, 0xFF]);
+
+        // List as a quicklist of a listpack ["x", 1000] and a plain node
+        body.extend_from_slice(&[0x12, 0x01, b'l', 0x02, 0x02, 0x0E]);
+        body.extend_from_slice(&[0x0E, 0x00, 0x00, 0x00, 0x02, 0x00]);
+        body.extend_from_slice(&[0x81, b'x', 0x02, 0xF1, 0xE8, 0x03, 0x03, 0xFF]);
+        body.extend_from_slice(&[0x01, 0x03, b'b', b'i', b'g']);
+
+        // Sorted set with text scores, 254 stands for +inf
+        body.extend_from_slice(&[0x03, 0x01, b'y', 0x01, 0x01, b'm', 0xFE]);
+
+        let entries = decode_rdb(&rdb_file(&body)).unwrap();
+        assert_eq!(entries.len(), 5);
+
+        match find(&entries, "h") {
+            DbValue::HashKey(key) => {
+                assert_eq!(key.data.get(&b"f"[..]).map(|v| &v[..]), Some(&b"5"[..]));
+                assert_eq!(key.data.get(&b"g"[..]).map(|v| &v[..]), Some(&b"-3"[..]));
+            }
+            other => panic!("{other:?}"),
+        }
+        match find(&entries, "s") {
+            DbValue::SetKey(key) => {
+                let mut members: Vec<&[u8]> = key.data.iter().map(|m| &m[..]).collect();
+                members.sort();
+                assert_eq!(members, vec![&b"-2"[..], b"1", b"300"]);
+            }
+            other => panic!("{other:?}"),
+        }
+        match find(&entries, "z") {
+            DbValue::ZSetKey(key) => {
+                let members: Vec<(&[u8], f64)> =
+                    key.data.iter().map(|(m, s)| (&m[..], s)).collect();
+                assert_eq!(members, vec![(&b"a"[..], 1.5), (&b"b"[..], 2.0)]);
+            }
+            other => panic!("{other:?}"),
+        }
+        match find(&entries, "l") {
+            DbValue::ListKey(key) => {
+                let values: Vec<&[u8]> = key.data.iter().map(|v| &v[..]).collect();
+                assert_eq!(values, vec![&b"x"[..], b"1000", b"big"]);
+            }
+            other => panic!("{other:?}"),
+        }
+        match find(&entries, "y") {
+            DbValue::ZSetKey(key) => assert_eq!(key.data.score(b"m"), Some(f64::INFINITY)),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_decode_skips_other_databases() {
+        let body = [
+            0xFE, 0x00, 0xFB, 0x01, 0x00, 0x00, 0x01, b'a', 0x01, b'1', // db 0
+            0xFE, 0x01, 0x00, 0x01, b'b', 0x01, b'2', // db 1
+        ];
+        let entries = decode_rdb(&rdb_file(&body)).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, "a");
+    }
+
+    #[test]
+    fn test_decode_invalid_rdb() {
+        let body = [0x00, 0x01, b'a', 0x01, b'1'];
+        let mut data = rdb_file(&body);
+
+        // A zero checksum means it was disabled
+        let len = data.len();
+        data[len - 8..].copy_from_slice(&[0; 8]);
+        assert!(decode_rdb(&data).is_ok());
+
+        data[len - 1] = 1;
+        assert!(matches!(decode_rdb(&data), Err(SiderError::InvalidRdb(_))));
+
+        let data = rdb_file(&body);
+        assert!(decode_rdb(&data[..data.len() - 10]).is_err());
+        assert!(decode_rdb(b"REDIS0099\xFF").is_err());
+        assert!(decode_rdb(b"SIDER").is_err());
+
+        // Module values can't be read past
+        let data = rdb_file(&[0x07, 0x01, b'm', 0x00]);
+        assert!(decode_rdb(&data).is_err());
+    }
+
+    #[test]
+    fn test_decode_skips_streams() {
+        let mut body = vec![0x0F, 0x01, b's'];
+        // A listpack node, 1 entry with ID 1-1
+        body.extend_from_slice(&[0x01, 0x10]);
+        body.extend_from_slice(&[0; 16]);
+        body.extend_from_slice(&[0x02, b'l', b'p', 0x01, 0x01, 0x01]);
+        // A group with an entry pending for its consumer
+        body.extend_from_slice(&[0x01, 0x01, b'g', 0x01, 0x01, 0x01]);
+        body.extend_from_slice(&[0; 24]);
+        body.extend_from_slice(&[0x01, 0x01, 0x01, b'c']);
+        body.extend_from_slice(&[0; 8]);
+        body.push(0x01);
+        body.extend_from_slice(&[0; 16]);
+        body.extend_from_slice(&[0x00, 0x01, b'a', 0x01, b'1']);
+
+        let entries = decode_rdb(&rdb_file(&body)).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, "a");
+
+        // A truncated stream is still invalid
+        let data = rdb_file(&[0x15, 0x01, b's', 0x00]);
+        assert!(decode_rdb(&data).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use sider::errors::SiderError;
+    use sider::resp::{parse_resp_command, split_inline_args, RespDecoder};
+
+    #[test]
+    fn test_decode_single_command() {
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*3\r\n$3\r\nSET\r\n$4\r\nname\r\n$5\r\nAlice\r\n");
+
+        let command = decoder.decode().unwrap();
+        assert_eq!(
+            command,
+            Some(vec!["SET".into(), "name".into(), "Alice".into()])
+        );
+        assert!(decoder.decode().unwrap().is_none());
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn test_decode_command_split_across_reads() {
+        let mut decoder = RespDecoder::new();
+        let raw = b"*2\r\n$3\r\nGET\r\n$10\r\nfirst_name\r\n";
+
+        for chunk in raw.chunks(3) {
+            assert!(decoder.decode().unwrap().is_none());
+            decoder.feed(chunk);
+        }
+
+        let command = decoder.decode().unwrap();
+        assert_eq!(command, Some(vec!["GET".into(), "first_name".into()]));
+    }
+
+    #[test]
+    fn test_decode_value_larger_than_a_read() {
+        let value = "x".repeat(10_000);
+        let raw = format!(
+            "*3\r\n$3\r\nSET\r\n$4\r\nblob\r\n${}\r\n{}\r\n",
+            value.len(),
+            value
+        );
+
+        let mut decoder = RespDecoder::new();
+        decoder.feed(&raw.as_bytes()[..1024]);
+        assert!(decoder.decode().unwrap().is_none());
+
+        decoder.feed(&raw.as_bytes()[1024..]);
+        let command = decoder.decode().unwrap().unwrap();
+        assert_eq!(command[2], value);
+    }
+
+    #[test]
+    fn test_decode_value_containing_crlf() {
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$4\r\na\r\nb\r\n");
+
+        let command = decoder.decode().unwrap().unwrap();
+        assert_eq!(command[2], "a\r\nb");
+    }
+
+    #[test]
+    fn test_decode_pipelined_commands_keeps_leftover() {
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*1\r\n$4\r\nPING\r\n*2\r\n$3\r\nGET\r\n$1");
+
+        assert_eq!(decoder.decode().unwrap(), Some(vec!["PING".into()]));
+        assert!(decoder.decode().unwrap().is_none());
+
+        decoder.feed(b"\r\nk\r\n");
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(vec!["GET".into(), "k".into()])
+        );
+    }
+
+    #[test]
+    fn test_decode_protocol_errors() {
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*x\r\n");
+        assert!(matches!(
+            decoder.decode(),
+            Err(SiderError::InvalidArrayLength)
+        ));
+
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*1\r\n+PING\r\n");
+        assert!(matches!(
+            decoder.decode(),
+            Err(SiderError::InvalidBulkStringPrefix)
+        ));
+
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"*1\r\n$2\r\nPING\r\n");
+        assert!(matches!(
+            decoder.decode(),
+            Err(SiderError::InvalidBulkLength)
+        ));
+    }
+
+    #[test]
+    fn test_parse_truncated_buffer() {
+        let result = parse_resp_command(b"*1\r\n$4\r\nPING\r\n*2\r\n$3\r\nGET\r\n");
+        assert!(matches!(result, Err(SiderError::WrongElementCount)));
+
+        let commands = parse_resp_command(b"*1\r\n$4\r\nPING\r\n*0\r\n").unwrap();
+        assert_eq!(commands, vec![vec!["PING".to_string()]]);
+    }
+
+    #[test]
+    fn test_decode_inline_commands() {
+        let mut decoder = RespDecoder::new();
+        decoder.feed(b"SET name   Alice\r\nPING\n\r\n*1\r\n$4\r\nPING\r\nGET na");
+
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(vec!["SET".into(), "name".into(), "Alice".into()])
+        );
+        assert_eq!(decoder.decode().unwrap(), Some(vec!["PING".into()]));
+        assert_eq!(decoder.decode().unwrap(), Some(vec!["PING".into()]));
+        assert!(decoder.decode().unwrap().is_none());
+
+        decoder.feed(b"me\r\n");
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Some(vec!["GET".into(), "name".into()])
+        );
+    }
+
+    #[test]
+    fn test_split_inline_quoted_args() {
+        let args =
+            split_inline_args(br#"SET "hello world" 'it\'s' "a\tb\x41\"\\" """#).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                Bytes::from("SET"),
+                Bytes::from("hello world"),
+                Bytes::from("it's"),
+                Bytes::from("a\tbA\"\\"),
+                Bytes::new(),
+            ]
+        );
+
+        let args = split_inline_args(br#"SET bin "\xff\x00""#).unwrap();
+        assert_eq!(args[2], Bytes::from_static(b"\xff\x00"));
+    }
+
+    #[test]
+    fn test_split_inline_unbalanced_quotes() {
+        let result = split_inline_args(br#"SET key "value"#);
+        assert!(matches!(result, Err(SiderError::UnbalancedQuotes)));
+
+        let result = split_inline_args(br#"SET key 'value"#);
+        assert!(matches!(result, Err(SiderError::UnbalancedQuotes)));
+
+        let result = split_inline_args(br#"SET key "value"trailing"#);
+        assert!(matches!(result, Err(SiderError::UnbalancedQuotes)));
+    }
+}
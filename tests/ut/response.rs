@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use sider::client::Protocol;
+    use sider::errors::SiderError;
+    use sider::response::SiderResponse;
+
+    #[test]
+    fn test_encode_scalars() {
+        assert_eq!(SiderResponse::Ok.to_bytes(), b"+OK\r\n");
+        assert_eq!(SiderResponse::Integer(-5).to_bytes(), b":-5\r\n");
+        assert_eq!(SiderResponse::NullBulk.to_bytes(), b"$-1\r\n");
+        assert_eq!(SiderResponse::NullArray.to_bytes(), b"*-1\r\n");
+        assert_eq!(
+            SiderResponse::SimpleString("PONG".to_string()).to_bytes(),
+            b"+PONG\r\n"
+        );
+        assert_eq!(
+            SiderResponse::Error(SiderError::WrongType).to_bytes(),
+            b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_bulk_string_with_crlf() {
+        let response = SiderResponse::BulkString(Bytes::from("a\r\nb"));
+        assert_eq!(response.to_bytes(), b"$4\r\na\r\nb\r\n");
+
+        let response = SiderResponse::BulkString(Bytes::new());
+        assert_eq!(response.to_bytes(), b"$0\r\n\r\n");
+    }
+
+    #[test]
+    fn test_encode_nested_mixed_array() {
+        let response = SiderResponse::Array(vec![
+            SiderResponse::Integer(1),
+            SiderResponse::NullBulk,
+            SiderResponse::bulk_strings(vec![Bytes::from("foo"), Bytes::from("bar")]),
+            SiderResponse::Array(vec![]),
+            SiderResponse::Error(SiderError::NotInt),
+        ]);
+
+        assert_eq!(
+            response.to_bytes(),
+            b"*5\r\n:1\r\n$-1\r\n*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n*0\r\n\
+              -ERR value is not an integer or out of range\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_resp3_types() {
+        let map = SiderResponse::Map(vec![(
+            SiderResponse::BulkString(Bytes::from("name")),
+            SiderResponse::BulkString(Bytes::from("Smith")),
+        )]);
+        assert_eq!(
+            map.encode_for(Protocol::Resp3),
+            b"%1\r\n$4\r\nname\r\n$5\r\nSmith\r\n"
+        );
+
+        let set = SiderResponse::Set(vec![SiderResponse::Integer(1)]);
+        assert_eq!(set.encode_for(Protocol::Resp3), b"~1\r\n:1\r\n");
+
+        assert_eq!(
+            SiderResponse::NullBulk.encode_for(Protocol::Resp3),
+            b"_\r\n"
+        );
+        assert_eq!(
+            SiderResponse::NullArray.encode_for(Protocol::Resp3),
+            b"_\r\n"
+        );
+        assert_eq!(
+            SiderResponse::Double(1.5).encode_for(Protocol::Resp3),
+            b",1.5\r\n"
+        );
+        assert_eq!(
+            SiderResponse::Double(f64::NEG_INFINITY).encode_for(Protocol::Resp3),
+            b",-inf\r\n"
+        );
+        assert_eq!(
+            SiderResponse::Boolean(true).encode_for(Protocol::Resp3),
+            b"#t\r\n"
+        );
+        assert_eq!(
+            SiderResponse::BigNumber("12345678901234567890".to_string())
+                .encode_for(Protocol::Resp3),
+            b"(12345678901234567890\r\n"
+        );
+
+        let verbatim = SiderResponse::Verbatim {
+            format: "txt".to_string(),
+            text: Bytes::from("Some string"),
+        };
+        assert_eq!(
+            verbatim.encode_for(Protocol::Resp3),
+            b"=15\r\ntxt:Some string\r\n"
+        );
+
+        let push = SiderResponse::Push(vec![SiderResponse::BulkString(Bytes::from("message"))]);
+        assert_eq!(push.encode_for(Protocol::Resp3), b">1\r\n$7\r\nmessage\r\n");
+    }
+
+    #[test]
+    fn test_encode_resp3_types_as_resp2() {
+        let map = SiderResponse::Map(vec![(
+            SiderResponse::BulkString(Bytes::from("name")),
+            SiderResponse::BulkString(Bytes::from("Smith")),
+        )]);
+        assert_eq!(map.to_bytes(), b"*2\r\n$4\r\nname\r\n$5\r\nSmith\r\n");
+
+        assert_eq!(SiderResponse::Set(vec![]).to_bytes(), b"*0\r\n");
+        assert_eq!(SiderResponse::Double(1.5).to_bytes(), b"$3\r\n1.5\r\n");
+        assert_eq!(SiderResponse::Boolean(false).to_bytes(), b":0\r\n");
+
+        let verbatim = SiderResponse::Verbatim {
+            format: "txt".to_string(),
+            text: Bytes::from("Some string"),
+        };
+        assert_eq!(verbatim.to_bytes(), b"$11\r\nSome string\r\n");
+    }
+}
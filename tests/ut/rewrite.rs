@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::args;
+    use bytes::Bytes;
+    use sider::client::Client;
+    use sider::process::process_command;
+    use sider::resp::parse_resp_command;
+    use sider::rewrite::{dump_shard, should_auto_rewrite};
+    use sider::types::*;
+    use std::sync::Arc;
+
+    async fn run(db: &Db, command: Vec<Bytes>) -> Vec<u8> {
+        process_command(command, db, &mut Client::new(), true)
+            .await
+            .unwrap()
+            .to_bytes()
+    }
+
+    async fn dump(db: &Store) -> Vec<Vec<Bytes>> {
+        let mut output = Vec::new();
+        for shard in db.read_all().await.iter() {
+            output.extend_from_slice(&dump_shard(shard));
+        }
+
+        parse_resp_command(&output).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dump_keeps_list_order_and_expiry() {
+        let db: Db = Arc::new(Store::new());
+        run(&db, args("RPUSH queue a b c")).await;
+        run(&db, args("HSET user name Ada")).await;
+        run(&db, args("PEXPIREAT user 4102444800000")).await;
+
+        let mut commands = dump(&db).await;
+        commands.sort();
+
+        assert_eq!(
+            commands,
+            vec![
+                args("HSET user name Ada"),
+                args("PEXPIREAT user 4102444800000"),
+                args("RPUSH queue a b c"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dump_replays_to_the_same_dataset() {
+        let db: Db = Arc::new(Store::new());
+        let items: Vec<String> = (0..150).map(|i| i.to_string()).collect();
+        run(&db, args(&format!("RPUSH numbers {}", items.join(" ")))).await;
+        run(
+            &db,
+            vec!["SET".into(), "greeting".into(), "hello world".into()],
+        )
+        .await;
+
+        let commands = dump(&db).await;
+        // Long collections are split
+        let pushes = commands.iter().filter(|command| command[0] == "RPUSH");
+        assert_eq!(pushes.count(), 3);
+
+        let restored: Db = Arc::new(Store::new());
+        for command in commands {
+            run(&restored, command).await;
+        }
+
+        assert_eq!(
+            run(&restored, args("LRANGE numbers 0 -1")).await,
+            run(&db, args("LRANGE numbers 0 -1")).await
+        );
+        assert_eq!(
+            run(&restored, args("GET greeting")).await,
+            b"$11\r\nhello world\r\n"
+        );
+    }
+
+    #[test]
+    fn test_should_auto_rewrite() {
+        let mb = 1024 * 1024;
+
+        // Too small, whatever the growth
+        assert!(!should_auto_rewrite(mb, 0));
+        // Doubled since the last rewrite
+        assert!(should_auto_rewrite(128 * mb, 64 * mb));
+        assert!(!should_auto_rewrite(100 * mb, 64 * mb));
+        assert!(should_auto_rewrite(70 * mb, 0));
+    }
+}
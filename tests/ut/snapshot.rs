@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+    use crate::ut::utils::process;
+    use sider::aof::AofPosition;
+    use sider::client::Client;
+    use sider::crc64::crc64;
+    use sider::errors::SiderError;
+    use sider::snapshot::*;
+    use sider::types::*;
+    use std::sync::Arc;
+
+    const AOF: AofPosition = AofPosition { size: 42, crc: 7 };
+
+    async fn snapshot_of(db: &Db) -> Vec<u8> {
+        let shards = db.read_all().await;
+        encode_snapshot(AOF, shards.iter().map(|shard| &**shard))
+    }
+
+    #[test]
+    fn test_crc64() {
+        // Check value of CRC-64/Jones, the one of Redis
+        assert_eq!(crc64(0, b"123456789"), 0xe9c6d914c4b8d9ca);
+        assert_eq!(crc64(crc64(0, b"1234"), b"56789"), 0xe9c6d914c4b8d9ca);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let db: Db = Arc::new(Store::new());
+        let commands = [
+            "SET name Ada",
+            "PEXPIREAT name 4102444800000",
+            "RPUSH queue a b c",
+            "SADD tags x y",
+            "HSET user name Ada lang rust",
+            "ZADD scores 1.5 alice 2 bob",
+            "XADD events 1-1 type click",
+            "XADD events 2-1 type view",
+            "XGROUP CREATE events workers 0",
+            "XREADGROUP GROUP workers alice COUNT 1 STREAMS events >",
+        ];
+        for command in commands {
+            process(&db, &mut Client::new(), command).await.unwrap();
+        }
+
+        let snapshot = decode_snapshot(&snapshot_of(&db).await).unwrap();
+        assert_eq!(snapshot.aof, AOF);
+        assert_eq!(snapshot.entries.len(), 6);
+
+        let restored: Db = Arc::new(Store::new());
+        {
+            let mut keyspace = restored.lock_all().await;
+            for (key, value) in snapshot.entries {
+                keyspace.insert(key, value);
+            }
+        }
+
+        let queries = [
+            "GET name",
+            "PEXPIRETIME name",
+            "LRANGE queue 0 -1",
+            "HGETALL user",
+            "ZRANGE scores 0 -1 WITHSCORES",
+            "XRANGE events - +",
+            "XPENDING events workers",
+        ];
+        for query in queries {
+            assert_eq!(
+                process(&restored, &mut Client::new(), query)
+                    .await
+                    .unwrap()
+                    .to_bytes(),
+                process(&db, &mut Client::new(), query)
+                    .await
+                    .unwrap()
+                    .to_bytes(),
+                "{query}"
+            );
+        }
+
+        let mut members = process(&restored, &mut Client::new(), "SMEMBERS tags")
+            .await
+            .unwrap()
+            .to_bytes();
+        members.sort();
+        let mut expected = process(&db, &mut Client::new(), "SMEMBERS tags")
+            .await
+            .unwrap()
+            .to_bytes();
+        expected.sort();
+        assert_eq!(members, expected);
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_snapshot_is_rejected() {
+        let db: Db = Arc::new(Store::new());
+        process(&db, &mut Client::new(), "SET name Ada")
+            .await
+            .unwrap();
+        let data = snapshot_of(&db).await;
+
+        let mut flipped = data.clone();
+        flipped[20] ^= 1;
+        assert!(matches!(
+            decode_snapshot(&flipped),
+            Err(SiderError::CorruptSnapshot(_))
+        ));
+
+        assert!(matches!(
+            decode_snapshot(&data[..data.len() - 1]),
+            Err(SiderError::CorruptSnapshot(_))
+        ));
+        assert!(decode_snapshot(b"*1\r\n$4\r\nPING\r\n").is_err());
+    }
+
+    #[test]
+    fn test_snapshot_matches_aof() {
+        let aof = b"*1\r\n$4\r\nPING\r\n*1\r\n$4\r\nPING\r\n";
+        let snapshot = Snapshot {
+            aof: AofPosition {
+                size: 14,
+                crc: crc64(0, &aof[..14]),
+            },
+            entries: Vec::new(),
+        };
+
+        // Written to since
+        assert!(snapshot.matches_aof(aof));
+        // Replaced, or shorter than when the snapshot was taken
+        assert!(!snapshot.matches_aof(b"*1\r\n$4\r\nPONG\r\n"));
+        assert!(!snapshot.matches_aof(b"*1\r\n"));
+    }
+
+    #[test]
+    fn test_save_rules() {
+        let rules = parse_save_rules(b"3600 1 300 100").unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                SaveRule {
+                    seconds: 3600,
+                    changes: 1
+                },
+                SaveRule {
+                    seconds: 300,
+                    changes: 100
+                },
+            ]
+        );
+        assert_eq!(format_save_rules(&rules), "3600 1 300 100");
+        assert_eq!(parse_save_rules(b""), Some(Vec::new()));
+        assert_eq!(parse_save_rules(b"3600"), None);
+        assert_eq!(parse_save_rules(b"3600 x"), None);
+
+        assert!(!save_rule_reached(&rules, 0, 7200));
+        assert!(save_rule_reached(&rules, 1, 3600));
+        assert!(!save_rule_reached(&rules, 99, 600));
+        assert!(save_rule_reached(&rules, 100, 300));
+    }
+}
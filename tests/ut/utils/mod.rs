@@ -0,0 +1,37 @@
+use bytes::Bytes;
+use sider::client::Client;
+use sider::errors::SiderError;
+use sider::parser::parse_command;
+use sider::process::{execute_command, process_command};
+use sider::response::SiderResponse;
+use sider::types::*;
+
+/// The arguments of the command, split on whitespace.
+pub fn args(command: &str) -> Vec<Bytes> {
+    command
+        .split_whitespace()
+        .map(|arg| Bytes::copy_from_slice(arg.as_bytes()))
+        .collect()
+}
+
+/// Runs the command on shards the caller already locked.
+pub fn run(db: &mut Keyspace, command: &str) -> Result<Vec<u8>, SiderError> {
+    let command = parse_command(args(command))?;
+    execute_command(db, &mut Client::new(), command).map(|reply| reply.to_bytes())
+}
+
+/// Runs the command like a client sent it. Replayed like the AOF restore
+/// does, so nothing is written to the AOF file.
+pub async fn process(
+    db: &Db,
+    client: &mut Client,
+    command: &str,
+) -> Result<SiderResponse, SiderError> {
+    process_command(args(command), db, client, true).await
+}
+
+/// The reply a blocked client got, if it was served already.
+pub fn served(client: &mut Client) -> Option<Vec<u8>> {
+    let wait = client.blocked.as_mut().expect("Client is not blocked");
+    wait.reply.try_recv().ok().map(|reply| reply.to_bytes())
+}